@@ -0,0 +1,69 @@
+let transcoderUrl = null;
+let basisModule = null;
+
+export function set_transcoder_url(url) {
+    if (url !== transcoderUrl) {
+        transcoderUrl = url;
+        basisModule = null;
+    }
+}
+
+function loadScript(url) {
+    return new Promise((resolve, reject) => {
+        const script = document.createElement('script');
+        script.addEventListener('load', () => resolve());
+        script.addEventListener('error', (event) => {
+            console.error('Could not load script from url: ', url);
+            reject(event);
+        });
+
+        script.src = url;
+        document.head.appendChild(script);
+    });
+}
+
+async function getBasisModule() {
+    if (!transcoderUrl) {
+        throw new Error('KTX2 textures need the Basis Universal transcoder, set its url with Scene.set_basis_transcoder_url');
+    }
+    if (!basisModule) {
+        basisModule = loadScript(transcoderUrl)
+            .then(() => BASIS())
+            .then((module) => {
+                module.initializeBasis();
+                return module;
+            });
+    }
+
+    return basisModule;
+}
+
+export async function transcode_ktx2(data, format) {
+    const basis = await getBasisModule();
+    const file = new basis.KTX2File(data);
+
+    try {
+        if (!file.isValid()) {
+            throw new Error('Invalid KTX2 file');
+        }
+        if (!file.startTranscoding()) {
+            throw new Error('Could not start KTX2 transcoding');
+        }
+
+        const levels = [];
+        for (let level = 0; level < file.getLevels(); level++) {
+            const size = file.getImageTranscodedSizeInBytes(level, 0, 0, format);
+            const destination = new Uint8Array(size);
+
+            if (!file.transcodeImage(destination, level, 0, 0, format, 0, -1, -1)) {
+                throw new Error('Could not transcode KTX2 level ' + level);
+            }
+            levels.push(destination);
+        }
+
+        return levels;
+    } finally {
+        file.close();
+        file.delete();
+    }
+}
@@ -19,3 +19,21 @@ export async function load_image(data) {
         throw new Error('Could not load image from buffer view');
     });
 }
+
+export async function load_image_url(url) {
+    return await loadHtmlImage(url).catch(() => {
+        throw new Error('Could not load image from ' + url);
+    });
+}
+
+export async function encode_png(width, height, pixels) {
+    const canvas = document.createElement('canvas');
+    canvas.width = width;
+    canvas.height = height;
+    const clamped = new Uint8ClampedArray(pixels.buffer, pixels.byteOffset, pixels.byteLength);
+    canvas.getContext('2d').putImageData(new ImageData(clamped, width, height), 0, 0);
+
+    return new Promise((resolve, reject) => {
+        canvas.toBlob(blob => blob ? resolve(blob) : reject(new Error('Could not encode png')), 'image/png');
+    });
+}
@@ -1,5 +1,5 @@
-import {compileFragmentShader, compileVertexShader, createProgram, resizeCanvas} from './utility/gl';
-import init, {Scene} from './wasm/sdx_browser_game';
+import {compileFragmentShader, compileVertexShader, createProgram} from './utility/gl';
+import init, {Game, RenderSettings, Scene} from './wasm/sdx_browser_game';
 
 const foo = {
     keyPress: {
@@ -82,60 +82,23 @@ const mouseUp = (event: MouseEvent) => {
 //     }
 // }
 
-const sampleRender = async (gl: WebGLRenderingContext, canvas: HTMLCanvasElement) => {
+const sampleRender = async (canvas: HTMLCanvasElement) => {
     await init();
 
-    // let scene = await Scene.from_url(gl, canvas, '/models/test/suzanne2.glb');
-    let scene = await Scene.from_url(gl, canvas, '/models/test/suzanne2.glb');
-
-    const draw = () => {
-        resizeCanvas(canvas);
-        gl.viewport(0, 0, canvas.width, canvas.height);
-
-        gl.clearColor(0.2, 0.2, 0.2, 1.0);
-        gl.clear(gl.COLOR_BUFFER_BIT);
-
-        gl.enable(gl.CULL_FACE);
-        gl.enable(gl.DEPTH_TEST);
-
-        scene.render(gl);
-        // if (foo.keyPress.w) {
-        //     descriptor.move_camera(0.0, 0.0, 0.1);
-        // }
-        // if (foo.keyPress.a) {
-        //     descriptor.move_camera(0.1, 0.0, 0.0);
-        // }
-        // if (foo.keyPress.s) {
-        //     descriptor.move_camera(0.0, 0.0, -0.1);
-        // }
-        // if (foo.keyPress.d) {
-        //     descriptor.move_camera(-0.1, 0.0, 0.0);
-        // }
-        // if (foo.mouseMove.up) {
-        //     descriptor.turn_x(0.1);
-        // }
-        // if (foo.mouseMove.down) {
-        //     descriptor.turn_x(0.1);
-        // }
-
-        // console.log('left', foo.mouseMove.left);
-        // console.log('right', foo.mouseMove.right);
-        // console.log('up', foo.mouseMove.up);
-        // console.log('down', foo.mouseMove.down);
-
-        // console.log(gl, descriptor);
-        // meshShader.render(gl, descriptor);
-        // descriptor.rotate_sample();
+    const settings = new RenderSettings();
+    settings.set_clear_color(0.2, 0.2, 0.2, 1.0);
+    const scene = await Scene.from_url_with_settings(canvas, '/models/test/suzanne2.glb', settings);
+    const game = new Game(scene);
 
+    const update = () => {
         foo.mouseMove.left = false;
         foo.mouseMove.right = false;
         foo.mouseMove.up = false;
         foo.mouseMove.down = false;
+    };
 
-        window.requestAnimationFrame(draw);
-    }
-
-    draw();
+    game.add_system(update);
+    game.start();
 }
 
 export default async function (): Promise<void> {
@@ -146,12 +109,8 @@ export default async function (): Promise<void> {
     if (!(canvas instanceof HTMLCanvasElement)) {
         return;
     }
-    const gl = canvas.getContext('webgl');
-    if (!gl) {
-        return;
-    }
 
     // const mesh = glb.json.glTf.meshes[0];
-    await sampleRender(gl, canvas);
+    await sampleRender(canvas);
     // await sampleRender2(mesh, glb, canvas, gl);
 }
\ No newline at end of file
@@ -1,8 +1,27 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfTextureTransform {
+    pub(crate) offset: Option<[f32; 2]>,
+    pub(crate) rotation: Option<f32>,
+    pub(crate) scale: Option<[f32; 2]>,
+    #[serde(rename = "texCoord")]
+    pub(crate) tex_coord: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct TextureReferenceExtensions {
+    #[serde(rename = "KHR_texture_transform")]
+    pub(crate) texture_transform: Option<GlTfTextureTransform>,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct TextureReference {
     pub(crate) index: usize,
+    #[serde(rename = "texCoord")]
+    pub(crate) tex_coord: Option<usize>,
+    pub(crate) extensions: Option<TextureReferenceExtensions>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -10,12 +29,27 @@ pub(crate) struct GlTfScene {
     pub(crate) nodes: Vec<usize>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfNodeLight {
+    pub(crate) light: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfNodeExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    pub(crate) lights_punctual: Option<GlTfNodeLight>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct GlTfNode {
-    pub(crate) name: String,
+    pub(crate) name: Option<String>,
     pub(crate) mesh: Option<usize>,
+    pub(crate) children: Option<Vec<usize>>,
+    pub(crate) matrix: Option<[f32; 16]>,
     pub(crate) translation: Option<[f32; 3]>,
     pub(crate) rotation: Option<[f32; 4]>,
+    pub(crate) scale: Option<[f32; 3]>,
+    pub(crate) extensions: Option<GlTfNodeExtensions>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +68,49 @@ pub(crate) struct GlTfPbrMetallicRoughness {
     pub(crate) roughness_texture: Option<TextureReference>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfMaterialUnlit {}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfMaterialEmissiveStrength {
+    #[serde(rename = "emissiveStrength")]
+    pub(crate) emissive_strength: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfMaterialClearcoat {
+    #[serde(rename = "clearcoatFactor")]
+    pub(crate) clearcoat_factor: Option<f32>,
+    #[serde(rename = "clearcoatTexture")]
+    pub(crate) clearcoat_texture: Option<TextureReference>,
+    #[serde(rename = "clearcoatRoughnessFactor")]
+    pub(crate) clearcoat_roughness_factor: Option<f32>,
+    #[serde(rename = "clearcoatRoughnessTexture")]
+    pub(crate) clearcoat_roughness_texture: Option<TextureReference>,
+    #[serde(rename = "clearcoatNormalTexture")]
+    pub(crate) clearcoat_normal_texture: Option<TextureReference>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfMaterialTransmission {
+    #[serde(rename = "transmissionFactor")]
+    pub(crate) transmission_factor: Option<f32>,
+    #[serde(rename = "transmissionTexture")]
+    pub(crate) transmission_texture: Option<TextureReference>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfMaterialExtensions {
+    #[serde(rename = "KHR_materials_unlit")]
+    pub(crate) unlit: Option<GlTfMaterialUnlit>,
+    #[serde(rename = "KHR_materials_emissive_strength")]
+    pub(crate) emissive_strength: Option<GlTfMaterialEmissiveStrength>,
+    #[serde(rename = "KHR_materials_clearcoat")]
+    pub(crate) clearcoat: Option<GlTfMaterialClearcoat>,
+    #[serde(rename = "KHR_materials_transmission")]
+    pub(crate) transmission: Option<GlTfMaterialTransmission>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct GlTfMaterial {
     #[serde(rename = "pbrMetallicRoughness")]
@@ -41,6 +118,9 @@ pub(crate) struct GlTfMaterial {
     pub(crate) name: Option<String>,
     #[serde(rename = "doubleSided")]
     pub(crate) double_sided: Option<bool>,
+    #[serde(rename = "emissiveFactor")]
+    pub(crate) emissive_factor: Option<[f32; 3]>,
+    pub(crate) extensions: Option<GlTfMaterialExtensions>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -51,6 +131,34 @@ pub(crate) struct GlTfMeshPrimitiveAttributes {
     pub(crate) normal: Option<usize>,
     #[serde(rename = "TEXCOORD_0")]
     pub(crate) texture_coord_0: Option<usize>,
+    #[serde(rename = "TEXCOORD_1")]
+    pub(crate) texture_coord_1: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfDracoMeshCompression {
+    #[serde(rename = "bufferView")]
+    pub(crate) buffer_view: usize,
+    pub(crate) attributes: HashMap<String, u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfMaterialMapping {
+    pub(crate) material: usize,
+    pub(crate) variants: Vec<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfPrimitiveMaterialsVariants {
+    pub(crate) mappings: Vec<GlTfMaterialMapping>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfMeshPrimitiveExtensions {
+    #[serde(rename = "KHR_draco_mesh_compression")]
+    pub(crate) draco_mesh_compression: Option<GlTfDracoMeshCompression>,
+    #[serde(rename = "KHR_materials_variants")]
+    pub(crate) materials_variants: Option<GlTfPrimitiveMaterialsVariants>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,20 +166,33 @@ pub(crate) struct GlTfMeshPrimitive {
     pub(crate) attributes: GlTfMeshPrimitiveAttributes,
     pub(crate) indices: usize,
     pub(crate) material: Option<usize>,
+    pub(crate) extensions: Option<GlTfMeshPrimitiveExtensions>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct GlTfMesh {
-    pub(crate) name: String,
+    pub(crate) name: Option<String>,
     pub(crate) primitives: Vec<GlTfMeshPrimitive>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub(crate) struct GlTfTexture {
-    pub(crate) sampler: usize,
+pub(crate) struct GlTfTextureBasisu {
     pub(crate) source: usize,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfTextureExtensions {
+    #[serde(rename = "KHR_texture_basisu")]
+    pub(crate) texture_basisu: Option<GlTfTextureBasisu>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfTexture {
+    pub(crate) sampler: Option<usize>,
+    pub(crate) source: Option<usize>,
+    pub(crate) extensions: Option<GlTfTextureExtensions>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct GlTfImage {
     pub(crate) uri: Option<String>,
@@ -89,30 +210,55 @@ pub(crate) struct GlTfBuffer {
     pub(crate) uri: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfMeshoptCompression {
+    pub(crate) buffer: usize,
+    #[serde(rename = "byteOffset", default)]
+    pub(crate) byte_offset: u32,
+    #[serde(rename = "byteLength")]
+    pub(crate) byte_length: u32,
+    #[serde(rename = "byteStride")]
+    pub(crate) byte_stride: usize,
+    pub(crate) count: usize,
+    pub(crate) mode: String,
+    pub(crate) filter: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfBufferViewExtensions {
+    #[serde(rename = "EXT_meshopt_compression")]
+    pub(crate) meshopt_compression: Option<GlTfMeshoptCompression>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct GlTfBufferView {
     pub(crate) buffer: usize,
-    #[serde(rename = "byteOffset")]
+    #[serde(rename = "byteOffset", default)]
     pub(crate) byte_offset: u32,
     #[serde(rename = "byteLength")]
     pub(crate) byte_length: u32,
     pub(crate) target: Option<usize>,
     #[serde(rename = "byteStride")]
     pub(crate) byte_stride: Option<usize>,
+    pub(crate) extensions: Option<GlTfBufferViewExtensions>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub(crate) struct GlTfSampler {
     #[serde(rename = "magFilter")]
-    pub(crate) mag_filter: usize,
+    pub(crate) mag_filter: Option<u32>,
     #[serde(rename = "minFilter")]
-    pub(crate) min_filter: usize,
+    pub(crate) min_filter: Option<u32>,
+    #[serde(rename = "wrapS")]
+    pub(crate) wrap_s: Option<u32>,
+    #[serde(rename = "wrapT")]
+    pub(crate) wrap_t: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct GlTfAccessor {
     #[serde(rename = "bufferView")]
-    pub(crate) buffer_view: usize,
+    pub(crate) buffer_view: Option<usize>,
     #[serde(rename = "componentType")]
     pub(crate) component_type: u32,
     pub(crate) count: usize,
@@ -120,6 +266,7 @@ pub(crate) struct GlTfAccessor {
     pub(crate) type_name: String,
     #[serde(rename = "byteOffset")]
     pub(crate) byte_offset: Option<u32>,
+    pub(crate) normalized: Option<bool>,
     pub(crate) max: Option<Vec<f32>>,
     pub(crate) min: Option<Vec<f32>>,
 }
@@ -129,6 +276,48 @@ pub(crate) struct GlTfAsset {
     pub(crate) version: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfMaterialVariant {
+    pub(crate) name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfMaterialsVariants {
+    pub(crate) variants: Vec<GlTfMaterialVariant>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfLightSpot {
+    #[serde(rename = "innerConeAngle")]
+    pub(crate) inner_cone_angle: Option<f32>,
+    #[serde(rename = "outerConeAngle")]
+    pub(crate) outer_cone_angle: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfLight {
+    pub(crate) name: Option<String>,
+    pub(crate) color: Option<[f32; 3]>,
+    pub(crate) intensity: Option<f32>,
+    #[serde(rename = "type")]
+    pub(crate) type_name: String,
+    pub(crate) range: Option<f32>,
+    pub(crate) spot: Option<GlTfLightSpot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfLightsPunctual {
+    pub(crate) lights: Vec<GlTfLight>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct GlTfExtensions {
+    #[serde(rename = "KHR_materials_variants")]
+    pub(crate) materials_variants: Option<GlTfMaterialsVariants>,
+    #[serde(rename = "KHR_lights_punctual")]
+    pub(crate) lights_punctual: Option<GlTfLightsPunctual>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct GlTf {
     pub(crate) scene: usize,
@@ -142,6 +331,12 @@ pub(crate) struct GlTf {
     pub(crate) buffers: Vec<GlTfBuffer>,
     #[serde(rename = "bufferViews")]
     pub(crate) buffer_views: Vec<GlTfBufferView>,
+    #[serde(default)]
     pub(crate) samplers: Vec<GlTfSampler>,
     pub(crate) asset: GlTfAsset,
+    #[serde(rename = "extensionsUsed")]
+    pub(crate) extensions_used: Option<Vec<String>>,
+    #[serde(rename = "extensionsRequired")]
+    pub(crate) extensions_required: Option<Vec<String>>,
+    pub(crate) extensions: Option<GlTfExtensions>,
 }
@@ -1,3 +1,14 @@
 mod definitions;
 mod loader;
 mod renderer;
+mod runtime;
+
+pub use crate::renderer::debug::{DebugOverlay, DebugView};
+pub use crate::renderer::environment::Environment;
+pub use crate::renderer::light::LightingModel;
+pub use crate::renderer::post::PostEffect;
+pub use crate::renderer::scene::Scene;
+pub use crate::renderer::settings::{
+    BlendMode, CullMode, DepthFunction, PowerPreference, RenderSettings,
+};
+pub use crate::runtime::Game;
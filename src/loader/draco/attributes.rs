@@ -0,0 +1,520 @@
+use crate::loader::draco::buffer::DecoderBuffer;
+use crate::loader::draco::connectivity::Edgebreaker;
+use crate::loader::draco::corner_table::Table;
+use crate::loader::draco::octahedron::OctahedronToolBox;
+use crate::loader::draco::prediction::{MeshData, PositionData, PredictionScheme};
+use crate::loader::draco::rans::{convert_symbol_to_signed, decode_symbols};
+use crate::loader::draco::traversal::{generate_sequence, point_to_value_map, TraversalMethod};
+use crate::loader::error::DecodeError;
+
+const ATTRIBUTE_POSITION: u8 = 0;
+const ATTRIBUTE_GENERIC: u8 = 4;
+
+const DECODER_GENERIC: u8 = 0;
+const DECODER_INTEGER: u8 = 1;
+const DECODER_QUANTIZATION: u8 = 2;
+const DECODER_NORMALS: u8 = 3;
+
+const MESH_VERTEX_ATTRIBUTE: u8 = 0;
+const MESH_CORNER_ATTRIBUTE: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DataType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    Bool,
+}
+
+impl DataType {
+    fn from_u8(value: u8) -> Result<DataType, DecodeError> {
+        Ok(match value {
+            1 => DataType::I8,
+            2 => DataType::U8,
+            3 => DataType::I16,
+            4 => DataType::U16,
+            5 => DataType::I32,
+            6 => DataType::U32,
+            7 => DataType::I64,
+            8 => DataType::U64,
+            9 => DataType::F32,
+            10 => DataType::F64,
+            11 => DataType::Bool,
+            _ => {
+                return Err(DecodeError::new(format!(
+                    "draco: invalid attribute data type {}",
+                    value
+                )))
+            }
+        })
+    }
+
+    pub(crate) fn size(self) -> usize {
+        match self {
+            DataType::I8 | DataType::U8 | DataType::Bool => 1,
+            DataType::I16 | DataType::U16 => 2,
+            DataType::I32 | DataType::U32 | DataType::F32 => 4,
+            DataType::I64 | DataType::U64 | DataType::F64 => 8,
+        }
+    }
+
+    fn write_integer(self, value: i32, out: &mut Vec<u8>) -> Result<(), DecodeError> {
+        match self {
+            DataType::I8 => out.push(value as i8 as u8),
+            DataType::U8 | DataType::Bool => out.push(value as u8),
+            DataType::I16 => out.extend_from_slice(&(value as i16).to_le_bytes()),
+            DataType::U16 => out.extend_from_slice(&(value as u16).to_le_bytes()),
+            DataType::I32 | DataType::U32 => out.extend_from_slice(&value.to_le_bytes()),
+            _ => {
+                return Err(DecodeError::new(
+                    "draco: integer decoder used for a non integer attribute",
+                ))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A decoded attribute with one value per point of the mesh.
+#[derive(Debug)]
+pub(crate) struct DracoAttribute {
+    pub(crate) unique_id: u32,
+    pub(crate) data_type: DataType,
+    pub(crate) num_components: usize,
+    pub(crate) normalized: bool,
+    /// Little endian values in `data_type`.
+    pub(crate) data: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct AttributeState {
+    attribute_type: u8,
+    data_type: DataType,
+    num_components: usize,
+    normalized: bool,
+    unique_id: u32,
+    decoder_type: u8,
+    point_to_value: Vec<u32>,
+    portable: Vec<i32>,
+    values: Vec<u8>,
+}
+
+impl AttributeState {
+    fn portable_components(&self) -> usize {
+        if self.decoder_type == DECODER_NORMALS {
+            2
+        } else {
+            self.num_components
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Controller {
+    attribute_data: Option<usize>,
+    corner_attribute: bool,
+    method: TraversalMethod,
+    attributes: Vec<usize>,
+}
+
+/// Decodes all attributes of the mesh, `connectivity` is `None` for sequentially
+/// encoded meshes.
+pub(super) fn decode_attributes(
+    buffer: &mut DecoderBuffer,
+    mut connectivity: Option<&mut Edgebreaker>,
+    faces: &[[u32; 3]],
+    num_points: u32,
+) -> Result<Vec<DracoAttribute>, DecodeError> {
+    let num_decoders = buffer.decode_u8()? as usize;
+    let mut controllers = Vec::with_capacity(num_decoders);
+
+    for decoder_id in 0..num_decoders {
+        let mut controller = Controller {
+            attribute_data: None,
+            corner_attribute: false,
+            method: TraversalMethod::DepthFirst,
+            attributes: vec![],
+        };
+
+        if let Some(edgebreaker) = connectivity.as_deref_mut() {
+            let attribute_data = buffer.decode_i8()?;
+            let decoder_type = buffer.decode_u8()?;
+            controller.method = TraversalMethod::from_u8(buffer.decode_u8()?)?;
+
+            if attribute_data >= 0 {
+                let data = edgebreaker
+                    .attribute_data
+                    .get_mut(attribute_data as usize)
+                    .ok_or_else(|| DecodeError::new("draco: invalid attribute data id"))?;
+                data.decoder_id = Some(decoder_id);
+                controller.attribute_data = Some(attribute_data as usize);
+            }
+
+            match decoder_type {
+                MESH_VERTEX_ATTRIBUTE => {
+                    if let Some(data) = controller.attribute_data {
+                        edgebreaker.attribute_data[data].is_connectivity_used = false;
+                    }
+                }
+                MESH_CORNER_ATTRIBUTE => {
+                    if controller.method != TraversalMethod::DepthFirst
+                        || controller.attribute_data.is_none()
+                    {
+                        return Err(DecodeError::new("draco: invalid corner attribute decoder"));
+                    }
+                    controller.corner_attribute = true;
+                }
+                _ => {
+                    return Err(DecodeError::new(format!(
+                        "draco: unknown attributes decoder type {}",
+                        decoder_type
+                    )))
+                }
+            }
+        }
+
+        controllers.push(controller);
+    }
+
+    let mut attributes: Vec<AttributeState> = vec![];
+    for controller in controllers.iter_mut() {
+        let num_attributes = buffer.decode_varint_u32()?;
+        if num_attributes == 0 || num_attributes as usize > buffer.remaining_size() {
+            return Err(DecodeError::new("draco: invalid number of attributes"));
+        }
+
+        for _ in 0..num_attributes {
+            let attribute_type = buffer.decode_u8()?;
+            if attribute_type > ATTRIBUTE_GENERIC {
+                return Err(DecodeError::new("draco: invalid attribute type"));
+            }
+            let data_type = DataType::from_u8(buffer.decode_u8()?)?;
+            let num_components = buffer.decode_u8()? as usize;
+            if num_components == 0 {
+                return Err(DecodeError::new("draco: attribute without components"));
+            }
+            let normalized = buffer.decode_u8()? > 0;
+            let unique_id = buffer.decode_varint_u32()?;
+
+            controller.attributes.push(attributes.len());
+            attributes.push(AttributeState {
+                attribute_type,
+                data_type,
+                num_components,
+                normalized,
+                unique_id,
+                decoder_type: DECODER_GENERIC,
+                point_to_value: vec![],
+                portable: vec![],
+                values: vec![],
+            });
+        }
+
+        for attribute in &controller.attributes {
+            let attribute = &mut attributes[*attribute];
+            attribute.decoder_type = buffer.decode_u8()?;
+            match attribute.decoder_type {
+                DECODER_GENERIC | DECODER_INTEGER => {}
+                DECODER_QUANTIZATION if attribute.data_type == DataType::F32 => {}
+                DECODER_NORMALS
+                    if attribute.data_type == DataType::F32 && attribute.num_components == 3 => {}
+                decoder_type => {
+                    return Err(DecodeError::new(format!(
+                        "draco: attribute decoder {} is not supported for this attribute",
+                        decoder_type
+                    )))
+                }
+            }
+        }
+    }
+
+    let position = attributes
+        .iter()
+        .position(|attribute| attribute.attribute_type == ATTRIBUTE_POSITION);
+
+    for (decoder_id, controller) in controllers.iter().enumerate() {
+        let point_ids = match connectivity.as_deref_mut() {
+            None => {
+                for attribute in &controller.attributes {
+                    attributes[*attribute].point_to_value = (0..num_points).collect();
+                }
+                (0..num_points).collect()
+            }
+            Some(edgebreaker) => {
+                let (point_ids, point_to_value) = match controller.attribute_data {
+                    Some(data) if controller.corner_attribute => {
+                        let data = &mut edgebreaker.attribute_data[data];
+                        let table = data.connectivity.view(&edgebreaker.corner_table);
+                        let point_ids = generate_sequence(
+                            &table,
+                            faces,
+                            controller.method,
+                            &mut data.encoding_data,
+                        )?;
+                        let map =
+                            point_to_value_map(&table, faces, &data.encoding_data, num_points)?;
+                        (point_ids, map)
+                    }
+                    Some(data) => {
+                        let data = &mut edgebreaker.attribute_data[data];
+                        let table = &edgebreaker.corner_table;
+                        let point_ids = generate_sequence(
+                            table,
+                            faces,
+                            controller.method,
+                            &mut data.encoding_data,
+                        )?;
+                        let map =
+                            point_to_value_map(table, faces, &data.encoding_data, num_points)?;
+                        (point_ids, map)
+                    }
+                    None => {
+                        let table = &edgebreaker.corner_table;
+                        let encoding_data = &mut edgebreaker.pos_encoding_data;
+                        let point_ids =
+                            generate_sequence(table, faces, controller.method, encoding_data)?;
+                        let map = point_to_value_map(table, faces, encoding_data, num_points)?;
+                        (point_ids, map)
+                    }
+                };
+                for attribute in &controller.attributes {
+                    attributes[*attribute].point_to_value = point_to_value.clone();
+                }
+                point_ids
+            }
+        };
+
+        let edgebreaker = connectivity.as_deref();
+        for attribute in &controller.attributes {
+            decode_portable_attribute(
+                buffer,
+                &mut attributes,
+                *attribute,
+                position,
+                &point_ids,
+                edgebreaker.map(|edgebreaker| (edgebreaker, decoder_id)),
+            )?;
+        }
+        let mut transforms = Vec::with_capacity(controller.attributes.len());
+        for attribute in &controller.attributes {
+            transforms.push(decode_transform_data(buffer, &attributes[*attribute])?);
+        }
+        for (attribute, transform) in controller.attributes.iter().zip(transforms) {
+            let attribute = &mut attributes[*attribute];
+            attribute.values = transform.apply(attribute)?;
+        }
+    }
+
+    attributes
+        .into_iter()
+        .map(|attribute| {
+            let value_size = attribute.data_type.size() * attribute.num_components;
+            let mut data = Vec::with_capacity(attribute.point_to_value.len() * value_size);
+            for value in &attribute.point_to_value {
+                let start = *value as usize * value_size;
+                data.extend_from_slice(
+                    attribute
+                        .values
+                        .get(start..start + value_size)
+                        .ok_or_else(|| DecodeError::new("draco: attribute value out of range"))?,
+                );
+            }
+
+            Ok(DracoAttribute {
+                unique_id: attribute.unique_id,
+                data_type: attribute.data_type,
+                num_components: attribute.num_components,
+                normalized: attribute.normalized,
+                data,
+            })
+        })
+        .collect()
+}
+
+fn decode_portable_attribute<'a>(
+    buffer: &mut DecoderBuffer<'a>,
+    attributes: &mut [AttributeState],
+    index: usize,
+    position: Option<usize>,
+    point_ids: &[u32],
+    edgebreaker: Option<(&Edgebreaker, usize)>,
+) -> Result<(), DecodeError> {
+    let num_entries = point_ids.len();
+    let attribute = &attributes[index];
+
+    if attribute.decoder_type == DECODER_GENERIC {
+        let size = num_entries * attribute.num_components * attribute.data_type.size();
+        attributes[index].values = buffer.decode_bytes(size)?.to_vec();
+        return Ok(());
+    }
+
+    let num_components = attribute.portable_components();
+    let is_normal = attribute.decoder_type == DECODER_NORMALS;
+    let mut prediction = PredictionScheme::decode_header(buffer, edgebreaker.is_some(), is_normal)?;
+
+    let num_values = num_entries * num_components;
+    let mut values: Vec<i32> = if buffer.decode_u8()? > 0 {
+        decode_symbols(buffer, num_values, num_components)?
+            .into_iter()
+            .map(|value| value as i32)
+            .collect()
+    } else {
+        let num_bytes = buffer.decode_u8()? as usize;
+        if num_bytes == 0 || num_bytes > 4 {
+            return Err(DecodeError::new("draco: invalid raw integer size"));
+        }
+        let bytes = buffer.decode_bytes(num_bytes * num_values)?;
+        bytes
+            .chunks(num_bytes)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .rev()
+                    .fold(0u32, |value, byte| (value << 8) | u32::from(*byte))
+                    as i32
+            })
+            .collect()
+    };
+
+    let corrections_positive = prediction
+        .as_ref()
+        .map(|prediction| prediction.are_corrections_positive())
+        .unwrap_or(false);
+    if !corrections_positive {
+        for value in values.iter_mut() {
+            *value = convert_symbol_to_signed(*value as u32);
+        }
+    }
+
+    if let Some(prediction) = prediction.as_mut() {
+        let mut view = None;
+        let mesh = edgebreaker.map(|(edgebreaker, decoder_id)| {
+            match edgebreaker.attribute_data_for_decoder(decoder_id) {
+                Some(data) => {
+                    let data = &edgebreaker.attribute_data[data];
+                    let table: &dyn Table = if data.is_connectivity_used {
+                        &*view.insert(data.connectivity.view(&edgebreaker.corner_table))
+                    } else {
+                        &edgebreaker.corner_table
+                    };
+                    MeshData {
+                        table,
+                        encoding_data: &data.encoding_data,
+                    }
+                }
+                None => MeshData {
+                    table: &edgebreaker.corner_table,
+                    encoding_data: &edgebreaker.pos_encoding_data,
+                },
+            }
+        });
+        let num_corners = edgebreaker
+            .map(|(edgebreaker, _)| edgebreaker.corner_table.num_faces() * 3)
+            .unwrap_or(0);
+        prediction.decode_prediction_data(buffer, num_corners)?;
+
+        let positions = match position {
+            Some(position) if prediction.needs_positions() && position != index => {
+                let position = &attributes[position];
+                if position.portable.is_empty() || position.portable_components() != 3 {
+                    return Err(DecodeError::new("draco: positions are not decoded yet"));
+                }
+                Some(PositionData {
+                    values: &position.portable,
+                    point_to_value: &position.point_to_value,
+                })
+            }
+            _ => None,
+        };
+        prediction.compute_original_values(
+            &mut values,
+            num_components,
+            point_ids,
+            mesh.as_ref(),
+            positions.as_ref(),
+        )?;
+    }
+
+    attributes[index].portable = values;
+
+    Ok(())
+}
+
+enum AttributeTransform {
+    None,
+    Integer,
+    Quantization {
+        min: Vec<f32>,
+        range: f32,
+        bits: u32,
+    },
+    Normals(OctahedronToolBox),
+}
+
+fn decode_transform_data(
+    buffer: &mut DecoderBuffer,
+    attribute: &AttributeState,
+) -> Result<AttributeTransform, DecodeError> {
+    Ok(match attribute.decoder_type {
+        DECODER_INTEGER => AttributeTransform::Integer,
+        DECODER_QUANTIZATION => {
+            let min = (0..attribute.num_components)
+                .map(|_| buffer.decode_f32())
+                .collect::<Result<Vec<_>, _>>()?;
+            let range = buffer.decode_f32()?;
+            let bits = u32::from(buffer.decode_u8()?);
+            if !(1..=30).contains(&bits) {
+                return Err(DecodeError::new("draco: invalid quantization bits"));
+            }
+            AttributeTransform::Quantization { min, range, bits }
+        }
+        DECODER_NORMALS => {
+            AttributeTransform::Normals(OctahedronToolBox::new(u32::from(buffer.decode_u8()?))?)
+        }
+        _ => AttributeTransform::None,
+    })
+}
+
+impl AttributeTransform {
+    /// Converts the portable values back into the attribute data type.
+    fn apply(self, attribute: &AttributeState) -> Result<Vec<u8>, DecodeError> {
+        let mut out = vec![];
+        match self {
+            AttributeTransform::None => return Ok(attribute.values.clone()),
+            AttributeTransform::Integer => {
+                for value in &attribute.portable {
+                    attribute.data_type.write_integer(*value, &mut out)?;
+                }
+            }
+            AttributeTransform::Quantization { min, range, bits } => {
+                let max_quantized_value = ((1u32 << bits) - 1) as f32;
+                let delta = range / max_quantized_value;
+                for value in attribute.portable.chunks(attribute.num_components) {
+                    for (component, min) in value.iter().zip(&min) {
+                        let value = *component as f32 * delta + min;
+                        out.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+            AttributeTransform::Normals(tool_box) => {
+                for value in attribute.portable.chunks(2) {
+                    let normal =
+                        tool_box.quantized_octahedral_coords_to_unit_vector(value[0], value[1]);
+                    for component in normal.iter() {
+                        out.extend_from_slice(&component.to_le_bytes());
+                    }
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
@@ -0,0 +1,146 @@
+use crate::loader::error::DecodeError;
+
+#[derive(Debug, Clone)]
+struct BitDecoder {
+    start: usize,
+    bit_offset: usize,
+}
+
+/// Cursor over a Draco bitstream. Mirrors the reference `DecoderBuffer`, including
+/// the bit decoding mode used by the edgebreaker symbols and tagged symbol coding.
+#[derive(Debug, Clone)]
+pub(super) struct DecoderBuffer<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_decoder: Option<BitDecoder>,
+}
+
+impl<'a> DecoderBuffer<'a> {
+    pub(super) fn new(data: &'a [u8]) -> DecoderBuffer<'a> {
+        DecoderBuffer {
+            data,
+            pos: 0,
+            bit_decoder: None,
+        }
+    }
+
+    pub(super) fn remaining_size(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub(super) fn advance(&mut self, bytes: usize) -> Result<(), DecodeError> {
+        if bytes > self.remaining_size() {
+            return Err(DecodeError::new("draco: unexpected end of data"));
+        }
+        self.pos += bytes;
+
+        Ok(())
+    }
+
+    pub(super) fn decode_bytes(&mut self, length: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self
+            .pos
+            .checked_add(length)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| DecodeError::new("draco: unexpected end of data"))?;
+        self.pos += length;
+
+        Ok(bytes)
+    }
+
+    pub(super) fn decode_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.decode_bytes(1)?[0])
+    }
+
+    pub(super) fn decode_i8(&mut self) -> Result<i8, DecodeError> {
+        Ok(self.decode_u8()? as i8)
+    }
+
+    pub(super) fn decode_u16(&mut self) -> Result<u16, DecodeError> {
+        let bytes = self.decode_bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(super) fn decode_u32(&mut self) -> Result<u32, DecodeError> {
+        let bytes = self.decode_bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub(super) fn decode_i32(&mut self) -> Result<i32, DecodeError> {
+        Ok(self.decode_u32()? as i32)
+    }
+
+    pub(super) fn decode_f32(&mut self) -> Result<f32, DecodeError> {
+        Ok(f32::from_bits(self.decode_u32()?))
+    }
+
+    pub(super) fn decode_varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.decode_u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(DecodeError::new("draco: invalid varint"))
+    }
+
+    pub(super) fn decode_varint_u32(&mut self) -> Result<u32, DecodeError> {
+        let value = self.decode_varint()?;
+        if value > u64::from(u32::MAX) {
+            return Err(DecodeError::new("draco: varint out of range"));
+        }
+
+        Ok(value as u32)
+    }
+
+    pub(super) fn start_bit_decoding(&mut self, decode_size: bool) -> Result<u64, DecodeError> {
+        let size = if decode_size {
+            self.decode_varint()?
+        } else {
+            0
+        };
+        self.bit_decoder = Some(BitDecoder {
+            start: self.pos,
+            bit_offset: 0,
+        });
+
+        Ok(size)
+    }
+
+    pub(super) fn end_bit_decoding(&mut self) {
+        if let Some(bit_decoder) = self.bit_decoder.take() {
+            let bytes_decoded = bit_decoder.bit_offset.div_ceil(8);
+            self.pos = (bit_decoder.start + bytes_decoded).min(self.data.len());
+        }
+    }
+
+    pub(super) fn decode_least_significant_bits32(
+        &mut self,
+        bits: u32,
+    ) -> Result<u32, DecodeError> {
+        if bits > 32 {
+            return Err(DecodeError::new(format!(
+                "draco: can not decode {} bits into 32 bits",
+                bits
+            )));
+        }
+        let data = self.data;
+        let bit_decoder = self
+            .bit_decoder
+            .as_mut()
+            .ok_or_else(|| DecodeError::new("draco: bit decoding was not started"))?;
+
+        let mut value = 0u32;
+        for bit in 0..bits {
+            let offset = bit_decoder.start * 8 + bit_decoder.bit_offset;
+            let byte = data.get(offset / 8).copied().unwrap_or(0);
+            value |= u32::from((byte >> (offset % 8)) & 1) << bit;
+            bit_decoder.bit_offset += 1;
+        }
+
+        Ok(value)
+    }
+}
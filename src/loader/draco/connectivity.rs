@@ -0,0 +1,672 @@
+use std::collections::HashMap;
+
+use crate::loader::draco::buffer::DecoderBuffer;
+use crate::loader::draco::corner_table::{
+    next, previous, AttributeConnectivity, CornerTable, Table, INVALID,
+};
+use crate::loader::draco::rans::{decode_symbols, RAnsBitDecoder};
+use crate::loader::error::DecodeError;
+
+const TOPOLOGY_C: u32 = 0;
+const TOPOLOGY_S: u32 = 1;
+const TOPOLOGY_L: u32 = 3;
+const TOPOLOGY_R: u32 = 5;
+const TOPOLOGY_E: u32 = 7;
+
+const RIGHT_FACE_EDGE: u32 = 1;
+
+const SEQUENTIAL_COMPRESSED_INDICES: u8 = 0;
+
+pub(super) fn decode_sequential(
+    buffer: &mut DecoderBuffer,
+) -> Result<(Vec<[u32; 3]>, u32), DecodeError> {
+    let num_faces = buffer.decode_varint_u32()? as usize;
+    let num_points = buffer.decode_varint_u32()?;
+    if num_faces > buffer.remaining_size() * 8 {
+        return Err(DecodeError::new("draco: invalid number of faces"));
+    }
+
+    let mut faces = Vec::with_capacity(num_faces);
+    if buffer.decode_u8()? == SEQUENTIAL_COMPRESSED_INDICES {
+        let symbols = decode_symbols(buffer, num_faces * 3, 1)?;
+        let mut last_index = 0i32;
+        for face in symbols.chunks(3) {
+            let mut indices = [0u32; 3];
+            for (index, symbol) in indices.iter_mut().zip(face) {
+                let diff = (*symbol >> 1) as i32;
+                last_index = if *symbol & 1 == 1 {
+                    last_index - diff
+                } else {
+                    last_index
+                        .checked_add(diff)
+                        .ok_or_else(|| DecodeError::new("draco: invalid sequential index"))?
+                };
+                if last_index < 0 {
+                    return Err(DecodeError::new("draco: invalid sequential index"));
+                }
+                *index = last_index as u32;
+            }
+            faces.push(indices);
+        }
+    } else {
+        for _ in 0..num_faces {
+            let mut indices = [0u32; 3];
+            for index in indices.iter_mut() {
+                *index = if num_points < 256 {
+                    u32::from(buffer.decode_u8()?)
+                } else if num_points < (1 << 16) {
+                    u32::from(buffer.decode_u16()?)
+                } else if num_points < (1 << 21) {
+                    buffer.decode_varint_u32()?
+                } else {
+                    buffer.decode_u32()?
+                };
+            }
+            faces.push(indices);
+        }
+    }
+
+    if faces.iter().flatten().any(|index| *index >= num_points) {
+        return Err(DecodeError::new("draco: face index out of range"));
+    }
+
+    Ok((faces, num_points))
+}
+
+/// Order in which attribute values were encoded, built while traversing the mesh.
+#[derive(Debug, Default, Clone)]
+pub(super) struct EncodingData {
+    pub(super) encoded_value_to_corner: Vec<u32>,
+    pub(super) vertex_to_encoded_value: Vec<i32>,
+}
+
+impl EncodingData {
+    fn new(num_vertices: u32) -> EncodingData {
+        EncodingData {
+            encoded_value_to_corner: Vec::with_capacity(num_vertices as usize),
+            vertex_to_encoded_value: vec![-1; num_vertices as usize],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct AttributeData {
+    pub(super) decoder_id: Option<usize>,
+    pub(super) connectivity: AttributeConnectivity,
+    pub(super) is_connectivity_used: bool,
+    pub(super) encoding_data: EncodingData,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TopologySplit {
+    split_symbol_id: u32,
+    source_symbol_id: u32,
+    source_edge: u32,
+}
+
+/// Decoded edgebreaker connectivity (standard traversal only).
+#[derive(Debug)]
+pub(super) struct Edgebreaker {
+    pub(super) corner_table: CornerTable,
+    pub(super) attribute_data: Vec<AttributeData>,
+    pub(super) pos_encoding_data: EncodingData,
+    is_vert_hole: Vec<bool>,
+    topology_splits: Vec<TopologySplit>,
+}
+
+struct TraversalDecoder<'a> {
+    symbol_buffer: DecoderBuffer<'a>,
+    start_face_decoder: RAnsBitDecoder<'a>,
+    seam_decoders: Vec<RAnsBitDecoder<'a>>,
+}
+
+impl<'a> TraversalDecoder<'a> {
+    fn start(
+        buffer: &DecoderBuffer<'a>,
+        num_attribute_data: usize,
+    ) -> Result<(TraversalDecoder<'a>, DecoderBuffer<'a>), DecodeError> {
+        let mut symbol_buffer = buffer.clone();
+        let traversal_size = symbol_buffer.start_bit_decoding(true)? as usize;
+        let mut buffer = symbol_buffer.clone();
+        buffer.end_bit_decoding();
+        buffer.advance(traversal_size)?;
+
+        let start_face_decoder = RAnsBitDecoder::start_decoding(&mut buffer)?;
+        let seam_decoders = (0..num_attribute_data)
+            .map(|_| RAnsBitDecoder::start_decoding(&mut buffer))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((
+            TraversalDecoder {
+                symbol_buffer,
+                start_face_decoder,
+                seam_decoders,
+            },
+            buffer,
+        ))
+    }
+
+    fn decode_symbol(&mut self) -> Result<u32, DecodeError> {
+        let symbol = self.symbol_buffer.decode_least_significant_bits32(1)?;
+        if symbol == TOPOLOGY_C {
+            return Ok(symbol);
+        }
+        let suffix = self.symbol_buffer.decode_least_significant_bits32(2)?;
+
+        Ok(symbol | (suffix << 1))
+    }
+}
+
+impl Edgebreaker {
+    pub(super) fn decode(
+        buffer: &mut DecoderBuffer,
+    ) -> Result<(Edgebreaker, Vec<[u32; 3]>, u32), DecodeError> {
+        let traverser_type = buffer.decode_u8()?;
+        if traverser_type != 0 {
+            return Err(DecodeError::new(format!(
+                "draco: edgebreaker traversal {} is not supported",
+                match traverser_type {
+                    2 => "valence",
+                    _ => "predictive",
+                }
+            )));
+        }
+
+        let num_encoded_vertices = buffer.decode_varint_u32()?;
+        let num_faces = buffer.decode_varint_u32()?;
+        if num_faces > u32::MAX / 3 {
+            return Err(DecodeError::new("draco: invalid number of faces"));
+        }
+        let num_attribute_data = buffer.decode_u8()? as usize;
+        let num_encoded_symbols = buffer.decode_varint_u32()?;
+        if num_encoded_symbols > num_faces {
+            return Err(DecodeError::new(
+                "draco: invalid number of edgebreaker symbols",
+            ));
+        }
+        let num_split_symbols = buffer.decode_varint_u32()?;
+        if num_split_symbols > num_encoded_symbols {
+            return Err(DecodeError::new("draco: invalid number of split symbols"));
+        }
+        // Every symbol takes at least one bit and adds at most one interior face and
+        // three vertices, which bounds the tables allocated below by the input size.
+        if num_encoded_symbols as usize > buffer.remaining_size() * 8
+            || u64::from(num_faces) > 2 * u64::from(num_encoded_symbols)
+            || u64::from(num_encoded_vertices) > 3 * u64::from(num_faces)
+        {
+            return Err(DecodeError::new("draco: invalid edgebreaker header"));
+        }
+
+        let max_vertices = (num_encoded_vertices + num_split_symbols) as usize;
+        let mut edgebreaker = Edgebreaker {
+            corner_table: CornerTable::new(num_faces),
+            attribute_data: vec![],
+            pos_encoding_data: EncodingData::default(),
+            is_vert_hole: vec![true; max_vertices],
+            topology_splits: vec![],
+        };
+        edgebreaker.decode_topology_splits(buffer, num_faces)?;
+
+        let (mut traversal, end_buffer) = TraversalDecoder::start(buffer, num_attribute_data)?;
+        let num_vertices = edgebreaker.decode_connectivity(
+            &mut traversal,
+            num_encoded_symbols,
+            num_attribute_data == 0,
+        )?;
+        *buffer = end_buffer;
+
+        let mut seam_corners = vec![vec![]; num_attribute_data];
+        if num_attribute_data > 0 {
+            for corner in (0..edgebreaker.corner_table.num_corners()).step_by(3) {
+                edgebreaker.decode_attribute_seams(&mut traversal, corner, &mut seam_corners);
+            }
+        }
+
+        for seams in seam_corners {
+            let mut connectivity = AttributeConnectivity::new(&edgebreaker.corner_table);
+            for corner in &seams {
+                connectivity.add_seam_edge(&edgebreaker.corner_table, *corner);
+            }
+            if !connectivity.recompute_vertices(&edgebreaker.corner_table) {
+                return Err(DecodeError::new("draco: invalid attribute seams"));
+            }
+
+            let num_vertices = connectivity
+                .view(&edgebreaker.corner_table)
+                .num_vertices()
+                .max(edgebreaker.corner_table.num_vertices());
+            edgebreaker.attribute_data.push(AttributeData {
+                decoder_id: None,
+                connectivity,
+                is_connectivity_used: true,
+                encoding_data: EncodingData::new(num_vertices),
+            });
+        }
+        edgebreaker.pos_encoding_data = EncodingData::new(edgebreaker.corner_table.num_vertices());
+
+        let (faces, num_points) = edgebreaker.assign_points_to_corners(num_vertices)?;
+
+        Ok((edgebreaker, faces, num_points))
+    }
+
+    fn decode_topology_splits(
+        &mut self,
+        buffer: &mut DecoderBuffer,
+        num_faces: u32,
+    ) -> Result<(), DecodeError> {
+        let num_splits = buffer.decode_varint_u32()?;
+        if num_splits > num_faces {
+            return Err(DecodeError::new("draco: invalid number of topology splits"));
+        }
+
+        let mut last_source_symbol_id = 0u32;
+        for _ in 0..num_splits {
+            let source_symbol_id = buffer
+                .decode_varint_u32()?
+                .checked_add(last_source_symbol_id)
+                .ok_or_else(|| DecodeError::new("draco: invalid topology split"))?;
+            let delta = buffer.decode_varint_u32()?;
+            if delta > source_symbol_id {
+                return Err(DecodeError::new("draco: invalid topology split"));
+            }
+            self.topology_splits.push(TopologySplit {
+                split_symbol_id: source_symbol_id - delta,
+                source_symbol_id,
+                source_edge: 0,
+            });
+            last_source_symbol_id = source_symbol_id;
+        }
+
+        if num_splits > 0 {
+            buffer.start_bit_decoding(false)?;
+            for split in &mut self.topology_splits {
+                split.source_edge = buffer.decode_least_significant_bits32(1)?;
+            }
+            buffer.end_bit_decoding();
+        }
+
+        Ok(())
+    }
+
+    fn is_topology_split(&mut self, encoder_symbol_id: u32) -> Option<Option<(u32, u32)>> {
+        let split = *self.topology_splits.last()?;
+        if split.source_symbol_id > encoder_symbol_id {
+            return Some(None);
+        }
+        if split.source_symbol_id != encoder_symbol_id {
+            return None;
+        }
+        self.topology_splits.pop();
+
+        Some(Some((split.source_edge, split.split_symbol_id)))
+    }
+
+    fn corrupted() -> DecodeError {
+        DecodeError::new("draco: corrupted edgebreaker connectivity")
+    }
+
+    fn decode_connectivity(
+        &mut self,
+        traversal: &mut TraversalDecoder,
+        num_symbols: u32,
+        remove_invalid_vertices: bool,
+    ) -> Result<u32, DecodeError> {
+        let mut active_corners: Vec<u32> = vec![];
+        let mut split_active_corners: HashMap<u32, u32> = HashMap::new();
+        let mut invalid_vertices = vec![];
+        let max_num_vertices = self.is_vert_hole.len() as u32;
+        let mut num_faces = 0u32;
+
+        for symbol_id in 0..num_symbols {
+            let corner = 3 * num_faces;
+            num_faces += 1;
+            let mut check_topology_split = false;
+            let table = &mut self.corner_table;
+
+            match traversal.decode_symbol()? {
+                TOPOLOGY_C => {
+                    let corner_a = *active_corners.last().ok_or_else(Edgebreaker::corrupted)?;
+                    let vertex_x = table.vertex(next(corner_a));
+                    let corner_b = next(table.left_most_corner(vertex_x));
+                    if corner_a == corner_b
+                        || table.opposite(corner_a) != INVALID
+                        || table.opposite(corner_b) != INVALID
+                    {
+                        return Err(Edgebreaker::corrupted());
+                    }
+
+                    table.set_opposite_corners(corner_a, corner + 1);
+                    table.set_opposite_corners(corner_b, corner + 2);
+
+                    let vert_a_prev = table.vertex(previous(corner_a));
+                    let vert_b_next = table.vertex(next(corner_b));
+                    if vertex_x == vert_a_prev || vertex_x == vert_b_next {
+                        return Err(Edgebreaker::corrupted());
+                    }
+                    table.map_corner_to_vertex(corner, vertex_x);
+                    table.map_corner_to_vertex(corner + 1, vert_b_next);
+                    table.map_corner_to_vertex(corner + 2, vert_a_prev);
+                    table.set_left_most_corner(vert_a_prev, corner + 2);
+                    self.is_vert_hole[vertex_x as usize] = false;
+                    *active_corners.last_mut().unwrap() = corner;
+                }
+                symbol @ (TOPOLOGY_R | TOPOLOGY_L) => {
+                    let corner_a = *active_corners.last().ok_or_else(Edgebreaker::corrupted)?;
+                    if table.opposite(corner_a) != INVALID {
+                        return Err(Edgebreaker::corrupted());
+                    }
+
+                    let (opp_corner, corner_l, corner_r) = if symbol == TOPOLOGY_R {
+                        (corner + 2, corner + 1, corner)
+                    } else {
+                        (corner + 1, corner, corner + 2)
+                    };
+                    table.set_opposite_corners(opp_corner, corner_a);
+                    let new_vertex = table.add_new_vertex();
+                    if table.num_vertices() > max_num_vertices {
+                        return Err(Edgebreaker::corrupted());
+                    }
+                    table.map_corner_to_vertex(opp_corner, new_vertex);
+                    table.set_left_most_corner(new_vertex, opp_corner);
+
+                    let vertex_r = table.vertex(previous(corner_a));
+                    table.map_corner_to_vertex(corner_r, vertex_r);
+                    table.set_left_most_corner(vertex_r, corner_r);
+                    let vertex_l = table.vertex(next(corner_a));
+                    table.map_corner_to_vertex(corner_l, vertex_l);
+                    *active_corners.last_mut().unwrap() = corner;
+                    check_topology_split = true;
+                }
+                TOPOLOGY_S => {
+                    let corner_b = active_corners.pop().ok_or_else(Edgebreaker::corrupted)?;
+                    if let Some(split_corner) = split_active_corners.get(&symbol_id) {
+                        active_corners.push(*split_corner);
+                    }
+                    let corner_a = *active_corners.last().ok_or_else(Edgebreaker::corrupted)?;
+                    if corner_a == corner_b
+                        || table.opposite(corner_a) != INVALID
+                        || table.opposite(corner_b) != INVALID
+                    {
+                        return Err(Edgebreaker::corrupted());
+                    }
+
+                    table.set_opposite_corners(corner_a, corner + 2);
+                    table.set_opposite_corners(corner_b, corner + 1);
+
+                    let vertex_p = table.vertex(previous(corner_a));
+                    table.map_corner_to_vertex(corner, vertex_p);
+                    table.map_corner_to_vertex(corner + 1, table.vertex(next(corner_a)));
+                    let vert_b_prev = table.vertex(previous(corner_b));
+                    table.map_corner_to_vertex(corner + 2, vert_b_prev);
+                    table.set_left_most_corner(vert_b_prev, corner + 2);
+
+                    let mut corner_n = next(corner_b);
+                    let vertex_n = table.vertex(corner_n);
+                    table.set_left_most_corner(vertex_p, table.left_most_corner(vertex_n));
+
+                    let first_corner = corner_n;
+                    while corner_n != INVALID {
+                        table.map_corner_to_vertex(corner_n, vertex_p);
+                        corner_n = table.swing_left(corner_n);
+                        if corner_n == first_corner {
+                            return Err(Edgebreaker::corrupted());
+                        }
+                    }
+                    table.make_vertex_isolated(vertex_n);
+                    if remove_invalid_vertices {
+                        invalid_vertices.push(vertex_n);
+                    }
+                    *active_corners.last_mut().unwrap() = corner;
+                }
+                TOPOLOGY_E => {
+                    let first_vertex = table.add_new_vertex();
+                    table.map_corner_to_vertex(corner, first_vertex);
+                    let second_vertex = table.add_new_vertex();
+                    table.map_corner_to_vertex(corner + 1, second_vertex);
+                    let third_vertex = table.add_new_vertex();
+                    table.map_corner_to_vertex(corner + 2, third_vertex);
+                    if table.num_vertices() > max_num_vertices {
+                        return Err(Edgebreaker::corrupted());
+                    }
+
+                    table.set_left_most_corner(first_vertex, corner);
+                    table.set_left_most_corner(second_vertex, corner + 1);
+                    table.set_left_most_corner(third_vertex, corner + 2);
+                    active_corners.push(corner);
+                    check_topology_split = true;
+                }
+                _ => return Err(Edgebreaker::corrupted()),
+            }
+
+            if check_topology_split {
+                let encoder_symbol_id = num_symbols - symbol_id - 1;
+                while let Some(split) = self.is_topology_split(encoder_symbol_id) {
+                    let (split_edge, encoder_split_symbol_id) =
+                        split.ok_or_else(Edgebreaker::corrupted)?;
+                    let act_top_corner = *active_corners.last().unwrap();
+                    let new_active_corner = if split_edge == RIGHT_FACE_EDGE {
+                        next(act_top_corner)
+                    } else {
+                        previous(act_top_corner)
+                    };
+                    let decoder_split_symbol_id = num_symbols
+                        .checked_sub(encoder_split_symbol_id + 1)
+                        .ok_or_else(Edgebreaker::corrupted)?;
+                    split_active_corners.insert(decoder_split_symbol_id, new_active_corner);
+                }
+            }
+        }
+
+        let table = &mut self.corner_table;
+        if table.num_vertices() > max_num_vertices {
+            return Err(Edgebreaker::corrupted());
+        }
+
+        while let Some(corner) = active_corners.pop() {
+            let interior_face = traversal.start_face_decoder.decode_next_bit();
+            if !interior_face {
+                continue;
+            }
+            if num_faces >= table.num_faces() {
+                return Err(Edgebreaker::corrupted());
+            }
+
+            let vert_n = table.vertex(next(corner));
+            let corner_b = next(table.left_most_corner(vert_n));
+            let vert_x = table.vertex(next(corner_b));
+            let corner_c = next(table.left_most_corner(vert_x));
+            if corner == corner_b
+                || corner == corner_c
+                || corner_b == corner_c
+                || table.opposite(corner) != INVALID
+                || table.opposite(corner_b) != INVALID
+                || table.opposite(corner_c) != INVALID
+            {
+                return Err(Edgebreaker::corrupted());
+            }
+            let vert_p = table.vertex(next(corner_c));
+
+            let new_corner = 3 * num_faces;
+            num_faces += 1;
+            table.set_opposite_corners(new_corner, corner);
+            table.set_opposite_corners(new_corner + 1, corner_b);
+            table.set_opposite_corners(new_corner + 2, corner_c);
+            table.map_corner_to_vertex(new_corner, vert_x);
+            table.map_corner_to_vertex(new_corner + 1, vert_p);
+            table.map_corner_to_vertex(new_corner + 2, vert_n);
+            for vertex in [vert_x, vert_p, vert_n].iter() {
+                self.is_vert_hole[*vertex as usize] = false;
+            }
+        }
+        if num_faces != table.num_faces() {
+            return Err(Edgebreaker::corrupted());
+        }
+
+        let mut num_vertices = table.num_vertices();
+        for invalid_vertex in invalid_vertices {
+            let mut src_vertex = num_vertices - 1;
+            while table.left_most_corner(src_vertex) == INVALID {
+                num_vertices -= 1;
+                src_vertex = num_vertices - 1;
+            }
+            if src_vertex < invalid_vertex {
+                continue;
+            }
+
+            for corner in vertex_corners(table, table.left_most_corner(src_vertex)) {
+                if table.vertex(corner) != src_vertex {
+                    return Err(Edgebreaker::corrupted());
+                }
+                table.map_corner_to_vertex(corner, invalid_vertex);
+            }
+            table.set_left_most_corner(invalid_vertex, table.left_most_corner(src_vertex));
+            table.make_vertex_isolated(src_vertex);
+            self.is_vert_hole[invalid_vertex as usize] = self.is_vert_hole[src_vertex as usize];
+            self.is_vert_hole[src_vertex as usize] = false;
+            num_vertices -= 1;
+        }
+
+        Ok(num_vertices)
+    }
+
+    fn decode_attribute_seams(
+        &self,
+        traversal: &mut TraversalDecoder,
+        corner: u32,
+        seam_corners: &mut [Vec<u32>],
+    ) {
+        let table = &self.corner_table;
+        let face = corner / 3;
+        for corner in [corner, next(corner), previous(corner)].iter() {
+            let opposite = table.opposite(*corner);
+            if opposite == INVALID {
+                for seams in seam_corners.iter_mut() {
+                    seams.push(*corner);
+                }
+                continue;
+            }
+            if opposite / 3 < face {
+                continue;
+            }
+            for (seams, decoder) in seam_corners.iter_mut().zip(&mut traversal.seam_decoders) {
+                if decoder.decode_next_bit() {
+                    seams.push(*corner);
+                }
+            }
+        }
+    }
+
+    fn assign_points_to_corners(
+        &self,
+        num_vertices: u32,
+    ) -> Result<(Vec<[u32; 3]>, u32), DecodeError> {
+        let table = &self.corner_table;
+        let face_corners = |corner_to_point: &dyn Fn(u32) -> u32| {
+            (0..table.num_faces())
+                .map(|face| {
+                    let corner = 3 * face;
+                    [
+                        corner_to_point(corner),
+                        corner_to_point(corner + 1),
+                        corner_to_point(corner + 2),
+                    ]
+                })
+                .collect::<Vec<_>>()
+        };
+
+        if self.attribute_data.is_empty() {
+            return Ok((face_corners(&|corner| table.vertex(corner)), num_vertices));
+        }
+
+        let views: Vec<_> = self
+            .attribute_data
+            .iter()
+            .map(|data| data.connectivity.view(table))
+            .collect();
+        let mut point_to_corner: Vec<u32> = vec![];
+        let mut corner_to_point = vec![0u32; table.num_corners() as usize];
+
+        for vertex in 0..table.num_vertices() {
+            let corner = table.left_most_corner(vertex);
+            if corner == INVALID {
+                continue;
+            }
+
+            let mut first_corner = corner;
+            if !self.is_vert_hole[vertex as usize] {
+                'attributes: for (data, view) in self.attribute_data.iter().zip(&views) {
+                    if !data.connectivity.is_corner_on_seam(table, corner) {
+                        continue;
+                    }
+                    let vertex_id = view.vertex(corner);
+                    let mut act_corner = table.swing_right(corner);
+                    while act_corner != corner {
+                        if act_corner == INVALID {
+                            return Err(Edgebreaker::corrupted());
+                        }
+                        if view.vertex(act_corner) != vertex_id {
+                            first_corner = act_corner;
+                            break 'attributes;
+                        }
+                        act_corner = table.swing_right(act_corner);
+                    }
+                }
+            }
+
+            corner_to_point[first_corner as usize] = point_to_corner.len() as u32;
+            point_to_corner.push(first_corner);
+            let mut prev_corner = first_corner;
+            let mut corner = table.swing_right(first_corner);
+            while corner != INVALID && corner != first_corner {
+                let seam = views
+                    .iter()
+                    .any(|view| view.vertex(corner) != view.vertex(prev_corner));
+                corner_to_point[corner as usize] = if seam {
+                    point_to_corner.push(corner);
+                    point_to_corner.len() as u32 - 1
+                } else {
+                    corner_to_point[prev_corner as usize]
+                };
+                prev_corner = corner;
+                corner = table.swing_right(corner);
+            }
+        }
+
+        Ok((
+            face_corners(&|corner| corner_to_point[corner as usize]),
+            point_to_corner.len() as u32,
+        ))
+    }
+
+    /// Attribute data that belongs to the given attributes decoder, if any.
+    pub(super) fn attribute_data_for_decoder(&self, decoder_id: usize) -> Option<usize> {
+        self.attribute_data
+            .iter()
+            .position(|data| data.decoder_id == Some(decoder_id))
+    }
+}
+
+/// Corners around the vertex of `start`, first swinging left and then right from the
+/// start corner once a boundary is reached (`VertexCornersIterator`).
+pub(super) fn vertex_corners<T: Table + ?Sized>(table: &T, start: u32) -> Vec<u32> {
+    let mut corners = vec![];
+    let mut corner = start;
+    let mut left_traversal = true;
+
+    while corner != INVALID {
+        corners.push(corner);
+        if left_traversal {
+            corner = table.swing_left(corner);
+            if corner == INVALID {
+                corner = table.swing_right(start);
+                left_traversal = false;
+            } else if corner == start {
+                corner = INVALID;
+            }
+        } else {
+            corner = table.swing_right(corner);
+        }
+    }
+
+    corners
+}
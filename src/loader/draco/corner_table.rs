@@ -0,0 +1,254 @@
+pub(super) const INVALID: u32 = u32::MAX;
+
+pub(super) fn next(corner: u32) -> u32 {
+    if corner == INVALID {
+        INVALID
+    } else if corner % 3 == 2 {
+        corner - 2
+    } else {
+        corner + 1
+    }
+}
+
+pub(super) fn previous(corner: u32) -> u32 {
+    if corner == INVALID {
+        INVALID
+    } else if corner.is_multiple_of(3) {
+        corner + 2
+    } else {
+        corner - 1
+    }
+}
+
+/// Connectivity queries shared by the position corner table and the per attribute
+/// corner tables that split vertices along attribute seams.
+pub(super) trait Table {
+    fn num_faces(&self) -> u32;
+    fn num_vertices(&self) -> u32;
+    fn vertex(&self, corner: u32) -> u32;
+    fn opposite(&self, corner: u32) -> u32;
+    fn left_most_corner(&self, vertex: u32) -> u32;
+
+    fn swing_left(&self, corner: u32) -> u32 {
+        next(self.opposite(next(corner)))
+    }
+
+    fn swing_right(&self, corner: u32) -> u32 {
+        previous(self.opposite(previous(corner)))
+    }
+
+    fn left_corner(&self, corner: u32) -> u32 {
+        self.opposite(previous(corner))
+    }
+
+    fn right_corner(&self, corner: u32) -> u32 {
+        self.opposite(next(corner))
+    }
+
+    fn is_on_boundary(&self, vertex: u32) -> bool {
+        let corner = self.left_most_corner(vertex);
+        corner == INVALID || self.swing_left(corner) == INVALID
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct CornerTable {
+    corner_to_vertex: Vec<u32>,
+    opposite_corners: Vec<u32>,
+    vertex_corners: Vec<u32>,
+}
+
+impl CornerTable {
+    pub(super) fn new(num_faces: u32) -> CornerTable {
+        let num_corners = num_faces as usize * 3;
+        CornerTable {
+            corner_to_vertex: vec![INVALID; num_corners],
+            opposite_corners: vec![INVALID; num_corners],
+            vertex_corners: vec![],
+        }
+    }
+
+    pub(super) fn num_corners(&self) -> u32 {
+        self.corner_to_vertex.len() as u32
+    }
+
+    pub(super) fn add_new_vertex(&mut self) -> u32 {
+        self.vertex_corners.push(INVALID);
+        self.vertex_corners.len() as u32 - 1
+    }
+
+    pub(super) fn map_corner_to_vertex(&mut self, corner: u32, vertex: u32) {
+        self.corner_to_vertex[corner as usize] = vertex;
+    }
+
+    pub(super) fn set_left_most_corner(&mut self, vertex: u32, corner: u32) {
+        if vertex != INVALID {
+            self.vertex_corners[vertex as usize] = corner;
+        }
+    }
+
+    pub(super) fn set_opposite_corners(&mut self, a: u32, b: u32) {
+        self.opposite_corners[a as usize] = b;
+        self.opposite_corners[b as usize] = a;
+    }
+
+    pub(super) fn make_vertex_isolated(&mut self, vertex: u32) {
+        self.vertex_corners[vertex as usize] = INVALID;
+    }
+}
+
+impl Table for CornerTable {
+    fn num_faces(&self) -> u32 {
+        self.corner_to_vertex.len() as u32 / 3
+    }
+
+    fn num_vertices(&self) -> u32 {
+        self.vertex_corners.len() as u32
+    }
+
+    fn vertex(&self, corner: u32) -> u32 {
+        if corner == INVALID {
+            return INVALID;
+        }
+        self.corner_to_vertex[corner as usize]
+    }
+
+    fn opposite(&self, corner: u32) -> u32 {
+        if corner == INVALID {
+            return INVALID;
+        }
+        self.opposite_corners[corner as usize]
+    }
+
+    fn left_most_corner(&self, vertex: u32) -> u32 {
+        if vertex == INVALID {
+            return INVALID;
+        }
+        self.vertex_corners[vertex as usize]
+    }
+}
+
+/// Seam information of one attribute on top of the position connectivity
+/// (`MeshAttributeCornerTable` in the reference decoder).
+#[derive(Debug, Default)]
+pub(super) struct AttributeConnectivity {
+    is_edge_on_seam: Vec<bool>,
+    is_vertex_on_seam: Vec<bool>,
+    corner_to_vertex: Vec<u32>,
+    vertex_to_left_most_corner: Vec<u32>,
+}
+
+impl AttributeConnectivity {
+    pub(super) fn new(table: &CornerTable) -> AttributeConnectivity {
+        AttributeConnectivity {
+            is_edge_on_seam: vec![false; table.num_corners() as usize],
+            is_vertex_on_seam: vec![false; table.num_vertices() as usize],
+            corner_to_vertex: vec![INVALID; table.num_corners() as usize],
+            vertex_to_left_most_corner: vec![],
+        }
+    }
+
+    pub(super) fn add_seam_edge(&mut self, table: &CornerTable, corner: u32) {
+        self.is_edge_on_seam[corner as usize] = true;
+        self.is_vertex_on_seam[table.vertex(next(corner)) as usize] = true;
+        self.is_vertex_on_seam[table.vertex(previous(corner)) as usize] = true;
+
+        let opposite = table.opposite(corner);
+        if opposite != INVALID {
+            self.is_edge_on_seam[opposite as usize] = true;
+            self.is_vertex_on_seam[table.vertex(next(opposite)) as usize] = true;
+            self.is_vertex_on_seam[table.vertex(previous(opposite)) as usize] = true;
+        }
+    }
+
+    pub(super) fn is_corner_on_seam(&self, table: &CornerTable, corner: u32) -> bool {
+        self.is_vertex_on_seam[table.vertex(corner) as usize]
+    }
+
+    pub(super) fn recompute_vertices(&mut self, table: &CornerTable) -> bool {
+        self.vertex_to_left_most_corner.clear();
+        let mut num_new_vertices = 0u32;
+
+        for vertex in 0..table.num_vertices() {
+            let corner = table.left_most_corner(vertex);
+            if corner == INVALID {
+                continue;
+            }
+
+            let mut first_vertex = num_new_vertices;
+            num_new_vertices += 1;
+            let mut first_corner = corner;
+
+            if self.is_vertex_on_seam[vertex as usize] {
+                let view = self.view(table);
+                let mut act_corner = view.swing_left(first_corner);
+                while act_corner != INVALID {
+                    first_corner = act_corner;
+                    act_corner = view.swing_left(act_corner);
+                    if act_corner == corner {
+                        return false;
+                    }
+                }
+            }
+
+            self.corner_to_vertex[first_corner as usize] = first_vertex;
+            self.vertex_to_left_most_corner.push(first_corner);
+
+            let mut act_corner = table.swing_right(first_corner);
+            while act_corner != INVALID && act_corner != first_corner {
+                if self.is_edge_on_seam[next(act_corner) as usize] {
+                    first_vertex = num_new_vertices;
+                    num_new_vertices += 1;
+                    self.vertex_to_left_most_corner.push(act_corner);
+                }
+                self.corner_to_vertex[act_corner as usize] = first_vertex;
+                act_corner = table.swing_right(act_corner);
+            }
+        }
+
+        true
+    }
+
+    pub(super) fn view<'a>(&'a self, table: &'a CornerTable) -> AttributeTable<'a> {
+        AttributeTable {
+            table,
+            connectivity: self,
+        }
+    }
+}
+
+pub(super) struct AttributeTable<'a> {
+    table: &'a CornerTable,
+    connectivity: &'a AttributeConnectivity,
+}
+
+impl<'a> Table for AttributeTable<'a> {
+    fn num_faces(&self) -> u32 {
+        self.table.num_faces()
+    }
+
+    fn num_vertices(&self) -> u32 {
+        self.connectivity.vertex_to_left_most_corner.len() as u32
+    }
+
+    fn vertex(&self, corner: u32) -> u32 {
+        if corner == INVALID {
+            return INVALID;
+        }
+        self.connectivity.corner_to_vertex[corner as usize]
+    }
+
+    fn opposite(&self, corner: u32) -> u32 {
+        if corner == INVALID || self.connectivity.is_edge_on_seam[corner as usize] {
+            return INVALID;
+        }
+        self.table.opposite(corner)
+    }
+
+    fn left_most_corner(&self, vertex: u32) -> u32 {
+        if vertex == INVALID {
+            return INVALID;
+        }
+        self.connectivity.vertex_to_left_most_corner[vertex as usize]
+    }
+}
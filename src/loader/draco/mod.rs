@@ -0,0 +1,133 @@
+//! Pure Rust decoder for Draco compressed meshes as used by `KHR_draco_mesh_compression`.
+//!
+//! Only the parts of the format the glTF extension relies on are implemented: triangle
+//! meshes of bitstream version 2.2 with sequential or standard edgebreaker
+//! connectivity.
+
+mod attributes;
+mod buffer;
+mod connectivity;
+mod corner_table;
+mod octahedron;
+mod prediction;
+mod rans;
+mod traversal;
+
+#[cfg(test)]
+mod tests;
+
+use crate::loader::draco::buffer::DecoderBuffer;
+use crate::loader::draco::connectivity::{decode_sequential, Edgebreaker};
+use crate::loader::error::DecodeError;
+
+pub(crate) use crate::loader::draco::attributes::{DataType, DracoAttribute};
+
+const DRACO_MAGIC: &[u8] = b"DRACO";
+const SUPPORTED_VERSION: (u8, u8) = (2, 2);
+const ENCODER_TRIANGULAR_MESH: u8 = 1;
+const METHOD_SEQUENTIAL: u8 = 0;
+const METHOD_EDGEBREAKER: u8 = 1;
+const FLAG_METADATA: u16 = 0x8000;
+
+#[derive(Debug)]
+pub(crate) struct DracoMesh {
+    pub(crate) faces: Vec<[u32; 3]>,
+    pub(crate) num_points: u32,
+    pub(crate) attributes: Vec<DracoAttribute>,
+}
+
+impl DracoMesh {
+    pub(crate) fn attribute(&self, unique_id: u32) -> Option<&DracoAttribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.unique_id == unique_id)
+    }
+}
+
+pub(crate) fn decode(data: &[u8]) -> Result<DracoMesh, DecodeError> {
+    let mut buffer = DecoderBuffer::new(data);
+
+    if buffer.decode_bytes(DRACO_MAGIC.len())? != DRACO_MAGIC {
+        return Err(DecodeError::new("draco: invalid header"));
+    }
+    let version = (buffer.decode_u8()?, buffer.decode_u8()?);
+    if version != SUPPORTED_VERSION {
+        return Err(DecodeError::new(format!(
+            "draco: bitstream version {}.{} is not supported",
+            version.0, version.1
+        )));
+    }
+    if buffer.decode_u8()? != ENCODER_TRIANGULAR_MESH {
+        return Err(DecodeError::new(
+            "draco: only triangle meshes are supported",
+        ));
+    }
+    let method = buffer.decode_u8()?;
+    let flags = buffer.decode_u16()?;
+    if flags & FLAG_METADATA != 0 {
+        skip_geometry_metadata(&mut buffer)?;
+    }
+
+    match method {
+        METHOD_SEQUENTIAL => {
+            let (faces, num_points) = decode_sequential(&mut buffer)?;
+            let attributes = attributes::decode_attributes(&mut buffer, None, &faces, num_points)?;
+            Ok(DracoMesh {
+                faces,
+                num_points,
+                attributes,
+            })
+        }
+        METHOD_EDGEBREAKER => {
+            let (mut edgebreaker, faces, num_points) = Edgebreaker::decode(&mut buffer)?;
+            let attributes = attributes::decode_attributes(
+                &mut buffer,
+                Some(&mut edgebreaker),
+                &faces,
+                num_points,
+            )?;
+            Ok(DracoMesh {
+                faces,
+                num_points,
+                attributes,
+            })
+        }
+        _ => Err(DecodeError::new(format!(
+            "draco: unknown encoding method {}",
+            method
+        ))),
+    }
+}
+
+fn skip_geometry_metadata(buffer: &mut DecoderBuffer) -> Result<(), DecodeError> {
+    let num_attribute_metadata = buffer.decode_varint_u32()?;
+    for _ in 0..num_attribute_metadata {
+        buffer.decode_varint_u32()?;
+        skip_metadata(buffer, 0)?;
+    }
+
+    skip_metadata(buffer, 0)
+}
+
+fn skip_metadata(buffer: &mut DecoderBuffer, depth: usize) -> Result<(), DecodeError> {
+    if depth > 32 {
+        return Err(DecodeError::new("draco: metadata is nested too deeply"));
+    }
+
+    let num_entries = buffer.decode_varint_u32()?;
+    for _ in 0..num_entries {
+        let name_length = buffer.decode_u8()? as usize;
+        buffer.advance(name_length)?;
+        let data_length = buffer.decode_varint_u32()? as usize;
+        buffer.advance(data_length)?;
+    }
+
+    let num_sub_metadata = buffer.decode_varint_u32()?;
+    for _ in 0..num_sub_metadata {
+        let name_length = buffer.decode_u8()? as usize;
+        buffer.advance(name_length)?;
+        skip_metadata(buffer, depth + 1)?;
+    }
+
+    Ok(())
+}
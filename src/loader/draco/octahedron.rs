@@ -0,0 +1,142 @@
+use crate::loader::error::DecodeError;
+
+/// Integer octahedral normal encoding helpers (`OctahedronToolBox`).
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct OctahedronToolBox {
+    pub(super) max_quantized_value: i32,
+    max_value: i32,
+    pub(super) center_value: i32,
+}
+
+impl OctahedronToolBox {
+    pub(super) fn new(quantization_bits: u32) -> Result<OctahedronToolBox, DecodeError> {
+        if !(2..=30).contains(&quantization_bits) {
+            return Err(DecodeError::new("draco: invalid normal quantization bits"));
+        }
+        let max_quantized_value = (1i32 << quantization_bits) - 1;
+        let max_value = max_quantized_value - 1;
+
+        Ok(OctahedronToolBox {
+            max_quantized_value,
+            max_value,
+            center_value: max_value / 2,
+        })
+    }
+
+    pub(super) fn is_in_diamond(&self, s: i32, t: i32) -> bool {
+        s.unsigned_abs() + t.unsigned_abs() <= self.center_value as u32
+    }
+
+    pub(super) fn invert_diamond(&self, s: &mut i32, t: &mut i32) {
+        let (sign_s, sign_t) = if *s >= 0 && *t >= 0 {
+            (1, 1)
+        } else if *s <= 0 && *t <= 0 {
+            (-1, -1)
+        } else {
+            (if *s > 0 { 1 } else { -1 }, if *t > 0 { 1 } else { -1 })
+        };
+
+        let corner_s = (sign_s * self.center_value) as u32;
+        let corner_t = (sign_t * self.center_value) as u32;
+        let mut us = (*s as u32).wrapping_add(*s as u32).wrapping_sub(corner_s);
+        let mut ut = (*t as u32).wrapping_add(*t as u32).wrapping_sub(corner_t);
+        if sign_s * sign_t >= 0 {
+            let temp = us;
+            us = ut.wrapping_neg();
+            ut = temp.wrapping_neg();
+        } else {
+            std::mem::swap(&mut us, &mut ut);
+        }
+        us = us.wrapping_add(corner_s);
+        ut = ut.wrapping_add(corner_t);
+
+        *s = (us as i32) / 2;
+        *t = (ut as i32) / 2;
+    }
+
+    pub(super) fn mod_max(&self, x: i32) -> i32 {
+        if x > self.center_value {
+            x - self.max_quantized_value
+        } else if x < -self.center_value {
+            x + self.max_quantized_value
+        } else {
+            x
+        }
+    }
+
+    pub(super) fn canonicalize_integer_vector(&self, vector: &mut [i32; 3]) {
+        let abs_sum: i64 = vector.iter().map(|value| i64::from(*value).abs()).sum();
+        if abs_sum == 0 {
+            vector[0] = self.center_value;
+            return;
+        }
+
+        let center = i64::from(self.center_value);
+        vector[0] = (i64::from(vector[0]) * center / abs_sum) as i32;
+        vector[1] = (i64::from(vector[1]) * center / abs_sum) as i32;
+        let z = self.center_value - vector[0].abs() - vector[1].abs();
+        vector[2] = if vector[2] >= 0 { z } else { -z };
+    }
+
+    pub(super) fn integer_vector_to_quantized_octahedral_coords(
+        &self,
+        vector: &[i32; 3],
+    ) -> [i32; 2] {
+        let (s, t) = if vector[0] >= 0 {
+            (vector[1] + self.center_value, vector[2] + self.center_value)
+        } else {
+            (
+                if vector[1] < 0 {
+                    vector[2].abs()
+                } else {
+                    self.max_value - vector[2].abs()
+                },
+                if vector[2] < 0 {
+                    vector[1].abs()
+                } else {
+                    self.max_value - vector[1].abs()
+                },
+            )
+        };
+
+        self.canonicalize_octahedral_coords(s, t)
+    }
+
+    fn canonicalize_octahedral_coords(&self, mut s: i32, mut t: i32) -> [i32; 2] {
+        let max = self.max_value;
+        let center = self.center_value;
+        if (s == 0 && (t == 0 || t == max)) || (s == max && t == 0) {
+            s = max;
+            t = max;
+        } else if s == 0 && t > center {
+            t = center - (t - center);
+        } else if s == max && t < center {
+            t = center + (center - t);
+        } else if t == max && s < center {
+            s = center + (center - s);
+        } else if t == 0 && s > center {
+            s = center - (s - center);
+        }
+
+        [s, t]
+    }
+
+    pub(super) fn quantized_octahedral_coords_to_unit_vector(&self, s: i32, t: i32) -> [f32; 3] {
+        let scale = 1.0 / self.max_value as f32;
+        let y = s as f32 * scale * 2.0 - 1.0;
+        let z = t as f32 * scale * 2.0 - 1.0;
+        let x = 1.0 - y.abs() - z.abs();
+
+        let x_offset = (-x).max(0.0);
+        let y = if y < 0.0 { y + x_offset } else { y - x_offset };
+        let z = if z < 0.0 { z + x_offset } else { z - x_offset };
+
+        let norm_squared = x * x + y * y + z * z;
+        if norm_squared < 1e-6 {
+            return [0.0, 0.0, 0.0];
+        }
+        let d = 1.0 / norm_squared.sqrt();
+
+        [x * d, y * d, z * d]
+    }
+}
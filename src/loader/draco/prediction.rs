@@ -0,0 +1,780 @@
+use crate::loader::draco::buffer::DecoderBuffer;
+use crate::loader::draco::connectivity::{vertex_corners, EncodingData};
+use crate::loader::draco::corner_table::{next, previous, Table, INVALID};
+use crate::loader::draco::octahedron::OctahedronToolBox;
+use crate::loader::draco::rans::RAnsBitDecoder;
+use crate::loader::error::DecodeError;
+
+const MAX_NUM_PARALLELOGRAMS: usize = 4;
+
+const PREDICTION_NONE: i8 = -2;
+const PREDICTION_DIFFERENCE: i8 = 0;
+const MESH_PREDICTION_PARALLELOGRAM: i8 = 1;
+const MESH_PREDICTION_CONSTRAINED_MULTI_PARALLELOGRAM: i8 = 4;
+const MESH_PREDICTION_TEX_COORDS_PORTABLE: i8 = 5;
+const MESH_PREDICTION_GEOMETRIC_NORMAL: i8 = 6;
+
+const PREDICTION_TRANSFORM_WRAP: i8 = 1;
+const PREDICTION_TRANSFORM_NORMAL_OCTAHEDRON: i8 = 2;
+const PREDICTION_TRANSFORM_NORMAL_OCTAHEDRON_CANONICALIZED: i8 = 3;
+
+#[derive(Debug)]
+enum Transform {
+    Wrap { min: i32, max: i32, max_dif: i32 },
+    Octahedron(OctahedronToolBox),
+    OctahedronCanonicalized(OctahedronToolBox),
+}
+
+impl Transform {
+    fn decode_transform_data(&mut self, buffer: &mut DecoderBuffer) -> Result<(), DecodeError> {
+        match self {
+            Transform::Wrap { min, max, max_dif } => {
+                *min = buffer.decode_i32()?;
+                *max = buffer.decode_i32()?;
+                let dif = i64::from(*max) - i64::from(*min);
+                if dif < 0 || dif >= i64::from(i32::MAX) {
+                    return Err(DecodeError::new("draco: invalid wrap transform bounds"));
+                }
+                *max_dif = 1 + dif as i32;
+            }
+            Transform::Octahedron(tool_box) | Transform::OctahedronCanonicalized(tool_box) => {
+                let max_quantized_value = buffer.decode_i32()?;
+                if max_quantized_value <= 0 || max_quantized_value % 2 == 0 {
+                    return Err(DecodeError::new("draco: invalid octahedron transform"));
+                }
+                let bits = 32 - max_quantized_value.leading_zeros();
+                *tool_box = OctahedronToolBox::new(bits)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn are_corrections_positive(&self) -> bool {
+        !matches!(self, Transform::Wrap { .. })
+    }
+
+    fn compute_original_value(&self, predicted: &[i32], corrections: &[i32], out: &mut [i32]) {
+        match self {
+            Transform::Wrap { min, max, max_dif } => {
+                for ((value, predicted), correction) in
+                    out.iter_mut().zip(predicted).zip(corrections)
+                {
+                    let predicted = (*predicted).clamp(*min, *max);
+                    let mut original = predicted.wrapping_add(*correction);
+                    if original > *max {
+                        original = original.wrapping_sub(*max_dif);
+                    } else if original < *min {
+                        original = original.wrapping_add(*max_dif);
+                    }
+                    *value = original;
+                }
+            }
+            Transform::Octahedron(tool_box) => {
+                let center = tool_box.center_value;
+                let mut s = predicted[0] - center;
+                let mut t = predicted[1] - center;
+                let in_diamond = tool_box.is_in_diamond(s, t);
+                if !in_diamond {
+                    tool_box.invert_diamond(&mut s, &mut t);
+                }
+                let mut os = tool_box.mod_max(s.wrapping_add(corrections[0]));
+                let mut ot = tool_box.mod_max(t.wrapping_add(corrections[1]));
+                if !in_diamond {
+                    tool_box.invert_diamond(&mut os, &mut ot);
+                }
+                out[0] = os + center;
+                out[1] = ot + center;
+            }
+            Transform::OctahedronCanonicalized(tool_box) => {
+                let center = tool_box.center_value;
+                let mut s = predicted[0] - center;
+                let mut t = predicted[1] - center;
+                let in_diamond = tool_box.is_in_diamond(s, t);
+                if !in_diamond {
+                    tool_box.invert_diamond(&mut s, &mut t);
+                }
+                let in_bottom_left = (s == 0 && t == 0) || (s < 0 && t <= 0);
+                let rotation_count = rotation_count(s, t);
+                if !in_bottom_left {
+                    let (rs, rt) = rotate_point(s, t, rotation_count);
+                    s = rs;
+                    t = rt;
+                }
+                let mut os = tool_box.mod_max(s.wrapping_add(corrections[0]));
+                let mut ot = tool_box.mod_max(t.wrapping_add(corrections[1]));
+                if !in_bottom_left {
+                    let (rs, rt) = rotate_point(os, ot, (4 - rotation_count) % 4);
+                    os = rs;
+                    ot = rt;
+                }
+                if !in_diamond {
+                    tool_box.invert_diamond(&mut os, &mut ot);
+                }
+                out[0] = os + center;
+                out[1] = ot + center;
+            }
+        }
+    }
+
+    fn tool_box(&self) -> Option<&OctahedronToolBox> {
+        match self {
+            Transform::Octahedron(tool_box) | Transform::OctahedronCanonicalized(tool_box) => {
+                Some(tool_box)
+            }
+            Transform::Wrap { .. } => None,
+        }
+    }
+}
+
+fn rotation_count(s: i32, t: i32) -> i32 {
+    if s == 0 {
+        if t == 0 {
+            0
+        } else if t > 0 {
+            3
+        } else {
+            1
+        }
+    } else if s > 0 {
+        if t >= 0 {
+            2
+        } else {
+            1
+        }
+    } else if t <= 0 {
+        0
+    } else {
+        3
+    }
+}
+
+fn rotate_point(s: i32, t: i32, rotation_count: i32) -> (i32, i32) {
+    match rotation_count {
+        1 => (t, -s),
+        2 => (-s, -t),
+        3 => (-t, s),
+        _ => (s, t),
+    }
+}
+
+fn int_sqrt(number: u64) -> u64 {
+    if number == 0 {
+        return 0;
+    }
+
+    let mut act_number = number;
+    let mut square_root = 1u64;
+    while act_number >= 2 {
+        square_root *= 2;
+        act_number /= 4;
+    }
+    loop {
+        square_root = (square_root + number / square_root) / 2;
+        if square_root.wrapping_mul(square_root) <= number {
+            return square_root;
+        }
+    }
+}
+
+/// Connectivity the mesh prediction schemes work on.
+pub(super) struct MeshData<'a> {
+    pub(super) table: &'a dyn Table,
+    pub(super) encoding_data: &'a EncodingData,
+}
+
+impl<'a> MeshData<'a> {
+    fn data_id(&self, corner: u32) -> i32 {
+        self.encoding_data
+            .vertex_to_encoded_value
+            .get(self.table.vertex(corner) as usize)
+            .copied()
+            .unwrap_or(-1)
+    }
+
+    fn parallelogram(
+        &self,
+        data_id: usize,
+        corner: u32,
+        data: &[i32],
+        num_components: usize,
+        out: &mut [i32],
+    ) -> bool {
+        let opposite = self.table.opposite(corner);
+        if opposite == INVALID {
+            return false;
+        }
+
+        let entries = [
+            self.data_id(opposite),
+            self.data_id(next(opposite)),
+            self.data_id(previous(opposite)),
+        ];
+        if entries
+            .iter()
+            .any(|entry| *entry < 0 || *entry as usize >= data_id)
+        {
+            return false;
+        }
+
+        let [opp, next, prev] = entries;
+        for (c, value) in out.iter_mut().enumerate().take(num_components) {
+            let component = |entry: i32| i64::from(data[entry as usize * num_components + c]);
+            *value = (component(next) + component(prev) - component(opp)) as i32;
+        }
+
+        true
+    }
+}
+
+/// Quantized positions a prediction scheme can use as its parent attribute.
+pub(super) struct PositionData<'a> {
+    pub(super) values: &'a [i32],
+    pub(super) point_to_value: &'a [u32],
+}
+
+impl<'a> PositionData<'a> {
+    fn position(&self, point: u32) -> Result<[i64; 3], DecodeError> {
+        let value = *self
+            .point_to_value
+            .get(point as usize)
+            .ok_or_else(|| DecodeError::new("draco: invalid position point"))?
+            as usize;
+        let position = self
+            .values
+            .get(value * 3..value * 3 + 3)
+            .ok_or_else(|| DecodeError::new("draco: invalid position value"))?;
+
+        Ok([
+            i64::from(position[0]),
+            i64::from(position[1]),
+            i64::from(position[2]),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Method {
+    Difference,
+    Parallelogram,
+    ConstrainedMultiParallelogram,
+    TexCoordsPortable,
+    GeometricNormal,
+}
+
+#[derive(Debug)]
+pub(super) struct PredictionScheme<'a> {
+    method: Method,
+    transform: Transform,
+    crease_edges: [Vec<bool>; MAX_NUM_PARALLELOGRAMS],
+    orientations: Vec<bool>,
+    flip_normal_decoder: Option<RAnsBitDecoder<'a>>,
+}
+
+impl<'a> PredictionScheme<'a> {
+    /// Reads the prediction method and transform of an integer attribute, `None` if the
+    /// values are stored without prediction.
+    pub(super) fn decode_header(
+        buffer: &mut DecoderBuffer<'a>,
+        has_connectivity: bool,
+        is_normal: bool,
+    ) -> Result<Option<PredictionScheme<'a>>, DecodeError> {
+        let method = buffer.decode_i8()?;
+        if method == PREDICTION_NONE {
+            return Ok(None);
+        }
+        let transform = buffer.decode_i8()?;
+
+        let transform = match (transform, is_normal) {
+            (PREDICTION_TRANSFORM_WRAP, false) => Transform::Wrap {
+                min: 0,
+                max: 0,
+                max_dif: 1,
+            },
+            (PREDICTION_TRANSFORM_NORMAL_OCTAHEDRON, true) => {
+                Transform::Octahedron(OctahedronToolBox::default())
+            }
+            (PREDICTION_TRANSFORM_NORMAL_OCTAHEDRON_CANONICALIZED, true) => {
+                Transform::OctahedronCanonicalized(OctahedronToolBox::default())
+            }
+            _ => {
+                return Err(DecodeError::new(format!(
+                    "draco: prediction transform {} is not supported",
+                    transform
+                )))
+            }
+        };
+
+        let method = match method {
+            PREDICTION_DIFFERENCE => Method::Difference,
+            _ if !has_connectivity => Method::Difference,
+            MESH_PREDICTION_PARALLELOGRAM => Method::Parallelogram,
+            MESH_PREDICTION_CONSTRAINED_MULTI_PARALLELOGRAM => {
+                Method::ConstrainedMultiParallelogram
+            }
+            MESH_PREDICTION_TEX_COORDS_PORTABLE => Method::TexCoordsPortable,
+            MESH_PREDICTION_GEOMETRIC_NORMAL if is_normal => Method::GeometricNormal,
+            _ => {
+                return Err(DecodeError::new(format!(
+                    "draco: prediction method {} is not supported",
+                    method
+                )))
+            }
+        };
+
+        Ok(Some(PredictionScheme {
+            method,
+            transform,
+            crease_edges: Default::default(),
+            orientations: vec![],
+            flip_normal_decoder: None,
+        }))
+    }
+
+    pub(super) fn are_corrections_positive(&self) -> bool {
+        self.transform.are_corrections_positive()
+    }
+
+    pub(super) fn needs_positions(&self) -> bool {
+        matches!(
+            self.method,
+            Method::TexCoordsPortable | Method::GeometricNormal
+        )
+    }
+
+    pub(super) fn decode_prediction_data(
+        &mut self,
+        buffer: &mut DecoderBuffer<'a>,
+        num_corners: u32,
+    ) -> Result<(), DecodeError> {
+        match self.method {
+            Method::ConstrainedMultiParallelogram => {
+                for crease_edges in self.crease_edges.iter_mut() {
+                    let num_flags = buffer.decode_varint_u32()?;
+                    if num_flags > num_corners {
+                        return Err(DecodeError::new("draco: invalid number of crease edges"));
+                    }
+                    if num_flags > 0 {
+                        let mut decoder = RAnsBitDecoder::start_decoding(buffer)?;
+                        *crease_edges = (0..num_flags).map(|_| decoder.decode_next_bit()).collect();
+                    }
+                }
+                self.transform.decode_transform_data(buffer)
+            }
+            Method::TexCoordsPortable => {
+                let num_orientations = buffer.decode_i32()?;
+                if num_orientations < 0 || num_orientations as u32 > num_corners {
+                    return Err(DecodeError::new("draco: invalid number of orientations"));
+                }
+                let mut decoder = RAnsBitDecoder::start_decoding(buffer)?;
+                let mut last_orientation = true;
+                self.orientations = (0..num_orientations)
+                    .map(|_| {
+                        if !decoder.decode_next_bit() {
+                            last_orientation = !last_orientation;
+                        }
+                        last_orientation
+                    })
+                    .collect();
+                self.transform.decode_transform_data(buffer)
+            }
+            Method::GeometricNormal => {
+                self.transform.decode_transform_data(buffer)?;
+                self.flip_normal_decoder = Some(RAnsBitDecoder::start_decoding(buffer)?);
+                Ok(())
+            }
+            Method::Difference | Method::Parallelogram => {
+                self.transform.decode_transform_data(buffer)
+            }
+        }
+    }
+
+    /// Reverts the prediction in place, `values` holds the decoded corrections.
+    pub(super) fn compute_original_values(
+        &mut self,
+        values: &mut [i32],
+        num_components: usize,
+        point_ids: &[u32],
+        mesh: Option<&MeshData>,
+        positions: Option<&PositionData>,
+    ) -> Result<(), DecodeError> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        let corrections = values.to_vec();
+        let zero = vec![0i32; num_components];
+        let num_entries = values.len() / num_components;
+
+        let mesh = match (self.method, mesh) {
+            (Method::Difference, _) | (_, None) => {
+                self.transform.compute_original_value(
+                    &zero,
+                    &corrections[..num_components],
+                    values,
+                );
+                for i in 1..num_entries {
+                    let (decoded, rest) = values.split_at_mut(i * num_components);
+                    self.transform.compute_original_value(
+                        &decoded[(i - 1) * num_components..],
+                        &corrections[i * num_components..(i + 1) * num_components],
+                        &mut rest[..num_components],
+                    );
+                }
+                return Ok(());
+            }
+            (_, Some(mesh)) => mesh,
+        };
+
+        if mesh.encoding_data.encoded_value_to_corner.len() < num_entries {
+            return Err(DecodeError::new("draco: missing attribute encoding order"));
+        }
+        let corner_of = |entry: usize| mesh.encoding_data.encoded_value_to_corner[entry];
+
+        match self.method {
+            Method::Parallelogram => {
+                let mut predicted = zero.clone();
+                self.transform.compute_original_value(
+                    &zero,
+                    &corrections[..num_components],
+                    values,
+                );
+                for p in 1..num_entries {
+                    let dst = p * num_components;
+                    let prediction = if mesh.parallelogram(
+                        p,
+                        corner_of(p),
+                        values,
+                        num_components,
+                        &mut predicted,
+                    ) {
+                        predicted.clone()
+                    } else {
+                        values[dst - num_components..dst].to_vec()
+                    };
+                    self.transform.compute_original_value(
+                        &prediction,
+                        &corrections[dst..dst + num_components],
+                        &mut values[dst..dst + num_components],
+                    );
+                }
+            }
+            Method::ConstrainedMultiParallelogram => {
+                self.constrained_multi_parallelogram(values, &corrections, num_components, mesh)?
+            }
+            Method::TexCoordsPortable => {
+                let positions = positions
+                    .ok_or_else(|| DecodeError::new("draco: missing positions for prediction"))?;
+                if num_components != 2 {
+                    return Err(DecodeError::new(
+                        "draco: invalid texture coordinate prediction",
+                    ));
+                }
+                for p in 0..num_entries {
+                    let predicted = self.predict_tex_coord(
+                        corner_of(p),
+                        values,
+                        p,
+                        mesh,
+                        positions,
+                        point_ids,
+                    )?;
+                    self.transform.compute_original_value(
+                        &predicted,
+                        &corrections[p * 2..p * 2 + 2],
+                        &mut values[p * 2..p * 2 + 2],
+                    );
+                }
+            }
+            Method::GeometricNormal => {
+                let positions = positions
+                    .ok_or_else(|| DecodeError::new("draco: missing positions for prediction"))?;
+                let tool_box = *self
+                    .transform
+                    .tool_box()
+                    .ok_or_else(|| DecodeError::new("draco: invalid normal prediction"))?;
+                if num_components != 2 {
+                    return Err(DecodeError::new("draco: invalid normal prediction"));
+                }
+                for p in 0..num_entries {
+                    let mut normal = predict_normal(corner_of(p), mesh, positions, point_ids)?;
+                    tool_box.canonicalize_integer_vector(&mut normal);
+                    let flip = self
+                        .flip_normal_decoder
+                        .as_mut()
+                        .map(|decoder| decoder.decode_next_bit())
+                        .unwrap_or(false);
+                    if flip {
+                        normal = [
+                            normal[0].wrapping_neg(),
+                            normal[1].wrapping_neg(),
+                            normal[2].wrapping_neg(),
+                        ];
+                    }
+                    let predicted = tool_box.integer_vector_to_quantized_octahedral_coords(&normal);
+                    self.transform.compute_original_value(
+                        &predicted,
+                        &corrections[p * 2..p * 2 + 2],
+                        &mut values[p * 2..p * 2 + 2],
+                    );
+                }
+            }
+            Method::Difference => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn constrained_multi_parallelogram(
+        &self,
+        values: &mut [i32],
+        corrections: &[i32],
+        num_components: usize,
+        mesh: &MeshData,
+    ) -> Result<(), DecodeError> {
+        let num_entries = values.len() / num_components;
+        let mut predicted = vec![vec![0i32; num_components]; MAX_NUM_PARALLELOGRAMS];
+        let mut crease_edge_pos = [0usize; MAX_NUM_PARALLELOGRAMS];
+        let table = mesh.table;
+
+        self.transform.compute_original_value(
+            &predicted[0],
+            &corrections[..num_components],
+            values,
+        );
+
+        for p in 1..num_entries {
+            let start_corner = mesh.encoding_data.encoded_value_to_corner[p];
+            let mut corner = start_corner;
+            let mut num_parallelograms = 0;
+            let mut first_pass = true;
+            while corner != INVALID {
+                if mesh.parallelogram(
+                    p,
+                    corner,
+                    values,
+                    num_components,
+                    &mut predicted[num_parallelograms],
+                ) {
+                    num_parallelograms += 1;
+                    if num_parallelograms == MAX_NUM_PARALLELOGRAMS {
+                        break;
+                    }
+                }
+
+                corner = if first_pass {
+                    table.swing_left(corner)
+                } else {
+                    table.swing_right(corner)
+                };
+                if corner == start_corner {
+                    break;
+                }
+                if corner == INVALID && first_pass {
+                    first_pass = false;
+                    corner = table.swing_right(start_corner);
+                }
+            }
+
+            let mut multi_prediction = vec![0i32; num_components];
+            let mut num_used = 0;
+            for prediction in predicted.iter().take(num_parallelograms) {
+                let context = num_parallelograms - 1;
+                let is_crease = *self.crease_edges[context]
+                    .get(crease_edge_pos[context])
+                    .ok_or_else(|| DecodeError::new("draco: missing crease edge flags"))?;
+                crease_edge_pos[context] += 1;
+                if !is_crease {
+                    num_used += 1;
+                    for (sum, value) in multi_prediction.iter_mut().zip(prediction) {
+                        *sum = sum.wrapping_add(*value);
+                    }
+                }
+            }
+
+            let dst = p * num_components;
+            let prediction = if num_used == 0 {
+                values[dst - num_components..dst].to_vec()
+            } else {
+                multi_prediction.iter().map(|sum| sum / num_used).collect()
+            };
+            self.transform.compute_original_value(
+                &prediction,
+                &corrections[dst..dst + num_components],
+                &mut values[dst..dst + num_components],
+            );
+        }
+
+        Ok(())
+    }
+
+    fn predict_tex_coord(
+        &mut self,
+        corner: u32,
+        data: &[i32],
+        data_id: usize,
+        mesh: &MeshData,
+        positions: &PositionData,
+        point_ids: &[u32],
+    ) -> Result<[i32; 2], DecodeError> {
+        let next_data_id = mesh.data_id(next(corner));
+        let prev_data_id = mesh.data_id(previous(corner));
+        let data_id_i = data_id as i32;
+        let uv = |entry: i32| {
+            [
+                i64::from(data[entry as usize * 2]),
+                i64::from(data[entry as usize * 2 + 1]),
+            ]
+        };
+        let position = |entry: i32| -> Result<[i64; 3], DecodeError> {
+            let point = point_ids
+                .get(entry as usize)
+                .ok_or_else(|| DecodeError::new("draco: invalid point id"))?;
+            positions.position(*point)
+        };
+
+        if prev_data_id >= 0
+            && next_data_id >= 0
+            && prev_data_id < data_id_i
+            && next_data_id < data_id_i
+        {
+            let n_uv = uv(next_data_id);
+            let p_uv = uv(prev_data_id);
+            if p_uv == n_uv {
+                return Ok([p_uv[0] as i32, p_uv[1] as i32]);
+            }
+
+            let tip_pos = position(data_id_i)?;
+            let next_pos = position(next_data_id)?;
+            let prev_pos = position(prev_data_id)?;
+            let pn = sub3(prev_pos, next_pos);
+            let pn_norm2_squared = dot3(pn, pn) as u64;
+            if pn_norm2_squared != 0 {
+                let cn = sub3(tip_pos, next_pos);
+                let cn_dot_pn = dot3(pn, cn);
+                let pn_uv = [p_uv[0] - n_uv[0], p_uv[1] - n_uv[1]];
+
+                let n_uv_absmax = n_uv[0].abs().max(n_uv[1].abs());
+                if n_uv_absmax > i64::MAX / pn_norm2_squared as i64 {
+                    return Err(DecodeError::new("draco: texture coordinate overflow"));
+                }
+                let pn_uv_absmax = pn_uv[0].abs().max(pn_uv[1].abs());
+                if cn_dot_pn > i64::MAX / pn_uv_absmax {
+                    return Err(DecodeError::new("draco: texture coordinate overflow"));
+                }
+                let x_uv = [
+                    n_uv[0]
+                        .wrapping_mul(pn_norm2_squared as i64)
+                        .wrapping_add(cn_dot_pn.wrapping_mul(pn_uv[0])),
+                    n_uv[1]
+                        .wrapping_mul(pn_norm2_squared as i64)
+                        .wrapping_add(cn_dot_pn.wrapping_mul(pn_uv[1])),
+                ];
+                let pn_absmax = pn[0].abs().max(pn[1].abs()).max(pn[2].abs());
+                if cn_dot_pn > i64::MAX / pn_absmax {
+                    return Err(DecodeError::new("draco: texture coordinate overflow"));
+                }
+
+                let mut x_pos = [0i64; 3];
+                for (i, value) in x_pos.iter_mut().enumerate() {
+                    *value = next_pos[i] + cn_dot_pn.wrapping_mul(pn[i]) / pn_norm2_squared as i64;
+                }
+                let cx = sub3(tip_pos, x_pos);
+                let cx_norm2_squared = dot3(cx, cx) as u64;
+
+                let norm_squared = int_sqrt(cx_norm2_squared.wrapping_mul(pn_norm2_squared)) as i64;
+                let cx_uv = [
+                    pn_uv[1].wrapping_mul(norm_squared),
+                    (-pn_uv[0]).wrapping_mul(norm_squared),
+                ];
+
+                let orientation = self
+                    .orientations
+                    .pop()
+                    .ok_or_else(|| DecodeError::new("draco: missing texture orientations"))?;
+                let combine = |a: i64, b: i64| {
+                    let value = if orientation {
+                        (a as u64).wrapping_add(b as u64)
+                    } else {
+                        (a as u64).wrapping_sub(b as u64)
+                    };
+                    (value as i64 / pn_norm2_squared as i64) as i32
+                };
+
+                return Ok([combine(x_uv[0], cx_uv[0]), combine(x_uv[1], cx_uv[1])]);
+            }
+        }
+
+        let entry = if next_data_id >= 0 && next_data_id < data_id_i {
+            next_data_id
+        } else if data_id > 0 {
+            data_id_i - 1
+        } else {
+            return Ok([0, 0]);
+        };
+
+        Ok([data[entry as usize * 2], data[entry as usize * 2 + 1]])
+    }
+}
+
+fn sub3(a: [i64; 3], b: [i64; 3]) -> [i64; 3] {
+    [
+        a[0].wrapping_sub(b[0]),
+        a[1].wrapping_sub(b[1]),
+        a[2].wrapping_sub(b[2]),
+    ]
+}
+
+fn dot3(a: [i64; 3], b: [i64; 3]) -> i64 {
+    a[0].wrapping_mul(b[0])
+        .wrapping_add(a[1].wrapping_mul(b[1]))
+        .wrapping_add(a[2].wrapping_mul(b[2]))
+}
+
+fn predict_normal(
+    corner: u32,
+    mesh: &MeshData,
+    positions: &PositionData,
+    point_ids: &[u32],
+) -> Result<[i32; 3], DecodeError> {
+    let position = |corner: u32| -> Result<[i64; 3], DecodeError> {
+        let data_id = mesh.data_id(corner);
+        let point = point_ids
+            .get(data_id as usize)
+            .ok_or_else(|| DecodeError::new("draco: invalid point id"))?;
+        positions.position(*point)
+    };
+
+    let center = position(corner)?;
+    let mut normal = [0i64; 3];
+    for corner in vertex_corners(mesh.table, corner) {
+        let delta_next = sub3(position(next(corner))?, center);
+        let delta_prev = sub3(position(previous(corner))?, center);
+        let cross = [
+            delta_next[1]
+                .wrapping_mul(delta_prev[2])
+                .wrapping_sub(delta_next[2].wrapping_mul(delta_prev[1])),
+            delta_next[2]
+                .wrapping_mul(delta_prev[0])
+                .wrapping_sub(delta_next[0].wrapping_mul(delta_prev[2])),
+            delta_next[0]
+                .wrapping_mul(delta_prev[1])
+                .wrapping_sub(delta_next[1].wrapping_mul(delta_prev[0])),
+        ];
+        for (sum, value) in normal.iter_mut().zip(&cross) {
+            *sum = sum.wrapping_add(*value);
+        }
+    }
+
+    const UPPER_BOUND: i64 = 1 << 29;
+    let abs_sum = normal
+        .iter()
+        .fold(0i64, |sum, value| sum.wrapping_add(value.wrapping_abs()));
+    if abs_sum > UPPER_BOUND {
+        let quotient = abs_sum / UPPER_BOUND;
+        for value in normal.iter_mut() {
+            *value /= quotient;
+        }
+    }
+
+    Ok([normal[0] as i32, normal[1] as i32, normal[2] as i32])
+}
@@ -0,0 +1,279 @@
+use crate::loader::draco::buffer::DecoderBuffer;
+use crate::loader::error::DecodeError;
+
+const ANS_IO_BASE: u32 = 256;
+const ANS_L_BASE: u32 = 4096;
+const ANS_P8_PRECISION: u32 = 256;
+
+const TAGGED_SYMBOLS: u8 = 0;
+const RAW_SYMBOLS: u8 = 1;
+
+fn read_init(
+    data: &[u8],
+    l_base: u32,
+    allow_four_bytes: bool,
+) -> Result<(usize, u32), DecodeError> {
+    let offset = data.len();
+    if offset < 1 {
+        return Err(DecodeError::new("draco: empty ans stream"));
+    }
+
+    let le = |length: usize| {
+        data[offset - length..offset]
+            .iter()
+            .rev()
+            .fold(0u32, |value, byte| (value << 8) | u32::from(*byte))
+    };
+
+    let (buf_offset, state) = match data[offset - 1] >> 6 {
+        0 => (offset - 1, le(1) & 0x3f),
+        1 if offset >= 2 => (offset - 2, le(2) & 0x3fff),
+        2 if offset >= 3 => (offset - 3, le(3) & 0x3f_ffff),
+        3 if allow_four_bytes && offset >= 4 => (offset - 4, le(4) & 0x3fff_ffff),
+        _ => return Err(DecodeError::new("draco: invalid ans stream header")),
+    };
+
+    let state = state + l_base;
+    if u64::from(state) >= u64::from(l_base) * u64::from(ANS_IO_BASE) {
+        return Err(DecodeError::new("draco: invalid ans state"));
+    }
+
+    Ok((buf_offset, state))
+}
+
+/// Binary rANS decoder with a fixed 8 bit probability of zero.
+#[derive(Debug, Default)]
+pub(super) struct RAnsBitDecoder<'a> {
+    data: &'a [u8],
+    buf_offset: usize,
+    state: u32,
+    prob_zero: u8,
+}
+
+impl<'a> RAnsBitDecoder<'a> {
+    pub(super) fn start_decoding(
+        buffer: &mut DecoderBuffer<'a>,
+    ) -> Result<RAnsBitDecoder<'a>, DecodeError> {
+        let prob_zero = buffer.decode_u8()?;
+        let size = buffer.decode_varint_u32()? as usize;
+        let data = buffer.decode_bytes(size)?;
+        let (buf_offset, state) = read_init(data, ANS_L_BASE, false)?;
+
+        Ok(RAnsBitDecoder {
+            data,
+            buf_offset,
+            state,
+            prob_zero,
+        })
+    }
+
+    pub(super) fn decode_next_bit(&mut self) -> bool {
+        let p = ANS_P8_PRECISION - u32::from(self.prob_zero);
+        if self.state < ANS_L_BASE && self.buf_offset > 0 {
+            self.buf_offset -= 1;
+            self.state = self.state * ANS_IO_BASE + u32::from(self.data[self.buf_offset]);
+        }
+
+        let x = self.state;
+        let quot = x / ANS_P8_PRECISION;
+        let rem = x % ANS_P8_PRECISION;
+        let xn = quot * p;
+        let value = rem < p;
+        self.state = if value { xn + rem } else { x - xn - p };
+
+        value
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct RAnsSymbol {
+    prob: u32,
+    cum_prob: u32,
+}
+
+/// Multi-symbol rANS decoder, the probability table precision is derived from the
+/// maximum bit length of the coded symbols like in the reference implementation.
+#[derive(Debug)]
+struct RAnsSymbolDecoder<'a> {
+    precision: u32,
+    probability_table: Vec<RAnsSymbol>,
+    lut: Vec<u32>,
+    data: &'a [u8],
+    buf_offset: usize,
+    state: u32,
+}
+
+impl<'a> RAnsSymbolDecoder<'a> {
+    fn create(
+        buffer: &mut DecoderBuffer<'a>,
+        unique_symbols_bit_length: u32,
+    ) -> Result<RAnsSymbolDecoder<'a>, DecodeError> {
+        let precision_bits = ((3 * unique_symbols_bit_length) / 2).clamp(12, 20);
+        let precision = 1u32 << precision_bits;
+
+        let num_symbols = buffer.decode_varint_u32()? as usize;
+        if num_symbols > buffer.remaining_size() * 64 {
+            return Err(DecodeError::new("draco: invalid number of rans symbols"));
+        }
+        let mut probabilities = vec![0u32; num_symbols];
+
+        let mut i = 0;
+        while i < num_symbols {
+            let prob_data = buffer.decode_u8()?;
+            let token = prob_data & 3;
+            if token == 3 {
+                let offset = (prob_data >> 2) as usize;
+                if i + offset >= num_symbols {
+                    return Err(DecodeError::new("draco: invalid rans probability table"));
+                }
+                i += offset + 1;
+            } else {
+                let mut prob = u32::from(prob_data >> 2);
+                for b in 0..token {
+                    let extra = buffer.decode_u8()?;
+                    prob |= u32::from(extra) << (8 * (u32::from(b) + 1) - 2);
+                }
+                probabilities[i] = prob;
+                i += 1;
+            }
+        }
+
+        let mut probability_table = Vec::with_capacity(num_symbols);
+        let mut lut = vec![0u32; precision as usize];
+        let mut cum_prob = 0u32;
+        for (symbol, prob) in probabilities.iter().enumerate() {
+            probability_table.push(RAnsSymbol {
+                prob: *prob,
+                cum_prob,
+            });
+            let next = cum_prob + prob;
+            if next > precision {
+                return Err(DecodeError::new(
+                    "draco: rans probabilities exceed precision",
+                ));
+            }
+            for entry in &mut lut[cum_prob as usize..next as usize] {
+                *entry = symbol as u32;
+            }
+            cum_prob = next;
+        }
+        if num_symbols > 0 && cum_prob != precision {
+            return Err(DecodeError::new("draco: rans probabilities do not sum up"));
+        }
+
+        Ok(RAnsSymbolDecoder {
+            precision,
+            probability_table,
+            lut,
+            data: &[],
+            buf_offset: 0,
+            state: 0,
+        })
+    }
+
+    fn num_symbols(&self) -> usize {
+        self.probability_table.len()
+    }
+
+    fn start_decoding(&mut self, buffer: &mut DecoderBuffer<'a>) -> Result<(), DecodeError> {
+        let size = buffer.decode_varint()? as usize;
+        let data = buffer.decode_bytes(size)?;
+        let (buf_offset, state) = read_init(data, self.precision * 4, true)?;
+        self.data = data;
+        self.buf_offset = buf_offset;
+        self.state = state;
+
+        Ok(())
+    }
+
+    fn decode_symbol(&mut self) -> Result<u32, DecodeError> {
+        let l_base = self.precision * 4;
+        while self.state < l_base && self.buf_offset > 0 {
+            self.buf_offset -= 1;
+            self.state = self.state * ANS_IO_BASE + u32::from(self.data[self.buf_offset]);
+        }
+
+        let quo = self.state / self.precision;
+        let rem = self.state % self.precision;
+        let symbol = self.lut[rem as usize];
+        let entry = self.probability_table[symbol as usize];
+        self.state = (quo * entry.prob + rem)
+            .checked_sub(entry.cum_prob)
+            .ok_or_else(|| DecodeError::new("draco: corrupted rans stream"))?;
+
+        Ok(symbol)
+    }
+}
+
+/// Decodes `num_values` entropy coded symbols (`DecodeSymbols` in the reference decoder).
+pub(super) fn decode_symbols(
+    buffer: &mut DecoderBuffer,
+    num_values: usize,
+    num_components: usize,
+) -> Result<Vec<u32>, DecodeError> {
+    if num_values == 0 {
+        return Ok(vec![]);
+    }
+
+    match buffer.decode_u8()? {
+        TAGGED_SYMBOLS => decode_tagged_symbols(buffer, num_values, num_components),
+        RAW_SYMBOLS => decode_raw_symbols(buffer, num_values),
+        scheme => Err(DecodeError::new(format!(
+            "draco: unknown symbol coding scheme {}",
+            scheme
+        ))),
+    }
+}
+
+fn decode_tagged_symbols(
+    buffer: &mut DecoderBuffer,
+    num_values: usize,
+    num_components: usize,
+) -> Result<Vec<u32>, DecodeError> {
+    let mut tag_decoder = RAnsSymbolDecoder::create(buffer, 5)?;
+    tag_decoder.start_decoding(buffer)?;
+    if tag_decoder.num_symbols() == 0 {
+        return Err(DecodeError::new("draco: missing symbol tags"));
+    }
+
+    let num_components = num_components.max(1);
+    // A tag may code values without any bits, so the count is only a hint.
+    let mut values = Vec::with_capacity(num_values.min(buffer.remaining_size() * 8));
+    buffer.start_bit_decoding(false)?;
+    while values.len() < num_values {
+        let bit_length = tag_decoder.decode_symbol()?;
+        for _ in 0..num_components {
+            values.push(buffer.decode_least_significant_bits32(bit_length)?);
+        }
+    }
+    buffer.end_bit_decoding();
+    values.truncate(num_values);
+
+    Ok(values)
+}
+
+fn decode_raw_symbols(
+    buffer: &mut DecoderBuffer,
+    num_values: usize,
+) -> Result<Vec<u32>, DecodeError> {
+    let max_bit_length = u32::from(buffer.decode_u8()?);
+    if max_bit_length == 0 || max_bit_length > 18 {
+        return Err(DecodeError::new("draco: invalid symbol bit length"));
+    }
+
+    let mut decoder = RAnsSymbolDecoder::create(buffer, max_bit_length)?;
+    if decoder.num_symbols() == 0 {
+        return Err(DecodeError::new("draco: missing symbol probabilities"));
+    }
+    decoder.start_decoding(buffer)?;
+
+    (0..num_values).map(|_| decoder.decode_symbol()).collect()
+}
+
+pub(super) fn convert_symbol_to_signed(value: u32) -> i32 {
+    if value & 1 == 0 {
+        (value >> 1) as i32
+    } else {
+        -((value >> 1) as i32) - 1
+    }
+}
@@ -0,0 +1,305 @@
+use super::{decode, DataType, DracoMesh};
+use crate::loader::draco::buffer::DecoderBuffer;
+use crate::loader::draco::rans::decode_symbols;
+use crate::loader::glb::Glb;
+use web_sys::WebGlRenderingContext;
+
+/// `models/test/draco_quad.glb`, a quad of two faces with edgebreaker connectivity.
+/// Positions are quantized to one bit and stored as raw integers, texture coordinates
+/// are quantized to seven bits and entropy coded with tagged symbols.
+const QUAD_GLB: &[u8] = include_bytes!("../../../models/test/draco_quad.glb");
+
+/// The Draco bitstream of the quad, which is the only buffer view of the glb.
+fn quad_bitstream() -> &'static [u8] {
+    let json_length = u32::from_le_bytes([QUAD_GLB[12], QUAD_GLB[13], QUAD_GLB[14], QUAD_GLB[15]]);
+    let json = std::str::from_utf8(&QUAD_GLB[20..20 + json_length as usize]).unwrap();
+    let gltf: serde_json::Value = serde_json::from_str(json).unwrap();
+    let byte_length = gltf["bufferViews"][0]["byteLength"].as_u64().unwrap() as usize;
+    let start = 20 + json_length as usize + 8;
+
+    &QUAD_GLB[start..start + byte_length]
+}
+
+fn values(mesh: &DracoMesh, unique_id: u32) -> Vec<Vec<f32>> {
+    let attribute = mesh.attribute(unique_id).unwrap();
+    assert_eq!(attribute.data_type, DataType::F32);
+    attribute
+        .data
+        .chunks(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect::<Vec<_>>()
+        .chunks(attribute.num_components)
+        .map(<[f32]>::to_vec)
+        .collect()
+}
+
+const F32_ZERO: [u8; 4] = [0, 0, 0, 0];
+const F32_ONE: [u8; 4] = [0, 0, 128, 63];
+const F32_TWO: [u8; 4] = [0, 0, 0, 64];
+const F32_MINUS_ONE: [u8; 4] = [0, 0, 128, 191];
+/// Wrap transform data for one bit quantized values: `min` 0 and `max` 1.
+const WRAP_ZERO_ONE: [u8; 8] = [0, 0, 0, 0, 1, 0, 0, 0];
+
+/// A quad of two faces with sequential connectivity, built by hand.
+///
+/// The indices are entropy coded as tagged symbols with a single three bit tag.
+/// Positions use the difference prediction with a wrap transform and normals the
+/// difference prediction with the octahedron transform, whose corrections wrap around.
+fn sequential_quad() -> Vec<u8> {
+    let mut data = b"DRACO".to_vec();
+    data.extend_from_slice(&[2, 2, 1, 0, 0, 0]);
+
+    // Two faces, four points and compressed indices. The delta coded indices
+    // 0, +1, +1, 0, -1, +2 are stored as (delta << 1 | sign) in three bits each.
+    data.extend_from_slice(&[2, 4, 0]);
+    data.extend_from_slice(&[0, 4, (2 << 2) | 3, 1, 64, 1, 0]);
+    data.extend_from_slice(&[0x90, 0x30, 0x02]);
+
+    // One decoder with a quantized position and a normal.
+    data.extend_from_slice(&[1, 2]);
+    data.extend_from_slice(&[0, 9, 3, 0, 0]);
+    data.extend_from_slice(&[1, 9, 3, 0, 1]);
+    data.extend_from_slice(&[2, 3]);
+
+    // Positions: difference prediction, wrap transform and raw signed corrections.
+    // The last correction of -1 wraps around to 1.
+    data.extend_from_slice(&[0, 1, 0, 1]);
+    data.extend_from_slice(&[0, 0, 0, 2, 0, 0, 1, 2, 0, 1, 0, 0]);
+    data.extend_from_slice(&WRAP_ZERO_ONE);
+
+    // Normals: difference prediction and octahedron transform for four bit
+    // coordinates (a maximum quantized value of 15).
+    data.extend_from_slice(&[0, 2, 0, 1]);
+    data.extend_from_slice(&[0, 7, 0, 8, 7, 0, 8, 8]);
+    data.extend_from_slice(&[15, 0, 0, 0]);
+
+    // Quantization of the positions and normals.
+    for value in &[F32_ZERO, F32_ZERO, F32_ZERO, F32_ONE] {
+        data.extend_from_slice(value);
+    }
+    data.extend_from_slice(&[1, 4]);
+
+    data
+}
+
+/// The quad of `QUAD_GLB` with its attributes replaced by predicted ones: parallelogram
+/// prediction for the positions, portable texture coordinate prediction and geometric
+/// normal prediction with the canonicalized octahedron transform.
+///
+/// The traversal encodes the points in the order 1, 2, 0, 3.
+fn predicted_quad() -> Vec<u8> {
+    // Header and connectivity of the quad, which ends before the attribute decoders.
+    let mut data = quad_bitstream()[..23].to_vec();
+
+    // One decoder using the position connectivity and depth first traversal, with a
+    // position, texture coordinates and a normal.
+    data.extend_from_slice(&[1, 255, 0, 0, 3]);
+    data.extend_from_slice(&[0, 9, 3, 0, 0]);
+    data.extend_from_slice(&[3, 9, 2, 0, 1]);
+    data.extend_from_slice(&[1, 9, 3, 0, 2]);
+    data.extend_from_slice(&[2, 2, 3]);
+
+    // Positions: the last point completes the parallelogram, so its correction is 0.
+    data.extend_from_slice(&[1, 1, 0, 1]);
+    data.extend_from_slice(&[2, 2, 0, 1, 1, 0, 2, 0, 0, 0, 0, 0]);
+    data.extend_from_slice(&WRAP_ZERO_ONE);
+
+    // Texture coordinates: the last two points are predicted from their neighbours,
+    // both with a positive orientation. Every bit of the orientation decoder is one.
+    data.extend_from_slice(&[5, 1, 0, 1]);
+    data.extend_from_slice(&[2, 0, 1, 2, 0, 0, 0, 0]);
+    data.extend_from_slice(&[2, 0, 0, 0, 0, 1, 0]);
+    data.extend_from_slice(&WRAP_ZERO_ONE);
+
+    // Normals: the geometric prediction is exact for the flat quad and no normal is
+    // flipped, so all corrections are 0.
+    data.extend_from_slice(&[6, 3, 0, 1]);
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(&[15, 0, 0, 0, 255, 1, 1]);
+
+    // Quantization of the positions, texture coordinates and normals.
+    for value in &[F32_MINUS_ONE, F32_MINUS_ONE, F32_ZERO, F32_TWO] {
+        data.extend_from_slice(value);
+    }
+    data.push(1);
+    for value in &[F32_ZERO, F32_ZERO, F32_ONE] {
+        data.extend_from_slice(value);
+    }
+    data.extend_from_slice(&[1, 4]);
+
+    data
+}
+
+fn fixtures() -> Vec<Vec<u8>> {
+    vec![
+        quad_bitstream().to_vec(),
+        sequential_quad(),
+        predicted_quad(),
+    ]
+}
+
+fn assert_normals(mesh: &DracoMesh, unique_id: u32, expected: &[[f32; 3]]) {
+    let normals = values(mesh, unique_id);
+    assert_eq!(normals.len(), expected.len());
+    for (normal, expected) in normals.iter().zip(expected) {
+        for (component, expected) in normal.iter().zip(expected) {
+            assert!(
+                (component - expected).abs() < 1e-6,
+                "{:?} != {:?}",
+                normals,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn decodes_edgebreaker_quad() {
+    let mesh = decode(quad_bitstream()).unwrap();
+
+    assert_eq!(mesh.faces, vec![[0, 1, 2], [2, 1, 3]]);
+    assert_eq!(mesh.num_points, 4);
+    assert_eq!(
+        values(&mesh, 0),
+        vec![
+            vec![1., -1., 0.],
+            vec![1., 1., 0.],
+            vec![-1., -1., 0.],
+            vec![-1., 1., 0.],
+        ]
+    );
+    assert_eq!(
+        values(&mesh, 1),
+        vec![vec![1., 1.], vec![1., 0.], vec![0., 1.], vec![0., 0.]]
+    );
+}
+
+#[test]
+fn decodes_sequential_quad() {
+    let mesh = decode(&sequential_quad()).unwrap();
+
+    assert_eq!(mesh.faces, vec![[0, 1, 2], [2, 1, 3]]);
+    assert_eq!(mesh.num_points, 4);
+    assert_eq!(
+        values(&mesh, 0),
+        vec![
+            vec![0., 0., 0.],
+            vec![1., 0., 0.],
+            vec![0., 1., 0.],
+            vec![1., 1., 0.],
+        ]
+    );
+    assert_normals(
+        &mesh,
+        1,
+        &[[0., 0., 1.], [1., 0., 0.], [0., 1., 0.], [0., 0., -1.]],
+    );
+}
+
+#[test]
+fn decodes_mesh_predictions() {
+    let mesh = decode(&predicted_quad()).unwrap();
+
+    assert_eq!(mesh.faces, vec![[0, 1, 2], [2, 1, 3]]);
+    assert_eq!(
+        values(&mesh, 0),
+        vec![
+            vec![1., -1., 0.],
+            vec![1., 1., 0.],
+            vec![-1., -1., 0.],
+            vec![-1., 1., 0.],
+        ]
+    );
+    assert_eq!(
+        values(&mesh, 1),
+        vec![vec![1., 1.], vec![1., 0.], vec![0., 1.], vec![0., 0.]]
+    );
+    assert_normals(&mesh, 2, &[[0., 0., 1.]; 4]);
+}
+
+#[test]
+fn replaces_draco_primitives_of_a_glb() {
+    let glb = Glb::from_bytes(QUAD_GLB).unwrap();
+    let gltf = &glb.json.gltf;
+    let primitive = &gltf.meshes[0].primitives[0];
+
+    let indices = &gltf.accessors[primitive.indices];
+    assert_eq!(indices.count, 6);
+    assert_eq!(
+        indices.component_type,
+        WebGlRenderingContext::UNSIGNED_SHORT
+    );
+    let view = &gltf.buffer_views[indices.buffer_view.unwrap()];
+    assert_eq!(
+        glb.buffer.view(view).unwrap(),
+        &[0, 0, 1, 0, 2, 0, 2, 0, 1, 0, 3, 0]
+    );
+
+    let position = &gltf.accessors[primitive.attributes.position];
+    assert_eq!(position.count, 4);
+    assert_eq!(position.component_type, WebGlRenderingContext::FLOAT);
+    assert!(position.buffer_view.is_some());
+}
+
+#[test]
+fn truncated_data_is_an_error() {
+    for data in fixtures() {
+        for length in 0..data.len() {
+            assert!(decode(&data[..length]).is_err(), "length {}", length);
+        }
+    }
+}
+
+#[test]
+fn corrupted_data_does_not_panic() {
+    for data in fixtures() {
+        for index in 0..data.len() {
+            for mask in &[0x01, 0x02, 0x10, 0x40, 0x80, 0xff] {
+                let mut corrupted = data.clone();
+                corrupted[index] ^= mask;
+                let _ = decode(&corrupted);
+            }
+        }
+    }
+}
+
+#[test]
+fn reading_more_than_32_bits_is_an_error() {
+    let mut buffer = DecoderBuffer::new(&[0xff; 8]);
+    buffer.start_bit_decoding(false).unwrap();
+
+    assert!(buffer.decode_least_significant_bits32(33).is_err());
+    assert_eq!(buffer.decode_least_significant_bits32(32), Ok(u32::MAX));
+}
+
+#[test]
+fn oversized_lengths_are_an_error() {
+    let mut buffer = DecoderBuffer::new(&[0; 4]);
+    buffer.decode_u8().unwrap();
+
+    assert!(buffer.decode_bytes(usize::MAX).is_err());
+    assert!(buffer.advance(usize::MAX).is_err());
+}
+
+#[test]
+fn symbol_tags_above_32_bits_are_an_error() {
+    // Tagged scheme whose only tag is 33: 33 skipped symbols, then the probability
+    // 4096 for the last one and a single byte of rANS state.
+    let data = [
+        0,
+        34,
+        (32 << 2) | 3,
+        1,
+        64,
+        1,
+        0,
+        0xff,
+        0xff,
+        0xff,
+        0xff,
+        0xff,
+    ];
+    let mut buffer = DecoderBuffer::new(&data);
+
+    assert!(decode_symbols(&mut buffer, 1, 1).is_err());
+}
@@ -0,0 +1,261 @@
+use crate::loader::draco::connectivity::EncodingData;
+use crate::loader::draco::corner_table::{next, previous, Table, INVALID};
+use crate::loader::error::DecodeError;
+
+const MAX_PRIORITY: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum TraversalMethod {
+    DepthFirst,
+    MaxPredictionDegree,
+}
+
+impl TraversalMethod {
+    pub(super) fn from_u8(value: u8) -> Result<TraversalMethod, DecodeError> {
+        match value {
+            0 => Ok(TraversalMethod::DepthFirst),
+            1 => Ok(TraversalMethod::MaxPredictionDegree),
+            _ => Err(DecodeError::new(format!(
+                "draco: unknown mesh traversal method {}",
+                value
+            ))),
+        }
+    }
+}
+
+/// Walks the mesh like the encoder did and records the order in which attribute values
+/// were encoded (`MeshTraversalSequencer` with its encoding observer).
+struct Traverser<'a, T: Table> {
+    table: &'a T,
+    faces: &'a [[u32; 3]],
+    encoding_data: &'a mut EncodingData,
+    point_ids: Vec<u32>,
+    is_face_visited: Vec<bool>,
+    is_vertex_visited: Vec<bool>,
+}
+
+impl<'a, T: Table> Traverser<'a, T> {
+    fn is_face_visited(&self, corner: u32) -> bool {
+        corner == INVALID || self.is_face_visited[(corner / 3) as usize]
+    }
+
+    fn visit_vertex(&mut self, vertex: u32, corner: u32) -> Result<(), DecodeError> {
+        let visited = self
+            .is_vertex_visited
+            .get_mut(vertex as usize)
+            .ok_or_else(|| DecodeError::new("draco: invalid vertex in traversal"))?;
+        if *visited {
+            return Ok(());
+        }
+        *visited = true;
+
+        let point = self.faces[(corner / 3) as usize][(corner % 3) as usize];
+        self.point_ids.push(point);
+        let value = self.encoding_data.encoded_value_to_corner.len() as i32;
+        self.encoding_data.encoded_value_to_corner.push(corner);
+        self.encoding_data.vertex_to_encoded_value[vertex as usize] = value;
+
+        Ok(())
+    }
+
+    fn visit_first_face(&mut self, corner: u32) -> Result<(), DecodeError> {
+        let next_corner = next(corner);
+        let previous_corner = previous(corner);
+        self.visit_vertex(self.table.vertex(next_corner), next_corner)?;
+        self.visit_vertex(self.table.vertex(previous_corner), previous_corner)
+    }
+
+    fn depth_first(&mut self, corner: u32) -> Result<(), DecodeError> {
+        if self.is_face_visited(corner) {
+            return Ok(());
+        }
+        self.visit_first_face(corner)?;
+
+        let mut stack = vec![corner];
+        while let Some(&top) = stack.last() {
+            let mut corner = top;
+            if self.is_face_visited(corner) {
+                stack.pop();
+                continue;
+            }
+
+            loop {
+                self.is_face_visited[(corner / 3) as usize] = true;
+                let vertex = self.table.vertex(corner);
+                if vertex == INVALID {
+                    return Err(DecodeError::new("draco: invalid vertex in traversal"));
+                }
+                if !self.is_vertex_visited[vertex as usize] {
+                    let on_boundary = self.table.is_on_boundary(vertex);
+                    self.visit_vertex(vertex, corner)?;
+                    if !on_boundary {
+                        corner = self.table.right_corner(corner);
+                        continue;
+                    }
+                }
+
+                let right_corner = self.table.right_corner(corner);
+                let left_corner = self.table.left_corner(corner);
+                match (
+                    self.is_face_visited(right_corner),
+                    self.is_face_visited(left_corner),
+                ) {
+                    (true, true) => {
+                        stack.pop();
+                        break;
+                    }
+                    (true, false) => corner = left_corner,
+                    (false, true) => corner = right_corner,
+                    (false, false) => {
+                        *stack.last_mut().unwrap() = left_corner;
+                        stack.push(right_corner);
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn max_prediction_degree(
+        &mut self,
+        corner: u32,
+        prediction_degree: &mut [u32],
+    ) -> Result<(), DecodeError> {
+        let mut stacks: [Vec<u32>; MAX_PRIORITY] = Default::default();
+        let mut best_priority = 0;
+        stacks[0].push(corner);
+
+        self.visit_first_face(corner)?;
+        self.visit_vertex(self.table.vertex(corner), corner)?;
+
+        let compute_priority = |traverser: &Self, degree: &mut [u32], corner: u32| {
+            let tip = traverser.table.vertex(corner);
+            if traverser.is_vertex_visited[tip as usize] {
+                return 0;
+            }
+            degree[tip as usize] += 1;
+            if degree[tip as usize] > 1 {
+                1
+            } else {
+                2
+            }
+        };
+
+        loop {
+            let popped = (best_priority..MAX_PRIORITY)
+                .find_map(|priority| stacks[priority].pop().map(|corner| (priority, corner)));
+            let mut corner = match popped {
+                Some((priority, corner)) => {
+                    best_priority = priority;
+                    corner
+                }
+                None => break,
+            };
+            if self.is_face_visited(corner) {
+                continue;
+            }
+
+            loop {
+                self.is_face_visited[(corner / 3) as usize] = true;
+                let vertex = self.table.vertex(corner);
+                if vertex == INVALID {
+                    return Err(DecodeError::new("draco: invalid vertex in traversal"));
+                }
+                self.visit_vertex(vertex, corner)?;
+
+                let right_corner = self.table.right_corner(corner);
+                let left_corner = self.table.left_corner(corner);
+                let is_right_visited = self.is_face_visited(right_corner);
+
+                if !self.is_face_visited(left_corner) {
+                    let priority = compute_priority(self, prediction_degree, left_corner);
+                    if is_right_visited && priority <= best_priority {
+                        corner = left_corner;
+                        continue;
+                    }
+                    stacks[priority].push(left_corner);
+                    best_priority = best_priority.min(priority);
+                }
+                if !is_right_visited {
+                    let priority = compute_priority(self, prediction_degree, right_corner);
+                    if priority <= best_priority {
+                        corner = right_corner;
+                        continue;
+                    }
+                    stacks[priority].push(right_corner);
+                    best_priority = best_priority.min(priority);
+                }
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Generates the encoding order of the attribute values and returns the point id of
+/// every encoded value.
+pub(super) fn generate_sequence<T: Table>(
+    table: &T,
+    faces: &[[u32; 3]],
+    method: TraversalMethod,
+    encoding_data: &mut EncodingData,
+) -> Result<Vec<u32>, DecodeError> {
+    let num_vertices = table.num_vertices() as usize;
+    if encoding_data.vertex_to_encoded_value.len() < num_vertices {
+        encoding_data
+            .vertex_to_encoded_value
+            .resize(num_vertices, -1);
+    }
+
+    let mut traverser = Traverser {
+        table,
+        faces,
+        encoding_data,
+        point_ids: Vec::with_capacity(num_vertices),
+        is_face_visited: vec![false; table.num_faces() as usize],
+        is_vertex_visited: vec![false; num_vertices],
+    };
+
+    let mut prediction_degree = vec![0u32; num_vertices];
+    for face in 0..table.num_faces() {
+        match method {
+            TraversalMethod::DepthFirst => traverser.depth_first(3 * face)?,
+            TraversalMethod::MaxPredictionDegree => {
+                traverser.max_prediction_degree(3 * face, &mut prediction_degree)?
+            }
+        }
+    }
+
+    Ok(traverser.point_ids)
+}
+
+/// Maps every point to the index of its decoded attribute value.
+pub(super) fn point_to_value_map<T: Table>(
+    table: &T,
+    faces: &[[u32; 3]],
+    encoding_data: &EncodingData,
+    num_points: u32,
+) -> Result<Vec<u32>, DecodeError> {
+    let mut map = vec![0u32; num_points as usize];
+    for (face_index, face) in faces.iter().enumerate() {
+        for (offset, point) in face.iter().enumerate() {
+            let vertex = table.vertex(3 * face_index as u32 + offset as u32);
+            let value = encoding_data
+                .vertex_to_encoded_value
+                .get(vertex as usize)
+                .copied()
+                .unwrap_or(-1);
+            if value < 0 || value as u32 >= num_points || *point >= num_points {
+                return Err(DecodeError::new(
+                    "draco: invalid point to attribute mapping",
+                ));
+            }
+            map[*point as usize] = value as u32;
+        }
+    }
+
+    Ok(map)
+}
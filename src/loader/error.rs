@@ -0,0 +1,25 @@
+use std::fmt;
+use wasm_bindgen::JsValue;
+
+/// Error raised by the pure Rust decoders of the loader. It is kept free of `JsValue`
+/// so the decoders can run (and fail) outside of a browser.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DecodeError(String);
+
+impl DecodeError {
+    pub(crate) fn new(message: impl Into<String>) -> DecodeError {
+        DecodeError(message.into())
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<DecodeError> for JsValue {
+    fn from(error: DecodeError) -> Self {
+        JsValue::from_str(&error.0)
+    }
+}
@@ -0,0 +1,17 @@
+use js_sys::ArrayBuffer;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Response;
+
+pub(crate) async fn fetch_array_buffer(url: &str) -> Result<ArrayBuffer, JsValue> {
+    let window = web_sys::window().expect("could not get window object");
+
+    let response = JsFuture::from(window.fetch_with_str(url))
+        .await?
+        .dyn_into::<Response>()?;
+
+    JsFuture::from(response.array_buffer()?)
+        .await?
+        .dyn_into::<ArrayBuffer>()
+}
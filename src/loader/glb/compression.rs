@@ -0,0 +1,231 @@
+use crate::definitions::gltf::{GlTf, GlTfBufferView, GlTfDracoMeshCompression};
+use crate::loader::draco::{self, DataType, DracoMesh};
+use crate::loader::glb::GlbBuffer;
+use crate::loader::meshopt;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+/// Decodes every `EXT_meshopt_compression` buffer view in place. The decoded data is
+/// appended to the binary chunk, so the fallback buffer the view pointed to is skipped.
+pub(super) fn decode_meshopt_buffer_views(
+    gltf: &mut GlTf,
+    buffer: &mut GlbBuffer,
+) -> Result<(), JsValue> {
+    for buffer_view in gltf.buffer_views.iter_mut() {
+        let extension = match buffer_view
+            .extensions
+            .as_mut()
+            .and_then(|extensions| extensions.meshopt_compression.take())
+        {
+            Some(extension) => extension,
+            None => continue,
+        };
+
+        // The decoded data replaces the view, so it has to be the size the view declares.
+        let decoded_length = extension
+            .count
+            .checked_mul(extension.byte_stride)
+            .filter(|length| *length == buffer_view.byte_length as usize)
+            .ok_or_else(|| {
+                JsValue::from_str(&format!(
+                    "meshopt buffer view of {} bytes can not hold {} elements of {} bytes",
+                    buffer_view.byte_length, extension.count, extension.byte_stride
+                ))
+            })?;
+        if buffer_view
+            .byte_stride
+            .is_some_and(|byte_stride| byte_stride != extension.byte_stride)
+        {
+            return Err(JsValue::from_str(
+                "meshopt byteStride differs from the byteStride of its buffer view",
+            ));
+        }
+
+        let data = meshopt::decode(
+            buffer.range(
+                extension.buffer,
+                extension.byte_offset,
+                extension.byte_length,
+            )?,
+            extension.count,
+            extension.byte_stride,
+            &extension.mode,
+            extension.filter.as_deref(),
+        )?;
+        if data.len() != decoded_length {
+            return Err(JsValue::from_str(&format!(
+                "meshopt data decoded to {} bytes instead of {}",
+                data.len(),
+                decoded_length
+            )));
+        }
+
+        buffer_view.buffer = 0;
+        buffer_view.byte_offset = buffer.append(&data);
+        buffer_view.byte_length = data.len() as u32;
+    }
+
+    Ok(())
+}
+
+/// Replaces every `KHR_draco_mesh_compression` primitive with plain index and attribute
+/// data, so the renderer only ever sees uncompressed buffer views.
+pub(super) fn decode_draco_primitives(
+    gltf: &mut GlTf,
+    buffer: &mut GlbBuffer,
+) -> Result<(), JsValue> {
+    for mesh_index in 0..gltf.meshes.len() {
+        for primitive_index in 0..gltf.meshes[mesh_index].primitives.len() {
+            let extension = match gltf.meshes[mesh_index].primitives[primitive_index]
+                .extensions
+                .as_mut()
+                .and_then(|extensions| extensions.draco_mesh_compression.take())
+            {
+                Some(extension) => extension,
+                None => continue,
+            };
+
+            let buffer_view = gltf
+                .buffer_views
+                .get(extension.buffer_view)
+                .ok_or_else(|| JsValue::from_str("could not find draco buffer view"))?;
+            let mesh = draco::decode(buffer.view(buffer_view)?)?;
+
+            decode_draco_primitive(gltf, buffer, mesh_index, primitive_index, &extension, &mesh)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn decode_draco_primitive(
+    gltf: &mut GlTf,
+    buffer: &mut GlbBuffer,
+    mesh_index: usize,
+    primitive_index: usize,
+    extension: &GlTfDracoMeshCompression,
+    mesh: &DracoMesh,
+) -> Result<(), JsValue> {
+    let primitive = &gltf.meshes[mesh_index].primitives[primitive_index];
+    let indices_accessor = primitive.indices;
+    let attribute_accessors = [
+        ("POSITION", Some(primitive.attributes.position)),
+        ("NORMAL", primitive.attributes.normal),
+        ("TEXCOORD_0", primitive.attributes.texture_coord_0),
+        ("TEXCOORD_1", primitive.attributes.texture_coord_1),
+    ];
+
+    let (component_type, indices) = if mesh.num_points <= u32::from(u16::MAX) {
+        let indices: Vec<u8> = mesh
+            .faces
+            .iter()
+            .flatten()
+            .flat_map(|index| (*index as u16).to_le_bytes().to_vec())
+            .collect();
+        (WebGlRenderingContext::UNSIGNED_SHORT, indices)
+    } else {
+        let indices: Vec<u8> = mesh
+            .faces
+            .iter()
+            .flatten()
+            .flat_map(|index| index.to_le_bytes().to_vec())
+            .collect();
+        (WebGlRenderingContext::UNSIGNED_INT, indices)
+    };
+    let buffer_view = append_buffer_view(
+        gltf,
+        buffer,
+        &indices,
+        WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+    );
+    let accessor = gltf
+        .accessors
+        .get_mut(indices_accessor)
+        .ok_or_else(|| JsValue::from_str("could not find indices accessor"))?;
+    accessor.buffer_view = Some(buffer_view);
+    accessor.byte_offset = None;
+    accessor.component_type = component_type;
+    accessor.count = mesh.faces.len() * 3;
+
+    for (name, accessor_index) in attribute_accessors.iter() {
+        let accessor_index = match accessor_index {
+            Some(accessor_index) => *accessor_index,
+            None => continue,
+        };
+        let unique_id = match extension.attributes.get(*name) {
+            Some(unique_id) => *unique_id,
+            None => continue,
+        };
+        let attribute = mesh.attribute(unique_id).ok_or_else(|| {
+            JsValue::from_str(&format!(
+                "draco data has no attribute {} for {}",
+                unique_id, name
+            ))
+        })?;
+
+        let component_type = match attribute.data_type {
+            DataType::I8 => WebGlRenderingContext::BYTE,
+            DataType::U8 => WebGlRenderingContext::UNSIGNED_BYTE,
+            DataType::I16 => WebGlRenderingContext::SHORT,
+            DataType::U16 => WebGlRenderingContext::UNSIGNED_SHORT,
+            DataType::U32 => WebGlRenderingContext::UNSIGNED_INT,
+            DataType::F32 => WebGlRenderingContext::FLOAT,
+            data_type => {
+                return Err(JsValue::from_str(&format!(
+                    "draco attribute type {:?} can not be used by glTF",
+                    data_type
+                )))
+            }
+        };
+
+        let type_name = gltf
+            .accessors
+            .get(accessor_index)
+            .map(|accessor| accessor.type_name.as_str());
+        let num_components = match type_name {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            _ => 0,
+        };
+        if num_components != attribute.num_components {
+            return Err(JsValue::from_str(&format!(
+                "draco attribute {} does not match the accessor type of {}",
+                unique_id, name
+            )));
+        }
+
+        let buffer_view = append_buffer_view(
+            gltf,
+            buffer,
+            &attribute.data,
+            WebGlRenderingContext::ARRAY_BUFFER,
+        );
+        let accessor = gltf
+            .accessors
+            .get_mut(accessor_index)
+            .ok_or_else(|| JsValue::from_str("could not find accessor"))?;
+        accessor.buffer_view = Some(buffer_view);
+        accessor.byte_offset = None;
+        accessor.component_type = component_type;
+        accessor.count = mesh.num_points as usize;
+        accessor.normalized = Some(accessor.normalized.unwrap_or(false) || attribute.normalized);
+    }
+
+    Ok(())
+}
+
+fn append_buffer_view(gltf: &mut GlTf, buffer: &mut GlbBuffer, data: &[u8], target: u32) -> usize {
+    let byte_offset = buffer.append(data);
+    gltf.buffer_views.push(GlTfBufferView {
+        buffer: 0,
+        byte_offset,
+        byte_length: data.len() as u32,
+        target: Some(target as usize),
+        byte_stride: None,
+        extensions: None,
+    });
+
+    gltf.buffer_views.len() - 1
+}
@@ -1,140 +1,277 @@
-use crate::definitions::gltf::GlTf;
-use js_sys::ArrayBuffer;
+mod compression;
+
+use crate::definitions::gltf::{GlTf, GlTfBufferView};
+use crate::loader::error::DecodeError;
+use crate::loader::fetch::fetch_array_buffer;
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::Response;
 
 const HEADER_MAGIC: u32 = 0x46546C67;
 const HEADER_VERSION_SUPPORT: u32 = 2;
 const CHUNK_TYPE_JSON: u32 = 0x4E4F534A;
 const CHUNK_TYPE_BIN: u32 = 0x004E4942;
 
-#[derive(Debug)]
-struct GlbHeader {
-    magic: u32,
-    version: u32,
-    length: u32,
-}
-
-impl GlbHeader {
-    fn new(bin: &ArrayBuffer) -> Result<GlbHeader, JsValue> {
-        let header_magic = js_sys::Uint32Array::new_with_byte_offset_and_length(&bin, 0, 1);
-        let header_version = js_sys::Uint32Array::new_with_byte_offset_and_length(&bin, 4, 1);
-        let header_length = js_sys::Uint32Array::new_with_byte_offset_and_length(&bin, 8, 1);
+/// Extensions the loader and renderer understand. Files listing anything else in
+/// `extensionsRequired` can not be displayed correctly and are rejected.
+///
+/// `KHR_materials_clearcoat` and `KHR_materials_transmission` are left out on purpose:
+/// only their factors are rendered, their textures and refraction are not, so files
+/// that require them are rejected while files that merely use them still load.
+const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "KHR_draco_mesh_compression",
+    "KHR_lights_punctual",
+    "KHR_materials_emissive_strength",
+    "KHR_materials_variants",
+    "KHR_materials_unlit",
+    "KHR_mesh_quantization",
+    "KHR_texture_basisu",
+    "KHR_texture_transform",
+    "EXT_meshopt_compression",
+];
 
-        let magic = header_magic.get_index(0);
-        let version = header_version.get_index(0);
-        let length = header_length.get_index(0);
+/// Checks the 12 byte header, which only identifies the file and states its length.
+fn check_header(data: &[u8]) -> Result<(), DecodeError> {
+    if read_u32(data, 0)? != HEADER_MAGIC {
+        return Err(DecodeError::new("not a glb file"));
+    }
+    let version = read_u32(data, 4)?;
+    if version != HEADER_VERSION_SUPPORT {
+        return Err(DecodeError::new(format!(
+            "glb version {} is not supported",
+            version
+        )));
+    }
+    let length = read_u32(data, 8)?;
+    if length as usize != data.len() {
+        return Err(DecodeError::new(format!(
+            "glb header states {} bytes but the file has {}",
+            length,
+            data.len()
+        )));
+    }
 
-        // Todo: Improve error handling
-        assert_eq!(&HEADER_MAGIC, &magic);
-        assert_eq!(&HEADER_VERSION_SUPPORT, &version);
-        assert_eq!(&bin.byte_length(), &length);
+    Ok(())
+}
 
-        Ok(GlbHeader {
-            magic,
-            version,
-            length,
-        })
+/// Content of the chunk starting at `offset`, which has to be of `chunk_type`.
+fn read_chunk<'a>(
+    data: &'a [u8],
+    offset: usize,
+    chunk_type: u32,
+    name: &str,
+) -> Result<&'a [u8], DecodeError> {
+    let byte_length = read_u32(data, offset)?;
+    if read_u32(data, offset + 4)? != chunk_type {
+        return Err(DecodeError::new(format!("glb {} chunk is missing", name)));
     }
+
+    let start = offset + 8;
+    start
+        .checked_add(byte_length as usize)
+        .and_then(|end| data.get(start..end))
+        .ok_or_else(|| DecodeError::new(format!("glb {} chunk exceeds the file", name)))
 }
 
 #[derive(Debug)]
 pub(crate) struct GlbJson {
-    byte_length: u32,
+    byte_length: usize,
     pub(crate) gltf: GlTf,
 }
 
 impl GlbJson {
-    fn new(bin: &ArrayBuffer) -> Result<GlbJson, JsValue> {
-        let json_chunk_length = js_sys::Uint32Array::new_with_byte_offset_and_length(&bin, 12, 1);
-        let chunk_type = js_sys::Uint32Array::new_with_byte_offset_and_length(&bin, 16, 1);
-
-        // Todo: Improve error handling
-        assert_eq!(&CHUNK_TYPE_JSON, &chunk_type.get_index(0));
+    fn new(data: &[u8]) -> Result<GlbJson, JsValue> {
+        let content_chunk = read_chunk(data, 12, CHUNK_TYPE_JSON, "json")?;
+        let json = std::str::from_utf8(content_chunk)
+            .map_err(|e| JsValue::from_str(&format!("glb json chunk is not utf-8: {}", e)))?;
 
-        let byte_length = json_chunk_length.get_index(0);
-        let content_chunk =
-            js_sys::Uint8Array::new_with_byte_offset_and_length(&bin, 20, byte_length);
+        let gltf: GlTf = serde_json::from_str(json)
+            .map_err(|e| JsValue::from_str(&format!("{}\nJson: {}", &e.to_string(), &json)))?;
 
-        let decoder = web_sys::TextDecoder::new()?;
-        let json = decoder.decode_with_buffer_source(&content_chunk)?;
-
-        web_sys::console::log_1(
-            &format!("{}", json).into(),
-        );
-
-        let gltf: GlTf = serde_json::from_str(&json).or_else(|e| {
-            return Err(JsValue::from_str(&format!(
-                "{}\nJson: {}",
-                &e.to_string(),
-                &json
-            )));
-        })?;
-
-        Ok(GlbJson { byte_length, gltf })
+        Ok(GlbJson {
+            byte_length: content_chunk.len(),
+            gltf,
+        })
     }
 }
 
 #[derive(Debug)]
 pub(crate) struct GlbBuffer {
-    pub(crate) byte_offset: u32,
-    pub(crate) byte_length: u32,
-    pub(crate) bin: ArrayBuffer,
+    pub(crate) data: Vec<u8>,
 }
 
 impl GlbBuffer {
-    fn new(bin: ArrayBuffer, json_chunk: &GlbJson) -> GlbBuffer {
+    fn new(data: &[u8], json_chunk: &GlbJson) -> Result<GlbBuffer, DecodeError> {
         let byte_offset = 20 + json_chunk.byte_length;
-        let bin_chunk_length =
-            js_sys::Uint32Array::new_with_byte_offset_and_length(&bin, byte_offset, 1);
-        let chunk_type =
-            js_sys::Uint32Array::new_with_byte_offset_and_length(&bin, byte_offset + 4, 1);
+        let data = read_chunk(data, byte_offset, CHUNK_TYPE_BIN, "binary")?.to_vec();
 
-        assert_eq!(&CHUNK_TYPE_BIN, &chunk_type.get_index(0));
+        Ok(GlbBuffer { data })
+    }
 
-        let byte_length = bin_chunk_length.get_index(0);
+    pub(crate) fn view(&self, buffer_view: &GlTfBufferView) -> Result<&[u8], DecodeError> {
+        self.range(
+            buffer_view.buffer,
+            buffer_view.byte_offset,
+            buffer_view.byte_length,
+        )
+    }
+
+    /// Only the binary chunk (buffer 0) holds data, other buffers such as the
+    /// `EXT_meshopt_compression` fallbacks are never read.
+    pub(crate) fn range(
+        &self,
+        buffer: usize,
+        byte_offset: u32,
+        byte_length: u32,
+    ) -> Result<&[u8], DecodeError> {
+        if buffer != 0 {
+            return Err(DecodeError::new(format!(
+                "buffer {} is not stored in the glb binary chunk",
+                buffer
+            )));
+        }
+        let start = byte_offset as usize;
+
+        start
+            .checked_add(byte_length as usize)
+            .and_then(|end| self.data.get(start..end))
+            .ok_or_else(|| DecodeError::new("buffer view exceeds the glb binary chunk"))
+    }
 
-        GlbBuffer {
-            byte_offset: byte_offset + 8,
-            byte_length,
-            bin,
+    /// Appends decoded data behind the binary chunk and returns its byte offset, so
+    /// decompressed geometry can be referenced by ordinary buffer views.
+    pub(crate) fn append(&mut self, data: &[u8]) -> u32 {
+        while !self.data.len().is_multiple_of(4) {
+            self.data.push(0);
         }
+        let byte_offset = self.data.len() as u32;
+        self.data.extend_from_slice(data);
+
+        byte_offset
     }
 }
 
 #[derive(Debug)]
 pub(crate) struct Glb {
-    header: GlbHeader,
     pub(crate) json: GlbJson,
     pub(crate) buffer: GlbBuffer,
 }
 
 impl Glb {
-    fn new(bin: ArrayBuffer) -> Result<Glb, JsValue> {
-        let header = GlbHeader::new(&bin)?;
-        let json = GlbJson::new(&bin)?;
-        let buffer = GlbBuffer::new(bin, &json);
-
-        Ok(Glb {
-            header,
-            json,
-            buffer,
-        })
+    /// Parses a complete glb file, decoding compressed geometry.
+    pub(crate) fn from_bytes(data: &[u8]) -> Result<Glb, JsValue> {
+        check_header(data)?;
+        let mut json = GlbJson::new(data)?;
+        Glb::check_required_extensions(&json.gltf)?;
+        let mut buffer = GlbBuffer::new(data, &json)?;
+        compression::decode_meshopt_buffer_views(&mut json.gltf, &mut buffer)?;
+        compression::decode_draco_primitives(&mut json.gltf, &mut buffer)?;
+
+        Ok(Glb { json, buffer })
+    }
+
+    fn check_required_extensions(gltf: &GlTf) -> Result<(), JsValue> {
+        let unsupported: Vec<&str> = gltf
+            .extensions_required
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .filter(|extension| !SUPPORTED_EXTENSIONS.contains(extension))
+            .collect();
+
+        if unsupported.is_empty() {
+            Ok(())
+        } else {
+            Err(JsValue::from_str(&format!(
+                "glTF requires unsupported extensions: {}",
+                unsupported.join(", ")
+            )))
+        }
     }
 
     pub async fn from_url(url: &str) -> Result<Glb, JsValue> {
-        let window = web_sys::window().expect("could not get window object");
+        let bin = fetch_array_buffer(url).await?;
+
+        Glb::from_bytes(&js_sys::Uint8Array::new(&bin).to_vec())
+    }
+}
+
+/// Little endian `u32` at `offset`, glb files are little endian throughout.
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, DecodeError> {
+    offset
+        .checked_add(4)
+        .and_then(|end| data.get(offset..end))
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| DecodeError::new("glb file is truncated"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_header, read_chunk, GlbBuffer, CHUNK_TYPE_BIN, CHUNK_TYPE_JSON, HEADER_MAGIC,
+    };
 
-        let response = JsFuture::from(window.fetch_with_str(url))
-            .await?
-            .dyn_into::<Response>()?;
+    /// A glb header followed by `chunks` of the given type and content.
+    fn glb(magic: u32, version: u32, chunks: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut data = vec![];
+        for (chunk_type, content) in chunks {
+            data.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            data.extend_from_slice(&chunk_type.to_le_bytes());
+            data.extend_from_slice(content);
+        }
+        let length = 12 + data.len() as u32;
+
+        [magic, version, length]
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .chain(data)
+            .collect()
+    }
+
+    #[test]
+    fn checks_magic_version_and_length() {
+        assert!(check_header(&glb(HEADER_MAGIC, 2, &[])).is_ok());
+        assert!(check_header(&glb(0x4654_4c66, 2, &[])).is_err());
+        assert!(check_header(&glb(HEADER_MAGIC, 1, &[])).is_err());
+
+        let mut data = glb(HEADER_MAGIC, 2, &[]);
+        data.push(0);
+        assert!(check_header(&data).is_err());
+        assert!(check_header(&data[..11]).is_err());
+    }
+
+    #[test]
+    fn chunks_must_have_the_expected_type_and_fit_the_file() {
+        let data = glb(
+            HEADER_MAGIC,
+            2,
+            &[(CHUNK_TYPE_JSON, b"{}  "), (CHUNK_TYPE_BIN, &[1, 2, 3, 4])],
+        );
+
+        assert_eq!(
+            read_chunk(&data, 12, CHUNK_TYPE_JSON, "json"),
+            Ok(&b"{}  "[..])
+        );
+        assert_eq!(
+            read_chunk(&data, 24, CHUNK_TYPE_BIN, "binary"),
+            Ok(&[1, 2, 3, 4][..])
+        );
+        assert!(read_chunk(&data, 12, CHUNK_TYPE_BIN, "binary").is_err());
+        assert!(read_chunk(&data, 36, CHUNK_TYPE_BIN, "binary").is_err());
+
+        let mut oversized = data.clone();
+        oversized[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read_chunk(&oversized, 24, CHUNK_TYPE_BIN, "binary").is_err());
+        assert!(read_chunk(&data[..data.len() - 1], 24, CHUNK_TYPE_BIN, "binary").is_err());
+    }
 
-        let bin = JsFuture::from(response.array_buffer()?)
-            .await?
-            .dyn_into::<ArrayBuffer>()?;
+    #[test]
+    fn ranges_outside_of_the_binary_chunk_are_an_error() {
+        let buffer = GlbBuffer {
+            data: vec![0, 1, 2, 3],
+        };
 
-        Glb::new(bin)
+        assert_eq!(buffer.range(0, 1, 3), Ok(&[1, 2, 3][..]));
+        assert!(buffer.range(0, 1, 4).is_err());
+        assert!(buffer.range(0, u32::MAX, u32::MAX).is_err());
+        assert!(buffer.range(1, 0, 0).is_err());
     }
 }
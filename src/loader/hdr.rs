@@ -0,0 +1,226 @@
+//! Decodes Radiance `.hdr` images, the usual container of equirectangular environment
+//! maps. Pixels stay RGBE encoded and are decoded on the GPU.
+
+use crate::loader::error::DecodeError;
+
+const MAX_HEADER_LENGTH: usize = 1024;
+
+#[derive(Debug)]
+pub(crate) struct HdrImage {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// RGBE texels, 4 bytes per pixel, top row first.
+    pub(crate) data: Vec<u8>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn line(&mut self) -> Result<&'a str, DecodeError> {
+        let rest = &self.data[self.offset..];
+        let length = rest
+            .iter()
+            .take(MAX_HEADER_LENGTH)
+            .position(|byte| *byte == b'\n')
+            .ok_or_else(|| DecodeError::new("hdr: header is truncated"))?;
+        self.offset += length + 1;
+
+        std::str::from_utf8(&rest[..length])
+            .map_err(|_| DecodeError::new("hdr: header is not valid text"))
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self
+            .data
+            .get(self.offset)
+            .ok_or_else(|| DecodeError::new("hdr: pixel data is truncated"))?;
+        self.offset += 1;
+
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + count)
+            .ok_or_else(|| DecodeError::new("hdr: pixel data is truncated"))?;
+        self.offset += count;
+
+        Ok(bytes)
+    }
+}
+
+pub(crate) fn parse(data: &[u8]) -> Result<HdrImage, DecodeError> {
+    let mut reader = Reader { data, offset: 0 };
+
+    let magic = reader.line()?;
+    if magic != "#?RADIANCE" && magic != "#?RGBE" {
+        return Err(DecodeError::new("hdr: invalid file identifier"));
+    }
+    loop {
+        let line = reader.line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(DecodeError::new(format!(
+                    "hdr: pixel format {} is not supported",
+                    format
+                )));
+            }
+        }
+    }
+
+    let resolution: Vec<&str> = reader.line()?.split_whitespace().collect();
+    let (width, height) = match resolution.as_slice() {
+        ["-Y", height, "+X", width] => (width.parse::<u32>().ok(), height.parse::<u32>().ok()),
+        _ => (None, None),
+    };
+    let (width, height) = width
+        .zip(height)
+        .ok_or_else(|| DecodeError::new("hdr: only -Y +X scanline order is supported"))?;
+
+    // Checked against the remaining data before anything is allocated, run length
+    // encoding packs at most 127 values of a channel into 2 bytes.
+    let length = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .ok_or_else(|| DecodeError::new("hdr: image is too large"))?;
+    let min_scanline_length = match width {
+        8..0x8000 => 4 + 8 * (width as usize).div_ceil(127),
+        _ => width as usize * 4,
+    };
+    if (height as usize)
+        .checked_mul(min_scanline_length)
+        .is_none_or(|min_length| min_length > data.len() - reader.offset)
+    {
+        return Err(DecodeError::new("hdr: pixel data is truncated"));
+    }
+
+    let mut pixels = Vec::with_capacity(length);
+    for _ in 0..height {
+        read_scanline(&mut reader, width as usize, &mut pixels)?;
+    }
+
+    Ok(HdrImage {
+        width,
+        height,
+        data: pixels,
+    })
+}
+
+fn read_scanline(
+    reader: &mut Reader<'_>,
+    width: usize,
+    pixels: &mut Vec<u8>,
+) -> Result<(), DecodeError> {
+    let header = reader.data.get(reader.offset..reader.offset + 4);
+    let run_length_encoded = (8..0x8000).contains(&width)
+        && header.is_some_and(|header| {
+            header[0] == 2
+                && header[1] == 2
+                && (usize::from(header[2]) << 8 | usize::from(header[3])) == width
+        });
+    if !run_length_encoded {
+        pixels.extend_from_slice(reader.bytes(width * 4)?);
+        return Ok(());
+    }
+    reader.offset += 4;
+
+    // Every channel is stored separately, as runs of one value or as literal bytes.
+    let start = pixels.len();
+    pixels.resize(start + width * 4, 0);
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = reader.byte()?;
+            let (count, value) = if count > 128 {
+                (usize::from(count - 128), Some(reader.byte()?))
+            } else {
+                (usize::from(count), None)
+            };
+            if count == 0 || x + count > width {
+                return Err(DecodeError::new("hdr: invalid scanline run"));
+            }
+
+            for offset in 0..count {
+                pixels[start + (x + offset) * 4 + channel] = match value {
+                    Some(value) => value,
+                    None => reader.byte()?,
+                };
+            }
+            x += count;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    fn hdr(resolution: &str, pixels: &[u8]) -> Vec<u8> {
+        let mut data =
+            format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{}\n", resolution).into_bytes();
+        data.extend_from_slice(pixels);
+
+        data
+    }
+
+    #[test]
+    fn parses_flat_scanlines() {
+        let pixels: Vec<u8> = (0..16).collect();
+        let image = parse(&hdr("-Y 2 +X 2", &pixels)).unwrap();
+
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.data, pixels);
+    }
+
+    #[test]
+    fn parses_run_length_encoded_scanlines() {
+        // One scanline of 8 pixels: a run for red, literals for green, runs for the rest.
+        let mut scanline = vec![2, 2, 0, 8];
+        scanline.extend_from_slice(&[128 + 8, 10]);
+        scanline.extend_from_slice(&[8, 0, 1, 2, 3, 4, 5, 6, 7]);
+        scanline.extend_from_slice(&[128 + 8, 20]);
+        scanline.extend_from_slice(&[128 + 8, 128]);
+        let image = parse(&hdr("-Y 1 +X 8", &scanline)).unwrap();
+
+        let expected: Vec<u8> = (0..8).flat_map(|x| [10, x, 20, 128]).collect();
+        assert_eq!((image.width, image.height), (8, 1));
+        assert_eq!(image.data, expected);
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        assert!(parse(b"#?JPEG\n\n-Y 1 +X 1\n\0\0\0\0").is_err());
+        assert!(parse(&hdr("+Y 1 +X 1", &[0; 4])).is_err());
+        assert!(parse(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0").is_err());
+        assert!(parse(b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe").is_err());
+    }
+
+    #[test]
+    fn rejects_dimensions_the_data_can_not_hold() {
+        assert!(parse(&hdr("-Y 4294967295 +X 4294967295", &[0; 64])).is_err());
+        assert!(parse(&hdr("-Y 65536 +X 65536", &[2, 2, 0, 0])).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_and_corrupted_pixels() {
+        assert!(parse(&hdr("-Y 2 +X 2", &[0; 12])).is_err());
+
+        // A run that overflows the scanline.
+        let scanline = [2, 2, 0, 8, 128 + 9, 10];
+        assert!(parse(&hdr("-Y 1 +X 8", &scanline)).is_err());
+
+        // Run length encoded scanline cut off before its alpha channel.
+        let mut scanline = vec![2, 2, 0, 8, 128 + 8, 10, 8, 0, 1, 2, 3, 4, 5, 6, 7];
+        scanline.extend_from_slice(&[128 + 8, 20]);
+        assert!(parse(&hdr("-Y 1 +X 8", &scanline)).is_err());
+    }
+}
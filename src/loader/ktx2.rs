@@ -0,0 +1,263 @@
+//! Reads the header of KTX2 containers holding Basis Universal data as used by
+//! `KHR_texture_basisu`. The payload itself is transcoded by the Basis transcoder.
+
+use crate::loader::error::DecodeError;
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const HEADER_LENGTH: usize = 80;
+const LEVEL_INDEX_ENTRY_LENGTH: usize = 24;
+const VK_FORMAT_UNDEFINED: u32 = 0;
+const SUPERCOMPRESSION_NONE: u32 = 0;
+const SUPERCOMPRESSION_BASIS_LZ: u32 = 1;
+const SUPERCOMPRESSION_ZSTD: u32 = 2;
+const DF_MODEL_ETC1S: u8 = 163;
+const DF_MODEL_UASTC: u8 = 166;
+const DF_CHANNEL_ETC1S_AAA: u8 = 15;
+const DF_CHANNEL_UASTC_RGBA: u8 = 3;
+const DF_CHANNEL_UASTC_RRRG: u8 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Ktx2Encoding {
+    Etc1s,
+    Uastc,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Ktx2Header {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) level_count: u32,
+    pub(crate) encoding: Ktx2Encoding,
+    pub(crate) has_alpha: bool,
+}
+
+impl Ktx2Header {
+    /// Size of the given mip level, levels never shrink below a single pixel.
+    pub(crate) fn level_size(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+}
+
+fn truncated() -> DecodeError {
+    DecodeError::new("ktx2: file is truncated")
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, DecodeError> {
+    offset
+        .checked_add(4)
+        .and_then(|end| data.get(offset..end))
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(truncated)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, DecodeError> {
+    let low = read_u32(data, offset)?;
+    let high = read_u32(data, offset + 4)?;
+
+    Ok(u64::from(high) << 32 | u64::from(low))
+}
+
+pub(crate) fn parse(data: &[u8]) -> Result<Ktx2Header, DecodeError> {
+    if data.len() < HEADER_LENGTH || data[..IDENTIFIER.len()] != IDENTIFIER {
+        return Err(DecodeError::new("ktx2: invalid file identifier"));
+    }
+
+    let vk_format = read_u32(data, 12)?;
+    let width = read_u32(data, 20)?;
+    let height = read_u32(data, 24)?;
+    let depth = read_u32(data, 28)?;
+    let layer_count = read_u32(data, 32)?;
+    let face_count = read_u32(data, 36)?;
+    let level_count = read_u32(data, 40)?.max(1);
+    let supercompression = read_u32(data, 44)?;
+    let dfd_offset = read_u32(data, 48)? as usize;
+
+    if vk_format != VK_FORMAT_UNDEFINED {
+        return Err(DecodeError::new(
+            "ktx2: only Basis Universal encoded textures are supported",
+        ));
+    }
+    if width == 0 || height == 0 || depth > 1 || layer_count > 1 || face_count != 1 {
+        return Err(DecodeError::new("ktx2: only 2D textures are supported"));
+    }
+
+    // A level is at least a single pixel, so the largest side bounds the level count.
+    if level_count > 32 - width.max(height).leading_zeros() {
+        return Err(DecodeError::new("ktx2: too many mip levels"));
+    }
+    for level in 0..level_count as usize {
+        let entry = HEADER_LENGTH + level * LEVEL_INDEX_ENTRY_LENGTH;
+        let byte_offset = read_u64(data, entry)?;
+        let byte_length = read_u64(data, entry + 8)?;
+        if byte_length == 0
+            || byte_offset
+                .checked_add(byte_length)
+                .is_none_or(|end| end > data.len() as u64)
+        {
+            return Err(DecodeError::new(format!(
+                "ktx2: mip level {} is outside of the file",
+                level
+            )));
+        }
+    }
+
+    // The basic data format descriptor follows the total size field of the DFD.
+    let descriptor = data
+        .get(dfd_offset.saturating_add(4)..)
+        .ok_or_else(truncated)?;
+    let block_size = (read_u32(descriptor, 4)? >> 16) as usize;
+    let color_model = *descriptor.get(8).ok_or_else(truncated)?;
+    let channel_ids = (24..block_size)
+        .step_by(16)
+        .map(|offset| read_u32(descriptor, offset).map(|sample| (sample >> 24) as u8 & 0x0F))
+        .collect::<Result<Vec<u8>, DecodeError>>()?;
+
+    let (encoding, has_alpha) = match (color_model, supercompression) {
+        (DF_MODEL_ETC1S, SUPERCOMPRESSION_BASIS_LZ) => (
+            Ktx2Encoding::Etc1s,
+            channel_ids.contains(&DF_CHANNEL_ETC1S_AAA),
+        ),
+        (DF_MODEL_UASTC, SUPERCOMPRESSION_NONE) | (DF_MODEL_UASTC, SUPERCOMPRESSION_ZSTD) => (
+            Ktx2Encoding::Uastc,
+            matches!(
+                channel_ids.first(),
+                Some(&DF_CHANNEL_UASTC_RGBA) | Some(&DF_CHANNEL_UASTC_RRRG)
+            ),
+        ),
+        _ => {
+            return Err(DecodeError::new(format!(
+                "ktx2: color model {} with supercompression scheme {} is not supported",
+                color_model, supercompression
+            )))
+        }
+    };
+
+    Ok(Ktx2Header {
+        width,
+        height,
+        level_count,
+        encoding,
+        has_alpha,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Ktx2Encoding, DF_MODEL_ETC1S, DF_MODEL_UASTC, IDENTIFIER};
+
+    /// A 2D KTX2 file with one sample per channel id and `levels` mip levels of 16 bytes.
+    fn ktx2(
+        width: u32,
+        height: u32,
+        levels: u32,
+        model: u8,
+        scheme: u32,
+        channels: &[u8],
+    ) -> Vec<u8> {
+        let level_index_length = levels.max(1) as usize * 24;
+        let dfd_offset = 80 + level_index_length;
+        let block_size = 24 + 16 * channels.len();
+        let data_offset = dfd_offset + 4 + block_size;
+
+        let mut data = IDENTIFIER.to_vec();
+        for value in &[0, 1, width, height, 0, 0, 1, levels, scheme] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&(dfd_offset as u32).to_le_bytes());
+        data.extend_from_slice(&(4 + block_size as u32).to_le_bytes());
+        data.extend_from_slice(&[0; 24]);
+
+        for level in 0..levels.max(1) as u64 {
+            data.extend_from_slice(&(data_offset as u64 + 16 * level).to_le_bytes());
+            data.extend_from_slice(&16u64.to_le_bytes());
+            data.extend_from_slice(&16u64.to_le_bytes());
+        }
+
+        data.extend_from_slice(&(4 + block_size as u32).to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&(2 | (block_size as u32) << 16).to_le_bytes());
+        data.extend_from_slice(&[model, 1, 2, 0]);
+        data.extend_from_slice(&[0; 12]);
+        for channel in channels {
+            data.extend_from_slice(&(u32::from(*channel) << 24).to_le_bytes());
+            data.extend_from_slice(&[0; 12]);
+        }
+
+        data.extend(vec![0; 16 * levels.max(1) as usize]);
+        data
+    }
+
+    #[test]
+    fn parses_etc1s_header() {
+        let header = parse(&ktx2(64, 32, 7, DF_MODEL_ETC1S, 1, &[0, 15])).unwrap();
+
+        assert_eq!((header.width, header.height), (64, 32));
+        assert_eq!(header.level_count, 7);
+        assert_eq!(header.encoding, Ktx2Encoding::Etc1s);
+        assert!(header.has_alpha);
+        assert_eq!(header.level_size(6), (1, 1));
+    }
+
+    #[test]
+    fn parses_uastc_header() {
+        let header = parse(&ktx2(16, 16, 0, DF_MODEL_UASTC, 2, &[0])).unwrap();
+
+        assert_eq!(header.level_count, 1);
+        assert_eq!(header.encoding, Ktx2Encoding::Uastc);
+        assert!(!header.has_alpha);
+    }
+
+    #[test]
+    fn levels_outside_of_the_file_are_an_error() {
+        let mut data = ktx2(4, 4, 3, DF_MODEL_ETC1S, 1, &[0]);
+        // The last level ends one byte behind the file.
+        let entry = 80 + 2 * 24;
+        data[entry + 8..entry + 16].copy_from_slice(&17u64.to_le_bytes());
+        assert!(parse(&data).is_err());
+
+        data[entry..entry + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn more_levels_than_the_size_allows_are_an_error() {
+        assert!(parse(&ktx2(4, 4, 3, DF_MODEL_ETC1S, 1, &[0])).is_ok());
+        assert!(parse(&ktx2(4, 4, 4, DF_MODEL_ETC1S, 1, &[0])).is_err());
+
+        let mut data = ktx2(4, 4, 1, DF_MODEL_ETC1S, 1, &[0]);
+        data[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn unsupported_files_are_an_error() {
+        // Not Basis Universal, a cube map and UASTC with BasisLZ supercompression.
+        let mut data = ktx2(4, 4, 1, DF_MODEL_ETC1S, 1, &[0]);
+        data[12] = 37;
+        assert!(parse(&data).is_err());
+
+        let mut data = ktx2(4, 4, 1, DF_MODEL_ETC1S, 1, &[0]);
+        data[36] = 6;
+        assert!(parse(&data).is_err());
+
+        assert!(parse(&ktx2(4, 4, 1, DF_MODEL_UASTC, 1, &[0])).is_err());
+    }
+
+    #[test]
+    fn bad_input_is_an_error() {
+        let data = ktx2(8, 8, 4, DF_MODEL_ETC1S, 1, &[0, 15]);
+        for length in 0..data.len() {
+            assert!(parse(&data[..length]).is_err(), "length {}", length);
+        }
+
+        let mut corrupted = data.clone();
+        corrupted[0] = 0;
+        assert!(parse(&corrupted).is_err());
+
+        let mut corrupted = data;
+        corrupted[48..52].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse(&corrupted).is_err());
+    }
+}
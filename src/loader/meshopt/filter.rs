@@ -0,0 +1,193 @@
+use crate::loader::error::DecodeError;
+
+fn round(value: f32) -> i32 {
+    (value + if value >= 0.0 { 0.5 } else { -0.5 }) as i32
+}
+
+/// Reconstructs unit vectors from octahedral encoded 8 or 16 bit components, the fourth
+/// component is left untouched.
+pub(super) fn decode_octahedral(data: &mut [u8], stride: usize) -> Result<(), DecodeError> {
+    match stride {
+        4 => {
+            for vector in data.chunks_exact_mut(4) {
+                let [x, y, z] = decode_octahedral_vector(
+                    [
+                        vector[0] as i8 as f32,
+                        vector[1] as i8 as f32,
+                        vector[2] as i8 as f32,
+                    ],
+                    127.0,
+                );
+                vector[0] = x as i8 as u8;
+                vector[1] = y as i8 as u8;
+                vector[2] = z as i8 as u8;
+            }
+        }
+        8 => {
+            for vector in data.chunks_exact_mut(8) {
+                let component =
+                    |i: usize| i16::from_le_bytes([vector[2 * i], vector[2 * i + 1]]) as f32;
+                let decoded =
+                    decode_octahedral_vector([component(0), component(1), component(2)], 32767.0);
+                for (i, value) in decoded.iter().enumerate() {
+                    vector[2 * i..2 * i + 2].copy_from_slice(&(*value as i16).to_le_bytes());
+                }
+            }
+        }
+        _ => {
+            return Err(DecodeError::new(
+                "meshopt: octahedral filter requires a byte stride of 4 or 8",
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+fn decode_octahedral_vector([x, y, z]: [f32; 3], max: f32) -> [i32; 3] {
+    let z = z - x.abs() - y.abs();
+
+    // fold back the lower hemisphere
+    let t = z.min(0.0);
+    let x = x + if x >= 0.0 { t } else { -t };
+    let y = y + if y >= 0.0 { t } else { -t };
+
+    let scale = max / (x * x + y * y + z * z).sqrt();
+
+    [round(x * scale), round(y * scale), round(z * scale)]
+}
+
+/// Reconstructs quaternions stored as three 16 bit components, the index of the dropped
+/// largest component and the scale of the others.
+pub(super) fn decode_quaternion(data: &mut [u8], stride: usize) -> Result<(), DecodeError> {
+    if stride != 8 {
+        return Err(DecodeError::new(
+            "meshopt: quaternion filter requires a byte stride of 8",
+        ));
+    }
+
+    for quaternion in data.chunks_exact_mut(8) {
+        let mut components = [0i16; 4];
+        for (i, component) in components.iter_mut().enumerate() {
+            *component = i16::from_le_bytes([quaternion[2 * i], quaternion[2 * i + 1]]);
+        }
+
+        let scale = std::f32::consts::FRAC_1_SQRT_2 / f32::from(components[3] | 3);
+        let x = f32::from(components[0]) * scale;
+        let y = f32::from(components[1]) * scale;
+        let z = f32::from(components[2]) * scale;
+        let w = (1.0 - x * x - y * y - z * z).max(0.0).sqrt();
+
+        let largest = (components[3] & 3) as usize;
+        let decoded = [
+            (largest + 1, round(x * 32767.0)),
+            (largest + 2, round(y * 32767.0)),
+            (largest + 3, round(z * 32767.0)),
+            (largest, (w * 32767.0 + 0.5) as i32),
+        ];
+        for (index, value) in decoded.iter() {
+            let offset = 2 * (index & 3);
+            quaternion[offset..offset + 2].copy_from_slice(&(*value as i16).to_le_bytes());
+        }
+    }
+
+    Ok(())
+}
+
+/// Expands 32 bit values made of an 8 bit exponent and a 24 bit mantissa into floats.
+pub(super) fn decode_exponential(data: &mut [u8], stride: usize) -> Result<(), DecodeError> {
+    if !stride.is_multiple_of(4) {
+        return Err(DecodeError::new(
+            "meshopt: exponential filter requires a byte stride divisible by 4",
+        ));
+    }
+
+    for value in data.chunks_exact_mut(4) {
+        let bits = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+        let mantissa = ((bits << 8) as i32) >> 8;
+        let exponent = (bits as i32) >> 24;
+        let decoded = f32::from_bits(((exponent + 127) as u32) << 23) * mantissa as f32;
+        value.copy_from_slice(&decoded.to_le_bytes());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_exponential, decode_octahedral, decode_quaternion};
+
+    // Vectors from the filter tests of meshoptimizer.
+
+    fn i16_bytes(values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn decodes_8_bit_octahedral() {
+        let mut data = vec![
+            0, 1, 127, 0, 0, 187, 127, 1, 255, 1, 127, 0, 14, 130, 127, 1,
+        ];
+        decode_octahedral(&mut data, 4).unwrap();
+
+        assert_eq!(
+            data,
+            vec![0, 1, 127, 0, 0, 159, 82, 1, 255, 1, 127, 0, 1, 130, 241, 1]
+        );
+    }
+
+    #[test]
+    fn decodes_12_bit_octahedral() {
+        let mut data = i16_bytes(&[
+            0, 1, 2047, 0, 0, 1870, 2047, 1, 2017, 1, 2047, 0, 14, 1300, 2047, 1,
+        ]);
+        decode_octahedral(&mut data, 8).unwrap();
+
+        assert_eq!(
+            data,
+            i16_bytes(&[
+                0, 16, 32767, 0, 0, 32621, 3088, 1, 32764, 16, 471, 0, 307, 28541, 16093, 1,
+            ])
+        );
+    }
+
+    #[test]
+    fn decodes_12_bit_quaternion() {
+        let mut data = i16_bytes(&[
+            0, 1, 0, 0x7fc, 0, 1870, 0, 0x7fd, 2017, 1, 0, 0x7fe, 14, 1300, 0, 0x7ff,
+        ]);
+        decode_quaternion(&mut data, 8).unwrap();
+
+        assert_eq!(
+            data,
+            i16_bytes(&[
+                32767, 0, 11, 0, 0, 25013, 0, 21166, 11, 0, 23504, 22830, 158, 14715, 0, 29277,
+            ])
+        );
+    }
+
+    #[test]
+    fn decodes_exponential() {
+        let mut data: Vec<u8> = [0u32, 0xff000003, 0x02fffff7, 0xfe7fffff]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        decode_exponential(&mut data, 4).unwrap();
+
+        let expected: Vec<u8> = [0u32, 0x3fc00000, 0xc2100000, 0x49fffffe]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn invalid_strides_are_an_error() {
+        assert!(decode_octahedral(&mut [0; 12], 6).is_err());
+        assert!(decode_quaternion(&mut [0; 8], 4).is_err());
+        assert!(decode_exponential(&mut [0; 6], 6).is_err());
+    }
+}
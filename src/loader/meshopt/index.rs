@@ -0,0 +1,375 @@
+use crate::loader::error::DecodeError;
+
+const INDEX_HEADER: u8 = 0xe0;
+const SEQUENCE_HEADER: u8 = 0xd0;
+
+fn truncated() -> DecodeError {
+    DecodeError::new("meshopt: index data is truncated")
+}
+
+fn decode_vbyte(data: &[u8], position: &mut usize) -> Result<u32, DecodeError> {
+    let mut result = 0u32;
+    for i in 0..5 {
+        let group = *data.get(*position).ok_or_else(truncated)?;
+        *position += 1;
+        result |= u32::from(group & 127) << (7 * i);
+        if group < 128 {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+fn decode_index(data: &[u8], position: &mut usize, last: u32) -> Result<u32, DecodeError> {
+    let value = decode_vbyte(data, position)?;
+    let delta = (value >> 1) ^ 0u32.wrapping_sub(value & 1);
+
+    Ok(last.wrapping_add(delta))
+}
+
+fn write_index(output: &mut Vec<u8>, index: u32, index_size: usize) {
+    if index_size == 2 {
+        output.extend_from_slice(&(index as u16).to_le_bytes());
+    } else {
+        output.extend_from_slice(&index.to_le_bytes());
+    }
+}
+
+fn check_index_size(index_size: usize) -> Result<(), DecodeError> {
+    if index_size == 2 || index_size == 4 {
+        Ok(())
+    } else {
+        Err(DecodeError::new("meshopt: index size must be 2 or 4"))
+    }
+}
+
+struct Fifos {
+    edges: [[u32; 2]; 16],
+    edge_offset: usize,
+    vertices: [u32; 16],
+    vertex_offset: usize,
+}
+
+impl Fifos {
+    fn edge(&self, back: usize) -> [u32; 2] {
+        self.edges[(self.edge_offset.wrapping_sub(1 + back)) & 15]
+    }
+
+    fn vertex(&self, back: usize) -> u32 {
+        self.vertices[(self.vertex_offset.wrapping_sub(back)) & 15]
+    }
+
+    fn push_edge(&mut self, a: u32, b: u32) {
+        self.edges[self.edge_offset] = [a, b];
+        self.edge_offset = (self.edge_offset + 1) & 15;
+    }
+
+    fn push_vertex(&mut self, vertex: u32, condition: bool) {
+        self.vertices[self.vertex_offset] = vertex;
+        self.vertex_offset = (self.vertex_offset + condition as usize) & 15;
+    }
+}
+
+/// Decodes a `TRIANGLES` mode buffer (`meshopt_decodeIndexBuffer`, versions 0 and 1).
+pub(super) fn decode_index_buffer(
+    data: &[u8],
+    count: usize,
+    index_size: usize,
+) -> Result<Vec<u8>, DecodeError> {
+    check_index_size(index_size)?;
+    if !count.is_multiple_of(3) {
+        return Err(DecodeError::new(
+            "meshopt: triangle index count must be a multiple of 3",
+        ));
+    }
+    if data.len() < 1 + count / 3 + 16 {
+        return Err(truncated());
+    }
+    if data[0] & 0xf0 != INDEX_HEADER || data[0] & 0x0f > 1 {
+        return Err(DecodeError::new(format!(
+            "meshopt: unsupported index codec header {:#x}",
+            data[0]
+        )));
+    }
+    let fec_max = if data[0] & 0x0f >= 1 { 13 } else { 15 };
+
+    let mut fifos = Fifos {
+        edges: [[u32::MAX; 2]; 16],
+        edge_offset: 0,
+        vertices: [u32::MAX; 16],
+        vertex_offset: 0,
+    };
+    let mut next = 0u32;
+    let mut last = 0u32;
+
+    let codes = &data[1..1 + count / 3];
+    let safe_end = data.len() - 16;
+    let codeaux_table = &data[safe_end..];
+    let data = &data[..safe_end];
+    let mut position = 1 + count / 3;
+    let mut output = Vec::with_capacity(count * index_size);
+
+    for &code in codes {
+        if position > safe_end {
+            return Err(truncated());
+        }
+
+        let (a, b, c) = if code < 0xf0 {
+            let [a, b] = fifos.edge((code >> 4) as usize);
+            let fec = code & 15;
+
+            let c = if fec < fec_max {
+                let c = if fec == 0 {
+                    next
+                } else {
+                    fifos.vertex(1 + fec as usize)
+                };
+                next += (fec == 0) as u32;
+                fifos.push_vertex(c, fec == 0);
+                c
+            } else {
+                last = if fec != 15 {
+                    // 13 and 14 encode a delta of -1 and 1 to the last free index
+                    last.wrapping_add((i32::from(fec) - i32::from(fec ^ 3)) as u32)
+                } else {
+                    decode_index(data, &mut position, last)?
+                };
+                fifos.push_vertex(last, true);
+                last
+            };
+
+            fifos.push_edge(c, b);
+            fifos.push_edge(a, c);
+            (a, b, c)
+        } else {
+            let (a, b, c, feb, fec) = if code < 0xfe {
+                let codeaux = codeaux_table[(code & 15) as usize];
+                let (feb, fec) = (codeaux >> 4, codeaux & 15);
+
+                let a = next;
+                next += 1;
+                let b = if feb == 0 {
+                    next
+                } else {
+                    fifos.vertex(feb as usize)
+                };
+                next += (feb == 0) as u32;
+                let c = if fec == 0 {
+                    next
+                } else {
+                    fifos.vertex(fec as usize)
+                };
+                next += (fec == 0) as u32;
+                (a, b, c, feb, fec)
+            } else {
+                let codeaux = *data.get(position).ok_or_else(truncated)?;
+                position += 1;
+                let fea = if code == 0xfe { 0 } else { 15 };
+                let (feb, fec) = (codeaux >> 4, codeaux & 15);
+                if codeaux == 0 {
+                    next = 0;
+                }
+
+                let mut a = if fea == 0 {
+                    next += 1;
+                    next - 1
+                } else {
+                    0
+                };
+                let mut b = if feb == 0 {
+                    next += 1;
+                    next - 1
+                } else {
+                    fifos.vertex(feb as usize)
+                };
+                let mut c = if fec == 0 {
+                    next += 1;
+                    next - 1
+                } else {
+                    fifos.vertex(fec as usize)
+                };
+
+                if fea == 15 {
+                    last = decode_index(data, &mut position, last)?;
+                    a = last;
+                }
+                if feb == 15 {
+                    last = decode_index(data, &mut position, last)?;
+                    b = last;
+                }
+                if fec == 15 {
+                    last = decode_index(data, &mut position, last)?;
+                    c = last;
+                }
+                (a, b, c, feb, fec)
+            };
+
+            fifos.push_vertex(a, true);
+            fifos.push_vertex(b, feb == 0 || feb == 15);
+            fifos.push_vertex(c, fec == 0 || fec == 15);
+            fifos.push_edge(b, a);
+            fifos.push_edge(c, b);
+            fifos.push_edge(a, c);
+            (a, b, c)
+        };
+
+        write_index(&mut output, a, index_size);
+        write_index(&mut output, b, index_size);
+        write_index(&mut output, c, index_size);
+    }
+
+    if position != safe_end {
+        return Err(DecodeError::new("meshopt: unexpected index data size"));
+    }
+
+    Ok(output)
+}
+
+/// Decodes an `INDICES` mode buffer (`meshopt_decodeIndexSequence`).
+pub(super) fn decode_index_sequence(
+    data: &[u8],
+    count: usize,
+    index_size: usize,
+) -> Result<Vec<u8>, DecodeError> {
+    check_index_size(index_size)?;
+    if data.len() < 1 + count + 4 {
+        return Err(truncated());
+    }
+    if data[0] & 0xf0 != SEQUENCE_HEADER || data[0] & 0x0f > 1 {
+        return Err(DecodeError::new(format!(
+            "meshopt: unsupported index sequence header {:#x}",
+            data[0]
+        )));
+    }
+
+    let safe_end = data.len() - 4;
+    let data = &data[..safe_end];
+    let mut position = 1;
+    let mut last = [0u32; 2];
+    let mut output = Vec::with_capacity(count * index_size);
+
+    for _ in 0..count {
+        if position >= safe_end {
+            return Err(truncated());
+        }
+        let value = decode_vbyte(data, &mut position)?;
+        // the lowest bit selects which of the two baselines the delta applies to
+        let baseline = (value & 1) as usize;
+        let delta = value >> 1;
+        let delta = (delta >> 1) ^ 0u32.wrapping_sub(delta & 1);
+        last[baseline] = last[baseline].wrapping_add(delta);
+        write_index(&mut output, last[baseline], index_size);
+    }
+
+    if position != safe_end {
+        return Err(DecodeError::new("meshopt: unexpected index data size"));
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_index_buffer, decode_index_sequence};
+
+    // `INDEX_DATA_V0` and the index sequence are vectors of the meshoptimizer test
+    // suite, the former is also what `meshopt_encodeIndexBuffer` of meshoptimizer 0.12
+    // produces for `INDEX_BUFFER`.
+    const INDEX_BUFFER: [u16; 12] = [0, 1, 2, 2, 1, 3, 4, 6, 5, 7, 8, 9];
+    const INDEX_DATA_V0: [u8; 27] = [
+        0xe0, 0xf0, 0x10, 0xfe, 0xff, 0xf0, 0x0c, 0xff, 0x02, 0x02, 0x02, 0x00, 0x76, 0x87, 0x56,
+        0x67, 0x78, 0xa9, 0x86, 0x65, 0x89, 0x68, 0x98, 0x01, 0x69, 0x00, 0x00,
+    ];
+    /// Version 1 codes the third vertex of the last two triangles as one above and one
+    /// below the last free index (`fec` 14 and 13), which version 0 reads from the FIFO.
+    const INDEX_BUFFER_V1: [u16; 12] = [0, 1, 2, 10, 11, 12, 10, 12, 13, 10, 13, 12];
+    const INDEX_DATA_V1: [u8; 25] = [
+        0xe1, 0xf0, 0xff, 0x0e, 0x0d, 0xff, 0x14, 0x02, 0x02, 0x00, 0x76, 0x87, 0x56, 0x67, 0x78,
+        0xa9, 0x86, 0x65, 0x89, 0x68, 0x98, 0x01, 0x69, 0x00, 0x00,
+    ];
+    const INDEX_SEQUENCE: [u32; 6] = [0, 1, 51, 2, 49, 1000];
+    const INDEX_SEQUENCE_DATA: [u8; 13] = [
+        0xd1, 0x00, 0x04, 0xcd, 0x01, 0x04, 0x07, 0x98, 0x1f, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    fn u16_bytes(values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn u32_bytes(values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn decodes_triangles() {
+        assert_eq!(
+            decode_index_buffer(&INDEX_DATA_V0, 12, 2),
+            Ok(u16_bytes(&INDEX_BUFFER))
+        );
+    }
+
+    #[test]
+    fn decodes_relative_vertices_of_version_1() {
+        assert_eq!(
+            decode_index_buffer(&INDEX_DATA_V1, 12, 2),
+            Ok(u16_bytes(&INDEX_BUFFER_V1))
+        );
+
+        let mut data = INDEX_DATA_V1;
+        data[0] = 0xe0;
+        assert_ne!(
+            decode_index_buffer(&data, 12, 2),
+            Ok(u16_bytes(&INDEX_BUFFER_V1))
+        );
+    }
+
+    #[test]
+    fn decodes_32_bit_triangles() {
+        let expected: Vec<u32> = INDEX_BUFFER.iter().map(|index| u32::from(*index)).collect();
+
+        assert_eq!(
+            decode_index_buffer(&INDEX_DATA_V0, 12, 4),
+            Ok(u32_bytes(&expected))
+        );
+    }
+
+    #[test]
+    fn decodes_index_sequence() {
+        assert_eq!(
+            decode_index_sequence(&INDEX_SEQUENCE_DATA, 6, 4),
+            Ok(u32_bytes(&INDEX_SEQUENCE))
+        );
+    }
+
+    #[test]
+    fn truncated_triangles_are_an_error() {
+        for length in 0..INDEX_DATA_V0.len() {
+            assert!(decode_index_buffer(&INDEX_DATA_V0[..length], 12, 2).is_err());
+        }
+    }
+
+    #[test]
+    fn truncated_index_sequence_is_an_error() {
+        for length in 0..INDEX_SEQUENCE_DATA.len() {
+            assert!(decode_index_sequence(&INDEX_SEQUENCE_DATA[..length], 6, 4).is_err());
+        }
+    }
+
+    #[test]
+    fn unknown_headers_are_an_error() {
+        let mut data = INDEX_DATA_V1;
+        data[0] = 0xe2;
+        assert!(decode_index_buffer(&data, 12, 2).is_err());
+
+        let mut data = INDEX_SEQUENCE_DATA;
+        data[0] = 0xe1;
+        assert!(decode_index_sequence(&data, 6, 4).is_err());
+    }
+}
@@ -0,0 +1,47 @@
+//! Pure Rust decoder for buffers compressed with meshoptimizer as used by
+//! `EXT_meshopt_compression`.
+//!
+//! Supports the `ATTRIBUTES`, `TRIANGLES` and `INDICES` modes together with the
+//! `OCTAHEDRAL`, `QUATERNION` and `EXPONENTIAL` filters.
+
+mod filter;
+mod index;
+mod vertex;
+
+use crate::loader::error::DecodeError;
+
+/// Decodes `count` elements of `stride` bytes each and applies `filter` to the result.
+pub(crate) fn decode(
+    data: &[u8],
+    count: usize,
+    stride: usize,
+    mode: &str,
+    filter: Option<&str>,
+) -> Result<Vec<u8>, DecodeError> {
+    let mut output = match mode {
+        "ATTRIBUTES" => vertex::decode_vertex_buffer(data, count, stride)?,
+        "TRIANGLES" => index::decode_index_buffer(data, count, stride)?,
+        "INDICES" => index::decode_index_sequence(data, count, stride)?,
+        _ => {
+            return Err(DecodeError::new(format!(
+                "meshopt: unknown compression mode {}",
+                mode
+            )))
+        }
+    };
+
+    match (mode, filter.unwrap_or("NONE")) {
+        (_, "NONE") => {}
+        ("ATTRIBUTES", "OCTAHEDRAL") => filter::decode_octahedral(&mut output, stride)?,
+        ("ATTRIBUTES", "QUATERNION") => filter::decode_quaternion(&mut output, stride)?,
+        ("ATTRIBUTES", "EXPONENTIAL") => filter::decode_exponential(&mut output, stride)?,
+        (_, filter) => {
+            return Err(DecodeError::new(format!(
+                "meshopt: filter {} can not be used with mode {}",
+                filter, mode
+            )))
+        }
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,209 @@
+use crate::loader::error::DecodeError;
+
+const VERTEX_HEADER: u8 = 0xa0;
+const VERTEX_BLOCK_SIZE_BYTES: usize = 8192;
+const VERTEX_BLOCK_MAX_SIZE: usize = 256;
+const BYTE_GROUP_SIZE: usize = 16;
+const BYTE_GROUP_DECODE_LIMIT: usize = 24;
+const TAIL_MAX_SIZE: usize = 32;
+
+fn vertex_block_size(vertex_size: usize) -> usize {
+    let result = (VERTEX_BLOCK_SIZE_BYTES / vertex_size) & !(BYTE_GROUP_SIZE - 1);
+    result.min(VERTEX_BLOCK_MAX_SIZE)
+}
+
+fn unzigzag8(value: u8) -> u8 {
+    (0u8.wrapping_sub(value & 1)) ^ (value >> 1)
+}
+
+fn truncated() -> DecodeError {
+    DecodeError::new("meshopt: vertex data is truncated")
+}
+
+/// Decodes one group of 16 bytes stored with 0, 2, 4 or 8 bits per byte, the 2 and 4
+/// bit encodings fall back to a trailing byte for their maximum value.
+fn decode_bytes_group(data: &[u8], out: &mut [u8], mode: u8) -> usize {
+    match mode {
+        0 => {
+            out.iter_mut().for_each(|value| *value = 0);
+            0
+        }
+        1 | 2 => {
+            let bits = if mode == 1 { 2 } else { 4 };
+            let sentinel = (1u8 << bits) - 1;
+            let mut extra = 16 * bits / 8;
+            for (i, value) in out.iter_mut().enumerate() {
+                let bit_offset = i * bits;
+                let byte = data[bit_offset / 8];
+                let encoded = (byte << (bit_offset % 8)) >> (8 - bits);
+                *value = if encoded == sentinel {
+                    extra += 1;
+                    data[extra - 1]
+                } else {
+                    encoded
+                };
+            }
+            extra
+        }
+        _ => {
+            out.copy_from_slice(&data[..BYTE_GROUP_SIZE]);
+            BYTE_GROUP_SIZE
+        }
+    }
+}
+
+/// Header bytes of one byte channel of a block of `vertex_count` vertices, the least
+/// a channel can take when every group is stored with 0 bits.
+fn header_size(vertex_count: usize) -> usize {
+    vertex_count.div_ceil(BYTE_GROUP_SIZE).div_ceil(4)
+}
+
+fn decode_bytes(data: &[u8], out: &mut [u8]) -> Result<usize, DecodeError> {
+    let header_size = header_size(out.len());
+    if data.len() < header_size {
+        return Err(truncated());
+    }
+    let (header, mut position) = (&data[..header_size], header_size);
+
+    for (group, chunk) in out.chunks_mut(BYTE_GROUP_SIZE).enumerate() {
+        if data.len() - position < BYTE_GROUP_DECODE_LIMIT {
+            return Err(truncated());
+        }
+        let mode = (header[group / 4] >> ((group % 4) * 2)) & 3;
+        position += decode_bytes_group(&data[position..], chunk, mode);
+    }
+
+    Ok(position)
+}
+
+/// Decodes an `ATTRIBUTES` mode buffer (`meshopt_decodeVertexBuffer`, version 0).
+pub(super) fn decode_vertex_buffer(
+    data: &[u8],
+    count: usize,
+    vertex_size: usize,
+) -> Result<Vec<u8>, DecodeError> {
+    if vertex_size == 0 || vertex_size > 256 || !vertex_size.is_multiple_of(4) {
+        return Err(DecodeError::new("meshopt: invalid vertex size"));
+    }
+    if data.len() < 1 + vertex_size {
+        return Err(truncated());
+    }
+    if data[0] != VERTEX_HEADER {
+        return Err(DecodeError::new(format!(
+            "meshopt: unsupported vertex codec header {:#x}",
+            data[0]
+        )));
+    }
+
+    // `count` comes from the file, the output is only allocated for as many vertices as
+    // the data can encode.
+    let block_size = vertex_block_size(vertex_size);
+    let tail_size = vertex_size.max(TAIL_MAX_SIZE);
+    let remainder = count % block_size;
+    let min_length = (count / block_size)
+        .checked_mul(header_size(block_size))
+        .and_then(|length| length.checked_add(header_size(remainder)))
+        .and_then(|length| length.checked_mul(vertex_size))
+        .and_then(|length| length.checked_add(1 + tail_size));
+    let output_length = count.checked_mul(vertex_size);
+    let output_length = match (min_length, output_length) {
+        (Some(min_length), Some(output_length)) if min_length <= data.len() => output_length,
+        _ => return Err(truncated()),
+    };
+
+    let mut last_vertex = data[data.len() - vertex_size..].to_vec();
+    let mut output = vec![0u8; output_length];
+    let mut buffer = vec![0u8; block_size];
+    let mut position = 1;
+
+    let mut vertex_offset = 0;
+    while vertex_offset < count {
+        let vertex_count = block_size.min(count - vertex_offset);
+        let aligned_count = (vertex_count + BYTE_GROUP_SIZE - 1) & !(BYTE_GROUP_SIZE - 1);
+        let block =
+            &mut output[vertex_offset * vertex_size..(vertex_offset + vertex_count) * vertex_size];
+
+        for k in 0..vertex_size {
+            position += decode_bytes(&data[position..], &mut buffer[..aligned_count])?;
+
+            let mut previous = last_vertex[k];
+            for (i, delta) in buffer.iter().take(vertex_count).enumerate() {
+                let value = unzigzag8(*delta).wrapping_add(previous);
+                block[i * vertex_size + k] = value;
+                previous = value;
+            }
+        }
+
+        last_vertex.copy_from_slice(&block[(vertex_count - 1) * vertex_size..]);
+        vertex_offset += vertex_count;
+    }
+
+    if data.len() - position != tail_size {
+        return Err(DecodeError::new("meshopt: unexpected vertex data size"));
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_vertex_buffer;
+
+    /// A 3x2 grid of four 16 bit components, `[0, 300, 600] x [0, 300]`.
+    const VERTICES: [[u16; 4]; 6] = [
+        [0, 0, 0, 0],
+        [300, 0, 0, 0],
+        [600, 0, 0, 0],
+        [0, 300, 0, 0],
+        [300, 300, 0, 0],
+        [600, 300, 0, 0],
+    ];
+    /// `VERTICES` encoded by `meshopt_encodeVertexBuffer` of meshoptimizer 0.12.
+    const VERTEX_DATA: [u8; 64] = [
+        0xa0, 0x01, 0x3f, 0xf0, 0x00, 0x00, 0x58, 0x58, 0xaf, 0x58, 0x58, 0x01, 0x2b, 0xa0, 0x00,
+        0x00, 0x03, 0x01, 0x03, 0x00, 0x00, 0x00, 0x58, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn decodes_vertex_buffer() {
+        let expected: Vec<u8> = VERTICES
+            .iter()
+            .flatten()
+            .flat_map(|component| component.to_le_bytes())
+            .collect();
+
+        assert_eq!(decode_vertex_buffer(&VERTEX_DATA, 6, 8), Ok(expected));
+    }
+
+    #[test]
+    fn truncated_data_is_an_error() {
+        for length in 0..VERTEX_DATA.len() {
+            assert!(decode_vertex_buffer(&VERTEX_DATA[..length], 6, 8).is_err());
+        }
+    }
+
+    #[test]
+    fn trailing_data_is_an_error() {
+        let mut data = VERTEX_DATA.to_vec();
+        data.push(0);
+
+        assert!(decode_vertex_buffer(&data, 6, 8).is_err());
+    }
+
+    #[test]
+    fn counts_the_data_can_not_encode_are_an_error() {
+        assert!(decode_vertex_buffer(&VERTEX_DATA, usize::MAX, 8).is_err());
+        assert!(decode_vertex_buffer(&VERTEX_DATA, usize::MAX / 8 + 1, 8).is_err());
+        // Each block of 256 vertices needs at least 4 header bytes per vertex byte.
+        assert!(decode_vertex_buffer(&VERTEX_DATA, 1 << 30, 8).is_err());
+    }
+
+    #[test]
+    fn invalid_vertex_sizes_are_an_error() {
+        assert!(decode_vertex_buffer(&VERTEX_DATA, 6, 6).is_err());
+        assert!(decode_vertex_buffer(&VERTEX_DATA, 6, 260).is_err());
+    }
+}
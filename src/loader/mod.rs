@@ -1 +1,9 @@
+pub(crate) mod draco;
+pub(crate) mod error;
+pub(crate) mod fetch;
 pub(crate) mod glb;
+pub(crate) mod hdr;
+pub(crate) mod ktx2;
+pub(crate) mod meshopt;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod png;
@@ -0,0 +1,605 @@
+//! Decodes PNG images without the browser, so textures can be loaded where no image
+//! element exists. Interlaced images are not supported.
+
+use crate::loader::error::DecodeError;
+
+const SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Base lengths and extra bits of the deflate length symbols 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order the code length code lengths of a dynamic block are stored in.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+#[derive(Debug)]
+pub(crate) struct PngImage {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// RGBA texels, 4 bytes per pixel, top row first.
+    pub(crate) data: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+}
+
+impl Header {
+    fn channels(&self) -> Result<usize, DecodeError> {
+        match self.color_type {
+            0 | 3 => Ok(1),
+            2 => Ok(3),
+            4 => Ok(2),
+            6 => Ok(4),
+            color_type => Err(DecodeError::new(format!(
+                "png: color type {} is not supported",
+                color_type
+            ))),
+        }
+    }
+}
+
+pub(crate) fn parse(data: &[u8]) -> Result<PngImage, DecodeError> {
+    if !data.starts_with(SIGNATURE) {
+        return Err(DecodeError::new("png: invalid signature"));
+    }
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = vec![];
+    let mut offset = SIGNATURE.len();
+    loop {
+        let length = be_u32(data, offset)? as usize;
+        let kind = data
+            .get(offset + 4..offset + 8)
+            .ok_or_else(|| DecodeError::new("png: chunk is truncated"))?;
+        let content = data
+            .get(offset + 8..offset + 8 + length)
+            .ok_or_else(|| DecodeError::new("png: chunk is truncated"))?;
+        // Chunk type, content and CRC.
+        offset += 12 + length;
+
+        match kind {
+            b"IHDR" => {
+                if content.len() < 13 {
+                    return Err(DecodeError::new("png: header is truncated"));
+                }
+                if content[12] != 0 {
+                    return Err(DecodeError::new("png: interlaced images are not supported"));
+                }
+                header = Some(Header {
+                    width: be_u32(content, 0)?,
+                    height: be_u32(content, 4)?,
+                    bit_depth: content[8],
+                    color_type: content[9],
+                });
+            }
+            b"PLTE" => palette = content,
+            b"tRNS" => transparency = content,
+            b"IDAT" => compressed.extend_from_slice(content),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or_else(|| DecodeError::new("png: header is missing"))?;
+    let channels = header.channels()?;
+    let bits_per_pixel = channels * header.bit_depth as usize;
+    if ![1, 2, 4, 8, 16].contains(&header.bit_depth) {
+        return Err(DecodeError::new(format!(
+            "png: bit depth {} is not supported",
+            header.bit_depth
+        )));
+    }
+
+    let stride = (header.width as usize * bits_per_pixel).div_ceil(8);
+    let mut scanlines = inflate_zlib(&compressed)?;
+    if scanlines.len() < (stride + 1) * header.height as usize {
+        return Err(DecodeError::new("png: image data is truncated"));
+    }
+    unfilter(
+        &mut scanlines,
+        stride,
+        header.height as usize,
+        bits_per_pixel.div_ceil(8),
+    )?;
+
+    let mut pixels = Vec::with_capacity(header.width as usize * header.height as usize * 4);
+    for row in 0..header.height as usize {
+        let line = &scanlines[row * (stride + 1) + 1..(row + 1) * (stride + 1)];
+        for column in 0..header.width as usize {
+            pixels.extend_from_slice(&rgba(&header, line, column, palette, transparency)?);
+        }
+    }
+
+    Ok(PngImage {
+        width: header.width,
+        height: header.height,
+        data: pixels,
+    })
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Result<u32, DecodeError> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| DecodeError::new("png: chunk is truncated"))
+}
+
+/// Reverses the per scanline filters in place, every line starts with its filter type.
+fn unfilter(
+    data: &mut [u8],
+    stride: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+) -> Result<(), DecodeError> {
+    for row in 0..height {
+        let start = row * (stride + 1);
+        let filter = data[start];
+        for index in 0..stride {
+            let position = start + 1 + index;
+            let left = if index >= bytes_per_pixel {
+                data[position - bytes_per_pixel]
+            } else {
+                0
+            };
+            let up = if row > 0 {
+                data[position - stride - 1]
+            } else {
+                0
+            };
+            let up_left = if row > 0 && index >= bytes_per_pixel {
+                data[position - stride - 1 - bytes_per_pixel]
+            } else {
+                0
+            };
+
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                filter => {
+                    return Err(DecodeError::new(format!(
+                        "png: unknown filter type {}",
+                        filter
+                    )))
+                }
+            };
+            data[position] = data[position].wrapping_add(predictor);
+        }
+    }
+
+    Ok(())
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance_left = (estimate - left as i16).abs();
+    let distance_up = (estimate - up as i16).abs();
+    let distance_up_left = (estimate - up_left as i16).abs();
+
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Sample `index` of a scanline, samples of 16 bits keep their high byte and samples
+/// below 8 bits are scaled to the full byte unless they index the palette.
+fn sample(header: &Header, line: &[u8], index: usize, scale: bool) -> u8 {
+    match header.bit_depth {
+        8 => line[index],
+        16 => line[index * 2],
+        depth => {
+            let depth = depth as usize;
+            let bit = index * depth;
+            let value = (line[bit / 8] >> (8 - depth - bit % 8)) & ((1 << depth) - 1) as u8;
+            if scale {
+                (value as u16 * 255 / ((1 << depth) - 1)) as u8
+            } else {
+                value
+            }
+        }
+    }
+}
+
+fn rgba(
+    header: &Header,
+    line: &[u8],
+    column: usize,
+    palette: &[u8],
+    transparency: &[u8],
+) -> Result<[u8; 4], DecodeError> {
+    let pixel = match header.color_type {
+        0 => {
+            let gray = sample(header, line, column, true);
+            [gray, gray, gray, 255]
+        }
+        2 => [
+            sample(header, line, column * 3, true),
+            sample(header, line, column * 3 + 1, true),
+            sample(header, line, column * 3 + 2, true),
+            255,
+        ],
+        3 => {
+            let index = sample(header, line, column, false) as usize;
+            let color = palette
+                .get(index * 3..index * 3 + 3)
+                .ok_or_else(|| DecodeError::new("png: palette index out of range"))?;
+            let alpha = transparency.get(index).copied().unwrap_or(255);
+            [color[0], color[1], color[2], alpha]
+        }
+        4 => {
+            let gray = sample(header, line, column * 2, true);
+            [gray, gray, gray, sample(header, line, column * 2 + 1, true)]
+        }
+        _ => [
+            sample(header, line, column * 4, true),
+            sample(header, line, column * 4 + 1, true),
+            sample(header, line, column * 4 + 2, true),
+            sample(header, line, column * 4 + 3, true),
+        ],
+    };
+
+    Ok(pixel)
+}
+
+/// Canonical Huffman code, decoded one bit at a time.
+struct Huffman {
+    /// Number of codes of each length.
+    counts: [u16; 16],
+    /// Symbols ordered by code.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+
+        Huffman { counts, symbols }
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    /// `count` bits, least significant first.
+    fn bits(&mut self, count: u32) -> Result<u32, DecodeError> {
+        while self.count < count {
+            let byte = *self
+                .data
+                .get(self.offset)
+                .ok_or_else(|| DecodeError::new("png: compressed data is truncated"))?;
+            self.buffer |= (byte as u64) << self.count;
+            self.offset += 1;
+            self.count += 8;
+        }
+        let value = (self.buffer & ((1u64 << count) - 1)) as u32;
+        self.buffer >>= count;
+        self.count -= count;
+
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        let skip = self.count % 8;
+        self.buffer >>= skip;
+        self.count -= skip;
+    }
+
+    fn decode(&mut self, huffman: &Huffman) -> Result<u16, DecodeError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= self.bits(1)? as i32;
+            let count = huffman.counts[length] as i32;
+            if code - first < count {
+                return Ok(huffman.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(DecodeError::new("png: invalid Huffman code"))
+    }
+}
+
+/// Inflates a zlib stream, the checksum is not verified.
+fn inflate_zlib(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    match data {
+        [method, flags, ..] if method & 0x0F == 8 && flags & 0x20 == 0 => {}
+        _ => return Err(DecodeError::new("png: unsupported zlib stream")),
+    }
+
+    let mut reader = BitReader {
+        data: &data[2..],
+        offset: 0,
+        buffer: 0,
+        count: 0,
+    };
+    let mut output = vec![];
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let length = reader.bits(16)? as usize;
+                reader.bits(16)?;
+                for _ in 0..length {
+                    output.push(reader.bits(8)? as u8);
+                }
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(DecodeError::new("png: invalid deflate block type")),
+        }
+
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+fn dynamic_codes(reader: &mut BitReader<'_>) -> Result<(Huffman, Huffman), DecodeError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for index in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[*index] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = vec![];
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match reader.decode(&code_lengths)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| DecodeError::new("png: length repeat without a length"))?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(DecodeError::new("png: too many code lengths"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader<'_>,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), DecodeError> {
+    loop {
+        let symbol = reader.decode(literals)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err(DecodeError::new("png: invalid length symbol"));
+                }
+                let length =
+                    LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+                let index = reader.decode(distances)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(DecodeError::new("png: invalid distance symbol"));
+                }
+                let distance = DISTANCE_BASE[index] as usize
+                    + reader.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+                if distance > output.len() {
+                    return Err(DecodeError::new("png: distance exceeds the output"));
+                }
+
+                let start = output.len() - distance;
+                for offset in 0..length {
+                    output.push(output[start + offset]);
+                }
+            }
+        }
+    }
+}
+
+/// Encodes RGBA bytes, top row first, as a PNG. Rows use the sub filter and are only
+/// compressed by run length, which suits rendered test images with plain backgrounds.
+#[cfg(test)]
+pub(crate) fn encode(width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+    let row_length = width as usize * 4;
+    let mut raw = Vec::with_capacity((row_length + 1) * height as usize);
+    for row in data.chunks_exact(row_length) {
+        raw.push(1);
+        raw.extend(
+            row.iter()
+                .enumerate()
+                .map(|(index, byte)| byte.wrapping_sub(if index < 4 { 0 } else { row[index - 4] })),
+        );
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    zlib.extend(deflate_runs(&raw));
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in &raw {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    zlib.extend_from_slice(&((b << 16) | a).to_be_bytes());
+
+    let mut header = vec![];
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = SIGNATURE.to_vec();
+    for (chunk_type, chunk) in [(b"IHDR", header), (b"IDAT", zlib), (b"IEND", vec![])] {
+        png.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend_from_slice(chunk_type);
+        png.extend_from_slice(&chunk);
+        let crc = crc32(&png[start..]);
+        png.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    png
+}
+
+/// A single block with the fixed codes, repeated bytes become matches at distance 1.
+#[cfg(test)]
+fn deflate_runs(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    writer.bits(0b011, 3);
+
+    let mut index = 0;
+    while index < data.len() {
+        let run = match index {
+            0 => 0,
+            _ => data[index..]
+                .iter()
+                .take(258)
+                .take_while(|byte| **byte == data[index - 1])
+                .count(),
+        };
+        if run < 3 {
+            writer.symbol(data[index] as u16);
+            index += 1;
+            continue;
+        }
+
+        let code = LENGTH_BASE
+            .iter()
+            .rposition(|base| *base as usize <= run)
+            .unwrap();
+        writer.symbol(257 + code as u16);
+        writer.bits(
+            (run - LENGTH_BASE[code] as usize) as u32,
+            LENGTH_EXTRA[code] as u32,
+        );
+        // Distance code 0, a distance of 1.
+        writer.bits(0, 5);
+        index += run;
+    }
+    writer.symbol(256);
+
+    writer.finish()
+}
+
+/// Writes deflate bits, least significant first.
+#[cfg(test)]
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit: u32,
+}
+
+#[cfg(test)]
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        for index in 0..count {
+            if self.bit == 0 {
+                self.bytes.push(0);
+            }
+            *self.bytes.last_mut().unwrap() |= (((value >> index) & 1) as u8) << self.bit;
+            self.bit = (self.bit + 1) % 8;
+        }
+    }
+
+    /// Writes a literal or length symbol with its fixed Huffman code, most significant
+    /// bit first.
+    fn symbol(&mut self, symbol: u16) {
+        let (code, length) = match symbol {
+            0..=143 => (0x30 + symbol as u32, 8),
+            144..=255 => (0x190 + symbol as u32 - 144, 9),
+            256..=279 => (symbol as u32 - 256, 7),
+            _ => (0xC0 + symbol as u32 - 280, 8),
+        };
+        for index in (0..length).rev() {
+            self.bits((code >> index) & 1, 1);
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (!(crc & 1)).wrapping_add(1));
+        }
+    }
+
+    !crc
+}
@@ -0,0 +1,1606 @@
+use crate::renderer::backend::{
+    Backend, Buffer, Framebuffer, Handle, Program, Query, Renderbuffer, Shader, Texture,
+    UniformLocation, UploadCounter, UploadTotals, VertexArray,
+};
+use software::Rasterizer;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+use web_sys::{HtmlImageElement, WebGlRenderingContext};
+
+/// `MAX_TEXTURE_SIZE` and `MAX_RENDERBUFFER_SIZE`, what most desktop GPUs report at least.
+const MAX_SIZE: i32 = 4096;
+/// `MAX_VERTEX_ATTRIBS` of most GPUs.
+const MAX_VERTEX_ATTRIBS: i32 = 16;
+
+mod software;
+
+/// One call made to a `HeadlessBackend`. Objects are named by the number of their handle.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Command {
+    CreateBuffer(u32),
+    DeleteBuffer(u32),
+    BindBuffer {
+        target: u32,
+        buffer: Option<u32>,
+    },
+    BufferData {
+        target: u32,
+        bytes: usize,
+    },
+    CreateTexture(u32),
+    DeleteTexture(u32),
+    ActiveTexture(u32),
+    BindTexture {
+        target: u32,
+        texture: Option<u32>,
+    },
+    TexParameter {
+        target: u32,
+        parameter: u32,
+        value: i32,
+    },
+    TexImage {
+        target: u32,
+        level: i32,
+        width: i32,
+        height: i32,
+    },
+    GenerateMipmap(u32),
+    CreateFramebuffer(u32),
+    DeleteFramebuffer(u32),
+    BindFramebuffer(Option<u32>),
+    FramebufferTexture {
+        attachment: u32,
+        texture: Option<u32>,
+    },
+    CreateRenderbuffer(u32),
+    DeleteRenderbuffer(u32),
+    BindRenderbuffer(Option<u32>),
+    RenderbufferStorage {
+        format: u32,
+        width: i32,
+        height: i32,
+    },
+    FramebufferRenderbuffer {
+        attachment: u32,
+        renderbuffer: Option<u32>,
+    },
+    CreateShader(u32),
+    CompileShader(u32),
+    DeleteShader(u32),
+    CreateProgram(u32),
+    DeleteProgram(u32),
+    LinkProgram(u32),
+    UseProgram(Option<u32>),
+    EnableAttribute(u32),
+    DisableAttribute(u32),
+    AttributePointer {
+        location: u32,
+        buffer: u32,
+        size: i32,
+        data_type: u32,
+        stride: i32,
+        offset: i32,
+    },
+    Uniform {
+        name: String,
+        values: Vec<f32>,
+    },
+    Enable(u32),
+    Disable(u32),
+    BlendFunc(u32, u32),
+    DepthMask(bool),
+    DepthFunc(u32),
+    CullFace(u32),
+    FrontFace(u32),
+    Viewport(i32, i32, i32, i32),
+    ClearColor([f32; 4]),
+    ClearDepth(f32),
+    Clear(u32),
+    DrawArrays {
+        mode: u32,
+        first: i32,
+        count: i32,
+    },
+    DrawElements {
+        mode: u32,
+        count: i32,
+        data_type: u32,
+        offset: i32,
+    },
+    ReadPixels {
+        width: i32,
+        height: i32,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AttributePointer {
+    buffer: u32,
+    size: i32,
+    data_type: u32,
+    normalized: bool,
+    stride: i32,
+    offset: i32,
+}
+
+#[derive(Debug, Default)]
+struct ProgramState {
+    shaders: Vec<u32>,
+    linked: bool,
+    /// Sources of the attached shaders when the program was linked.
+    sources: Vec<String>,
+    attributes: Vec<String>,
+    uniforms: HashSet<String>,
+    /// Last values set for each uniform.
+    values: HashMap<String, Vec<f32>>,
+}
+
+#[derive(Debug, Default)]
+struct TextureState {
+    width: i32,
+    height: i32,
+    /// RGBA bytes of level 0, empty when it was uploaded in another format.
+    pixels: Vec<u8>,
+    wrap_s: u32,
+    wrap_t: u32,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_handle: u32,
+    commands: Vec<Command>,
+    errors: Vec<String>,
+    /// Contents of every live buffer.
+    buffers: HashMap<u32, Vec<u8>>,
+    textures: HashMap<u32, TextureState>,
+    framebuffers: HashSet<u32>,
+    renderbuffers: HashSet<u32>,
+    /// Source of every live shader.
+    shaders: HashMap<u32, String>,
+    programs: HashMap<u32, ProgramState>,
+    /// Program and name of every uniform location handed out.
+    uniform_locations: HashMap<u32, (u32, String)>,
+    array_buffer: Option<u32>,
+    element_array_buffer: Option<u32>,
+    active_unit: u32,
+    /// Texture bound to each unit and target.
+    bound_textures: HashMap<(u32, u32), u32>,
+    program: Option<u32>,
+    enabled_attributes: HashSet<u32>,
+    attribute_pointers: HashMap<u32, AttributePointer>,
+    capabilities: HashSet<u32>,
+    framebuffer: Option<u32>,
+    viewport: [i32; 4],
+    clear_color: [f32; 4],
+    clear_depth: f32,
+    blend_func: (u32, u32),
+    depth_writes_disabled: bool,
+    depth_func: u32,
+    cull_face: u32,
+    front_face: u32,
+    /// Draws into the default framebuffer on the CPU when set.
+    rasterizer: Option<Rasterizer>,
+}
+
+impl State {
+    /// Defaults of a new context with a drawing buffer of `width` by `height`.
+    fn new(width: i32, height: i32) -> State {
+        State {
+            viewport: [0, 0, width, height],
+            clear_depth: 1.,
+            blend_func: (WebGlRenderingContext::ONE, WebGlRenderingContext::ZERO),
+            depth_func: WebGlRenderingContext::LESS,
+            cull_face: WebGlRenderingContext::BACK,
+            front_face: WebGlRenderingContext::CCW,
+            ..State::default()
+        }
+    }
+
+    fn create(&mut self) -> u32 {
+        self.next_handle += 1;
+        self.next_handle
+    }
+
+    fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    /// The number of a handle, errors for handles of another backend.
+    fn id<T>(&mut self, call: &str, handle: &Handle<T>) -> Option<u32> {
+        let id = handle.headless();
+        if id.is_none() {
+            self.error(format!("{}: handle of another backend", call));
+        }
+
+        id
+    }
+
+    fn bound_buffer(&mut self, call: &str, target: u32) -> Option<u32> {
+        let buffer = match target {
+            WebGlRenderingContext::ARRAY_BUFFER => self.array_buffer,
+            WebGlRenderingContext::ELEMENT_ARRAY_BUFFER => self.element_array_buffer,
+            _ => {
+                self.error(format!("{}: invalid buffer target {:#x}", call, target));
+                return None;
+            }
+        };
+        if buffer.is_none() {
+            self.error(format!("{}: no buffer bound to {:#x}", call, target));
+        }
+
+        buffer
+    }
+
+    fn buffer_data(&mut self, target: u32, data: Vec<u8>) {
+        self.commands.push(Command::BufferData {
+            target,
+            bytes: data.len(),
+        });
+        if let Some(buffer) = self.bound_buffer("buffer_data", target) {
+            self.buffers.insert(buffer, data);
+        }
+    }
+
+    /// Texture target whose binding `target` uploads to, cube faces upload to the cubemap.
+    fn texture_binding(target: u32) -> u32 {
+        match target {
+            WebGlRenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X
+                ..=WebGlRenderingContext::TEXTURE_CUBE_MAP_NEGATIVE_Z => {
+                WebGlRenderingContext::TEXTURE_CUBE_MAP
+            }
+            target => target,
+        }
+    }
+
+    fn bound_texture(&mut self, call: &str, target: u32) -> Option<u32> {
+        let binding = State::texture_binding(target);
+        let texture = self
+            .bound_textures
+            .get(&(self.active_unit, binding))
+            .copied();
+        if texture.is_none() {
+            self.error(format!(
+                "{}: no texture bound to {:#x} of unit {}",
+                call, binding, self.active_unit
+            ));
+        }
+
+        texture
+    }
+
+    /// Uploads to the bound texture, only RGBA bytes of a 2D level 0 are kept.
+    fn tex_image(
+        &mut self,
+        call: &str,
+        target: u32,
+        level: i32,
+        width: i32,
+        height: i32,
+        pixels: Vec<u8>,
+    ) {
+        self.commands.push(Command::TexImage {
+            target,
+            level,
+            width,
+            height,
+        });
+        let texture = match self.bound_texture(call, target) {
+            Some(texture) => texture,
+            None => return,
+        };
+        if level != 0 || target != WebGlRenderingContext::TEXTURE_2D {
+            return;
+        }
+        if let Some(texture) = self.textures.get_mut(&texture) {
+            texture.width = width;
+            texture.height = height;
+            texture.pixels = pixels;
+        }
+    }
+
+    /// Runs a draw through the rasterizer, if there is one and the default framebuffer
+    /// is bound. Draws that failed validation are not executed.
+    fn rasterize(&mut self, errors_before: usize, mode: u32, indices: &[u32]) {
+        if self.framebuffer.is_some() || self.errors.len() > errors_before {
+            return;
+        }
+        if let Some(mut rasterizer) = self.rasterizer.take() {
+            rasterizer.draw(self, mode, indices);
+            self.rasterizer = Some(rasterizer);
+        }
+    }
+
+    fn uniform(&mut self, location: Option<&UniformLocation>, values: Vec<f32>) {
+        let location = match location {
+            Some(location) => location,
+            // Uniforms the program does not use are silently ignored, as in WebGL.
+            None => return,
+        };
+        let id = match self.id("uniform", location) {
+            Some(id) => id,
+            None => return,
+        };
+        let (program, name) = match self.uniform_locations.get(&id) {
+            Some((program, name)) => (*program, name.clone()),
+            None => {
+                self.error(format!("uniform: unknown location {}", id));
+                return;
+            }
+        };
+        if self.program != Some(program) {
+            self.error(format!(
+                "uniform: {} belongs to program {}, not the one in use",
+                name, program
+            ));
+        }
+        if let Some(program) = self.programs.get_mut(&program) {
+            program.values.insert(name.clone(), values.clone());
+        }
+
+        self.commands.push(Command::Uniform { name, values });
+    }
+
+    /// Checks that the program in use and every enabled attribute array can provide
+    /// `vertex_count` vertices.
+    fn check_draw(&mut self, call: &str, mode: u32, count: i32, vertex_count: usize) {
+        if mode > WebGlRenderingContext::TRIANGLE_FAN {
+            self.error(format!("{}: invalid mode {:#x}", call, mode));
+        }
+        if count < 0 {
+            self.error(format!("{}: negative count {}", call, count));
+        }
+        match self.program.and_then(|program| self.programs.get(&program)) {
+            Some(program) if program.linked => {}
+            _ => self.error(format!("{}: no linked program in use", call)),
+        }
+
+        let mut locations: Vec<u32> = self.enabled_attributes.iter().copied().collect();
+        locations.sort_unstable();
+        for location in locations {
+            let pointer = match self.attribute_pointers.get(&location) {
+                Some(pointer) => *pointer,
+                None => {
+                    self.error(format!(
+                        "{}: attribute {} is enabled without a buffer",
+                        call, location
+                    ));
+                    continue;
+                }
+            };
+            let length = match self.buffers.get(&pointer.buffer) {
+                Some(data) => data.len(),
+                None => {
+                    self.error(format!(
+                        "{}: attribute {} reads deleted buffer {}",
+                        call, location, pointer.buffer
+                    ));
+                    continue;
+                }
+            };
+            if vertex_count == 0 {
+                continue;
+            }
+
+            let element_size = pointer.size as usize * component_size(pointer.data_type);
+            let stride = match pointer.stride {
+                0 => element_size,
+                stride => stride as usize,
+            };
+            let required = pointer.offset as usize + (vertex_count - 1) * stride + element_size;
+            if required > length {
+                self.error(format!(
+                    "{}: attribute {} needs {} bytes of buffer {}, it has {}",
+                    call, location, required, pointer.buffer, length
+                ));
+            }
+        }
+    }
+}
+
+fn component_size(data_type: u32) -> usize {
+    match data_type {
+        WebGlRenderingContext::BYTE | WebGlRenderingContext::UNSIGNED_BYTE => 1,
+        WebGlRenderingContext::SHORT | WebGlRenderingContext::UNSIGNED_SHORT => 2,
+        _ => 4,
+    }
+}
+
+/// Names declared with `qualifier` in a shader, preprocessor conditions are ignored.
+fn declarations(source: &str, qualifier: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| {
+            // `uniform highp vec3 name[4];` declares `name`.
+            let mut words = line.split_whitespace();
+            if words.next() != Some(qualifier) {
+                return None;
+            }
+
+            words
+                .last()
+                .and_then(|name| name.split(['[', ';']).next())
+                .map(String::from)
+        })
+        .collect()
+}
+
+/// Backend without a GPU that records every call and validates it the way WebGL would,
+/// so rendering can be tested natively. Nothing is drawn unless a rasterizer is added,
+/// otherwise reading pixels returns zeros. It reports no extensions unless they are
+/// listed, and has no vertex array objects.
+#[derive(Debug)]
+pub(crate) struct HeadlessBackend {
+    width: Cell<i32>,
+    height: Cell<i32>,
+    extensions: HashSet<String>,
+    context_lost: Cell<bool>,
+    uploads: UploadCounter,
+    state: RefCell<State>,
+}
+
+impl HeadlessBackend {
+    pub(crate) fn new(width: i32, height: i32) -> HeadlessBackend {
+        HeadlessBackend {
+            width: Cell::new(width),
+            height: Cell::new(height),
+            extensions: HashSet::new(),
+            context_lost: Cell::new(false),
+            uploads: UploadCounter::default(),
+            state: RefCell::new(State::new(width, height)),
+        }
+    }
+
+    pub(crate) fn with_extensions(mut self, extensions: &[&str]) -> HeadlessBackend {
+        self.extensions = extensions.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Draws into the default framebuffer with the software rasterizer.
+    pub(crate) fn with_rasterizer(self) -> HeadlessBackend {
+        let (width, height) = (self.width.get(), self.height.get());
+        self.state.borrow_mut().rasterizer = Some(Rasterizer::new(width, height));
+        self
+    }
+
+    /// Resizes the drawing buffer like a resized canvas, which also clears it.
+    pub(crate) fn resize(&self, width: i32, height: i32) {
+        self.width.set(width);
+        self.height.set(height);
+        let mut state = self.state.borrow_mut();
+        if state.rasterizer.is_some() {
+            state.rasterizer = Some(Rasterizer::new(width, height));
+        }
+    }
+
+    /// Loses the context like `WEBGL_lose_context`, every object is gone and the state
+    /// is back to its defaults. Handles of the lost context stay invalid after
+    /// `restore_context`.
+    pub(crate) fn lose_context(&self) {
+        self.context_lost.set(true);
+        let mut state = self.state.borrow_mut();
+        let (width, height) = (self.width.get(), self.height.get());
+        *state = State {
+            next_handle: state.next_handle,
+            commands: std::mem::take(&mut state.commands),
+            errors: std::mem::take(&mut state.errors),
+            rasterizer: state
+                .rasterizer
+                .as_ref()
+                .map(|_| Rasterizer::new(width, height)),
+            ..State::new(width, height)
+        };
+    }
+
+    pub(crate) fn restore_context(&self) {
+        self.context_lost.set(false);
+    }
+
+    /// Commands recorded since the last call.
+    pub(crate) fn take_commands(&self) -> Vec<Command> {
+        std::mem::take(&mut self.state.borrow_mut().commands)
+    }
+
+    /// Invalid calls recorded so far.
+    pub(crate) fn errors(&self) -> Vec<String> {
+        self.state.borrow().errors.clone()
+    }
+
+    /// Attribute locations currently enabled as arrays, in ascending order.
+    pub(crate) fn enabled_attributes(&self) -> Vec<u32> {
+        let mut locations: Vec<u32> = self
+            .state
+            .borrow()
+            .enabled_attributes
+            .iter()
+            .copied()
+            .collect();
+        locations.sort_unstable();
+        locations
+    }
+
+    /// Buffers, textures, framebuffers, renderbuffers, shaders and programs that were
+    /// created and not deleted yet.
+    pub(crate) fn live_objects(&self) -> usize {
+        let state = self.state.borrow();
+        state.buffers.len()
+            + state.textures.len()
+            + state.framebuffers.len()
+            + state.renderbuffers.len()
+            + state.shaders.len()
+            + state.programs.len()
+    }
+
+    fn record(&self, command: Command) {
+        self.state.borrow_mut().commands.push(command);
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+
+    fn is_context_lost(&self) -> bool {
+        self.context_lost.get()
+    }
+
+    fn drawing_buffer_width(&self) -> i32 {
+        self.width.get()
+    }
+
+    fn drawing_buffer_height(&self) -> i32 {
+        self.height.get()
+    }
+
+    fn get_parameter_i32(&self, parameter: u32) -> Option<i32> {
+        match parameter {
+            WebGlRenderingContext::MAX_TEXTURE_SIZE
+            | WebGlRenderingContext::MAX_RENDERBUFFER_SIZE => Some(MAX_SIZE),
+            WebGlRenderingContext::MAX_VERTEX_ATTRIBS => Some(MAX_VERTEX_ATTRIBS),
+            _ => None,
+        }
+    }
+
+    fn upload_totals(&self) -> UploadTotals {
+        self.uploads.totals()
+    }
+
+    fn create_buffer(&self) -> Option<Buffer> {
+        let mut state = self.state.borrow_mut();
+        let id = state.create();
+        state.buffers.insert(id, vec![]);
+        state.commands.push(Command::CreateBuffer(id));
+
+        Some(Handle::Headless(id))
+    }
+
+    fn delete_buffer(&self, buffer: &Buffer) {
+        let mut state = self.state.borrow_mut();
+        if let Some(id) = state.id("delete_buffer", buffer) {
+            state.commands.push(Command::DeleteBuffer(id));
+            state.buffers.remove(&id);
+            if state.array_buffer == Some(id) {
+                state.array_buffer = None;
+            }
+            if state.element_array_buffer == Some(id) {
+                state.element_array_buffer = None;
+            }
+        }
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<&Buffer>) {
+        let mut state = self.state.borrow_mut();
+        let id = match buffer {
+            Some(buffer) => match state.id("bind_buffer", buffer) {
+                Some(id) => Some(id),
+                None => return,
+            },
+            None => None,
+        };
+        state
+            .commands
+            .push(Command::BindBuffer { target, buffer: id });
+        if let Some(id) = id.filter(|id| !state.buffers.contains_key(id)) {
+            state.error(format!("bind_buffer: buffer {} does not exist", id));
+            return;
+        }
+
+        match target {
+            WebGlRenderingContext::ARRAY_BUFFER => state.array_buffer = id,
+            WebGlRenderingContext::ELEMENT_ARRAY_BUFFER => state.element_array_buffer = id,
+            _ => state.error(format!("bind_buffer: invalid target {:#x}", target)),
+        }
+    }
+
+    fn buffer_data_u8(&self, target: u32, data: &[u8], _usage: u32) {
+        self.uploads.buffer(data.len());
+        self.state.borrow_mut().buffer_data(target, data.to_vec());
+    }
+
+    fn buffer_data_u16(&self, target: u32, data: &[u16], _usage: u32) {
+        self.uploads.buffer(std::mem::size_of_val(data));
+        let bytes = data.iter().flat_map(|value| value.to_le_bytes()).collect();
+        self.state.borrow_mut().buffer_data(target, bytes);
+    }
+
+    fn buffer_data_u32(&self, target: u32, data: &[u32], _usage: u32) {
+        self.uploads.buffer(std::mem::size_of_val(data));
+        let bytes = data.iter().flat_map(|value| value.to_le_bytes()).collect();
+        self.state.borrow_mut().buffer_data(target, bytes);
+    }
+
+    fn buffer_data_f32(&self, target: u32, data: &[f32], _usage: u32) {
+        self.uploads.buffer(std::mem::size_of_val(data));
+        let bytes = data.iter().flat_map(|value| value.to_le_bytes()).collect();
+        self.state.borrow_mut().buffer_data(target, bytes);
+    }
+
+    fn create_texture(&self) -> Option<Texture> {
+        let mut state = self.state.borrow_mut();
+        let id = state.create();
+        state.textures.insert(id, TextureState::default());
+        state.commands.push(Command::CreateTexture(id));
+
+        Some(Handle::Headless(id))
+    }
+
+    fn delete_texture(&self, texture: &Texture) {
+        let mut state = self.state.borrow_mut();
+        if let Some(id) = state.id("delete_texture", texture) {
+            state.commands.push(Command::DeleteTexture(id));
+            state.textures.remove(&id);
+            state.bound_textures.retain(|_, bound| *bound != id);
+        }
+    }
+
+    fn active_texture(&self, unit: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::ActiveTexture(unit));
+        state.active_unit = unit - WebGlRenderingContext::TEXTURE0;
+    }
+
+    fn bind_texture(&self, target: u32, texture: Option<&Texture>) {
+        let mut state = self.state.borrow_mut();
+        let id = match texture {
+            Some(texture) => match state.id("bind_texture", texture) {
+                Some(id) => Some(id),
+                None => return,
+            },
+            None => None,
+        };
+        state.commands.push(Command::BindTexture {
+            target,
+            texture: id,
+        });
+
+        let unit = state.active_unit;
+        match id {
+            Some(id) if !state.textures.contains_key(&id) => {
+                state.error(format!("bind_texture: texture {} does not exist", id));
+            }
+            Some(id) => {
+                state.bound_textures.insert((unit, target), id);
+            }
+            None => {
+                state.bound_textures.remove(&(unit, target));
+            }
+        }
+    }
+
+    fn tex_parameteri(&self, target: u32, parameter: u32, value: i32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::TexParameter {
+            target,
+            parameter,
+            value,
+        });
+        let texture = state.bound_texture("tex_parameteri", target);
+        if let Some(texture) = texture.and_then(|texture| state.textures.get_mut(&texture)) {
+            match parameter {
+                WebGlRenderingContext::TEXTURE_WRAP_S => texture.wrap_s = value as u32,
+                WebGlRenderingContext::TEXTURE_WRAP_T => texture.wrap_t = value as u32,
+                _ => {}
+            }
+        }
+    }
+
+    fn tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        _internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        data_type: u32,
+        pixels: Option<&[u8]>,
+    ) -> Result<(), JsValue> {
+        self.uploads.image(width, height, format, data_type);
+        let mut state = self.state.borrow_mut();
+        let rgba_bytes = format == WebGlRenderingContext::RGBA
+            && data_type == WebGlRenderingContext::UNSIGNED_BYTE;
+        let kept = match pixels {
+            Some(pixels) if rgba_bytes && pixels.len() < (width * height * 4) as usize => {
+                state.error(format!(
+                    "tex_image_2d: {} bytes for a {}x{} RGBA image",
+                    pixels.len(),
+                    width,
+                    height
+                ));
+                vec![]
+            }
+            Some(pixels) if rgba_bytes => pixels.to_vec(),
+            _ => vec![],
+        };
+        state.tex_image("tex_image_2d", target, level, width, height, kept);
+
+        Ok(())
+    }
+
+    fn tex_image_2d_image(
+        &self,
+        target: u32,
+        level: i32,
+        _internal_format: i32,
+        format: u32,
+        data_type: u32,
+        image: &HtmlImageElement,
+    ) -> Result<(), JsValue> {
+        self.uploads.image(
+            image.natural_width() as i32,
+            image.natural_height() as i32,
+            format,
+            data_type,
+        );
+        self.state.borrow_mut().tex_image(
+            "tex_image_2d_image",
+            target,
+            level,
+            image.natural_width() as i32,
+            image.natural_height() as i32,
+            vec![],
+        );
+
+        Ok(())
+    }
+
+    fn compressed_tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        _format: u32,
+        width: i32,
+        height: i32,
+        data: &[u8],
+    ) {
+        self.uploads.texture(data.len());
+        self.state.borrow_mut().tex_image(
+            "compressed_tex_image_2d",
+            target,
+            level,
+            width,
+            height,
+            vec![],
+        );
+    }
+
+    fn copy_tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: u32,
+        _x: i32,
+        _y: i32,
+        width: i32,
+        height: i32,
+    ) {
+        self.uploads.image(
+            width,
+            height,
+            internal_format,
+            WebGlRenderingContext::UNSIGNED_BYTE,
+        );
+        self.state.borrow_mut().tex_image(
+            "copy_tex_image_2d",
+            target,
+            level,
+            width,
+            height,
+            vec![],
+        );
+    }
+
+    fn generate_mipmap(&self, target: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::GenerateMipmap(target));
+        state.bound_texture("generate_mipmap", target);
+    }
+
+    fn create_framebuffer(&self) -> Option<Framebuffer> {
+        let mut state = self.state.borrow_mut();
+        let id = state.create();
+        state.framebuffers.insert(id);
+        state.commands.push(Command::CreateFramebuffer(id));
+
+        Some(Handle::Headless(id))
+    }
+
+    fn delete_framebuffer(&self, framebuffer: &Framebuffer) {
+        let mut state = self.state.borrow_mut();
+        if let Some(id) = state.id("delete_framebuffer", framebuffer) {
+            state.commands.push(Command::DeleteFramebuffer(id));
+            state.framebuffers.remove(&id);
+        }
+    }
+
+    fn bind_framebuffer(&self, _target: u32, framebuffer: Option<&Framebuffer>) {
+        let mut state = self.state.borrow_mut();
+        let id = match framebuffer {
+            Some(framebuffer) => match state.id("bind_framebuffer", framebuffer) {
+                Some(id) => Some(id),
+                None => return,
+            },
+            None => None,
+        };
+        state.commands.push(Command::BindFramebuffer(id));
+        if let Some(id) = id.filter(|id| !state.framebuffers.contains(id)) {
+            state.error(format!(
+                "bind_framebuffer: framebuffer {} does not exist",
+                id
+            ));
+        }
+        state.framebuffer = id;
+    }
+
+    fn framebuffer_texture_2d(
+        &self,
+        _target: u32,
+        attachment: u32,
+        _texture_target: u32,
+        texture: Option<&Texture>,
+        _level: i32,
+    ) {
+        let mut state = self.state.borrow_mut();
+        let id = texture.and_then(|texture| state.id("framebuffer_texture_2d", texture));
+        state.commands.push(Command::FramebufferTexture {
+            attachment,
+            texture: id,
+        });
+    }
+
+    fn check_framebuffer_status(&self, _target: u32) -> u32 {
+        WebGlRenderingContext::FRAMEBUFFER_COMPLETE
+    }
+
+    fn create_renderbuffer(&self) -> Option<Renderbuffer> {
+        let mut state = self.state.borrow_mut();
+        let id = state.create();
+        state.renderbuffers.insert(id);
+        state.commands.push(Command::CreateRenderbuffer(id));
+
+        Some(Handle::Headless(id))
+    }
+
+    fn delete_renderbuffer(&self, renderbuffer: &Renderbuffer) {
+        let mut state = self.state.borrow_mut();
+        if let Some(id) = state.id("delete_renderbuffer", renderbuffer) {
+            state.commands.push(Command::DeleteRenderbuffer(id));
+            state.renderbuffers.remove(&id);
+        }
+    }
+
+    fn bind_renderbuffer(&self, _target: u32, renderbuffer: Option<&Renderbuffer>) {
+        let mut state = self.state.borrow_mut();
+        let id = renderbuffer.and_then(|renderbuffer| state.id("bind_renderbuffer", renderbuffer));
+        state.commands.push(Command::BindRenderbuffer(id));
+        if let Some(id) = id.filter(|id| !state.renderbuffers.contains(id)) {
+            state.error(format!(
+                "bind_renderbuffer: renderbuffer {} does not exist",
+                id
+            ));
+        }
+    }
+
+    fn renderbuffer_storage(&self, _target: u32, format: u32, width: i32, height: i32) {
+        self.uploads.renderbuffer(format, width, height);
+        self.record(Command::RenderbufferStorage {
+            format,
+            width,
+            height,
+        });
+    }
+
+    fn framebuffer_renderbuffer(
+        &self,
+        _target: u32,
+        attachment: u32,
+        _renderbuffer_target: u32,
+        renderbuffer: Option<&Renderbuffer>,
+    ) {
+        let mut state = self.state.borrow_mut();
+        let id = renderbuffer
+            .and_then(|renderbuffer| state.id("framebuffer_renderbuffer", renderbuffer));
+        state.commands.push(Command::FramebufferRenderbuffer {
+            attachment,
+            renderbuffer: id,
+        });
+    }
+
+    fn create_shader(&self, _shader_type: u32) -> Option<Shader> {
+        let mut state = self.state.borrow_mut();
+        let id = state.create();
+        state.shaders.insert(id, String::new());
+        state.commands.push(Command::CreateShader(id));
+
+        Some(Handle::Headless(id))
+    }
+
+    fn shader_source(&self, shader: &Shader, source: &str) {
+        let mut state = self.state.borrow_mut();
+        if let Some(id) = state.id("shader_source", shader) {
+            state.shaders.insert(id, source.to_string());
+        }
+    }
+
+    fn compile_shader(&self, shader: &Shader) {
+        let mut state = self.state.borrow_mut();
+        if let Some(id) = state.id("compile_shader", shader) {
+            state.commands.push(Command::CompileShader(id));
+        }
+    }
+
+    /// Shaders are not compiled, any shader with a source counts as compiled.
+    fn shader_compiled(&self, shader: &Shader) -> bool {
+        let state = self.state.borrow();
+        shader
+            .headless()
+            .and_then(|id| state.shaders.get(&id))
+            .is_some_and(|source| !source.is_empty())
+    }
+
+    fn shader_info_log(&self, _shader: &Shader) -> Option<String> {
+        None
+    }
+
+    fn delete_shader(&self, shader: &Shader) {
+        let mut state = self.state.borrow_mut();
+        if let Some(id) = state.id("delete_shader", shader) {
+            state.commands.push(Command::DeleteShader(id));
+            state.shaders.remove(&id);
+        }
+    }
+
+    fn create_program(&self) -> Option<Program> {
+        let mut state = self.state.borrow_mut();
+        let id = state.create();
+        state.programs.insert(id, ProgramState::default());
+        state.commands.push(Command::CreateProgram(id));
+
+        Some(Handle::Headless(id))
+    }
+
+    fn delete_program(&self, program: &Program) {
+        let mut state = self.state.borrow_mut();
+        if let Some(id) = state.id("delete_program", program) {
+            state.commands.push(Command::DeleteProgram(id));
+            state.programs.remove(&id);
+        }
+    }
+
+    fn attach_shader(&self, program: &Program, shader: &Shader) {
+        let mut state = self.state.borrow_mut();
+        let shader = match state.id("attach_shader", shader) {
+            Some(shader) => shader,
+            None => return,
+        };
+        if let Some(id) = state.id("attach_shader", program) {
+            match state.programs.get_mut(&id) {
+                Some(program) => program.shaders.push(shader),
+                None => state.error(format!("attach_shader: program {} does not exist", id)),
+            }
+        }
+    }
+
+    fn detach_shader(&self, program: &Program, shader: &Shader) {
+        let mut state = self.state.borrow_mut();
+        let shader = match state.id("detach_shader", shader) {
+            Some(shader) => shader,
+            None => return,
+        };
+        if let Some(id) = state.id("detach_shader", program) {
+            let attached = state
+                .programs
+                .get_mut(&id)
+                .and_then(|program| {
+                    let index = program.shaders.iter().position(|id| *id == shader)?;
+                    Some(program.shaders.remove(index))
+                })
+                .is_some();
+            if !attached {
+                state.error(format!(
+                    "detach_shader: shader {} is not attached to program {}",
+                    shader, id
+                ));
+            }
+        }
+    }
+
+    /// Attributes get locations in the order the shaders declare them, uniforms are
+    /// located by name.
+    fn link_program(&self, program: &Program) {
+        let mut state = self.state.borrow_mut();
+        let id = match state.id("link_program", program) {
+            Some(id) => id,
+            None => return,
+        };
+        state.commands.push(Command::LinkProgram(id));
+
+        let shaders = match state.programs.get(&id) {
+            Some(program) => program.shaders.clone(),
+            None => {
+                state.error(format!("link_program: program {} does not exist", id));
+                return;
+            }
+        };
+        let sources: Vec<String> = shaders
+            .iter()
+            .filter_map(|shader| state.shaders.get(shader).cloned())
+            .collect();
+        let mut attributes = vec![];
+        let mut uniforms = HashSet::new();
+        for source in &sources {
+            for attribute in declarations(source, "attribute") {
+                if !attributes.contains(&attribute) {
+                    attributes.push(attribute);
+                }
+            }
+            uniforms.extend(declarations(source, "uniform"));
+        }
+
+        if let Some(program) = state.programs.get_mut(&id) {
+            program.linked = sources.len() == 2 && sources.iter().all(|source| !source.is_empty());
+            program.sources = sources;
+            program.attributes = attributes;
+            program.uniforms = uniforms;
+        }
+    }
+
+    fn program_linked(&self, program: &Program) -> bool {
+        let state = self.state.borrow();
+        program
+            .headless()
+            .and_then(|id| state.programs.get(&id))
+            .is_some_and(|program| program.linked)
+    }
+
+    fn program_info_log(&self, _program: &Program) -> Option<String> {
+        None
+    }
+
+    fn use_program(&self, program: Option<&Program>) {
+        let mut state = self.state.borrow_mut();
+        let id = match program {
+            Some(program) => match state.id("use_program", program) {
+                Some(id) => Some(id),
+                None => return,
+            },
+            None => None,
+        };
+        state.commands.push(Command::UseProgram(id));
+
+        if let Some(id) = id {
+            let linked = state
+                .programs
+                .get(&id)
+                .is_some_and(|program| program.linked);
+            if !linked {
+                state.error(format!("use_program: program {} is not linked", id));
+            }
+        }
+        state.program = id;
+    }
+
+    fn get_attrib_location(&self, program: &Program, name: &str) -> i32 {
+        let state = self.state.borrow();
+        program
+            .headless()
+            .and_then(|id| state.programs.get(&id))
+            .and_then(|program| {
+                program
+                    .attributes
+                    .iter()
+                    .position(|attribute| attribute == name)
+            })
+            .map(|location| location as i32)
+            .unwrap_or(-1)
+    }
+
+    fn get_uniform_location(&self, program: &Program, name: &str) -> Option<UniformLocation> {
+        let mut state = self.state.borrow_mut();
+        let id = program.headless()?;
+        if !state
+            .programs
+            .get(&id)
+            .is_some_and(|program| program.uniforms.contains(name))
+        {
+            return None;
+        }
+
+        let location = state.create();
+        state
+            .uniform_locations
+            .insert(location, (id, name.to_string()));
+
+        Some(Handle::Headless(location))
+    }
+
+    fn enable_vertex_attrib_array(&self, location: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::EnableAttribute(location));
+        if location >= MAX_VERTEX_ATTRIBS as u32 {
+            state.error(format!(
+                "enableVertexAttribArray: invalid location {}",
+                location
+            ));
+            return;
+        }
+        state.enabled_attributes.insert(location);
+    }
+
+    fn disable_vertex_attrib_array(&self, location: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::DisableAttribute(location));
+        if location >= MAX_VERTEX_ATTRIBS as u32 {
+            state.error(format!(
+                "disableVertexAttribArray: invalid location {}",
+                location
+            ));
+            return;
+        }
+        state.enabled_attributes.remove(&location);
+    }
+
+    fn vertex_attrib_pointer(
+        &self,
+        location: u32,
+        size: i32,
+        data_type: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    ) {
+        let mut state = self.state.borrow_mut();
+        let buffer = match state
+            .bound_buffer("vertex_attrib_pointer", WebGlRenderingContext::ARRAY_BUFFER)
+        {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        state.commands.push(Command::AttributePointer {
+            location,
+            buffer,
+            size,
+            data_type,
+            stride,
+            offset,
+        });
+        if !(1..=4).contains(&size) {
+            state.error(format!("vertex_attrib_pointer: invalid size {}", size));
+        }
+        state.attribute_pointers.insert(
+            location,
+            AttributePointer {
+                buffer,
+                size,
+                data_type,
+                normalized,
+                stride,
+                offset,
+            },
+        );
+    }
+
+    fn vertex_attrib3fv(&self, _location: u32, _value: &[f32]) {}
+
+    fn uniform1i(&self, location: Option<&UniformLocation>, value: i32) {
+        self.state
+            .borrow_mut()
+            .uniform(location, vec![value as f32]);
+    }
+
+    fn uniform1f(&self, location: Option<&UniformLocation>, value: f32) {
+        self.state.borrow_mut().uniform(location, vec![value]);
+    }
+
+    fn uniform2f(&self, location: Option<&UniformLocation>, x: f32, y: f32) {
+        self.state.borrow_mut().uniform(location, vec![x, y]);
+    }
+
+    fn uniform2fv(&self, location: Option<&UniformLocation>, value: &[f32]) {
+        self.state.borrow_mut().uniform(location, value.to_vec());
+    }
+
+    fn uniform3fv(&self, location: Option<&UniformLocation>, value: &[f32]) {
+        self.state.borrow_mut().uniform(location, value.to_vec());
+    }
+
+    fn uniform4fv(&self, location: Option<&UniformLocation>, value: &[f32]) {
+        self.state.borrow_mut().uniform(location, value.to_vec());
+    }
+
+    fn uniform_matrix3fv(&self, location: Option<&UniformLocation>, value: &[f32]) {
+        self.state.borrow_mut().uniform(location, value.to_vec());
+    }
+
+    fn uniform_matrix4fv(&self, location: Option<&UniformLocation>, value: &[f32]) {
+        self.state.borrow_mut().uniform(location, value.to_vec());
+    }
+
+    fn enable(&self, capability: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::Enable(capability));
+        state.capabilities.insert(capability);
+    }
+
+    fn disable(&self, capability: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::Disable(capability));
+        state.capabilities.remove(&capability);
+    }
+
+    fn is_enabled(&self, capability: u32) -> bool {
+        self.state.borrow().capabilities.contains(&capability)
+    }
+
+    fn blend_func(&self, source: u32, destination: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::BlendFunc(source, destination));
+        state.blend_func = (source, destination);
+    }
+
+    fn depth_mask(&self, enabled: bool) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::DepthMask(enabled));
+        state.depth_writes_disabled = !enabled;
+    }
+
+    fn depth_func(&self, function: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::DepthFunc(function));
+        state.depth_func = function;
+    }
+
+    fn cull_face(&self, mode: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::CullFace(mode));
+        state.cull_face = mode;
+    }
+
+    fn front_face(&self, mode: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::FrontFace(mode));
+        state.front_face = mode;
+    }
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::Viewport(x, y, width, height));
+        state.viewport = [x, y, width, height];
+    }
+
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32) {
+        let mut state = self.state.borrow_mut();
+        state
+            .commands
+            .push(Command::ClearColor([red, green, blue, alpha]));
+        state.clear_color = [red, green, blue, alpha];
+    }
+
+    fn clear_depth(&self, depth: f32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::ClearDepth(depth));
+        state.clear_depth = depth;
+    }
+
+    fn clear(&self, mask: u32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::Clear(mask));
+        let (clear_color, clear_depth) = (state.clear_color, state.clear_depth);
+        if let (None, Some(rasterizer)) = (state.framebuffer, &mut state.rasterizer) {
+            rasterizer.clear(mask, clear_color, clear_depth);
+        }
+    }
+
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32) {
+        let mut state = self.state.borrow_mut();
+        state
+            .commands
+            .push(Command::DrawArrays { mode, first, count });
+        let errors = state.errors.len();
+        state.check_draw("draw_arrays", mode, count, (first + count).max(0) as usize);
+
+        let indices: Vec<u32> = (first.max(0)..(first + count).max(0))
+            .map(|index| index as u32)
+            .collect();
+        state.rasterize(errors, mode, &indices);
+    }
+
+    fn draw_elements(&self, mode: u32, count: i32, data_type: u32, offset: i32) {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::DrawElements {
+            mode,
+            count,
+            data_type,
+            offset,
+        });
+        let errors = state.errors.len();
+
+        let index_size = match data_type {
+            WebGlRenderingContext::UNSIGNED_BYTE => 1,
+            WebGlRenderingContext::UNSIGNED_SHORT => 2,
+            WebGlRenderingContext::UNSIGNED_INT if self.has_extension("OES_element_index_uint") => {
+                4
+            }
+            _ => {
+                state.error(format!(
+                    "draw_elements: invalid index type {:#x}",
+                    data_type
+                ));
+                return;
+            }
+        };
+        if offset < 0 || !(offset as usize).is_multiple_of(index_size) {
+            state.error(format!(
+                "draw_elements: offset {} is not a multiple of the index size",
+                offset
+            ));
+            return;
+        }
+
+        let buffer = match state
+            .bound_buffer("draw_elements", WebGlRenderingContext::ELEMENT_ARRAY_BUFFER)
+        {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let data = state.buffers.get(&buffer).cloned().unwrap_or_default();
+        let end = offset as usize + count.max(0) as usize * index_size;
+        let indices = match data.get(offset as usize..end) {
+            Some(indices) => indices,
+            None => {
+                state.error(format!(
+                    "draw_elements: {} indices from byte {} exceed the {} bytes of buffer {}",
+                    count,
+                    offset,
+                    data.len(),
+                    buffer
+                ));
+                return;
+            }
+        };
+        let indices: Vec<u32> = indices
+            .chunks_exact(index_size)
+            .map(|index| match index {
+                [byte] => *byte as u32,
+                [low, high] => u16::from_le_bytes([*low, *high]) as u32,
+                _ => u32::from_le_bytes([index[0], index[1], index[2], index[3]]),
+            })
+            .collect();
+        let vertex_count = indices
+            .iter()
+            .max()
+            .map(|index| *index as usize + 1)
+            .unwrap_or(0);
+
+        state.check_draw("draw_elements", mode, count, vertex_count);
+        state.rasterize(errors, mode, &indices);
+    }
+
+    fn read_pixels(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        _format: u32,
+        _data_type: u32,
+        pixels: &mut [u8],
+    ) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+        state.commands.push(Command::ReadPixels { width, height });
+        if pixels.len() < (width * height * 4) as usize {
+            state.error(format!(
+                "read_pixels: {} bytes for {}x{} pixels",
+                pixels.len(),
+                width,
+                height
+            ));
+            return Ok(());
+        }
+
+        match (state.framebuffer, &state.rasterizer) {
+            (None, Some(rasterizer)) => rasterizer.read(x, y, width, height, pixels),
+            _ => pixels.fill(0),
+        }
+
+        Ok(())
+    }
+
+    fn has_timer_queries(&self) -> bool {
+        false
+    }
+
+    fn begin_timer_query(&self) -> Option<Query> {
+        None
+    }
+
+    fn end_timer_query(&self) {}
+
+    fn timer_query_result(&self, _query: &Query) -> Option<f64> {
+        None
+    }
+
+    fn timer_disjoint(&self) -> bool {
+        false
+    }
+
+    fn delete_query(&self, _query: &Query) {}
+
+    fn create_vertex_array(&self) -> Option<VertexArray> {
+        None
+    }
+
+    fn delete_vertex_array(&self, _vertex_array: &VertexArray) {}
+
+    fn bind_vertex_array(&self, _vertex_array: Option<&VertexArray>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V_SHADER: &str = "attribute vec3 position;\nuniform mat4 matrix;\nvoid main() {}";
+    const F_SHADER: &str = "precision mediump float;\nvoid main() {}";
+
+    /// A linked program in use and a 3 vertex position buffer at location 0.
+    fn triangle(gl: &HeadlessBackend) -> Program {
+        let program = gl.create_program().unwrap();
+        for (shader_type, source) in &[
+            (WebGlRenderingContext::VERTEX_SHADER, V_SHADER),
+            (WebGlRenderingContext::FRAGMENT_SHADER, F_SHADER),
+        ] {
+            let shader = gl.create_shader(*shader_type).unwrap();
+            gl.shader_source(&shader, source);
+            gl.compile_shader(&shader);
+            gl.attach_shader(&program, &shader);
+        }
+        gl.link_program(&program);
+        gl.use_program(Some(&program));
+
+        let buffer = gl.create_buffer().unwrap();
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&buffer));
+        gl.buffer_data_f32(
+            WebGlRenderingContext::ARRAY_BUFFER,
+            &[0.; 9],
+            WebGlRenderingContext::STATIC_DRAW,
+        );
+        let location = gl.get_attrib_location(&program, "position");
+        assert_eq!(location, 0);
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer(0, 3, WebGlRenderingContext::FLOAT, false, 0, 0);
+
+        program
+    }
+
+    fn index_buffer(gl: &HeadlessBackend, indices: &[u16]) {
+        let buffer = gl.create_buffer().unwrap();
+        gl.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&buffer));
+        gl.buffer_data_u16(
+            WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+            indices,
+            WebGlRenderingContext::STATIC_DRAW,
+        );
+    }
+
+    #[test]
+    fn valid_draws_record_without_errors() {
+        let gl = HeadlessBackend::new(4, 4);
+        let program = triangle(&gl);
+        let matrix = gl.get_uniform_location(&program, "matrix");
+        gl.uniform_matrix4fv(matrix.as_ref(), &[1.; 16]);
+        gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, 3);
+        index_buffer(&gl, &[0, 1, 2]);
+        gl.draw_elements(
+            WebGlRenderingContext::TRIANGLES,
+            3,
+            WebGlRenderingContext::UNSIGNED_SHORT,
+            0,
+        );
+
+        assert_eq!(gl.errors(), Vec::<String>::new());
+        let commands = gl.take_commands();
+        assert!(commands.contains(&Command::Uniform {
+            name: String::from("matrix"),
+            values: vec![1.; 16],
+        }));
+        assert!(commands.contains(&Command::DrawElements {
+            mode: WebGlRenderingContext::TRIANGLES,
+            count: 3,
+            data_type: WebGlRenderingContext::UNSIGNED_SHORT,
+            offset: 0,
+        }));
+    }
+
+    #[test]
+    fn drawing_without_an_element_buffer_is_an_error() {
+        let gl = HeadlessBackend::new(4, 4);
+        triangle(&gl);
+        gl.draw_elements(
+            WebGlRenderingContext::TRIANGLES,
+            3,
+            WebGlRenderingContext::UNSIGNED_SHORT,
+            0,
+        );
+
+        assert_eq!(gl.errors().len(), 1);
+        assert!(gl.errors()[0].contains("no buffer bound"));
+    }
+
+    #[test]
+    fn index_count_beyond_the_buffer_is_an_error() {
+        let gl = HeadlessBackend::new(4, 4);
+        triangle(&gl);
+        index_buffer(&gl, &[0, 1, 2]);
+        gl.draw_elements(
+            WebGlRenderingContext::TRIANGLES,
+            6,
+            WebGlRenderingContext::UNSIGNED_SHORT,
+            0,
+        );
+
+        assert_eq!(gl.errors().len(), 1);
+        assert!(gl.errors()[0].contains("exceed"));
+    }
+
+    #[test]
+    fn indices_beyond_the_vertices_are_an_error() {
+        let gl = HeadlessBackend::new(4, 4);
+        triangle(&gl);
+        index_buffer(&gl, &[0, 1, 3]);
+        gl.draw_elements(
+            WebGlRenderingContext::TRIANGLES,
+            3,
+            WebGlRenderingContext::UNSIGNED_SHORT,
+            0,
+        );
+
+        assert_eq!(gl.errors().len(), 1);
+        assert!(gl.errors()[0].contains("attribute 0"));
+    }
+
+    #[test]
+    fn deleted_buffers_can_not_be_drawn() {
+        let gl = HeadlessBackend::new(4, 4);
+        triangle(&gl);
+        let buffer = Handle::Headless(gl.state.borrow().array_buffer.unwrap());
+        gl.delete_buffer(&buffer);
+        gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, 3);
+
+        assert_eq!(gl.errors().len(), 1);
+        assert!(gl.errors()[0].contains("deleted buffer"));
+    }
+
+    #[test]
+    fn uniforms_of_another_program_are_an_error() {
+        let gl = HeadlessBackend::new(4, 4);
+        let first = triangle(&gl);
+        let matrix = gl.get_uniform_location(&first, "matrix");
+        triangle(&gl);
+        gl.uniform_matrix4fv(matrix.as_ref(), &[0.; 16]);
+
+        assert_eq!(gl.errors().len(), 1);
+        assert!(gl.errors()[0].contains("not the one in use"));
+    }
+}
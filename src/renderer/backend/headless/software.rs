@@ -0,0 +1,870 @@
+//! Rasterizes draws of the mesh program on the CPU, so rendered frames can be compared
+//! to golden images without a GPU. The program is not interpreted: its vertex transform,
+//! base color texture and punctual lighting are reimplemented here and selected by the
+//! defines it was compiled with. Shadows and image based lighting are not emulated,
+//! draws with any other program and draws into framebuffer objects are skipped.
+
+use super::{component_size, ProgramState, State, TextureState};
+use web_sys::WebGlRenderingContext;
+
+const PI: f32 = std::f32::consts::PI;
+
+/// Interpolated per vertex: normal, world position and texture coordinates.
+const VARYINGS: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+struct Vertex {
+    clip: [f32; 4],
+    varyings: [f32; VARYINGS],
+}
+
+/// Color and depth of the default framebuffer, rows start at the bottom as in GL.
+#[derive(Debug)]
+pub(super) struct Rasterizer {
+    width: i32,
+    height: i32,
+    color: Vec<u8>,
+    depth: Vec<f32>,
+}
+
+impl Rasterizer {
+    pub(super) fn new(width: i32, height: i32) -> Rasterizer {
+        let pixels = (width * height) as usize;
+
+        Rasterizer {
+            width,
+            height,
+            color: vec![0; pixels * 4],
+            depth: vec![1.; pixels],
+        }
+    }
+
+    pub(super) fn clear(&mut self, mask: u32, color: [f32; 4], depth: f32) {
+        if mask & WebGlRenderingContext::COLOR_BUFFER_BIT != 0 {
+            let bytes = color.map(to_byte);
+            for pixel in self.color.chunks_exact_mut(4) {
+                pixel.copy_from_slice(&bytes);
+            }
+        }
+        if mask & WebGlRenderingContext::DEPTH_BUFFER_BIT != 0 {
+            self.depth.fill(depth.clamp(0., 1.));
+        }
+    }
+
+    /// Copies RGBA bytes like `readPixels`, pixels outside the framebuffer are zero.
+    pub(super) fn read(&self, x: i32, y: i32, width: i32, height: i32, pixels: &mut [u8]) {
+        for row in 0..height {
+            for column in 0..width {
+                let target = ((row * width + column) * 4) as usize;
+                let (source_x, source_y) = (x + column, y + row);
+                let texel = if source_x >= 0
+                    && source_x < self.width
+                    && source_y >= 0
+                    && source_y < self.height
+                {
+                    let source = ((source_y * self.width + source_x) * 4) as usize;
+                    [
+                        self.color[source],
+                        self.color[source + 1],
+                        self.color[source + 2],
+                        self.color[source + 3],
+                    ]
+                } else {
+                    [0; 4]
+                };
+                pixels[target..target + 4].copy_from_slice(&texel);
+            }
+        }
+    }
+
+    /// Draws triangles of the program in use, other primitives are skipped.
+    pub(super) fn draw(&mut self, state: &State, mode: u32, indices: &[u32]) {
+        if mode != WebGlRenderingContext::TRIANGLES {
+            return;
+        }
+        let pipeline = match state
+            .program
+            .and_then(|program| state.programs.get(&program))
+            .and_then(|program| MeshPipeline::new(state, program))
+        {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+
+        for triangle in indices.chunks_exact(3) {
+            let vertices = [
+                pipeline.vertex(triangle[0]),
+                pipeline.vertex(triangle[1]),
+                pipeline.vertex(triangle[2]),
+            ];
+            let clipped = clip_near(&vertices);
+            for index in 1..clipped.len().saturating_sub(1) {
+                self.triangle(
+                    state,
+                    &pipeline,
+                    [clipped[0], clipped[index], clipped[index + 1]],
+                );
+            }
+        }
+    }
+
+    fn triangle(&mut self, state: &State, pipeline: &MeshPipeline<'_>, vertices: [Vertex; 3]) {
+        let [viewport_x, viewport_y, viewport_width, viewport_height] = state.viewport;
+        // Window x, y and depth plus 1 / w for perspective correct interpolation.
+        let window = vertices.map(|vertex| {
+            let inverse_w = 1. / vertex.clip[3];
+            [
+                viewport_x as f32 + (vertex.clip[0] * inverse_w + 1.) * 0.5 * viewport_width as f32,
+                viewport_y as f32
+                    + (vertex.clip[1] * inverse_w + 1.) * 0.5 * viewport_height as f32,
+                (vertex.clip[2] * inverse_w + 1.) * 0.5,
+                inverse_w,
+            ]
+        });
+
+        let area = edge(window[0], window[1], window[2][0], window[2][1]);
+        if area == 0. || !area.is_finite() {
+            return;
+        }
+        let front = (area > 0.) == (state.front_face == WebGlRenderingContext::CCW);
+        let culled = match state.cull_face {
+            WebGlRenderingContext::FRONT => front,
+            WebGlRenderingContext::BACK => !front,
+            _ => true,
+        };
+        if culled
+            && state
+                .capabilities
+                .contains(&WebGlRenderingContext::CULL_FACE)
+        {
+            return;
+        }
+
+        let min_x = window
+            .iter()
+            .map(|vertex| vertex[0])
+            .fold(f32::MAX, f32::min);
+        let max_x = window
+            .iter()
+            .map(|vertex| vertex[0])
+            .fold(f32::MIN, f32::max);
+        let min_y = window
+            .iter()
+            .map(|vertex| vertex[1])
+            .fold(f32::MAX, f32::min);
+        let max_y = window
+            .iter()
+            .map(|vertex| vertex[1])
+            .fold(f32::MIN, f32::max);
+        let start_x = (min_x.floor() as i32).max(viewport_x).max(0);
+        let end_x = (max_x.ceil() as i32)
+            .min(viewport_x + viewport_width)
+            .min(self.width);
+        let start_y = (min_y.floor() as i32).max(viewport_y).max(0);
+        let end_y = (max_y.ceil() as i32)
+            .min(viewport_y + viewport_height)
+            .min(self.height);
+
+        let depth_test = state
+            .capabilities
+            .contains(&WebGlRenderingContext::DEPTH_TEST);
+        let blend = state.capabilities.contains(&WebGlRenderingContext::BLEND);
+
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                let (center_x, center_y) = (x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [
+                    edge(window[1], window[2], center_x, center_y) / area,
+                    edge(window[2], window[0], center_x, center_y) / area,
+                    edge(window[0], window[1], center_x, center_y) / area,
+                ];
+                if weights.iter().any(|weight| *weight < 0.) {
+                    continue;
+                }
+
+                let depth = weights[0] * window[0][2]
+                    + weights[1] * window[1][2]
+                    + weights[2] * window[2][2];
+                let pixel = (y * self.width + x) as usize;
+                if !(0. ..=1.).contains(&depth)
+                    || (depth_test && !depth_passes(state.depth_func, depth, self.depth[pixel]))
+                {
+                    continue;
+                }
+
+                let perspective = [
+                    weights[0] * window[0][3],
+                    weights[1] * window[1][3],
+                    weights[2] * window[2][3],
+                ];
+                let total: f32 = perspective.iter().sum();
+                let mut varyings = [0.; VARYINGS];
+                for (index, varying) in varyings.iter_mut().enumerate() {
+                    *varying = (0..3)
+                        .map(|vertex| perspective[vertex] * vertices[vertex].varyings[index])
+                        .sum::<f32>()
+                        / total;
+                }
+
+                let color = pipeline.fragment(&varyings);
+                let color = match blend {
+                    true => self.blend(state.blend_func, pixel, color),
+                    false => color,
+                };
+                self.color[pixel * 4..pixel * 4 + 4].copy_from_slice(&color.map(to_byte));
+                if depth_test && !state.depth_writes_disabled {
+                    self.depth[pixel] = depth;
+                }
+            }
+        }
+    }
+
+    fn blend(&self, (source, destination): (u32, u32), pixel: usize, color: [f32; 4]) -> [f32; 4] {
+        let current = [0, 1, 2, 3].map(|channel| self.color[pixel * 4 + channel] as f32 / 255.);
+        let factor = |function: u32| match function {
+            WebGlRenderingContext::ZERO => 0.,
+            WebGlRenderingContext::SRC_ALPHA => color[3],
+            WebGlRenderingContext::ONE_MINUS_SRC_ALPHA => 1. - color[3],
+            WebGlRenderingContext::DST_ALPHA => current[3],
+            WebGlRenderingContext::ONE_MINUS_DST_ALPHA => 1. - current[3],
+            _ => 1.,
+        };
+        let (source, destination) = (factor(source), factor(destination));
+
+        [0, 1, 2, 3].map(|channel| color[channel] * source + current[channel] * destination)
+    }
+}
+
+fn depth_passes(function: u32, depth: f32, stored: f32) -> bool {
+    match function {
+        WebGlRenderingContext::NEVER => false,
+        WebGlRenderingContext::LESS => depth < stored,
+        WebGlRenderingContext::EQUAL => depth == stored,
+        WebGlRenderingContext::LEQUAL => depth <= stored,
+        WebGlRenderingContext::GREATER => depth > stored,
+        WebGlRenderingContext::NOTEQUAL => depth != stored,
+        WebGlRenderingContext::GEQUAL => depth >= stored,
+        _ => true,
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `(x, y)`.
+fn edge(a: [f32; 4], b: [f32; 4], x: f32, y: f32) -> f32 {
+    (b[0] - a[0]) * (y - a[1]) - (b[1] - a[1]) * (x - a[0])
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0., 1.) * 255.).round() as u8
+}
+
+/// Clips the triangle against the near plane, returns the polygon that is left.
+fn clip_near(vertices: &[Vertex; 3]) -> Vec<Vertex> {
+    let distance = |vertex: &Vertex| vertex.clip[2] + vertex.clip[3];
+    let mut clipped = Vec::with_capacity(4);
+    for index in 0..3 {
+        let current = &vertices[index];
+        let next = &vertices[(index + 1) % 3];
+        let (current_distance, next_distance) = (distance(current), distance(next));
+        if current_distance >= 0. {
+            clipped.push(*current);
+        }
+        if (current_distance >= 0.) != (next_distance >= 0.) {
+            let t = current_distance / (current_distance - next_distance);
+            let mut vertex = *current;
+            for (value, next) in vertex.clip.iter_mut().zip(&next.clip) {
+                *value += (next - *value) * t;
+            }
+            for (value, next) in vertex.varyings.iter_mut().zip(&next.varyings) {
+                *value += (next - *value) * t;
+            }
+            clipped.push(vertex);
+        }
+    }
+
+    clipped
+}
+
+/// The mesh program with the uniforms and attribute arrays of the draw.
+struct MeshPipeline<'a> {
+    state: &'a State,
+    program: &'a ProgramState,
+    /// Attribute locations of position, normal and texture coordinates.
+    attributes: [Option<u32>; 3],
+    model_matrix: [f32; 16],
+    view_projection: [f32; 16],
+    normal_matrix: [f32; 9],
+    texture_transform: [f32; 9],
+    base_color_factor: [f32; 4],
+    base_color_texture: Option<&'a TextureState>,
+    unlit: bool,
+    blinn_phong: bool,
+    lighting: bool,
+    emissive: bool,
+    clearcoat: bool,
+    transmission: bool,
+    light_count: usize,
+}
+
+impl MeshPipeline<'_> {
+    /// `None` for programs other than the mesh program.
+    fn new<'a>(state: &'a State, program: &'a ProgramState) -> Option<MeshPipeline<'a>> {
+        if !program.uniforms.contains("baseColorFactor") {
+            return None;
+        }
+
+        let defined = |name: &str| {
+            program
+                .sources
+                .iter()
+                .flat_map(|source| source.lines())
+                .any(|line| line.trim() == format!("#define {}", name))
+        };
+        let max_lights = program
+            .sources
+            .iter()
+            .flat_map(|source| source.lines())
+            .find_map(|line| line.trim().strip_prefix("#define MAX_LIGHTS "))
+            .and_then(|count| count.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        let attribute = |name: &str| {
+            program
+                .attributes
+                .iter()
+                .position(|attribute| attribute == name)
+                .map(|location| location as u32)
+        };
+
+        let lighting = !defined("UNLIT") && defined("HAS_NORMALS") && max_lights > 0;
+        let mut pipeline = MeshPipeline {
+            state,
+            program,
+            attributes: [
+                attribute("position"),
+                attribute("normal"),
+                attribute("textureCoords"),
+            ],
+            model_matrix: [0.; 16],
+            view_projection: [0.; 16],
+            normal_matrix: [0.; 9],
+            texture_transform: [0.; 9],
+            base_color_factor: [0.; 4],
+            base_color_texture: None,
+            unlit: defined("UNLIT"),
+            blinn_phong: defined("BLINN_PHONG"),
+            lighting,
+            emissive: defined("EMISSIVE"),
+            // The shader drops the coat layer along with the lighting.
+            clearcoat: defined("CLEARCOAT") && lighting,
+            transmission: defined("TRANSMISSION"),
+            light_count: 0,
+        };
+        pipeline.model_matrix = pipeline.uniform("modelMatrix");
+        pipeline.view_projection = multiply(
+            &pipeline.uniform("projectionMatrix"),
+            &pipeline.uniform("viewMatrix"),
+        );
+        pipeline.normal_matrix = pipeline.uniform("normalMatrix");
+        pipeline.texture_transform = pipeline.uniform("textureTransform");
+        pipeline.base_color_factor = pipeline.uniform("baseColorFactor");
+        pipeline.light_count = (pipeline.uniform::<1>("lightCount")[0] as usize).min(max_lights);
+        if defined("HAS_BASE_COLOR_TEXTURE") {
+            let unit = pipeline.uniform::<1>("uTexture")[0] as u32;
+            pipeline.base_color_texture = state
+                .bound_textures
+                .get(&(unit, WebGlRenderingContext::TEXTURE_2D))
+                .and_then(|texture| state.textures.get(texture));
+        }
+
+        Some(pipeline)
+    }
+
+    /// Value of a uniform, zeros where it was never set.
+    fn uniform<const N: usize>(&self, name: &str) -> [f32; N] {
+        self.uniform_array(name, 0)
+    }
+
+    /// Element `index` of a uniform array with elements of `N` components.
+    fn uniform_array<const N: usize>(&self, name: &str, index: usize) -> [f32; N] {
+        let mut value = [0.; N];
+        if let Some(values) = self.program.values.get(name) {
+            for (component, target) in value.iter_mut().enumerate() {
+                *target = values.get(index * N + component).copied().unwrap_or(0.);
+            }
+        }
+
+        value
+    }
+
+    /// Attribute value of `vertex`, disabled attributes read as `(0, 0, 0, 1)`.
+    fn attribute(&self, attribute: usize, vertex: u32) -> [f32; 4] {
+        let mut value = [0., 0., 0., 1.];
+        let location = match self.attributes[attribute] {
+            Some(location) if self.state.enabled_attributes.contains(&location) => location,
+            _ => return value,
+        };
+        let pointer = match self.state.attribute_pointers.get(&location) {
+            Some(pointer) => pointer,
+            None => return value,
+        };
+        let data = match self.state.buffers.get(&pointer.buffer) {
+            Some(data) => data,
+            None => return value,
+        };
+
+        let component_size = component_size(pointer.data_type);
+        let stride = match pointer.stride {
+            0 => pointer.size as usize * component_size,
+            stride => stride as usize,
+        };
+        let start = pointer.offset as usize + vertex as usize * stride;
+        for (component, target) in value.iter_mut().take(pointer.size as usize).enumerate() {
+            let offset = start + component * component_size;
+            let bytes = &data[offset..offset + component_size];
+            *target = match (pointer.data_type, pointer.normalized) {
+                (WebGlRenderingContext::UNSIGNED_BYTE, true) => bytes[0] as f32 / 255.,
+                (WebGlRenderingContext::UNSIGNED_BYTE, false) => bytes[0] as f32,
+                (WebGlRenderingContext::BYTE, true) => (bytes[0] as i8 as f32 / 127.).max(-1.),
+                (WebGlRenderingContext::BYTE, false) => bytes[0] as i8 as f32,
+                (WebGlRenderingContext::UNSIGNED_SHORT, normalized) => {
+                    let value = u16::from_le_bytes([bytes[0], bytes[1]]) as f32;
+                    if normalized {
+                        value / 65535.
+                    } else {
+                        value
+                    }
+                }
+                (WebGlRenderingContext::SHORT, normalized) => {
+                    let value = i16::from_le_bytes([bytes[0], bytes[1]]) as f32;
+                    if normalized {
+                        (value / 32767.).max(-1.)
+                    } else {
+                        value
+                    }
+                }
+                _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            };
+        }
+
+        value
+    }
+
+    fn vertex(&self, index: u32) -> Vertex {
+        let position = self.attribute(0, index);
+        let normal = self.attribute(1, index);
+        let texture_coords = self.attribute(2, index);
+
+        let world = transform4(
+            &self.model_matrix,
+            [position[0], position[1], position[2], 1.],
+        );
+        let normal = transform3(&self.normal_matrix, [normal[0], normal[1], normal[2]]);
+        let texture_coords = transform3(
+            &self.texture_transform,
+            [texture_coords[0], texture_coords[1], 1.],
+        );
+
+        Vertex {
+            clip: transform4(&self.view_projection, world),
+            varyings: [
+                normal[0],
+                normal[1],
+                normal[2],
+                world[0],
+                world[1],
+                world[2],
+                texture_coords[0],
+                texture_coords[1],
+            ],
+        }
+    }
+
+    fn fragment(&self, varyings: &[f32; VARYINGS]) -> [f32; 4] {
+        let mut base_color = self.base_color_factor;
+        if let Some(texture) = self.base_color_texture {
+            let texel = sample(texture, varyings[6], varyings[7]);
+            for (channel, texel) in base_color.iter_mut().zip(&texel) {
+                *channel *= texel;
+            }
+        }
+        if self.unlit {
+            return base_color;
+        }
+
+        let albedo = [base_color[0], base_color[1], base_color[2]];
+        let mut color = albedo;
+        let n = normalize([varyings[0], varyings[1], varyings[2]]);
+        let world = [varyings[3], varyings[4], varyings[5]];
+        let v = normalize(subtract(self.uniform("cameraPosition"), world));
+        if self.lighting {
+            // Scenes without any light keep showing the plain base color.
+            if self.light_count > 0 {
+                let ground: [f32; 3] = self.uniform("ambientGround");
+                let sky: [f32; 3] = self.uniform("ambientSky");
+                // Hemispheric for Blinn-Phong, constant for PBR.
+                let t = match self.blinn_phong {
+                    true => n[1] * 0.5 + 0.5,
+                    false => 0.5,
+                };
+                let coat = 1. - self.clearcoat_fresnel(n, v);
+                color =
+                    [0, 1, 2].map(|i| (ground[i] + (sky[i] - ground[i]) * t) * albedo[i] * coat);
+            }
+            for light in 0..self.light_count {
+                let contribution = self.punctual_light(light, n, v, world, albedo);
+                color = [0, 1, 2].map(|i| color[i] + contribution[i]);
+            }
+        }
+        let mut alpha = base_color[3];
+        if self.transmission {
+            alpha *= 1. - self.uniform::<1>("transmission")[0];
+        }
+        if self.emissive {
+            let emissive: [f32; 3] = self.uniform("emissive");
+            color = [0, 1, 2].map(|i| color[i] + emissive[i]);
+        }
+
+        [color[0], color[1], color[2], alpha]
+    }
+
+    fn punctual_light(
+        &self,
+        light: usize,
+        n: [f32; 3],
+        v: [f32; 3],
+        world: [f32; 3],
+        albedo: [f32; 3],
+    ) -> [f32; 3] {
+        let position: [f32; 4] = self.uniform_array("lightPositions", light);
+        let direction: [f32; 4] = self.uniform_array("lightDirections", light);
+        let light_color: [f32; 3] = self.uniform_array("lightColors", light);
+        let cone: [f32; 2] = self.uniform_array("lightCones", light);
+
+        let mut l = [-direction[0], -direction[1], -direction[2]];
+        let mut attenuation = 1.;
+        if position[3] > 0.5 {
+            let to_light = subtract([position[0], position[1], position[2]], world);
+            let distance_squared = dot(to_light, to_light).max(0.0001);
+            l = to_light.map(|value| value / distance_squared.sqrt());
+            attenuation = 1. / distance_squared;
+
+            if direction[3] > 0. {
+                let ratio = distance_squared / (direction[3] * direction[3]);
+                attenuation *= (1. - ratio * ratio).clamp(0., 1.);
+            }
+            if position[3] > 1.5 {
+                let spot = (-dot([direction[0], direction[1], direction[2]], l) * cone[0]
+                    + cone[1])
+                    .clamp(0., 1.);
+                attenuation *= spot * spot;
+            }
+        }
+
+        let mut brdf = match self.blinn_phong {
+            true => self.blinn_phong_brdf(n, v, l, albedo),
+            false => self.pbr_brdf(n, v, l, albedo),
+        };
+        if self.clearcoat {
+            let coat_fresnel = self.clearcoat_fresnel(n, v);
+            let coat = self.uniform::<2>("clearcoat")[0] * self.clearcoat_specular(n, v, l);
+            brdf = brdf.map(|channel| channel * (1. - coat_fresnel) + coat);
+        }
+
+        [0, 1, 2].map(|i| brdf[i] * light_color[i] * attenuation)
+    }
+
+    /// Share of the light the coat layer reflects before it reaches the base layer.
+    fn clearcoat_fresnel(&self, n: [f32; 3], v: [f32; 3]) -> f32 {
+        if !self.clearcoat {
+            return 0.;
+        }
+
+        self.uniform::<2>("clearcoat")[0] * fresnel_schlick(dot(n, v).clamp(0., 1.))
+    }
+
+    fn clearcoat_specular(&self, n: [f32; 3], v: [f32; 3], l: [f32; 3]) -> f32 {
+        let roughness = self.uniform::<2>("clearcoat")[1].max(0.1);
+        let alpha_squared = roughness.powi(4);
+
+        let h = normalize([v[0] + l[0], v[1] + l[1], v[2] + l[2]]);
+        let n_dot_l = dot(n, l).clamp(0., 1.);
+        let n_dot_v = dot(n, v).clamp(0.0001, 1.);
+        let n_dot_h = dot(n, h).clamp(0., 1.);
+        let v_dot_h = dot(v, h).clamp(0., 1.);
+
+        let denominator = n_dot_h * n_dot_h * (alpha_squared - 1.) + 1.;
+        let distribution = alpha_squared / (PI * denominator * denominator);
+        let visibility = 0.5
+            / (n_dot_l * (n_dot_v * n_dot_v * (1. - alpha_squared) + alpha_squared).sqrt()
+                + n_dot_v * (n_dot_l * n_dot_l * (1. - alpha_squared) + alpha_squared).sqrt())
+            .max(0.0001);
+
+        fresnel_schlick(v_dot_h) * distribution * visibility * n_dot_l
+    }
+
+    fn blinn_phong_brdf(
+        &self,
+        n: [f32; 3],
+        v: [f32; 3],
+        l: [f32; 3],
+        albedo: [f32; 3],
+    ) -> [f32; 3] {
+        let [metallic, roughness] = self.uniform("metallicRoughness");
+        let roughness = roughness.max(0.05);
+        let shininess = (2. / roughness.powi(4) - 2.).min(2048.);
+
+        let h = normalize([v[0] + l[0], v[1] + l[1], v[2] + l[2]]);
+        let n_dot_l = dot(n, l).max(0.);
+        let n_dot_h = dot(n, h).max(0.);
+
+        [0, 1, 2].map(|i| {
+            let diffuse = albedo[i] * (1. - metallic);
+            let specular = (0.04 + (albedo[i] - 0.04) * metallic) * n_dot_h.powf(shininess);
+            (diffuse + specular) * n_dot_l
+        })
+    }
+
+    fn pbr_brdf(&self, n: [f32; 3], v: [f32; 3], l: [f32; 3], albedo: [f32; 3]) -> [f32; 3] {
+        let [metallic, roughness] = self.uniform("metallicRoughness");
+        let alpha_roughness = roughness * roughness;
+        let alpha_squared = alpha_roughness * alpha_roughness;
+
+        let h = normalize([v[0] + l[0], v[1] + l[1], v[2] + l[2]]);
+        let n_dot_l = dot(n, l).clamp(0., 1.);
+        let n_dot_v = dot(n, v).clamp(0.0001, 1.);
+        let n_dot_h = dot(n, h).clamp(0., 1.);
+        let v_dot_h = dot(v, h).clamp(0., 1.);
+
+        let denominator = n_dot_h * n_dot_h * (alpha_squared - 1.) + 1.;
+        let distribution = alpha_squared / (PI * denominator * denominator);
+        let visibility = 0.5
+            / (n_dot_l * (n_dot_v * n_dot_v * (1. - alpha_squared) + alpha_squared).sqrt()
+                + n_dot_v * (n_dot_l * n_dot_l * (1. - alpha_squared) + alpha_squared).sqrt())
+            .max(0.0001);
+
+        [0, 1, 2].map(|i| {
+            let f0 = 0.04 + (albedo[i] - 0.04) * metallic;
+            let fresnel = f0 + (1. - f0) * (1. - v_dot_h).powi(5);
+            let diffuse = (1. - fresnel) * (1. - metallic) * albedo[i] / PI;
+            let specular = fresnel * distribution * visibility;
+            (diffuse + specular) * n_dot_l
+        })
+    }
+}
+
+/// Bilinear sample of level 0, textures without RGBA bytes read as opaque black.
+fn sample(texture: &TextureState, u: f32, v: f32) -> [f32; 4] {
+    if texture.pixels.is_empty() {
+        return [0., 0., 0., 1.];
+    }
+
+    let x = u * texture.width as f32 - 0.5;
+    let y = v * texture.height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fraction_x, fraction_y) = (x - x0, y - y0);
+    let texel = |x: i64, y: i64| {
+        let x = wrap(x, texture.width as i64, texture.wrap_s);
+        let y = wrap(y, texture.height as i64, texture.wrap_t);
+        let index = (y * texture.width as usize + x) * 4;
+        [0, 1, 2, 3].map(|channel| texture.pixels[index + channel] as f32 / 255.)
+    };
+
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let [top_left, top_right, bottom_left, bottom_right] = [
+        texel(x0, y0),
+        texel(x0 + 1, y0),
+        texel(x0, y0 + 1),
+        texel(x0 + 1, y0 + 1),
+    ];
+
+    [0, 1, 2, 3].map(|channel| {
+        let top = top_left[channel] + (top_right[channel] - top_left[channel]) * fraction_x;
+        let bottom =
+            bottom_left[channel] + (bottom_right[channel] - bottom_left[channel]) * fraction_x;
+        top + (bottom - top) * fraction_y
+    })
+}
+
+/// Texel index for a wrap mode, `REPEAT` unless the texture sets another.
+fn wrap(coordinate: i64, size: i64, mode: u32) -> usize {
+    let wrapped = match mode {
+        WebGlRenderingContext::CLAMP_TO_EDGE => coordinate.clamp(0, size - 1),
+        WebGlRenderingContext::MIRRORED_REPEAT => {
+            let period = coordinate.rem_euclid(size * 2);
+            if period < size {
+                period
+            } else {
+                size * 2 - 1 - period
+            }
+        }
+        _ => coordinate.rem_euclid(size),
+    };
+
+    wrapped as usize
+}
+
+/// Schlick's approximation for the coat layer, which has an index of refraction of 1.5.
+fn fresnel_schlick(cos_theta: f32) -> f32 {
+    0.04 + 0.96 * (1. - cos_theta).powi(5)
+}
+
+/// Column major `a * b`.
+fn multiply(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
+    let mut out = [0.; 16];
+    for column in 0..4 {
+        for row in 0..4 {
+            out[column * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[column * 4 + k]).sum();
+        }
+    }
+
+    out
+}
+
+fn transform4(matrix: &[f32; 16], vector: [f32; 4]) -> [f32; 4] {
+    [0, 1, 2, 3].map(|row| {
+        (0..4)
+            .map(|column| matrix[column * 4 + row] * vector[column])
+            .sum()
+    })
+}
+
+fn transform3(matrix: &[f32; 9], vector: [f32; 3]) -> [f32; 3] {
+    [0, 1, 2].map(|row| {
+        (0..3)
+            .map(|column| matrix[column * 3 + row] * vector[column])
+            .sum()
+    })
+}
+
+fn subtract(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(vector: [f32; 3]) -> [f32; 3] {
+    let length = dot(vector, vector).sqrt();
+    if length == 0. {
+        return vector;
+    }
+
+    vector.map(|value| value / length)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::backend::headless::HeadlessBackend;
+    use crate::renderer::backend::{Backend, Handle};
+    use web_sys::WebGlRenderingContext;
+
+    const V_SHADER: &str = "attribute vec3 position;
+uniform mat4 modelMatrix;
+uniform mat4 viewMatrix;
+uniform mat4 projectionMatrix;
+void main() {}";
+    const F_SHADER: &str = "#define UNLIT
+uniform vec4 baseColorFactor;
+void main() {}";
+    const IDENTITY: [f32; 16] = [
+        1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1.,
+    ];
+
+    /// A 2x2 framebuffer with an unlit mesh program in use and identity matrices.
+    fn pipeline() -> HeadlessBackend {
+        let gl = HeadlessBackend::new(2, 2).with_rasterizer();
+        let program = gl.create_program().unwrap();
+        for (shader_type, source) in &[
+            (WebGlRenderingContext::VERTEX_SHADER, V_SHADER),
+            (WebGlRenderingContext::FRAGMENT_SHADER, F_SHADER),
+        ] {
+            let shader = gl.create_shader(*shader_type).unwrap();
+            gl.shader_source(&shader, source);
+            gl.attach_shader(&program, &shader);
+        }
+        gl.link_program(&program);
+        gl.use_program(Some(&program));
+        for name in &["modelMatrix", "viewMatrix", "projectionMatrix"] {
+            let location = gl.get_uniform_location(&program, name);
+            gl.uniform_matrix4fv(location.as_ref(), &IDENTITY);
+        }
+
+        gl
+    }
+
+    /// Draws a triangle covering the framebuffer at depth `z` in `color`.
+    fn draw(gl: &HeadlessBackend, counter_clockwise: bool, z: f32, color: [f32; 4]) {
+        let program = gl.state.borrow().program.unwrap();
+        let location = gl.get_uniform_location(&Handle::Headless(program), "baseColorFactor");
+        gl.uniform4fv(location.as_ref(), &color);
+
+        let (second, third) = match counter_clockwise {
+            true => ([3., -1., z], [-1., 3., z]),
+            false => ([-1., 3., z], [3., -1., z]),
+        };
+        let mut positions = vec![-1., -1., z];
+        positions.extend_from_slice(&second);
+        positions.extend_from_slice(&third);
+        let buffer = gl.create_buffer().unwrap();
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&buffer));
+        gl.buffer_data_f32(
+            WebGlRenderingContext::ARRAY_BUFFER,
+            &positions,
+            WebGlRenderingContext::STATIC_DRAW,
+        );
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer(0, 3, WebGlRenderingContext::FLOAT, false, 0, 0);
+        gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, 3);
+    }
+
+    fn pixels(gl: &HeadlessBackend) -> Vec<u8> {
+        let mut pixels = vec![0; 16];
+        gl.read_pixels(
+            0,
+            0,
+            2,
+            2,
+            WebGlRenderingContext::RGBA,
+            WebGlRenderingContext::UNSIGNED_BYTE,
+            &mut pixels,
+        )
+        .unwrap();
+        assert_eq!(gl.errors(), Vec::<String>::new());
+
+        pixels
+    }
+
+    #[test]
+    fn culls_clockwise_triangles() {
+        let gl = pipeline();
+        gl.enable(WebGlRenderingContext::CULL_FACE);
+        draw(&gl, false, 0., [1., 0., 0., 1.]);
+        assert_eq!(pixels(&gl), vec![0; 16]);
+
+        draw(&gl, true, 0., [1., 0., 0., 1.]);
+        assert_eq!(pixels(&gl), [255, 0, 0, 255].repeat(4));
+
+        gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+        gl.front_face(WebGlRenderingContext::CW);
+        draw(&gl, true, 0., [1., 0., 0., 1.]);
+        assert_eq!(pixels(&gl), vec![0; 16]);
+    }
+
+    #[test]
+    fn keeps_the_nearest_fragment() {
+        let gl = pipeline();
+        gl.enable(WebGlRenderingContext::DEPTH_TEST);
+        gl.clear(WebGlRenderingContext::DEPTH_BUFFER_BIT);
+        draw(&gl, true, -0.5, [0., 1., 0., 1.]);
+        draw(&gl, true, 0.5, [1., 0., 0., 1.]);
+
+        assert_eq!(pixels(&gl), [0, 255, 0, 255].repeat(4));
+
+        gl.depth_func(WebGlRenderingContext::GREATER);
+        gl.clear_depth(0.);
+        gl.clear(WebGlRenderingContext::DEPTH_BUFFER_BIT);
+        draw(&gl, true, -0.5, [0., 1., 0., 1.]);
+        draw(&gl, true, 0.5, [1., 0., 0., 1.]);
+        assert_eq!(pixels(&gl), [255, 0, 0, 255].repeat(4));
+    }
+}
@@ -0,0 +1,343 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    HtmlCanvasElement, HtmlImageElement, WebGlBuffer, WebGlContextAttributes, WebGlFramebuffer,
+    WebGlProgram, WebGlQuery, WebGlRenderbuffer, WebGlRenderingContext, WebGlShader, WebGlTexture,
+    WebGlUniformLocation, WebGlVertexArrayObject,
+};
+
+pub(crate) use webgl::WebGlBackend;
+pub(crate) use webgl2::WebGl2Backend;
+
+#[cfg(test)]
+pub(crate) mod headless;
+mod webgl;
+mod webgl2;
+
+/// `HALF_FLOAT_OES` of `OES_texture_half_float`, WebGL1 has no constant for it. WebGL 2
+/// backends map it to their own half float type.
+pub(crate) const HALF_FLOAT_OES: u32 = 0x8D61;
+
+/// Object created by a backend, WebGL backends hold the JS object.
+#[derive(Debug, Clone)]
+pub(crate) enum Handle<T> {
+    WebGl(T),
+    #[cfg(test)]
+    Headless(u32),
+}
+
+impl<T> Handle<T> {
+    /// The JS object, `None` for handles of another kind of backend.
+    pub(crate) fn web_gl(&self) -> Option<&T> {
+        match self {
+            Handle::WebGl(object) => Some(object),
+            #[cfg(test)]
+            Handle::Headless(_) => None,
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn headless(&self) -> Option<u32> {
+        match self {
+            Handle::Headless(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) type Buffer = Handle<WebGlBuffer>;
+pub(crate) type Texture = Handle<WebGlTexture>;
+pub(crate) type Shader = Handle<WebGlShader>;
+pub(crate) type Program = Handle<WebGlProgram>;
+pub(crate) type UniformLocation = Handle<WebGlUniformLocation>;
+pub(crate) type Framebuffer = Handle<WebGlFramebuffer>;
+pub(crate) type Renderbuffer = Handle<WebGlRenderbuffer>;
+pub(crate) type Query = Handle<WebGlQuery>;
+pub(crate) type VertexArray = Handle<WebGlVertexArrayObject>;
+
+/// Bytes a backend handed to the GPU since it was created. Texture and renderbuffer
+/// storage allocated without data counts with its size.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct UploadTotals {
+    pub(crate) buffer_bytes: u64,
+    pub(crate) texture_bytes: u64,
+}
+
+impl UploadTotals {
+    /// Bytes uploaded since `earlier` totals of the same backend.
+    pub(crate) fn since(&self, earlier: &UploadTotals) -> UploadTotals {
+        UploadTotals {
+            buffer_bytes: self.buffer_bytes.saturating_sub(earlier.buffer_bytes),
+            texture_bytes: self.texture_bytes.saturating_sub(earlier.texture_bytes),
+        }
+    }
+
+    pub(crate) fn add(&mut self, other: &UploadTotals) {
+        self.buffer_bytes = self.buffer_bytes.saturating_add(other.buffer_bytes);
+        self.texture_bytes = self.texture_bytes.saturating_add(other.texture_bytes);
+    }
+}
+
+/// Counts the uploads of a backend, every backend calls it from its upload methods so
+/// no caller can miss it.
+#[derive(Debug, Default)]
+pub(crate) struct UploadCounter(Cell<UploadTotals>);
+
+impl UploadCounter {
+    pub(crate) fn buffer(&self, bytes: usize) {
+        let mut totals = self.0.get();
+        totals.buffer_bytes = totals.buffer_bytes.saturating_add(bytes as u64);
+        self.0.set(totals);
+    }
+
+    pub(crate) fn texture(&self, bytes: usize) {
+        let mut totals = self.0.get();
+        totals.texture_bytes = totals.texture_bytes.saturating_add(bytes as u64);
+        self.0.set(totals);
+    }
+
+    /// Storage of an uncompressed `width` x `height` image of `format` and `data_type`.
+    pub(crate) fn image(&self, width: i32, height: i32, format: u32, data_type: u32) {
+        let channels = match format {
+            WebGlRenderingContext::RGBA => 4,
+            WebGlRenderingContext::RGB => 3,
+            WebGlRenderingContext::LUMINANCE_ALPHA => 2,
+            _ => 1,
+        };
+        let texel_size = match data_type {
+            WebGlRenderingContext::UNSIGNED_SHORT_5_6_5
+            | WebGlRenderingContext::UNSIGNED_SHORT_4_4_4_4
+            | WebGlRenderingContext::UNSIGNED_SHORT_5_5_5_1 => 2,
+            WebGlRenderingContext::UNSIGNED_SHORT | HALF_FLOAT_OES => channels * 2,
+            WebGlRenderingContext::UNSIGNED_INT | WebGlRenderingContext::FLOAT => channels * 4,
+            _ => channels,
+        };
+
+        self.texture(pixel_count(width, height).saturating_mul(texel_size));
+    }
+
+    pub(crate) fn renderbuffer(&self, format: u32, width: i32, height: i32) {
+        let texel_size = match format {
+            WebGlRenderingContext::DEPTH_COMPONENT16
+            | WebGlRenderingContext::RGBA4
+            | WebGlRenderingContext::RGB565
+            | WebGlRenderingContext::RGB5_A1 => 2,
+            WebGlRenderingContext::STENCIL_INDEX8 => 1,
+            _ => 4,
+        };
+
+        self.texture(pixel_count(width, height).saturating_mul(texel_size));
+    }
+
+    pub(crate) fn totals(&self) -> UploadTotals {
+        self.0.get()
+    }
+}
+
+fn pixel_count(width: i32, height: i32) -> usize {
+    (width.max(0) as usize).saturating_mul(height.max(0) as usize)
+}
+
+/// Backend for the context of `canvas`, WebGL 2 where the browser supports it and WebGL 1
+/// otherwise. A canvas that already has a context keeps it.
+pub(crate) fn for_canvas(canvas: &HtmlCanvasElement) -> Result<Rc<dyn Backend>, JsValue> {
+    for_canvas_with_attributes(canvas, &WebGlContextAttributes::new())
+}
+
+/// Like `for_canvas`, but creates a missing context with `attributes`.
+pub(crate) fn for_canvas_with_attributes(
+    canvas: &HtmlCanvasElement,
+    attributes: &WebGlContextAttributes,
+) -> Result<Rc<dyn Backend>, JsValue> {
+    if let Some(context) = canvas.get_context_with_context_options("webgl2", attributes)? {
+        return Ok(Rc::new(WebGl2Backend::new(context.dyn_into()?)));
+    }
+
+    let context = canvas
+        .get_context_with_context_options("webgl", attributes)?
+        .ok_or_else(|| JsValue::from_str("WebGL is not supported"))?;
+
+    Ok(Rc::new(WebGlBackend::new(context.dyn_into()?)))
+}
+
+/// Everything the renderer asks of the GPU. Methods follow the WebGL 1 calls of the same
+/// name and take the same `WebGlRenderingContext` constants.
+pub(crate) trait Backend: std::fmt::Debug {
+    /// Whether the extension is available, querying it enables it.
+    fn has_extension(&self, name: &str) -> bool;
+    /// Set from the loss of the context until it is restored. Objects created before the
+    /// loss stay invalid after it.
+    fn is_context_lost(&self) -> bool;
+    fn drawing_buffer_width(&self) -> i32;
+    fn drawing_buffer_height(&self) -> i32;
+    /// Integer parameter such as `MAX_TEXTURE_SIZE`, `None` when the context can not
+    /// answer, for example because it is lost.
+    fn get_parameter_i32(&self, parameter: u32) -> Option<i32>;
+    /// Bytes uploaded by the buffer, texture and renderbuffer calls below.
+    fn upload_totals(&self) -> UploadTotals;
+
+    fn create_buffer(&self) -> Option<Buffer>;
+    fn delete_buffer(&self, buffer: &Buffer);
+    fn bind_buffer(&self, target: u32, buffer: Option<&Buffer>);
+    fn buffer_data_u8(&self, target: u32, data: &[u8], usage: u32);
+    fn buffer_data_u16(&self, target: u32, data: &[u16], usage: u32);
+    fn buffer_data_u32(&self, target: u32, data: &[u32], usage: u32);
+    fn buffer_data_f32(&self, target: u32, data: &[f32], usage: u32);
+
+    fn create_texture(&self) -> Option<Texture>;
+    fn delete_texture(&self, texture: &Texture);
+    fn active_texture(&self, unit: u32);
+    fn bind_texture(&self, target: u32, texture: Option<&Texture>);
+    fn tex_parameteri(&self, target: u32, parameter: u32, value: i32);
+    #[allow(clippy::too_many_arguments)]
+    fn tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        data_type: u32,
+        pixels: Option<&[u8]>,
+    ) -> Result<(), JsValue>;
+    fn tex_image_2d_image(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        format: u32,
+        data_type: u32,
+        image: &HtmlImageElement,
+    ) -> Result<(), JsValue>;
+    fn compressed_tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        format: u32,
+        width: i32,
+        height: i32,
+        data: &[u8],
+    );
+    #[allow(clippy::too_many_arguments)]
+    fn copy_tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: u32,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    );
+    fn generate_mipmap(&self, target: u32);
+
+    fn create_framebuffer(&self) -> Option<Framebuffer>;
+    fn delete_framebuffer(&self, framebuffer: &Framebuffer);
+    fn bind_framebuffer(&self, target: u32, framebuffer: Option<&Framebuffer>);
+    fn framebuffer_texture_2d(
+        &self,
+        target: u32,
+        attachment: u32,
+        texture_target: u32,
+        texture: Option<&Texture>,
+        level: i32,
+    );
+    fn check_framebuffer_status(&self, target: u32) -> u32;
+    fn create_renderbuffer(&self) -> Option<Renderbuffer>;
+    fn delete_renderbuffer(&self, renderbuffer: &Renderbuffer);
+    fn bind_renderbuffer(&self, target: u32, renderbuffer: Option<&Renderbuffer>);
+    fn renderbuffer_storage(&self, target: u32, format: u32, width: i32, height: i32);
+    fn framebuffer_renderbuffer(
+        &self,
+        target: u32,
+        attachment: u32,
+        renderbuffer_target: u32,
+        renderbuffer: Option<&Renderbuffer>,
+    );
+
+    fn create_shader(&self, shader_type: u32) -> Option<Shader>;
+    fn shader_source(&self, shader: &Shader, source: &str);
+    fn compile_shader(&self, shader: &Shader);
+    fn shader_compiled(&self, shader: &Shader) -> bool;
+    fn shader_info_log(&self, shader: &Shader) -> Option<String>;
+    fn delete_shader(&self, shader: &Shader);
+    fn create_program(&self) -> Option<Program>;
+    fn delete_program(&self, program: &Program);
+    fn attach_shader(&self, program: &Program, shader: &Shader);
+    fn detach_shader(&self, program: &Program, shader: &Shader);
+    fn link_program(&self, program: &Program);
+    fn program_linked(&self, program: &Program) -> bool;
+    fn program_info_log(&self, program: &Program) -> Option<String>;
+    fn use_program(&self, program: Option<&Program>);
+    fn get_attrib_location(&self, program: &Program, name: &str) -> i32;
+    fn get_uniform_location(&self, program: &Program, name: &str) -> Option<UniformLocation>;
+
+    fn enable_vertex_attrib_array(&self, location: u32);
+    fn disable_vertex_attrib_array(&self, location: u32);
+    fn vertex_attrib_pointer(
+        &self,
+        location: u32,
+        size: i32,
+        data_type: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    );
+    fn vertex_attrib3fv(&self, location: u32, value: &[f32]);
+
+    fn uniform1i(&self, location: Option<&UniformLocation>, value: i32);
+    fn uniform1f(&self, location: Option<&UniformLocation>, value: f32);
+    fn uniform2f(&self, location: Option<&UniformLocation>, x: f32, y: f32);
+    fn uniform2fv(&self, location: Option<&UniformLocation>, value: &[f32]);
+    fn uniform3fv(&self, location: Option<&UniformLocation>, value: &[f32]);
+    fn uniform4fv(&self, location: Option<&UniformLocation>, value: &[f32]);
+    fn uniform_matrix3fv(&self, location: Option<&UniformLocation>, value: &[f32]);
+    fn uniform_matrix4fv(&self, location: Option<&UniformLocation>, value: &[f32]);
+
+    fn enable(&self, capability: u32);
+    fn disable(&self, capability: u32);
+    fn is_enabled(&self, capability: u32) -> bool;
+    fn blend_func(&self, source: u32, destination: u32);
+    fn depth_mask(&self, enabled: bool);
+    fn depth_func(&self, function: u32);
+    fn cull_face(&self, mode: u32);
+    fn front_face(&self, mode: u32);
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32);
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32);
+    fn clear_depth(&self, depth: f32);
+    fn clear(&self, mask: u32);
+
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32);
+    fn draw_elements(&self, mode: u32, count: i32, data_type: u32, offset: i32);
+    #[allow(clippy::too_many_arguments)]
+    fn read_pixels(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        data_type: u32,
+        pixels: &mut [u8],
+    ) -> Result<(), JsValue>;
+
+    /// Whether `EXT_disjoint_timer_query` or an equivalent is available.
+    fn has_timer_queries(&self) -> bool;
+    /// Starts measuring GPU time, only one query can run at a time.
+    fn begin_timer_query(&self) -> Option<Query>;
+    fn end_timer_query(&self);
+    /// Elapsed nanoseconds once the GPU has finished the query.
+    fn timer_query_result(&self, query: &Query) -> Option<f64>;
+    /// Whether GPU timings since the last check are unreliable.
+    fn timer_disjoint(&self) -> bool;
+    fn delete_query(&self, query: &Query);
+
+    /// `None` when the backend has no vertex array objects, attribute arrays are then
+    /// set up before every draw.
+    fn create_vertex_array(&self) -> Option<VertexArray>;
+    fn delete_vertex_array(&self, vertex_array: &VertexArray);
+    fn bind_vertex_array(&self, vertex_array: Option<&VertexArray>);
+}
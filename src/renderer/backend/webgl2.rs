@@ -0,0 +1,642 @@
+use crate::renderer::backend::{
+    Backend, Buffer, Framebuffer, Handle, Program, Query, Renderbuffer, Shader, Texture,
+    UniformLocation, UploadCounter, UploadTotals, VertexArray, HALF_FLOAT_OES,
+};
+use wasm_bindgen::prelude::*;
+use web_sys::{ExtDisjointTimerQuery, HtmlImageElement, WebGl2RenderingContext};
+
+/// The WebGL 2 context of a canvas. Shaders are written in GLSL ES 1.00 and translated,
+/// extensions WebGL 2 took into the core are reported as available.
+#[derive(Debug)]
+pub(crate) struct WebGl2Backend {
+    gl: WebGl2RenderingContext,
+    timer_queries: bool,
+    uploads: UploadCounter,
+}
+
+impl WebGl2Backend {
+    pub(crate) fn new(gl: WebGl2RenderingContext) -> WebGl2Backend {
+        let timer_queries = matches!(
+            gl.get_extension("EXT_disjoint_timer_query_webgl2"),
+            Ok(Some(_))
+        );
+
+        WebGl2Backend {
+            gl,
+            timer_queries,
+            uploads: UploadCounter::default(),
+        }
+    }
+
+    fn extension_enabled(&self, name: &str) -> bool {
+        matches!(self.gl.get_extension(name), Ok(Some(_)))
+    }
+}
+
+impl Backend for WebGl2Backend {
+    fn has_extension(&self, name: &str) -> bool {
+        match name {
+            "ANGLE_instanced_arrays"
+            | "EXT_blend_minmax"
+            | "EXT_frag_depth"
+            | "EXT_shader_texture_lod"
+            | "EXT_sRGB"
+            | "OES_element_index_uint"
+            | "OES_standard_derivatives"
+            | "OES_texture_half_float"
+            | "OES_texture_half_float_linear"
+            | "OES_vertex_array_object"
+            | "WEBGL_depth_texture"
+            | "WEBGL_draw_buffers" => true,
+            "EXT_color_buffer_half_float" => {
+                self.extension_enabled("EXT_color_buffer_float")
+                    || self.extension_enabled("EXT_color_buffer_half_float")
+            }
+            "EXT_disjoint_timer_query" => self.timer_queries,
+            _ => self.extension_enabled(name),
+        }
+    }
+
+    fn is_context_lost(&self) -> bool {
+        self.gl.is_context_lost()
+    }
+
+    fn drawing_buffer_width(&self) -> i32 {
+        self.gl.drawing_buffer_width()
+    }
+
+    fn drawing_buffer_height(&self) -> i32 {
+        self.gl.drawing_buffer_height()
+    }
+
+    fn get_parameter_i32(&self, parameter: u32) -> Option<i32> {
+        self.gl
+            .get_parameter(parameter)
+            .ok()
+            .and_then(|value| value.as_f64())
+            .map(|value| value as i32)
+    }
+
+    fn upload_totals(&self) -> UploadTotals {
+        self.uploads.totals()
+    }
+
+    fn create_buffer(&self) -> Option<Buffer> {
+        self.gl.create_buffer().map(Handle::WebGl)
+    }
+
+    fn delete_buffer(&self, buffer: &Buffer) {
+        self.gl.delete_buffer(buffer.web_gl());
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<&Buffer>) {
+        self.gl.bind_buffer(target, buffer.and_then(Handle::web_gl));
+    }
+
+    fn buffer_data_u8(&self, target: u32, data: &[u8], usage: u32) {
+        self.gl.buffer_data_with_u8_array(target, data, usage);
+        self.uploads.buffer(data.len());
+    }
+
+    fn buffer_data_u16(&self, target: u32, data: &[u16], usage: u32) {
+        self.gl
+            .buffer_data_with_array_buffer_view(target, &js_sys::Uint16Array::from(data), usage);
+        self.uploads.buffer(data.len() * 2);
+    }
+
+    fn buffer_data_u32(&self, target: u32, data: &[u32], usage: u32) {
+        self.gl
+            .buffer_data_with_array_buffer_view(target, &js_sys::Uint32Array::from(data), usage);
+        self.uploads.buffer(data.len() * 4);
+    }
+
+    fn buffer_data_f32(&self, target: u32, data: &[f32], usage: u32) {
+        self.gl.buffer_data_with_array_buffer_view(
+            target,
+            &js_sys::Float32Array::from(data),
+            usage,
+        );
+        self.uploads.buffer(data.len() * 4);
+    }
+
+    fn create_texture(&self) -> Option<Texture> {
+        self.gl.create_texture().map(Handle::WebGl)
+    }
+
+    fn delete_texture(&self, texture: &Texture) {
+        self.gl.delete_texture(texture.web_gl());
+    }
+
+    fn active_texture(&self, unit: u32) {
+        self.gl.active_texture(unit);
+    }
+
+    fn bind_texture(&self, target: u32, texture: Option<&Texture>) {
+        self.gl
+            .bind_texture(target, texture.and_then(Handle::web_gl));
+    }
+
+    fn tex_parameteri(&self, target: u32, parameter: u32, value: i32) {
+        self.gl.tex_parameteri(target, parameter, value);
+    }
+
+    fn tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        data_type: u32,
+        pixels: Option<&[u8]>,
+    ) -> Result<(), JsValue> {
+        // Half float storage needs a sized internal format in WebGL 2.
+        let (sized_format, sized_type) = match data_type {
+            HALF_FLOAT_OES => (
+                WebGl2RenderingContext::RGBA16F as i32,
+                WebGl2RenderingContext::HALF_FLOAT,
+            ),
+            _ => (internal_format, data_type),
+        };
+
+        self.gl
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                target,
+                level,
+                sized_format,
+                width,
+                height,
+                0,
+                format,
+                sized_type,
+                pixels,
+            )?;
+        self.uploads.image(width, height, format, data_type);
+
+        Ok(())
+    }
+
+    fn tex_image_2d_image(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        format: u32,
+        data_type: u32,
+        image: &HtmlImageElement,
+    ) -> Result<(), JsValue> {
+        self.gl
+            .tex_image_2d_with_u32_and_u32_and_html_image_element(
+                target,
+                level,
+                internal_format,
+                format,
+                data_type,
+                image,
+            )?;
+        self.uploads.image(
+            image.natural_width() as i32,
+            image.natural_height() as i32,
+            format,
+            data_type,
+        );
+
+        Ok(())
+    }
+
+    fn compressed_tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        format: u32,
+        width: i32,
+        height: i32,
+        data: &[u8],
+    ) {
+        self.gl
+            .compressed_tex_image_2d_with_u8_array(target, level, format, width, height, 0, data);
+        self.uploads.texture(data.len());
+    }
+
+    fn copy_tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: u32,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    ) {
+        self.gl
+            .copy_tex_image_2d(target, level, internal_format, x, y, width, height, 0);
+        self.uploads.image(
+            width,
+            height,
+            internal_format,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+        );
+    }
+
+    fn generate_mipmap(&self, target: u32) {
+        self.gl.generate_mipmap(target);
+    }
+
+    fn create_framebuffer(&self) -> Option<Framebuffer> {
+        self.gl.create_framebuffer().map(Handle::WebGl)
+    }
+
+    fn delete_framebuffer(&self, framebuffer: &Framebuffer) {
+        self.gl.delete_framebuffer(framebuffer.web_gl());
+    }
+
+    fn bind_framebuffer(&self, target: u32, framebuffer: Option<&Framebuffer>) {
+        self.gl
+            .bind_framebuffer(target, framebuffer.and_then(Handle::web_gl));
+    }
+
+    fn framebuffer_texture_2d(
+        &self,
+        target: u32,
+        attachment: u32,
+        texture_target: u32,
+        texture: Option<&Texture>,
+        level: i32,
+    ) {
+        self.gl.framebuffer_texture_2d(
+            target,
+            attachment,
+            texture_target,
+            texture.and_then(Handle::web_gl),
+            level,
+        );
+    }
+
+    fn check_framebuffer_status(&self, target: u32) -> u32 {
+        self.gl.check_framebuffer_status(target)
+    }
+
+    fn create_renderbuffer(&self) -> Option<Renderbuffer> {
+        self.gl.create_renderbuffer().map(Handle::WebGl)
+    }
+
+    fn delete_renderbuffer(&self, renderbuffer: &Renderbuffer) {
+        self.gl.delete_renderbuffer(renderbuffer.web_gl());
+    }
+
+    fn bind_renderbuffer(&self, target: u32, renderbuffer: Option<&Renderbuffer>) {
+        self.gl
+            .bind_renderbuffer(target, renderbuffer.and_then(Handle::web_gl));
+    }
+
+    fn renderbuffer_storage(&self, target: u32, format: u32, width: i32, height: i32) {
+        self.gl.renderbuffer_storage(target, format, width, height);
+        self.uploads.renderbuffer(format, width, height);
+    }
+
+    fn framebuffer_renderbuffer(
+        &self,
+        target: u32,
+        attachment: u32,
+        renderbuffer_target: u32,
+        renderbuffer: Option<&Renderbuffer>,
+    ) {
+        self.gl.framebuffer_renderbuffer(
+            target,
+            attachment,
+            renderbuffer_target,
+            renderbuffer.and_then(Handle::web_gl),
+        );
+    }
+
+    fn create_shader(&self, shader_type: u32) -> Option<Shader> {
+        self.gl.create_shader(shader_type).map(Handle::WebGl)
+    }
+
+    fn shader_source(&self, shader: &Shader, source: &str) {
+        if let Some(shader) = shader.web_gl() {
+            let shader_type = self
+                .gl
+                .get_shader_parameter(shader, WebGl2RenderingContext::SHADER_TYPE)
+                .as_f64()
+                .unwrap_or(0.) as u32;
+            self.gl
+                .shader_source(shader, &glsl_300_es(source, shader_type));
+        }
+    }
+
+    fn compile_shader(&self, shader: &Shader) {
+        if let Some(shader) = shader.web_gl() {
+            self.gl.compile_shader(shader);
+        }
+    }
+
+    fn shader_compiled(&self, shader: &Shader) -> bool {
+        shader.web_gl().is_some_and(|shader| {
+            self.gl
+                .get_shader_parameter(shader, WebGl2RenderingContext::COMPILE_STATUS)
+                .as_bool()
+                .unwrap_or(false)
+        })
+    }
+
+    fn shader_info_log(&self, shader: &Shader) -> Option<String> {
+        shader
+            .web_gl()
+            .and_then(|shader| self.gl.get_shader_info_log(shader))
+    }
+
+    fn delete_shader(&self, shader: &Shader) {
+        self.gl.delete_shader(shader.web_gl());
+    }
+
+    fn create_program(&self) -> Option<Program> {
+        self.gl.create_program().map(Handle::WebGl)
+    }
+
+    fn delete_program(&self, program: &Program) {
+        self.gl.delete_program(program.web_gl());
+    }
+
+    fn attach_shader(&self, program: &Program, shader: &Shader) {
+        if let (Some(program), Some(shader)) = (program.web_gl(), shader.web_gl()) {
+            self.gl.attach_shader(program, shader);
+        }
+    }
+
+    fn detach_shader(&self, program: &Program, shader: &Shader) {
+        if let (Some(program), Some(shader)) = (program.web_gl(), shader.web_gl()) {
+            self.gl.detach_shader(program, shader);
+        }
+    }
+
+    fn link_program(&self, program: &Program) {
+        if let Some(program) = program.web_gl() {
+            self.gl.link_program(program);
+        }
+    }
+
+    fn program_linked(&self, program: &Program) -> bool {
+        program.web_gl().is_some_and(|program| {
+            self.gl
+                .get_program_parameter(program, WebGl2RenderingContext::LINK_STATUS)
+                .as_bool()
+                .unwrap_or(false)
+        })
+    }
+
+    fn program_info_log(&self, program: &Program) -> Option<String> {
+        program
+            .web_gl()
+            .and_then(|program| self.gl.get_program_info_log(program))
+    }
+
+    fn use_program(&self, program: Option<&Program>) {
+        self.gl.use_program(program.and_then(Handle::web_gl));
+    }
+
+    fn get_attrib_location(&self, program: &Program, name: &str) -> i32 {
+        program
+            .web_gl()
+            .map(|program| self.gl.get_attrib_location(program, name))
+            .unwrap_or(-1)
+    }
+
+    fn get_uniform_location(&self, program: &Program, name: &str) -> Option<UniformLocation> {
+        program
+            .web_gl()
+            .and_then(|program| self.gl.get_uniform_location(program, name))
+            .map(Handle::WebGl)
+    }
+
+    fn enable_vertex_attrib_array(&self, location: u32) {
+        self.gl.enable_vertex_attrib_array(location);
+    }
+
+    fn disable_vertex_attrib_array(&self, location: u32) {
+        self.gl.disable_vertex_attrib_array(location);
+    }
+
+    fn vertex_attrib_pointer(
+        &self,
+        location: u32,
+        size: i32,
+        data_type: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    ) {
+        self.gl
+            .vertex_attrib_pointer_with_i32(location, size, data_type, normalized, stride, offset);
+    }
+
+    fn vertex_attrib3fv(&self, location: u32, value: &[f32]) {
+        self.gl.vertex_attrib3fv_with_f32_array(location, value);
+    }
+
+    fn uniform1i(&self, location: Option<&UniformLocation>, value: i32) {
+        self.gl.uniform1i(location.and_then(Handle::web_gl), value);
+    }
+
+    fn uniform1f(&self, location: Option<&UniformLocation>, value: f32) {
+        self.gl.uniform1f(location.and_then(Handle::web_gl), value);
+    }
+
+    fn uniform2f(&self, location: Option<&UniformLocation>, x: f32, y: f32) {
+        self.gl.uniform2f(location.and_then(Handle::web_gl), x, y);
+    }
+
+    fn uniform2fv(&self, location: Option<&UniformLocation>, value: &[f32]) {
+        self.gl
+            .uniform2fv_with_f32_array(location.and_then(Handle::web_gl), value);
+    }
+
+    fn uniform3fv(&self, location: Option<&UniformLocation>, value: &[f32]) {
+        self.gl
+            .uniform3fv_with_f32_array(location.and_then(Handle::web_gl), value);
+    }
+
+    fn uniform4fv(&self, location: Option<&UniformLocation>, value: &[f32]) {
+        self.gl
+            .uniform4fv_with_f32_array(location.and_then(Handle::web_gl), value);
+    }
+
+    fn uniform_matrix3fv(&self, location: Option<&UniformLocation>, value: &[f32]) {
+        self.gl
+            .uniform_matrix3fv_with_f32_array(location.and_then(Handle::web_gl), false, value);
+    }
+
+    fn uniform_matrix4fv(&self, location: Option<&UniformLocation>, value: &[f32]) {
+        self.gl
+            .uniform_matrix4fv_with_f32_array(location.and_then(Handle::web_gl), false, value);
+    }
+
+    fn enable(&self, capability: u32) {
+        self.gl.enable(capability);
+    }
+
+    fn disable(&self, capability: u32) {
+        self.gl.disable(capability);
+    }
+
+    fn is_enabled(&self, capability: u32) -> bool {
+        self.gl.is_enabled(capability)
+    }
+
+    fn blend_func(&self, source: u32, destination: u32) {
+        self.gl.blend_func(source, destination);
+    }
+
+    fn depth_mask(&self, enabled: bool) {
+        self.gl.depth_mask(enabled);
+    }
+
+    fn depth_func(&self, function: u32) {
+        self.gl.depth_func(function);
+    }
+
+    fn cull_face(&self, mode: u32) {
+        self.gl.cull_face(mode);
+    }
+
+    fn front_face(&self, mode: u32) {
+        self.gl.front_face(mode);
+    }
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.gl.viewport(x, y, width, height);
+    }
+
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32) {
+        self.gl.clear_color(red, green, blue, alpha);
+    }
+
+    fn clear_depth(&self, depth: f32) {
+        self.gl.clear_depth(depth);
+    }
+
+    fn clear(&self, mask: u32) {
+        self.gl.clear(mask);
+    }
+
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32) {
+        self.gl.draw_arrays(mode, first, count);
+    }
+
+    fn draw_elements(&self, mode: u32, count: i32, data_type: u32, offset: i32) {
+        self.gl
+            .draw_elements_with_i32(mode, count, data_type, offset);
+    }
+
+    fn read_pixels(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        data_type: u32,
+        pixels: &mut [u8],
+    ) -> Result<(), JsValue> {
+        self.gl
+            .read_pixels_with_opt_u8_array(x, y, width, height, format, data_type, Some(pixels))
+    }
+
+    fn has_timer_queries(&self) -> bool {
+        self.timer_queries
+    }
+
+    fn begin_timer_query(&self) -> Option<Query> {
+        if !self.timer_queries {
+            return None;
+        }
+        let query = self.gl.create_query()?;
+        self.gl
+            .begin_query(ExtDisjointTimerQuery::TIME_ELAPSED_EXT, &query);
+
+        Some(Handle::WebGl(query))
+    }
+
+    fn end_timer_query(&self) {
+        if self.timer_queries {
+            self.gl.end_query(ExtDisjointTimerQuery::TIME_ELAPSED_EXT);
+        }
+    }
+
+    fn timer_query_result(&self, query: &Query) -> Option<f64> {
+        let query = query.web_gl()?;
+        let available = self
+            .gl
+            .get_query_parameter(query, WebGl2RenderingContext::QUERY_RESULT_AVAILABLE)
+            .as_bool()
+            .unwrap_or(false);
+        if !available {
+            return None;
+        }
+
+        self.gl
+            .get_query_parameter(query, WebGl2RenderingContext::QUERY_RESULT)
+            .as_f64()
+    }
+
+    fn timer_disjoint(&self) -> bool {
+        self.timer_queries
+            && self
+                .gl
+                .get_parameter(ExtDisjointTimerQuery::GPU_DISJOINT_EXT)
+                .ok()
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false)
+    }
+
+    fn delete_query(&self, query: &Query) {
+        self.gl.delete_query(query.web_gl());
+    }
+
+    fn create_vertex_array(&self) -> Option<VertexArray> {
+        self.gl.create_vertex_array().map(Handle::WebGl)
+    }
+
+    fn delete_vertex_array(&self, vertex_array: &VertexArray) {
+        self.gl.delete_vertex_array(vertex_array.web_gl());
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<&VertexArray>) {
+        self.gl
+            .bind_vertex_array(vertex_array.and_then(Handle::web_gl));
+    }
+}
+
+/// Rewrites a GLSL ES 1.00 shader as GLSL ES 3.00. Renamed keywords and functions are
+/// mapped with defines, extension directives are dropped as their features are core.
+fn glsl_300_es(source: &str, shader_type: u32) -> String {
+    let body = match source.strip_prefix("#version 100") {
+        Some(body) => body,
+        None => return source.to_string(),
+    };
+
+    let mut translated = String::from(
+        "#version 300 es
+#define texture2D texture
+#define textureCube texture
+#define texture2DLodEXT textureLod
+#define textureCubeLodEXT textureLod
+",
+    );
+    if shader_type == WebGl2RenderingContext::VERTEX_SHADER {
+        translated.push_str("#define attribute in\n#define varying out\n");
+    } else {
+        translated.push_str(
+            "#define varying in\nout highp vec4 fragColor;\n#define gl_FragColor fragColor\n",
+        );
+    }
+    for line in body.lines() {
+        if !line.trim_start().starts_with("#extension") {
+            translated.push_str(line);
+            translated.push('\n');
+        }
+    }
+
+    translated
+}
@@ -1,5 +1,7 @@
 use gl_matrix::common::to_radian;
-use gl_matrix::{mat4};
+use gl_matrix::{mat4, vec3};
+
+const FIELD_OF_VIEW: f32 = 60.;
 
 #[derive(Debug)]
 struct CameraView {
@@ -11,6 +13,7 @@ struct CameraView {
 #[derive(Debug)]
 pub(in super::super) struct SimpleCamera {
     view: CameraView,
+    aspect: f32,
     projection: [f32; 16],
 }
 
@@ -21,26 +24,26 @@ impl SimpleCamera {
         up: [f32; 3],
         aspect: f32,
     ) -> SimpleCamera {
-        let mut projection = [0.; 16];
-        mat4::perspective(&mut projection, to_radian(60.), aspect, 0.1, Some(100.));
-
         let view = CameraView {
             position,
             target,
             up,
         };
-        SimpleCamera { view, projection }
+        SimpleCamera {
+            view,
+            aspect,
+            projection: perspective(aspect, 100.),
+        }
     }
 
-    pub(in super::super) fn translate(&mut self, x: f32, y: f32, z: f32) {
-        self.view.position[0] = self.view.position[0] + x;
-        self.view.position[1] = self.view.position[1] + y;
-        self.view.position[2] = self.view.position[2] + z;
-        self.view.target[0] = self.view.target[0] + x;
-        self.view.target[1] = self.view.target[1] + y;
-        self.view.target[2] = self.view.target[2] + z;
+    pub(in super::super) fn aspect(&self) -> f32 {
+        self.aspect
     }
 
+    pub(in super::super) fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+        self.projection = perspective(aspect, 100.);
+    }
 
     pub(in super::super) fn view(&self) -> [f32; 16] {
         let mut view = [0.; 16];
@@ -54,7 +57,45 @@ impl SimpleCamera {
         view
     }
 
+    pub(in super::super) fn position(&self) -> [f32; 3] {
+        self.view.position
+    }
+
     pub(in super::super) fn projection(&self) -> [f32; 16] {
         self.projection
     }
+
+    /// World space corners of the view frustum cut off at `far`.
+    pub(in super::super) fn frustum_corners(&self, far: f32) -> [[f32; 3]; 8] {
+        let projection = perspective(self.aspect, far);
+        let mut view_projection = [0.; 16];
+        mat4::multiply(&mut view_projection, &projection, &self.view());
+        let mut inverse = mat4::create();
+        mat4::invert(&mut inverse, &view_projection);
+
+        let mut corners = [[0.; 3]; 8];
+        for (index, corner) in corners.iter_mut().enumerate() {
+            let ndc = [
+                if index & 1 == 0 { -1. } else { 1. },
+                if index & 2 == 0 { -1. } else { 1. },
+                if index & 4 == 0 { -1. } else { 1. },
+            ];
+            vec3::transform_mat4(corner, &ndc, &inverse);
+        }
+
+        corners
+    }
+}
+
+fn perspective(aspect: f32, far: f32) -> [f32; 16] {
+    let mut projection = [0.; 16];
+    mat4::perspective(
+        &mut projection,
+        to_radian(FIELD_OF_VIEW),
+        aspect,
+        0.1,
+        Some(far),
+    );
+
+    projection
 }
@@ -0,0 +1,67 @@
+use crate::renderer::backend::Backend;
+use js_sys::Promise;
+use std::convert::TryFrom;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+#[wasm_bindgen(module = "/assets/ffi/utility.js")]
+extern "C" {
+    fn encode_png(width: i32, height: i32, pixels: js_sys::Uint8Array) -> Promise;
+}
+
+/// Reads the bound framebuffer as RGBA bytes, top row first.
+pub(crate) fn read_pixels(gl: &dyn Backend, width: i32, height: i32) -> Result<Vec<u8>, JsValue> {
+    let row_length = usize::try_from(width)
+        .ok()
+        .and_then(|width| width.checked_mul(4));
+    let length = row_length
+        .zip(usize::try_from(height).ok())
+        .and_then(|(row_length, height)| row_length.checked_mul(height));
+    let (row_length, length) = row_length
+        .zip(length)
+        .ok_or_else(|| JsValue::from_str(&format!("can not read {}x{} pixels", width, height)))?;
+    let mut pixels = vec![0; length];
+    gl.read_pixels(
+        0,
+        0,
+        width,
+        height,
+        WebGlRenderingContext::RGBA,
+        WebGlRenderingContext::UNSIGNED_BYTE,
+        &mut pixels,
+    )?;
+
+    // GL rows start at the bottom of the image.
+    let mut flipped = Vec::with_capacity(pixels.len());
+    for row in pixels.chunks_exact(row_length).rev() {
+        flipped.extend_from_slice(row);
+    }
+
+    Ok(flipped)
+}
+
+/// Fails unless the GPU can render offscreen at `width` x `height`.
+pub(crate) fn check_size(gl: &dyn Backend, width: i32, height: i32) -> Result<(), JsValue> {
+    let limits = [
+        WebGlRenderingContext::MAX_TEXTURE_SIZE,
+        WebGlRenderingContext::MAX_RENDERBUFFER_SIZE,
+    ];
+    let max_size = limits
+        .iter()
+        .filter_map(|limit| gl.get_parameter_i32(*limit))
+        .min();
+    match max_size {
+        Some(max_size) if width > max_size || height > max_size => {
+            Err(JsValue::from_str(&format!(
+                "capture size {}x{} exceeds the maximum of {} pixels per side",
+                width, height, max_size
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Encodes RGBA bytes, top row first, as a PNG `Blob`.
+pub(crate) fn png(width: i32, height: i32, pixels: &[u8]) -> Promise {
+    encode_png(width, height, js_sys::Uint8Array::from(pixels))
+}
@@ -0,0 +1,153 @@
+use gl_matrix::{mat4, vec3};
+
+pub(super) const AXIS_COLORS: [[f32; 3]; 3] = [[1., 0.2, 0.2], [0.2, 1., 0.2], [0.2, 0.4, 1.]];
+const GRID_COLOR: [f32; 3] = [0.45, 0.45, 0.45];
+const CIRCLE_SEGMENTS: usize = 24;
+
+/// World space line segments of one frame, interleaved as position and color.
+#[derive(Debug, Default)]
+pub(crate) struct LineBatch {
+    pub(super) vertices: Vec<f32>,
+}
+
+impl LineBatch {
+    pub(crate) fn line(&mut self, from: &[f32; 3], to: &[f32; 3], color: &[f32; 3]) {
+        self.vertices.extend_from_slice(from);
+        self.vertices.extend_from_slice(color);
+        self.vertices.extend_from_slice(to);
+        self.vertices.extend_from_slice(color);
+    }
+
+    /// The local X, Y and Z axes of `model_matrix` in red, green and blue.
+    pub(crate) fn axes(&mut self, model_matrix: &[f32; 16], length: f32) {
+        let origin = transform(&[0., 0., 0.], model_matrix);
+        for (axis, color) in AXIS_COLORS.iter().enumerate() {
+            let mut end = [0.; 3];
+            end[axis] = length;
+            self.line(&origin, &transform(&end, model_matrix), color);
+        }
+    }
+
+    /// The twelve edges of a box given by its corners, corner `i` lies at the maximum of
+    /// axis `n` when bit `n` of `i` is set.
+    pub(crate) fn corners(&mut self, corners: &[[f32; 3]; 8], color: &[f32; 3]) {
+        for (index, corner) in corners.iter().enumerate() {
+            for bit in &[1, 2, 4] {
+                if index & bit == 0 {
+                    self.line(corner, &corners[index | bit], color);
+                }
+            }
+        }
+    }
+
+    /// Axis aligned box from `min` to `max` in the space of `model_matrix`.
+    pub(crate) fn bounding_box(
+        &mut self,
+        min: &[f32; 3],
+        max: &[f32; 3],
+        model_matrix: &[f32; 16],
+        color: &[f32; 3],
+    ) {
+        let mut corners = [[0.; 3]; 8];
+        for (index, corner) in corners.iter_mut().enumerate() {
+            let local = [
+                if index & 1 == 0 { min[0] } else { max[0] },
+                if index & 2 == 0 { min[1] } else { max[1] },
+                if index & 4 == 0 { min[2] } else { max[2] },
+            ];
+            *corner = transform(&local, model_matrix);
+        }
+
+        self.corners(&corners, color);
+    }
+
+    /// The volume clip space `matrix` projects into the unit cube.
+    pub(crate) fn frustum(&mut self, matrix: &[f32; 16], color: &[f32; 3]) {
+        let mut inverse = mat4::create();
+        if mat4::invert(&mut inverse, matrix).is_none() {
+            return;
+        }
+
+        let mut corners = [[0.; 3]; 8];
+        for (index, corner) in corners.iter_mut().enumerate() {
+            let ndc = [
+                if index & 1 == 0 { -1. } else { 1. },
+                if index & 2 == 0 { -1. } else { 1. },
+                if index & 4 == 0 { -1. } else { 1. },
+            ];
+            vec3::transform_mat4(corner, &ndc, &inverse);
+        }
+
+        self.corners(&corners, color);
+    }
+
+    /// Lines on the y = 0 plane, `extent` units around the origin.
+    pub(crate) fn grid(&mut self, extent: i32) {
+        let far = extent as f32;
+        for step in -extent..=extent {
+            let offset = step as f32;
+            let (x_color, z_color) = match step {
+                0 => (&AXIS_COLORS[2], &AXIS_COLORS[0]),
+                _ => (&GRID_COLOR, &GRID_COLOR),
+            };
+            self.line(&[offset, 0., -far], &[offset, 0., far], x_color);
+            self.line(&[-far, 0., offset], &[far, 0., offset], z_color);
+        }
+    }
+
+    /// Cone of a spot light shining along the local -Z axis of `model_matrix`.
+    pub(crate) fn cone(
+        &mut self,
+        model_matrix: &[f32; 16],
+        angle: f32,
+        length: f32,
+        color: &[f32; 3],
+    ) {
+        let apex = transform(&[0., 0., 0.], model_matrix);
+        let radius = length * angle.tan();
+        let rim = circle(radius, -length, model_matrix);
+        for (index, point) in rim.iter().enumerate() {
+            if index % (CIRCLE_SEGMENTS / 4) == 0 {
+                self.line(&apex, point, color);
+            }
+            self.line(point, &rim[(index + 1) % CIRCLE_SEGMENTS], color);
+        }
+    }
+
+    /// Three circles around the origin of `model_matrix`, one per axis plane.
+    pub(crate) fn sphere(&mut self, model_matrix: &[f32; 16], radius: f32, color: &[f32; 3]) {
+        let mut rotated = mat4::create();
+        for axis in &[[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]] {
+            mat4::rotate(
+                &mut rotated,
+                model_matrix,
+                std::f32::consts::FRAC_PI_2,
+                axis,
+            );
+            let points = circle(radius, 0., &rotated);
+            for (index, point) in points.iter().enumerate() {
+                self.line(point, &points[(index + 1) % CIRCLE_SEGMENTS], color);
+            }
+        }
+    }
+}
+
+/// Points of a circle in the local XY plane at depth `z`.
+fn circle(radius: f32, z: f32, model_matrix: &[f32; 16]) -> Vec<[f32; 3]> {
+    (0..CIRCLE_SEGMENTS)
+        .map(|index| {
+            let angle = index as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::PI * 2.;
+            transform(
+                &[angle.cos() * radius, angle.sin() * radius, z],
+                model_matrix,
+            )
+        })
+        .collect()
+}
+
+fn transform(point: &[f32; 3], matrix: &[f32; 16]) -> [f32; 3] {
+    let mut result = [0.; 3];
+    vec3::transform_mat4(&mut result, point, matrix);
+
+    result
+}
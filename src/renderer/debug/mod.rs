@@ -0,0 +1,335 @@
+use crate::renderer::backend::{Backend, Buffer, Program, UniformLocation};
+use crate::renderer::mesh::shader::program;
+use crate::renderer::stats::FrameCounters;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+pub(crate) use lines::LineBatch;
+
+mod lines;
+
+pub(crate) const WIREFRAME_COLOR: [f32; 3] = [0.1, 1., 0.4];
+pub(crate) const BOUNDING_BOX_COLOR: [f32; 3] = [1., 0.8, 0.1];
+pub(crate) const LIGHT_COLOR: [f32; 3] = [1., 1., 0.5];
+pub(crate) const CAMERA_COLOR: [f32; 3] = [0.6, 0.8, 1.];
+pub(crate) const GRID_EXTENT: i32 = 10;
+pub(crate) const AXIS_LENGTH: f32 = 0.5;
+/// Length of directional light arrows and spot cones of lights without a range, radius
+/// of point lights without one.
+pub(crate) const LIGHT_OUTLINE_LENGTH: f32 = 2.;
+/// Lines are pulled towards the camera by this much clip space depth, so the wireframe
+/// does not fight with the surface it outlines.
+const LINE_DEPTH_BIAS: f32 = 0.0005;
+
+const SURFACE_V_SHADER: &str = "#version 100
+
+attribute vec3 position;
+attribute vec3 normal;
+attribute vec2 textureCoords;
+
+varying vec3 fNormal;
+varying vec2 fTextureCoords;
+
+uniform mat4 modelMatrix;
+uniform mat4 viewProjectionMatrix;
+uniform mat3 normalMatrix;
+
+void main() {
+    gl_Position = viewProjectionMatrix * modelMatrix * vec4(position, 1.0);
+    fNormal = normalMatrix * normal;
+    fTextureCoords = textureCoords;
+}";
+
+const SURFACE_F_SHADER: &str = "#version 100
+
+precision mediump float;
+
+varying vec3 fNormal;
+varying vec2 fTextureCoords;
+
+// 1: world space normals, 2: texture coordinates
+uniform int view;
+
+void main() {
+    if (view == 1) {
+        gl_FragColor = vec4(normalize(fNormal) * 0.5 + 0.5, 1.0);
+    } else {
+        gl_FragColor = vec4(fract(fTextureCoords), 0.0, 1.0);
+    }
+}";
+
+const LINE_V_SHADER: &str = "#version 100
+
+attribute vec3 position;
+attribute vec3 color;
+
+varying vec3 fColor;
+
+uniform mat4 modelMatrix;
+uniform mat4 viewProjectionMatrix;
+uniform float depthBias;
+
+void main() {
+    gl_Position = viewProjectionMatrix * modelMatrix * vec4(position, 1.0);
+    gl_Position.z -= depthBias * gl_Position.w;
+    fColor = color;
+}";
+
+const LINE_F_SHADER: &str = "#version 100
+
+precision mediump float;
+
+varying vec3 fColor;
+
+void main() {
+    gl_FragColor = vec4(fColor, 1.0);
+}";
+
+const IDENTITY: [f32; 16] = [
+    1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1.,
+];
+
+/// What the mesh surfaces show.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    /// Regular materials and lighting.
+    Shaded,
+    /// World space normals mapped to colors.
+    Normals,
+    /// Texture coordinates as red and green.
+    TextureCoords,
+}
+
+/// Lines drawn on top of the scene.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugOverlay {
+    /// Triangle edges of every mesh.
+    Wireframe,
+    /// Local axes of every node.
+    Axes,
+    /// Bounds of every mesh from the `min` and `max` of its position accessors.
+    BoundingBoxes,
+    /// Lines on the y = 0 plane.
+    Grid,
+    /// Light cones and ranges, the shadow volume and the camera frustum up to the shadow
+    /// distance.
+    Frustums,
+}
+
+/// Attribute locations of the debug surface program, meshes bind their buffers to them.
+#[derive(Debug)]
+pub(crate) struct DebugAttributes {
+    pub(crate) position: i32,
+    pub(crate) normal: i32,
+    pub(crate) texture_coord: i32,
+}
+
+#[derive(Debug)]
+pub(crate) struct DebugRenderer {
+    pub(crate) view: DebugView,
+    overlays: Vec<DebugOverlay>,
+    surface_program: Program,
+    surface_attributes: DebugAttributes,
+    surface_model_matrix: Option<UniformLocation>,
+    surface_view_projection_matrix: Option<UniformLocation>,
+    surface_normal_matrix: Option<UniformLocation>,
+    surface_view: Option<UniformLocation>,
+    line_program: Program,
+    line_position: i32,
+    line_color: i32,
+    line_model_matrix: Option<UniformLocation>,
+    line_view_projection_matrix: Option<UniformLocation>,
+    line_depth_bias: Option<UniformLocation>,
+    line_buffer: Buffer,
+}
+
+impl DebugRenderer {
+    pub(crate) fn new(gl: &dyn Backend) -> Result<DebugRenderer, JsValue> {
+        let surface_program = program::compile_to_program(gl, SURFACE_V_SHADER, SURFACE_F_SHADER)?;
+        let line_program = program::compile_to_program(gl, LINE_V_SHADER, LINE_F_SHADER)?;
+        let line_buffer = gl
+            .create_buffer()
+            .ok_or_else(|| JsValue::from_str("could not create debug line buffer"))?;
+
+        Ok(DebugRenderer {
+            view: DebugView::Shaded,
+            overlays: vec![],
+            surface_attributes: DebugAttributes {
+                position: gl.get_attrib_location(&surface_program, "position"),
+                normal: gl.get_attrib_location(&surface_program, "normal"),
+                texture_coord: gl.get_attrib_location(&surface_program, "textureCoords"),
+            },
+            surface_model_matrix: gl.get_uniform_location(&surface_program, "modelMatrix"),
+            surface_view_projection_matrix: gl
+                .get_uniform_location(&surface_program, "viewProjectionMatrix"),
+            surface_normal_matrix: gl.get_uniform_location(&surface_program, "normalMatrix"),
+            surface_view: gl.get_uniform_location(&surface_program, "view"),
+            surface_program,
+            line_position: gl.get_attrib_location(&line_program, "position"),
+            line_color: gl.get_attrib_location(&line_program, "color"),
+            line_model_matrix: gl.get_uniform_location(&line_program, "modelMatrix"),
+            line_view_projection_matrix: gl
+                .get_uniform_location(&line_program, "viewProjectionMatrix"),
+            line_depth_bias: gl.get_uniform_location(&line_program, "depthBias"),
+            line_program,
+            line_buffer,
+        })
+    }
+
+    /// The same view and overlays with new GL objects, for a restored context.
+    pub(crate) fn recreate(&self, gl: &dyn Backend) -> Result<DebugRenderer, JsValue> {
+        Ok(DebugRenderer {
+            view: self.view,
+            overlays: self.overlays.clone(),
+            ..DebugRenderer::new(gl)?
+        })
+    }
+
+    pub(crate) fn set_overlay(&mut self, overlay: DebugOverlay, enabled: bool) {
+        self.overlays.retain(|existing| *existing != overlay);
+        if enabled {
+            self.overlays.push(overlay);
+        }
+    }
+
+    pub(crate) fn has_overlay(&self, overlay: DebugOverlay) -> bool {
+        self.overlays.contains(&overlay)
+    }
+
+    /// Nothing left to draw, the renderer can be dropped.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.view == DebugView::Shaded && self.overlays.is_empty()
+    }
+
+    /// Prepares drawing mesh surfaces in the current `view`.
+    pub(crate) fn begin_surfaces(
+        &self,
+        gl: &dyn Backend,
+        view_projection: &[f32; 16],
+        counters: &FrameCounters,
+    ) {
+        gl.use_program(Some(&self.surface_program));
+        counters.program();
+        gl.uniform_matrix4fv(
+            self.surface_view_projection_matrix.as_ref(),
+            view_projection,
+        );
+        gl.uniform1i(
+            self.surface_view.as_ref(),
+            match self.view {
+                DebugView::Normals => 1,
+                _ => 2,
+            },
+        );
+    }
+
+    pub(crate) fn set_surface_matrices(
+        &self,
+        gl: &dyn Backend,
+        model_matrix: &[f32; 16],
+        normal_matrix: &[f32; 9],
+    ) {
+        gl.uniform_matrix4fv(self.surface_model_matrix.as_ref(), model_matrix);
+        gl.uniform_matrix3fv(self.surface_normal_matrix.as_ref(), normal_matrix);
+    }
+
+    pub(crate) fn surface_attributes(&self) -> &DebugAttributes {
+        &self.surface_attributes
+    }
+
+    /// Disables the attribute arrays drawing the surfaces enabled.
+    pub(crate) fn end_surfaces(&self, gl: &dyn Backend) {
+        let attributes = &self.surface_attributes;
+        for location in &[
+            attributes.position,
+            attributes.normal,
+            attributes.texture_coord,
+        ] {
+            if *location >= 0 {
+                gl.disable_vertex_attrib_array(*location as u32);
+            }
+        }
+    }
+
+    /// Prepares drawing mesh wireframes in a single color.
+    pub(crate) fn begin_wireframes(
+        &self,
+        gl: &dyn Backend,
+        view_projection: &[f32; 16],
+        counters: &FrameCounters,
+    ) {
+        self.begin_lines(gl, view_projection, counters);
+        gl.disable_vertex_attrib_array(self.line_color as u32);
+        gl.vertex_attrib3fv(self.line_color as u32, &WIREFRAME_COLOR);
+    }
+
+    /// Disables the position array drawing the wireframes enabled.
+    pub(crate) fn end_wireframes(&self, gl: &dyn Backend) {
+        gl.disable_vertex_attrib_array(self.line_position as u32);
+    }
+
+    pub(crate) fn set_wireframe_model_matrix(&self, gl: &dyn Backend, model_matrix: &[f32; 16]) {
+        gl.uniform_matrix4fv(self.line_model_matrix.as_ref(), model_matrix);
+    }
+
+    pub(crate) fn wireframe_position_location(&self) -> u32 {
+        self.line_position as u32
+    }
+
+    /// Draws world space lines.
+    pub(crate) fn draw_lines(
+        &self,
+        gl: &dyn Backend,
+        lines: &LineBatch,
+        view_projection: &[f32; 16],
+        counters: &FrameCounters,
+    ) {
+        if lines.vertices.is_empty() {
+            return;
+        }
+
+        self.begin_lines(gl, view_projection, counters);
+        gl.uniform_matrix4fv(self.line_model_matrix.as_ref(), &IDENTITY);
+
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.line_buffer));
+        gl.buffer_data_f32(
+            WebGlRenderingContext::ARRAY_BUFFER,
+            lines.vertices.as_slice(),
+            WebGlRenderingContext::STREAM_DRAW,
+        );
+        for (location, offset) in &[(self.line_position, 0), (self.line_color, 12)] {
+            gl.enable_vertex_attrib_array(*location as u32);
+            gl.vertex_attrib_pointer(
+                *location as u32,
+                3,
+                WebGlRenderingContext::FLOAT,
+                false,
+                24,
+                *offset,
+            );
+        }
+        gl.draw_arrays(
+            WebGlRenderingContext::LINES,
+            0,
+            (lines.vertices.len() / 6) as i32,
+        );
+        counters.draw(0);
+        gl.disable_vertex_attrib_array(self.line_position as u32);
+        gl.disable_vertex_attrib_array(self.line_color as u32);
+    }
+
+    pub(crate) fn delete(&self, gl: &dyn Backend) {
+        gl.delete_program(&self.surface_program);
+        gl.delete_program(&self.line_program);
+        gl.delete_buffer(&self.line_buffer);
+    }
+
+    fn begin_lines(&self, gl: &dyn Backend, view_projection: &[f32; 16], counters: &FrameCounters) {
+        gl.use_program(Some(&self.line_program));
+        counters.program();
+        gl.uniform_matrix4fv(self.line_view_projection_matrix.as_ref(), view_projection);
+        gl.uniform1f(self.line_depth_bias.as_ref(), LINE_DEPTH_BIAS);
+    }
+}
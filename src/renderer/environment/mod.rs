@@ -0,0 +1,416 @@
+use crate::loader::fetch::fetch_array_buffer;
+use crate::renderer::backend::{self, Backend, Buffer, Program, Texture, UniformLocation};
+use crate::renderer::environment::prefilter::{
+    create_texture, Prefilter, PrefilterSource, RGBM_GLSL,
+};
+use crate::renderer::mesh::shader::program;
+use crate::renderer::state::{self, StateTracker};
+use crate::renderer::stats::FrameCounters;
+use gl_matrix::mat4;
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{HtmlCanvasElement, HtmlImageElement, WebGlRenderingContext};
+
+mod prefilter;
+
+/// Texture units of the image based lighting maps, after base color (0) and shadow (1).
+pub(crate) const IRRADIANCE_TEXTURE_UNIT: u32 = 2;
+pub(crate) const SPECULAR_TEXTURE_UNIT: u32 = 3;
+pub(crate) const BRDF_LUT_TEXTURE_UNIT: u32 = 4;
+const ENVIRONMENT_SIZE: i32 = 512;
+const IRRADIANCE_SIZE: i32 = 32;
+const SPECULAR_SIZE: i32 = 128;
+/// Mip level of the specular cubemap prefiltered for roughness 1.
+const SPECULAR_ROUGHEST_LEVEL: u32 = 5;
+const BRDF_LUT_SIZE: i32 = 128;
+
+#[wasm_bindgen(module = "/assets/ffi/utility.js")]
+extern "C" {
+    fn load_image_url(url: &str) -> Promise;
+}
+
+const SKYBOX_V_SHADER: &str = "#version 100
+
+attribute vec2 position;
+
+varying vec3 fDirection;
+
+uniform mat4 inverseViewProjection;
+
+void main() {
+    vec4 direction = inverseViewProjection * vec4(position, 1.0, 1.0);
+    fDirection = direction.xyz / direction.w;
+    gl_Position = vec4(position, 1.0, 1.0);
+}";
+
+const SKYBOX_F_SHADER: &str = "
+varying vec3 fDirection;
+
+uniform samplerCube environment;
+uniform float intensity;
+
+void main() {
+    // Written linear like the mesh shaders, gamma is left to the output stage.
+    vec3 color = decodeRgbm(textureCube(environment, normalize(fDirection))) * intensity;
+    gl_FragColor = vec4(color, 1.0);
+}";
+
+/// How the mesh shaders pick a roughness level of the specular cubemap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum EnvironmentSampling {
+    /// Explicit level through `EXT_shader_texture_lod`.
+    TextureLod,
+    /// Level bias of `textureCube`, clamped to the levels of the map. Only close to the
+    /// explicit level where the base level is magnified, strongly curved surfaces come
+    /// out blurrier.
+    LodBias,
+}
+
+/// Images an environment was built from, kept to build it again.
+#[derive(Debug, Clone)]
+enum EnvironmentAsset {
+    Cubemap(Vec<String>),
+    Equirectangular(String),
+}
+
+#[derive(Debug)]
+struct SkyboxLocations {
+    position: i32,
+    inverse_view_projection: Option<UniformLocation>,
+    environment: Option<UniformLocation>,
+    intensity: Option<UniformLocation>,
+}
+
+/// Skybox and image based lighting built from one environment image. The source is
+/// captured into an RGBM cubemap and prefiltered into diffuse irradiance, GGX
+/// specular mip levels and the split sum BRDF lookup table.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct Environment {
+    pub(crate) sampling: EnvironmentSampling,
+    asset: EnvironmentAsset,
+    id: u32,
+    cubemap: Texture,
+    irradiance: Texture,
+    specular: Texture,
+    brdf_lut: Texture,
+    quad: Buffer,
+    skybox_program: Program,
+    skybox_locations: SkyboxLocations,
+    pub(crate) intensity: f32,
+    pub(crate) skybox_visible: bool,
+}
+
+#[wasm_bindgen]
+impl Environment {
+    /// Builds the environment from six cube face images ordered +X, -X, +Y, -Y, +Z, -Z,
+    /// for the scene rendering to `canvas`.
+    pub async fn from_cubemap_urls(
+        canvas: HtmlCanvasElement,
+        urls: js_sys::Array,
+    ) -> Result<Environment, JsValue> {
+        let backend = backend::for_canvas(&canvas)?;
+        if urls.length() != 6 {
+            return Err(JsValue::from_str("a cubemap needs exactly six face urls"));
+        }
+        let urls = urls
+            .iter()
+            .map(|url| {
+                url.as_string()
+                    .ok_or_else(|| JsValue::from_str("cubemap face urls must be strings"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Environment::load(&*backend, EnvironmentAsset::Cubemap(urls)).await
+    }
+
+    /// Builds the environment from an equirectangular panorama, either a Radiance
+    /// `.hdr` file or any image the browser decodes.
+    pub async fn from_equirectangular_url(
+        canvas: HtmlCanvasElement,
+        url: String,
+    ) -> Result<Environment, JsValue> {
+        let backend = backend::for_canvas(&canvas)?;
+
+        Environment::load(&*backend, EnvironmentAsset::Equirectangular(url)).await
+    }
+}
+
+impl Environment {
+    /// Loads the environment again for a restored context, keeping its settings.
+    pub(crate) async fn reload(&self, gl: &dyn Backend) -> Result<Environment, JsValue> {
+        let mut environment = Environment::load(gl, self.asset.clone()).await?;
+        environment.intensity = self.intensity;
+        environment.skybox_visible = self.skybox_visible;
+
+        Ok(environment)
+    }
+
+    async fn load(gl: &dyn Backend, asset: EnvironmentAsset) -> Result<Environment, JsValue> {
+        match &asset {
+            EnvironmentAsset::Cubemap(urls) => {
+                let mut faces = vec![];
+                for url in urls {
+                    faces.push(
+                        JsFuture::from(load_image_url(url))
+                            .await?
+                            .dyn_into::<HtmlImageElement>()?,
+                    );
+                }
+
+                let source = create_texture(gl, WebGlRenderingContext::TEXTURE_CUBE_MAP)?;
+                for (face, image) in faces.iter().enumerate() {
+                    gl.tex_image_2d_image(
+                        WebGlRenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                        0,
+                        WebGlRenderingContext::RGBA as i32,
+                        WebGlRenderingContext::RGBA,
+                        WebGlRenderingContext::UNSIGNED_BYTE,
+                        image,
+                    )?;
+                }
+
+                let environment = Environment::prefilter(gl, PrefilterSource::Cube(&source), asset);
+                gl.delete_texture(&source);
+
+                environment
+            }
+            EnvironmentAsset::Equirectangular(url) => {
+                let source = create_texture(gl, WebGlRenderingContext::TEXTURE_2D)?;
+
+                let rgbe = url.to_lowercase().ends_with(".hdr");
+                if rgbe {
+                    let bin = fetch_array_buffer(url).await?;
+                    let image = crate::loader::hdr::parse(&js_sys::Uint8Array::new(&bin).to_vec())?;
+
+                    gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&source));
+                    // RGBE texels must not be interpolated before they are decoded.
+                    for filter in &[
+                        WebGlRenderingContext::TEXTURE_MIN_FILTER,
+                        WebGlRenderingContext::TEXTURE_MAG_FILTER,
+                    ] {
+                        gl.tex_parameteri(
+                            WebGlRenderingContext::TEXTURE_2D,
+                            *filter,
+                            WebGlRenderingContext::NEAREST as i32,
+                        );
+                    }
+                    gl.tex_image_2d(
+                        WebGlRenderingContext::TEXTURE_2D,
+                        0,
+                        WebGlRenderingContext::RGBA as i32,
+                        image.width as i32,
+                        image.height as i32,
+                        WebGlRenderingContext::RGBA,
+                        WebGlRenderingContext::UNSIGNED_BYTE,
+                        Some(&image.data),
+                    )?;
+                } else {
+                    let image = JsFuture::from(load_image_url(url))
+                        .await?
+                        .dyn_into::<HtmlImageElement>()?;
+
+                    gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&source));
+                    gl.tex_image_2d_image(
+                        WebGlRenderingContext::TEXTURE_2D,
+                        0,
+                        WebGlRenderingContext::RGBA as i32,
+                        WebGlRenderingContext::RGBA,
+                        WebGlRenderingContext::UNSIGNED_BYTE,
+                        &image,
+                    )?;
+                }
+
+                let environment = Environment::prefilter(
+                    gl,
+                    PrefilterSource::Equirectangular {
+                        texture: &source,
+                        rgbe,
+                    },
+                    asset.clone(),
+                );
+                gl.delete_texture(&source);
+
+                environment
+            }
+        }
+    }
+
+    fn prefilter(
+        gl: &dyn Backend,
+        source: PrefilterSource<'_>,
+        asset: EnvironmentAsset,
+    ) -> Result<Environment, JsValue> {
+        let sampling = if gl.has_extension("EXT_shader_texture_lod") {
+            EnvironmentSampling::TextureLod
+        } else {
+            EnvironmentSampling::LodBias
+        };
+
+        // One triangle covering the whole viewport.
+        let quad = gl
+            .create_buffer()
+            .ok_or_else(|| JsValue::from_str("could not create environment buffer"))?;
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&quad));
+        gl.buffer_data_f32(
+            WebGlRenderingContext::ARRAY_BUFFER,
+            &[-1f32, -1., 3., -1., -1., 3.][..],
+            WebGlRenderingContext::STATIC_DRAW,
+        );
+
+        let depth_test = gl.is_enabled(WebGlRenderingContext::DEPTH_TEST);
+        let blend = gl.is_enabled(WebGlRenderingContext::BLEND);
+
+        let prefilter = Prefilter::new(gl, &quad, ENVIRONMENT_SIZE)?;
+        let cubemap = prefilter.capture(source, ENVIRONMENT_SIZE)?;
+        let irradiance = prefilter.irradiance(&cubemap, IRRADIANCE_SIZE)?;
+        let specular = prefilter.specular(&cubemap, SPECULAR_SIZE, SPECULAR_ROUGHEST_LEVEL)?;
+        let brdf_lut = prefilter.brdf_lut(BRDF_LUT_SIZE)?;
+        prefilter.finish();
+
+        if depth_test {
+            gl.enable(WebGlRenderingContext::DEPTH_TEST);
+        }
+        if blend {
+            gl.enable(WebGlRenderingContext::BLEND);
+        }
+
+        let skybox_program = program::compile_to_program(
+            gl,
+            SKYBOX_V_SHADER,
+            &format!(
+                "#version 100\n\nprecision mediump float;\n{}{}",
+                RGBM_GLSL, SKYBOX_F_SHADER
+            ),
+        )?;
+        let skybox_locations = SkyboxLocations {
+            position: gl.get_attrib_location(&skybox_program, "position"),
+            inverse_view_projection: gl
+                .get_uniform_location(&skybox_program, "inverseViewProjection"),
+            environment: gl.get_uniform_location(&skybox_program, "environment"),
+            intensity: gl.get_uniform_location(&skybox_program, "intensity"),
+        };
+
+        Ok(Environment {
+            sampling,
+            asset,
+            id: state::next_id(),
+            cubemap,
+            irradiance,
+            specular,
+            brdf_lut,
+            quad,
+            skybox_program,
+            skybox_locations,
+            intensity: 1.,
+            skybox_visible: true,
+        })
+    }
+
+    /// Draws the skybox behind everything, without touching the depth buffer.
+    pub(crate) fn render_skybox(
+        &self,
+        gl: &dyn Backend,
+        view_matrix: &[f32; 16],
+        projection_matrix: &[f32; 16],
+        counters: &FrameCounters,
+    ) {
+        if !self.skybox_visible {
+            return;
+        }
+
+        // Only the camera rotation matters for directions at infinity.
+        let mut rotation = *view_matrix;
+        rotation[12] = 0.;
+        rotation[13] = 0.;
+        rotation[14] = 0.;
+        let mut view_projection = mat4::create();
+        mat4::multiply(&mut view_projection, projection_matrix, &rotation);
+        let mut inverse_view_projection = mat4::create();
+        mat4::invert(&mut inverse_view_projection, &view_projection);
+
+        let depth_test = gl.is_enabled(WebGlRenderingContext::DEPTH_TEST);
+        gl.disable(WebGlRenderingContext::DEPTH_TEST);
+        gl.depth_mask(false);
+
+        let locations = &self.skybox_locations;
+        gl.use_program(Some(&self.skybox_program));
+        gl.uniform_matrix4fv(
+            locations.inverse_view_projection.as_ref(),
+            &inverse_view_projection,
+        );
+        gl.uniform1f(locations.intensity.as_ref(), self.intensity);
+        gl.active_texture(WebGlRenderingContext::TEXTURE0);
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_CUBE_MAP, Some(&self.cubemap));
+        gl.uniform1i(locations.environment.as_ref(), 0);
+        counters.program();
+        counters.textures(1);
+
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.quad));
+        gl.enable_vertex_attrib_array(locations.position as u32);
+        gl.vertex_attrib_pointer(
+            locations.position as u32,
+            2,
+            WebGlRenderingContext::FLOAT,
+            false,
+            0,
+            0,
+        );
+        gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, 3);
+        counters.draw(1);
+        gl.disable_vertex_attrib_array(locations.position as u32);
+
+        gl.depth_mask(true);
+        if depth_test {
+            gl.enable(WebGlRenderingContext::DEPTH_TEST);
+        }
+    }
+
+    /// Binds the lighting maps to their texture units.
+    pub(crate) fn bind(
+        &self,
+        gl: &dyn Backend,
+        state: &mut StateTracker,
+        counters: &FrameCounters,
+    ) {
+        for (unit, target, texture) in &[
+            (
+                IRRADIANCE_TEXTURE_UNIT,
+                WebGlRenderingContext::TEXTURE_CUBE_MAP,
+                &self.irradiance,
+            ),
+            (
+                SPECULAR_TEXTURE_UNIT,
+                WebGlRenderingContext::TEXTURE_CUBE_MAP,
+                &self.specular,
+            ),
+            (
+                BRDF_LUT_TEXTURE_UNIT,
+                WebGlRenderingContext::TEXTURE_2D,
+                &self.brdf_lut,
+            ),
+        ] {
+            state.bind_texture(gl, *unit, *target, self.id, texture, counters);
+        }
+    }
+
+    /// x: intensity, y: mip level of the roughest prefiltered radiance
+    pub(crate) fn params(&self) -> [f32; 2] {
+        [self.intensity, SPECULAR_ROUGHEST_LEVEL as f32]
+    }
+
+    pub(crate) fn delete(&self, gl: &dyn Backend) {
+        for texture in &[
+            &self.cubemap,
+            &self.irradiance,
+            &self.specular,
+            &self.brdf_lut,
+        ] {
+            gl.delete_texture(texture);
+        }
+        gl.delete_buffer(&self.quad);
+        gl.delete_program(&self.skybox_program);
+    }
+}
@@ -0,0 +1,483 @@
+use crate::renderer::backend::{Backend, Buffer, Framebuffer, Program, Texture};
+use crate::renderer::mesh::shader::program;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+pub(super) const RGBM_GLSL: &str = "
+#define RGBM_RANGE 8.0
+
+vec3 decodeRgbm(vec4 rgbm) {
+    return rgbm.rgb * rgbm.a * RGBM_RANGE;
+}
+
+vec4 encodeRgbm(vec3 color) {
+    color /= RGBM_RANGE;
+    float m = clamp(max(max(color.r, color.g), max(color.b, 0.000001)), 0.0, 1.0);
+    m = ceil(m * 255.0) / 255.0;
+    return vec4(color / m, m);
+}
+";
+
+const QUAD_V_SHADER: &str = "#version 100
+
+attribute vec2 position;
+
+varying vec2 fUv;
+
+void main() {
+    fUv = position;
+    gl_Position = vec4(position, 0.0, 1.0);
+}";
+
+const FACE_GLSL: &str = "
+uniform vec3 faceForward;
+uniform vec3 faceRight;
+uniform vec3 faceUp;
+
+varying vec2 fUv;
+
+vec3 faceDirection() {
+    return normalize(faceForward + fUv.x * faceRight + fUv.y * faceUp);
+}
+";
+
+/// Converts the source image into an RGBM cubemap.
+const CAPTURE_F_SHADER: &str = "
+#ifdef SOURCE_EQUIRECTANGULAR
+uniform sampler2D source;
+#else
+uniform samplerCube source;
+#endif
+
+vec3 sampleSource(vec3 direction) {
+#ifdef SOURCE_EQUIRECTANGULAR
+    vec2 uv = vec2(
+        atan(direction.z, direction.x) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI
+    );
+    vec4 texel = texture2D(source, uv);
+#else
+    vec4 texel = textureCube(source, direction);
+#endif
+#ifdef SOURCE_RGBE
+    return texel.rgb * 255.0 * exp2(texel.a * 255.0 - 136.0);
+#else
+    return pow(texel.rgb, vec3(2.2));
+#endif
+}
+
+void main() {
+    gl_FragColor = encodeRgbm(sampleSource(faceDirection()));
+}";
+
+/// Cosine weighted convolution of the environment for diffuse lighting.
+const IRRADIANCE_F_SHADER: &str = "
+#define PHI_STEPS 32
+#define THETA_STEPS 12
+
+uniform samplerCube environment;
+
+void main() {
+    vec3 n = faceDirection();
+    vec3 up = abs(n.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, n));
+    up = cross(n, right);
+
+    vec3 irradiance = vec3(0.0);
+    for (int i = 0; i < PHI_STEPS; i++) {
+        float phi = float(i) * 2.0 * PI / float(PHI_STEPS);
+        for (int j = 0; j < THETA_STEPS; j++) {
+            float theta = (float(j) + 0.5) * 0.5 * PI / float(THETA_STEPS);
+            vec3 tangent = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 direction = tangent.x * right + tangent.y * up + tangent.z * n;
+            irradiance += decodeRgbm(textureCube(environment, direction)) * cos(theta) * sin(theta);
+        }
+    }
+    irradiance *= PI / float(PHI_STEPS * THETA_STEPS);
+
+    gl_FragColor = encodeRgbm(irradiance);
+}";
+
+const IMPORTANCE_SAMPLE_GLSL: &str = "
+// Van der Corput sequence without bit operations, which GLSL ES 1.0 lacks.
+float radicalInverse(float n) {
+    float result = 0.0;
+    float factor = 0.5;
+    for (int bit = 0; bit < 10; bit++) {
+        result += factor * mod(n, 2.0);
+        n = floor(n / 2.0);
+        factor *= 0.5;
+    }
+    return result;
+}
+
+vec3 importanceSampleGgx(vec2 xi, vec3 n, float roughness) {
+    float alpha = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cosTheta = sqrt((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y));
+    float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+
+    vec3 up = abs(n.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, n));
+    vec3 bitangent = cross(n, tangent);
+
+    return normalize(tangent * cos(phi) * sinTheta + bitangent * sin(phi) * sinTheta + n * cosTheta);
+}
+";
+
+/// GGX prefiltered radiance for one roughness, written into one mip level.
+const SPECULAR_F_SHADER: &str = "
+#define SAMPLE_COUNT 64
+
+uniform samplerCube environment;
+uniform float roughness;
+
+void main() {
+    vec3 n = faceDirection();
+
+    vec3 color = vec3(0.0);
+    float weight = 0.0;
+    for (int i = 0; i < SAMPLE_COUNT; i++) {
+        vec2 xi = vec2(float(i) / float(SAMPLE_COUNT), radicalInverse(float(i)));
+        vec3 h = importanceSampleGgx(xi, n, roughness);
+        vec3 l = normalize(2.0 * dot(n, h) * h - n);
+        float nDotL = dot(n, l);
+        if (nDotL > 0.0) {
+            color += decodeRgbm(textureCube(environment, l)) * nDotL;
+            weight += nDotL;
+        }
+    }
+
+    gl_FragColor = encodeRgbm(color / max(weight, 0.0001));
+}";
+
+/// Split sum scale (r) and bias (g) of the specular BRDF, by n·v (x) and roughness (y).
+const BRDF_LUT_F_SHADER: &str = "
+#define SAMPLE_COUNT 128
+
+varying vec2 fUv;
+
+void main() {
+    vec2 uv = fUv * 0.5 + 0.5;
+    float nDotV = max(uv.x, 0.001);
+    float roughness = uv.y;
+    vec3 v = vec3(sqrt(1.0 - nDotV * nDotV), 0.0, nDotV);
+    vec3 n = vec3(0.0, 0.0, 1.0);
+
+    float scale = 0.0;
+    float bias = 0.0;
+    for (int i = 0; i < SAMPLE_COUNT; i++) {
+        vec2 xi = vec2(float(i) / float(SAMPLE_COUNT), radicalInverse(float(i)));
+        vec3 h = importanceSampleGgx(xi, n, roughness);
+        vec3 l = normalize(2.0 * dot(v, h) * h - v);
+        float nDotL = max(l.z, 0.0);
+        float nDotH = max(h.z, 0.0);
+        float vDotH = max(dot(v, h), 0.0);
+
+        if (nDotL > 0.0) {
+            float k = roughness * roughness / 2.0;
+            float geometry = nDotV / (nDotV * (1.0 - k) + k) * nDotL / (nDotL * (1.0 - k) + k);
+            float visibility = geometry * vDotH / max(nDotH * nDotV, 0.0001);
+            float fresnel = pow(1.0 - vDotH, 5.0);
+            scale += (1.0 - fresnel) * visibility;
+            bias += fresnel * visibility;
+        }
+    }
+
+    gl_FragColor = vec4(scale, bias, 0.0, float(SAMPLE_COUNT)) / float(SAMPLE_COUNT);
+}";
+
+/// Forward, right and up vector of every cube face, matching the texel orientation of
+/// `TEXTURE_CUBE_MAP_POSITIVE_X + face` when copied out of the framebuffer.
+const FACES: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+    ([1., 0., 0.], [0., 0., -1.], [0., -1., 0.]),
+    ([-1., 0., 0.], [0., 0., 1.], [0., -1., 0.]),
+    ([0., 1., 0.], [1., 0., 0.], [0., 0., 1.]),
+    ([0., -1., 0.], [1., 0., 0.], [0., 0., -1.]),
+    ([0., 0., 1.], [1., 0., 0.], [0., -1., 0.]),
+    ([0., 0., -1.], [-1., 0., 0.], [0., -1., 0.]),
+];
+
+/// Where a prefilter pass reads its input from.
+pub(super) enum PrefilterSource<'a> {
+    Cube(&'a Texture),
+    Equirectangular { texture: &'a Texture, rgbe: bool },
+}
+
+/// GPU passes that turn an environment image into the textures of image based lighting.
+/// Faces are rendered into a scratch framebuffer and copied into the cubemaps, which also
+/// works for mip levels where WebGL1 cannot attach them to a framebuffer.
+pub(super) struct Prefilter<'a> {
+    gl: &'a dyn Backend,
+    quad: &'a Buffer,
+    framebuffer: Framebuffer,
+    target: Texture,
+    size: i32,
+}
+
+impl<'a> Prefilter<'a> {
+    pub(super) fn new(
+        gl: &'a dyn Backend,
+        quad: &'a Buffer,
+        size: i32,
+    ) -> Result<Prefilter<'a>, JsValue> {
+        let target = create_texture(gl, WebGlRenderingContext::TEXTURE_2D)?;
+        gl.tex_image_2d(
+            WebGlRenderingContext::TEXTURE_2D,
+            0,
+            WebGlRenderingContext::RGBA as i32,
+            size,
+            size,
+            WebGlRenderingContext::RGBA,
+            WebGlRenderingContext::UNSIGNED_BYTE,
+            None,
+        )?;
+
+        let framebuffer = gl
+            .create_framebuffer()
+            .ok_or_else(|| JsValue::from_str("could not create prefilter framebuffer"))?;
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&framebuffer));
+        gl.framebuffer_texture_2d(
+            WebGlRenderingContext::FRAMEBUFFER,
+            WebGlRenderingContext::COLOR_ATTACHMENT0,
+            WebGlRenderingContext::TEXTURE_2D,
+            Some(&target),
+            0,
+        );
+        gl.disable(WebGlRenderingContext::DEPTH_TEST);
+        gl.disable(WebGlRenderingContext::BLEND);
+
+        Ok(Prefilter {
+            gl,
+            quad,
+            framebuffer,
+            target,
+            size,
+        })
+    }
+
+    /// Converts the source into an RGBM cubemap of `size`.
+    pub(super) fn capture(
+        &self,
+        source: PrefilterSource<'_>,
+        size: i32,
+    ) -> Result<Texture, JsValue> {
+        let (defines, texture, target): (&[&str], _, _) = match source {
+            PrefilterSource::Cube(texture) => {
+                (&[], texture, WebGlRenderingContext::TEXTURE_CUBE_MAP)
+            }
+            PrefilterSource::Equirectangular {
+                texture,
+                rgbe: true,
+            } => (
+                &["SOURCE_EQUIRECTANGULAR", "SOURCE_RGBE"],
+                texture,
+                WebGlRenderingContext::TEXTURE_2D,
+            ),
+            PrefilterSource::Equirectangular {
+                texture,
+                rgbe: false,
+            } => (
+                &["SOURCE_EQUIRECTANGULAR"],
+                texture,
+                WebGlRenderingContext::TEXTURE_2D,
+            ),
+        };
+        let program = self.compile(&[FACE_GLSL, CAPTURE_F_SHADER], defines)?;
+
+        let cubemap = self.create_cubemap(WebGlRenderingContext::LINEAR)?;
+        self.bind_input(&program, "source", target, texture);
+        self.render_faces(&program, &cubemap, size, 0);
+        self.gl.delete_program(&program);
+
+        Ok(cubemap)
+    }
+
+    pub(super) fn irradiance(&self, environment: &Texture, size: i32) -> Result<Texture, JsValue> {
+        let program = self.compile(&[FACE_GLSL, IRRADIANCE_F_SHADER], &[])?;
+
+        let cubemap = self.create_cubemap(WebGlRenderingContext::LINEAR)?;
+        self.bind_input(
+            &program,
+            "environment",
+            WebGlRenderingContext::TEXTURE_CUBE_MAP,
+            environment,
+        );
+        self.render_faces(&program, &cubemap, size, 0);
+        self.gl.delete_program(&program);
+
+        Ok(cubemap)
+    }
+
+    /// Prefilters every mip level down to 1x1, level `roughest_level` and below hold
+    /// the radiance for roughness 1.
+    pub(super) fn specular(
+        &self,
+        environment: &Texture,
+        size: i32,
+        roughest_level: u32,
+    ) -> Result<Texture, JsValue> {
+        let gl = self.gl;
+        let program = self.compile(&[FACE_GLSL, IMPORTANCE_SAMPLE_GLSL, SPECULAR_F_SHADER], &[])?;
+        let roughness_location = gl.get_uniform_location(&program, "roughness");
+
+        let cubemap = self.create_cubemap(WebGlRenderingContext::LINEAR_MIPMAP_LINEAR)?;
+        self.bind_input(
+            &program,
+            "environment",
+            WebGlRenderingContext::TEXTURE_CUBE_MAP,
+            environment,
+        );
+        let mut level = 0;
+        loop {
+            let level_size = (size >> level).max(1);
+            let roughness = (level as f32 / roughest_level as f32).min(1.);
+            gl.uniform1f(roughness_location.as_ref(), roughness);
+            self.render_faces(&program, &cubemap, level_size, level);
+
+            if level_size == 1 {
+                break;
+            }
+            level += 1;
+        }
+        gl.delete_program(&program);
+
+        Ok(cubemap)
+    }
+
+    pub(super) fn brdf_lut(&self, size: i32) -> Result<Texture, JsValue> {
+        let gl = self.gl;
+        let program = self.compile(&[IMPORTANCE_SAMPLE_GLSL, BRDF_LUT_F_SHADER], &[])?;
+
+        gl.use_program(Some(&program));
+        self.draw_quad(&program, size);
+
+        let texture = create_texture(gl, WebGlRenderingContext::TEXTURE_2D)?;
+        gl.copy_tex_image_2d(
+            WebGlRenderingContext::TEXTURE_2D,
+            0,
+            WebGlRenderingContext::RGBA,
+            0,
+            0,
+            size,
+            size,
+        );
+        gl.delete_program(&program);
+
+        Ok(texture)
+    }
+
+    /// Releases the scratch framebuffer and switches back to the default one.
+    pub(super) fn finish(self) {
+        let gl = self.gl;
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+        gl.viewport(0, 0, gl.drawing_buffer_width(), gl.drawing_buffer_height());
+        gl.delete_framebuffer(&self.framebuffer);
+        gl.delete_texture(&self.target);
+    }
+
+    /// Pass sources only hold declarations and `main`, the shared header is prepended.
+    fn compile(&self, sources: &[&str], defines: &[&str]) -> Result<Program, JsValue> {
+        let defines: String = defines
+            .iter()
+            .map(|define| format!("#define {}\n", define))
+            .collect();
+        let fragment_shader = format!(
+            "#version 100\n{}\nprecision highp float;\n\n#define PI 3.14159265\n{}{}",
+            defines,
+            RGBM_GLSL,
+            sources.concat()
+        );
+
+        program::compile_to_program(self.gl, QUAD_V_SHADER, &fragment_shader)
+    }
+
+    fn create_cubemap(&self, min_filter: u32) -> Result<Texture, JsValue> {
+        let gl = self.gl;
+        let cubemap = create_texture(gl, WebGlRenderingContext::TEXTURE_CUBE_MAP)?;
+        gl.tex_parameteri(
+            WebGlRenderingContext::TEXTURE_CUBE_MAP,
+            WebGlRenderingContext::TEXTURE_MIN_FILTER,
+            min_filter as i32,
+        );
+
+        Ok(cubemap)
+    }
+
+    fn bind_input(&self, program: &Program, name: &str, target: u32, texture: &Texture) {
+        let gl = self.gl;
+        gl.use_program(Some(program));
+        gl.active_texture(WebGlRenderingContext::TEXTURE0);
+        gl.bind_texture(target, Some(texture));
+        gl.uniform1i(gl.get_uniform_location(program, name).as_ref(), 0);
+    }
+
+    fn render_faces(&self, program: &Program, cubemap: &Texture, size: i32, level: i32) {
+        let gl = self.gl;
+        let forward_location = gl.get_uniform_location(program, "faceForward");
+        let right_location = gl.get_uniform_location(program, "faceRight");
+        let up_location = gl.get_uniform_location(program, "faceUp");
+
+        for (face, (forward, right, up)) in FACES.iter().enumerate() {
+            gl.uniform3fv(forward_location.as_ref(), forward);
+            gl.uniform3fv(right_location.as_ref(), right);
+            gl.uniform3fv(up_location.as_ref(), up);
+            self.draw_quad(program, size);
+
+            // The input stays bound to unit 0, the copy target goes to unit 1.
+            gl.active_texture(WebGlRenderingContext::TEXTURE1);
+            gl.bind_texture(WebGlRenderingContext::TEXTURE_CUBE_MAP, Some(cubemap));
+            gl.copy_tex_image_2d(
+                WebGlRenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                level,
+                WebGlRenderingContext::RGBA,
+                0,
+                0,
+                size,
+                size,
+            );
+            gl.active_texture(WebGlRenderingContext::TEXTURE0);
+        }
+    }
+
+    fn draw_quad(&self, program: &Program, size: i32) {
+        let gl = self.gl;
+        let location = gl.get_attrib_location(program, "position") as u32;
+
+        gl.viewport(0, 0, size.min(self.size), size.min(self.size));
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(self.quad));
+        gl.enable_vertex_attrib_array(location);
+        gl.vertex_attrib_pointer(location, 2, WebGlRenderingContext::FLOAT, false, 0, 0);
+        gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, 3);
+        gl.disable_vertex_attrib_array(location);
+    }
+}
+
+pub(super) fn create_texture(gl: &dyn Backend, target: u32) -> Result<Texture, JsValue> {
+    let texture = gl
+        .create_texture()
+        .ok_or_else(|| JsValue::from_str("could not create environment texture"))?;
+    gl.bind_texture(target, Some(&texture));
+    for (parameter, value) in &[
+        (
+            WebGlRenderingContext::TEXTURE_MIN_FILTER,
+            WebGlRenderingContext::LINEAR,
+        ),
+        (
+            WebGlRenderingContext::TEXTURE_MAG_FILTER,
+            WebGlRenderingContext::LINEAR,
+        ),
+        (
+            WebGlRenderingContext::TEXTURE_WRAP_S,
+            WebGlRenderingContext::CLAMP_TO_EDGE,
+        ),
+        (
+            WebGlRenderingContext::TEXTURE_WRAP_T,
+            WebGlRenderingContext::CLAMP_TO_EDGE,
+        ),
+    ] {
+        gl.tex_parameteri(target, *parameter, *value as i32);
+    }
+
+    Ok(texture)
+}
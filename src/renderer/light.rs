@@ -0,0 +1,186 @@
+use crate::definitions::gltf::GlTfLight;
+use crate::renderer::environment::EnvironmentSampling;
+use crate::renderer::shadow::ShadowDepthFormat;
+use gl_matrix::{mat4, vec3};
+use wasm_bindgen::prelude::*;
+
+pub(crate) const DEFAULT_MAX_LIGHTS: usize = 4;
+pub(crate) const DEFAULT_AMBIENT_SKY: [f32; 3] = [0.3, 0.3, 0.3];
+pub(crate) const DEFAULT_AMBIENT_GROUND: [f32; 3] = [0.1, 0.1, 0.1];
+
+const LIGHT_TYPE_DIRECTIONAL: f32 = 0.;
+const LIGHT_TYPE_POINT: f32 = 1.;
+const LIGHT_TYPE_SPOT: f32 = 2.;
+
+/// How the mesh shaders shade lit surfaces.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LightingModel {
+    /// Metallic-roughness PBR, matching the glTF material model.
+    Pbr,
+    /// Hemispheric ambient plus Blinn-Phong diffuse and specular, for low-end devices.
+    BlinnPhong,
+}
+
+/// Scene wide settings that are compiled into every mesh shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct LightingOptions {
+    pub(crate) max_lights: usize,
+    pub(crate) model: LightingModel,
+    /// Set while the scene renders a shadow map.
+    pub(crate) shadows: Option<ShadowDepthFormat>,
+    /// Set while the scene has an environment for image based lighting.
+    pub(crate) environment: Option<EnvironmentSampling>,
+}
+
+impl Default for LightingOptions {
+    fn default() -> Self {
+        LightingOptions {
+            max_lights: DEFAULT_MAX_LIGHTS,
+            model: LightingModel::Pbr,
+            shadows: None,
+            environment: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LightKind {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+/// A `KHR_lights_punctual` light. Position and direction come from the node it is
+/// attached to, lights shine along the local -Z axis.
+#[derive(Debug, Clone)]
+pub(crate) struct Light {
+    pub(crate) id: u32,
+    pub(crate) kind: LightKind,
+    pub(crate) color: [f32; 3],
+    pub(crate) intensity: f32,
+    pub(crate) range: Option<f32>,
+}
+
+impl Light {
+    pub(crate) fn from_gltf(id: u32, light: &GlTfLight) -> Result<Light, JsValue> {
+        let kind = match light.type_name.as_str() {
+            "directional" => LightKind::Directional,
+            "point" => LightKind::Point,
+            "spot" => {
+                let spot = light.spot.as_ref();
+                LightKind::Spot {
+                    inner_cone_angle: spot.and_then(|spot| spot.inner_cone_angle).unwrap_or(0.),
+                    outer_cone_angle: spot
+                        .and_then(|spot| spot.outer_cone_angle)
+                        .unwrap_or(std::f32::consts::FRAC_PI_4),
+                }
+            }
+            type_name => {
+                return Err(JsValue::from_str(&format!(
+                    "light type {} is not supported",
+                    type_name
+                )))
+            }
+        };
+
+        Ok(Light {
+            id,
+            kind,
+            color: light.color.unwrap_or([1., 1., 1.]),
+            intensity: light.intensity.unwrap_or(1.),
+            range: light.range,
+        })
+    }
+
+    /// Model matrix of a light placed at `position` and pointing along `direction`.
+    pub(crate) fn model_matrix(position: &[f32; 3], direction: &[f32; 3]) -> [f32; 16] {
+        let target = [
+            position[0] + direction[0],
+            position[1] + direction[1],
+            position[2] + direction[2],
+        ];
+        let mut normalized = vec3::create();
+        vec3::normalize(&mut normalized, direction);
+        let up = if normalized[1].abs() > 0.999 {
+            [0., 0., 1.]
+        } else {
+            [0., 1., 0.]
+        };
+
+        let mut matrix = mat4::create();
+        mat4::target_to(&mut matrix, position, &target, &up);
+
+        matrix
+    }
+}
+
+/// Lights of one frame, laid out for the uniform arrays of the mesh shader.
+#[derive(Debug, Default)]
+pub(crate) struct ShaderLights {
+    pub(crate) count: i32,
+    /// xyz: world position, w: light type
+    pub(crate) positions: Vec<f32>,
+    /// xyz: world direction, w: range, 0 for lights without range
+    pub(crate) directions: Vec<f32>,
+    /// Color premultiplied with the intensity.
+    pub(crate) colors: Vec<f32>,
+    /// Scale and offset turning the spot cone angle cosine into an attenuation.
+    pub(crate) cones: Vec<f32>,
+    /// Hemispheric ambient light of `LightingModel::BlinnPhong`. `LightingModel::Pbr`
+    /// uses their mean as a constant ambient light while there is no environment.
+    pub(crate) ambient_sky: [f32; 3],
+    pub(crate) ambient_ground: [f32; 3],
+}
+
+impl ShaderLights {
+    /// World direction of the light at `index`.
+    pub(crate) fn direction(&self, index: usize) -> [f32; 3] {
+        [
+            self.directions[index * 4],
+            self.directions[index * 4 + 1],
+            self.directions[index * 4 + 2],
+        ]
+    }
+
+    pub(crate) fn push(&mut self, light: &Light, model_matrix: &[f32; 16]) {
+        let mut direction = vec3::create();
+        vec3::normalize(
+            &mut direction,
+            &[-model_matrix[8], -model_matrix[9], -model_matrix[10]],
+        );
+
+        let (light_type, cone) = match light.kind {
+            LightKind::Directional => (LIGHT_TYPE_DIRECTIONAL, [0., 0.]),
+            LightKind::Point => (LIGHT_TYPE_POINT, [0., 0.]),
+            LightKind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => {
+                let outer = outer_cone_angle.cos();
+                let scale = 1. / (inner_cone_angle.cos() - outer).max(0.001);
+                (LIGHT_TYPE_SPOT, [scale, -outer * scale])
+            }
+        };
+
+        self.count += 1;
+        self.positions.extend_from_slice(&[
+            model_matrix[12],
+            model_matrix[13],
+            model_matrix[14],
+            light_type,
+        ]);
+        self.directions.extend_from_slice(&[
+            direction[0],
+            direction[1],
+            direction[2],
+            light.range.unwrap_or(0.),
+        ]);
+        self.colors
+            .extend(light.color.iter().map(|value| value * light.intensity));
+        self.cones.extend_from_slice(&cone);
+    }
+}
@@ -1,11 +1,19 @@
 use wasm_bindgen::prelude::*;
 
-use crate::definitions::gltf::{GlTf, GlTfMesh};
+use crate::definitions::gltf::GlTf;
 use crate::loader::glb::GlbBuffer;
-use crate::renderer::mesh::shader::MeshShader;
-use web_sys::{ WebGlRenderingContext};
+use crate::renderer::backend::Backend;
+use crate::renderer::debug::DebugAttributes;
+use crate::renderer::environment::Environment;
+use crate::renderer::light::{LightingOptions, ShaderLights};
+use crate::renderer::mesh::shader::{MeshShader, ProgramCache};
+use crate::renderer::queue::RenderQueue;
+use crate::renderer::shadow::ShadowMap;
+use crate::renderer::state::StateTracker;
+use crate::renderer::stats::FrameCounters;
+use crate::renderer::texture_format::CompressedTextureSupport;
 
-mod shader;
+pub(crate) mod shader;
 
 #[derive(Debug)]
 struct MeshPrimitive {
@@ -14,47 +22,180 @@ struct MeshPrimitive {
 
 #[derive(Debug)]
 pub(super) struct Mesh {
-    name: String,
+    /// Index of the glTF mesh, to load it again.
+    id: usize,
     primitives: Vec<MeshPrimitive>,
 }
 
 impl Mesh {
     pub(super) async fn from_gltf(
-        gl: &WebGlRenderingContext,
-        mesh: &GlTfMesh,
+        gl: &dyn Backend,
+        id: usize,
         gltf: &GlTf,
         glb_buffer: &GlbBuffer,
+        texture_support: &CompressedTextureSupport,
+        lighting: LightingOptions,
+        programs: &mut ProgramCache,
     ) -> Result<Mesh, JsValue> {
+        let mesh = gltf
+            .meshes
+            .get(id)
+            .ok_or_else(|| JsValue::from_str(&format!("could not find mesh with id {}", id)))?;
         let mut primitives = vec![];
 
         for primitive in &mesh.primitives {
-            let shader = MeshShader::from_gltf(&gl, &primitive, &gltf, &glb_buffer).await?;
+            let shader = MeshShader::from_gltf(
+                gl,
+                primitive,
+                gltf,
+                glb_buffer,
+                texture_support,
+                lighting,
+                programs,
+            )
+            .await?;
 
             primitives.push(MeshPrimitive { shader });
         }
-        let name = mesh.name.clone();
+        Ok(Mesh { id, primitives })
+    }
+
+    pub(super) fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Deletes the GPU resources of every primitive.
+    pub(super) fn delete(&self, gl: &dyn Backend) {
+        for primitive in &self.primitives {
+            primitive.shader.delete(gl);
+        }
+    }
 
-        Ok(Mesh { name, primitives })
+    pub(super) fn activate_variant(&mut self, variant: Option<usize>) {
+        for primitive in &mut self.primitives {
+            primitive.shader.activate_variant(variant);
+        }
     }
 
-    pub(super) fn render(
+    pub(super) fn set_lighting(
+        &mut self,
+        gl: &dyn Backend,
+        lighting: LightingOptions,
+        programs: &mut ProgramCache,
+    ) -> Result<(), JsValue> {
+        for primitive in &mut self.primitives {
+            primitive.shader.set_lighting(gl, lighting, programs)?;
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn render_depth(
         &self,
-        gl: &WebGlRenderingContext,
-        descriptor: &MeshRenderDescriptor,
+        gl: &dyn Backend,
+        shadow_map: &ShadowMap,
+        counters: &FrameCounters,
     ) -> Result<(), JsValue> {
         for primitive in &self.primitives {
-            primitive.shader.render(&gl, &descriptor)?;
+            primitive
+                .shader
+                .render_depth(gl, shadow_map.position_location(), counters)?;
         }
 
         Ok(())
     }
+
+    pub(super) fn render_debug(
+        &self,
+        gl: &dyn Backend,
+        attributes: &DebugAttributes,
+        counters: &FrameCounters,
+    ) -> Result<(), JsValue> {
+        for primitive in &self.primitives {
+            primitive.shader.render_debug(gl, attributes, counters)?;
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn build_wireframe(&mut self, gl: &dyn Backend) -> Result<(), JsValue> {
+        for primitive in &mut self.primitives {
+            primitive.shader.build_wireframe(gl)?;
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn render_wireframe(
+        &self,
+        gl: &dyn Backend,
+        position_location: u32,
+        counters: &FrameCounters,
+    ) -> Result<(), JsValue> {
+        for primitive in &self.primitives {
+            primitive
+                .shader
+                .render_wireframe(gl, position_location, counters)?;
+        }
+
+        Ok(())
+    }
+
+    /// Local bounds enclosing every primitive that declares them.
+    pub(super) fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        self.primitives
+            .iter()
+            .filter_map(|primitive| primitive.shader.bounds())
+            .reduce(|(min, max), (primitive_min, primitive_max)| {
+                (
+                    [
+                        min[0].min(primitive_min[0]),
+                        min[1].min(primitive_min[1]),
+                        min[2].min(primitive_min[2]),
+                    ],
+                    [
+                        max[0].max(primitive_max[0]),
+                        max[1].max(primitive_max[1]),
+                        max[2].max(primitive_max[2]),
+                    ],
+                )
+            })
+    }
+
+    /// Adds every primitive to `queue`, `depth` is the distance of `node` from the camera.
+    pub(super) fn queue(&self, node: usize, depth: f32, queue: &mut RenderQueue) {
+        for (index, primitive) in self.primitives.iter().enumerate() {
+            queue.push(primitive.shader.draw_item(node, index, depth));
+        }
+    }
+
+    pub(super) fn render_primitive(
+        &self,
+        gl: &dyn Backend,
+        primitive: usize,
+        descriptor: &MeshRenderDescriptor<'_>,
+        state: &mut StateTracker,
+    ) -> Result<(), JsValue> {
+        match self.primitives.get(primitive) {
+            Some(primitive) => primitive.shader.render(gl, descriptor, state),
+            None => Err(JsValue::from_str("could not find mesh primitive")),
+        }
+    }
 }
 
 #[derive(Debug)]
-pub(super) struct MeshRenderDescriptor {
+pub(super) struct MeshRenderDescriptor<'a> {
     pub(super) model_matrix: [f32; 16],
+    pub(super) normal_matrix: [f32; 9],
     pub(super) view_matrix: [f32; 16],
     pub(super) projection_matrix: [f32; 16],
+    pub(super) camera_position: [f32; 3],
+    pub(super) lights: &'a ShaderLights,
+    pub(super) shadow_map: Option<&'a ShadowMap>,
+    /// Index of the shadow casting light in `lights`, -1 if the node receives no shadow.
+    pub(super) shadow_light: i32,
+    pub(super) environment: Option<&'a Environment>,
+    pub(super) counters: &'a FrameCounters,
 }
 
 // #[wasm_bindgen]
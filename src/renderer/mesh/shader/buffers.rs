@@ -1,36 +1,17 @@
-use crate::definitions::gltf::{GlTf, GlTfAccessor, GlTfBufferView, GlTfMeshPrimitive};
+use crate::definitions::gltf::{GlTf, GlTfAccessor, GlTfMeshPrimitive};
 use crate::loader::glb::GlbBuffer;
+use crate::renderer::backend::{Backend, Buffer};
+use std::collections::HashSet;
 use wasm_bindgen::prelude::*;
-use web_sys::{WebGlBuffer, WebGlRenderingContext};
-
-#[derive(Debug)]
-pub(super) struct BufferView {
-    pub(super) buffer: usize,
-    pub(super) byte_offset: u32,
-    pub(super) byte_length: u32,
-    pub(super) target: Option<usize>,
-    pub(super) byte_stride: Option<usize>,
-}
-
-impl From<&GlTfBufferView> for BufferView {
-    fn from(buffer_view: &GlTfBufferView) -> Self {
-        BufferView {
-            buffer: buffer_view.buffer,
-            byte_offset: buffer_view.byte_offset,
-            byte_length: buffer_view.byte_length,
-            target: buffer_view.target,
-            byte_stride: buffer_view.byte_stride,
-        }
-    }
-}
+use web_sys::WebGlRenderingContext;
 
 #[derive(Debug)]
 pub(super) struct Accessor {
-    pub(super) buffer_view: usize,
     pub(super) component_type: u32,
     pub(super) count: usize,
     pub(super) type_name: String,
     pub(super) byte_offset: Option<u32>,
+    pub(super) normalized: bool,
     pub(super) max: Option<Vec<f32>>,
     pub(super) min: Option<Vec<f32>>,
 }
@@ -38,11 +19,11 @@ pub(super) struct Accessor {
 impl From<&GlTfAccessor> for Accessor {
     fn from(accessor: &GlTfAccessor) -> Self {
         Accessor {
-            buffer_view: accessor.buffer_view,
             component_type: accessor.component_type,
             count: accessor.count,
             type_name: String::from(&accessor.type_name),
             byte_offset: accessor.byte_offset,
+            normalized: accessor.normalized.unwrap_or(false),
             max: accessor.max.clone(),
             min: accessor.min.clone(),
         }
@@ -51,105 +32,77 @@ impl From<&GlTfAccessor> for Accessor {
 
 #[derive(Debug)]
 pub(super) struct MeshShaderFrameBuffer {
-    pub(super) buffer: WebGlBuffer,
-    pub(super) buffer_view: BufferView,
+    pub(super) buffer: Buffer,
+    /// `byteStride` of the buffer view, 0 for tightly packed data.
+    pub(super) byte_stride: usize,
     pub(super) accessor: Accessor,
 }
 
 impl MeshShaderFrameBuffer {
     fn new_from_accessor(
-        gl: &WebGlRenderingContext,
+        gl: &dyn Backend,
         target: u32,
         accessor: &GlTfAccessor,
         gltf: &GlTf,
         glb_buffer: &GlbBuffer,
     ) -> Result<MeshShaderFrameBuffer, JsValue> {
-        let buffer_view = gltf
-            .buffer_views
-            .get(accessor.buffer_view)
-            .ok_or(JsValue::from_str("could not find buffer view"))?;
-
-        let index = match accessor.component_type {
-            WebGlRenderingContext::FLOAT => MeshShaderFrameBuffer::new_float32_buffer(
-                &gl,
-                target,
-                &glb_buffer,
-                &accessor,
-                &buffer_view,
-            ),
-            WebGlRenderingContext::UNSIGNED_SHORT => MeshShaderFrameBuffer::new_uint16_buffer(
-                &gl,
-                target,
-                &glb_buffer,
-                &accessor,
-                &buffer_view,
-            ),
-            _ => Err(JsValue::from_str("foo")),
-        }?;
-
-        Ok(index)
-    }
+        let buffer_view = accessor
+            .buffer_view
+            .and_then(|index| gltf.buffer_views.get(index))
+            .ok_or_else(|| JsValue::from_str("could not find buffer view"))?;
 
-    fn new_float32_buffer(
-        gl: &WebGlRenderingContext,
-        target: u32,
-        glb_buffer: &GlbBuffer,
-        accessor: &GlTfAccessor,
-        buffer_view: &GlTfBufferView,
-    ) -> Result<MeshShaderFrameBuffer, JsValue> {
-        let byte_offset = glb_buffer.byte_offset + buffer_view.byte_offset;
-        let length = buffer_view.byte_length;
+        MeshShaderFrameBuffer::check_component_type(gl, target, accessor)?;
 
-        let buffer_data =
-            js_sys::DataView::new(&glb_buffer.bin, byte_offset as usize, length as usize);
+        // Vertex and index data is uploaded as raw bytes, the component type is only
+        // interpreted by `vertex_attrib_pointer` and `draw_elements` later on.
+        let buffer_data = glb_buffer.view(buffer_view)?;
 
-        let buffer = MeshShaderFrameBuffer::new_buffer(&gl)?;
+        let buffer = MeshShaderFrameBuffer::new_buffer(gl)?;
         gl.bind_buffer(target, Some(&buffer));
-        gl.buffer_data_with_array_buffer_view(
-            target,
-            &buffer_data,
-            WebGlRenderingContext::STATIC_DRAW,
-        );
+        gl.buffer_data_u8(target, buffer_data, WebGlRenderingContext::STATIC_DRAW);
 
         Ok(MeshShaderFrameBuffer {
             accessor: Accessor::from(accessor),
-            buffer_view: BufferView::from(buffer_view),
+            byte_stride: buffer_view.byte_stride.unwrap_or(0),
             buffer,
         })
     }
 
-    fn new_uint16_buffer(
-        gl: &WebGlRenderingContext,
+    fn check_component_type(
+        gl: &dyn Backend,
         target: u32,
-        glb_buffer: &GlbBuffer,
         accessor: &GlTfAccessor,
-        buffer_view: &GlTfBufferView,
-    ) -> Result<MeshShaderFrameBuffer, JsValue> {
-        let byte_offset = glb_buffer.byte_offset + buffer_view.byte_offset;
-        let length = buffer_view.byte_length;
-
-        let buffer_data = js_sys::Uint16Array::new_with_byte_offset_and_length(
-            &glb_buffer.bin,
-            byte_offset,
-            length,
-        );
-
-        let buffer = MeshShaderFrameBuffer::new_buffer(&gl)?;
-        gl.bind_buffer(target, Some(&buffer));
-        gl.buffer_data_with_array_buffer_view(
-            target,
-            &buffer_data,
-            WebGlRenderingContext::STATIC_DRAW,
-        );
+    ) -> Result<(), JsValue> {
+        let supported = match (target, accessor.component_type) {
+            (WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, WebGlRenderingContext::UNSIGNED_INT) => {
+                gl.has_extension("OES_element_index_uint")
+            }
+            (
+                WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+                WebGlRenderingContext::UNSIGNED_BYTE | WebGlRenderingContext::UNSIGNED_SHORT,
+            ) => true,
+            (
+                WebGlRenderingContext::ARRAY_BUFFER,
+                WebGlRenderingContext::FLOAT
+                | WebGlRenderingContext::BYTE
+                | WebGlRenderingContext::UNSIGNED_BYTE
+                | WebGlRenderingContext::SHORT
+                | WebGlRenderingContext::UNSIGNED_SHORT,
+            ) => true,
+            _ => false,
+        };
 
-        Ok(MeshShaderFrameBuffer {
-            accessor: Accessor::from(accessor),
-            buffer_view: BufferView::from(buffer_view),
-            buffer,
-        })
+        if supported {
+            Ok(())
+        } else {
+            Err(JsValue::from_str(&format!(
+                "component type {} is not supported for buffer target {}",
+                accessor.component_type, target
+            )))
+        }
     }
 
-    fn new_buffer(gl: &WebGlRenderingContext) -> Result<WebGlBuffer, JsValue> {
+    fn new_buffer(gl: &dyn Backend) -> Result<Buffer, JsValue> {
         match gl.create_buffer() {
             Some(buffer) => Ok(buffer),
             None => Err(JsValue::from_str("Could not create webgl buffer")),
@@ -157,17 +110,90 @@ impl MeshShaderFrameBuffer {
     }
 }
 
+/// Index buffer drawing every triangle edge once as `LINES`.
+#[derive(Debug)]
+pub(super) struct WireframeBuffer {
+    pub(super) buffer: Buffer,
+    pub(super) count: i32,
+    pub(super) component_type: u32,
+}
+
+impl WireframeBuffer {
+    fn new(
+        gl: &dyn Backend,
+        indices: &[u8],
+        component_type: u32,
+    ) -> Result<WireframeBuffer, JsValue> {
+        let triangles: Vec<u32> = match component_type {
+            WebGlRenderingContext::UNSIGNED_BYTE => {
+                indices.iter().map(|index| *index as u32).collect()
+            }
+            WebGlRenderingContext::UNSIGNED_SHORT => indices
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as u32)
+                .collect(),
+            _ => indices
+                .chunks_exact(4)
+                .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect(),
+        };
+
+        let mut seen = HashSet::new();
+        let mut edges = vec![];
+        for triangle in triangles.chunks_exact(3) {
+            for (from, to) in &[(0, 1), (1, 2), (2, 0)] {
+                let (a, b) = (triangle[*from], triangle[*to]);
+                if seen.insert((a.min(b), a.max(b))) {
+                    edges.extend_from_slice(&[a, b]);
+                }
+            }
+        }
+
+        let buffer = MeshShaderFrameBuffer::new_buffer(gl)?;
+        gl.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&buffer));
+        let component_type = match component_type {
+            WebGlRenderingContext::UNSIGNED_INT => {
+                gl.buffer_data_u32(
+                    WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+                    edges.as_slice(),
+                    WebGlRenderingContext::STATIC_DRAW,
+                );
+                WebGlRenderingContext::UNSIGNED_INT
+            }
+            _ => {
+                let edges: Vec<u16> = edges.iter().map(|index| *index as u16).collect();
+                gl.buffer_data_u16(
+                    WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+                    edges.as_slice(),
+                    WebGlRenderingContext::STATIC_DRAW,
+                );
+                WebGlRenderingContext::UNSIGNED_SHORT
+            }
+        };
+
+        Ok(WireframeBuffer {
+            buffer,
+            count: edges.len() as i32,
+            component_type,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub(super) struct MeshShaderFrameBuffers {
     pub(super) index: MeshShaderFrameBuffer,
     pub(super) position: MeshShaderFrameBuffer,
     pub(super) normal: Option<MeshShaderFrameBuffer>,
-    pub(super) texture_coord: Option<MeshShaderFrameBuffer>,
+    texture_coord_0: Option<MeshShaderFrameBuffer>,
+    texture_coord_1: Option<MeshShaderFrameBuffer>,
+    /// Index bytes of the primitive, kept to build the wireframe on demand.
+    index_data: Vec<u8>,
+    pub(super) wireframe: Option<WireframeBuffer>,
 }
 
 impl MeshShaderFrameBuffers {
     pub(super) fn from_gltf(
-        gl: &WebGlRenderingContext,
+        gl: &dyn Backend,
         primitive: &GlTfMeshPrimitive,
         gltf: &GlTf,
         glb_buffer: &GlbBuffer,
@@ -175,45 +201,128 @@ impl MeshShaderFrameBuffers {
         let indices_accessor = gltf
             .accessors
             .get(primitive.indices)
-            .ok_or(JsValue::from_str("could not find indices accessor"))?;
+            .ok_or_else(|| JsValue::from_str("could not find indices accessor"))?;
         let position_accessor = gltf
             .accessors
             .get(primitive.attributes.position)
-            .ok_or(JsValue::from_str("could not find position accessor"))?;
+            .ok_or_else(|| JsValue::from_str("could not find position accessor"))?;
 
         let index = MeshShaderFrameBuffer::new_from_accessor(
-            &gl,
+            gl,
             WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
-            &indices_accessor,
-            &gltf,
-            &glb_buffer,
+            indices_accessor,
+            gltf,
+            glb_buffer,
         )?;
+        let index_data = MeshShaderFrameBuffers::index_data(indices_accessor, gltf, glb_buffer)?;
         let position = MeshShaderFrameBuffer::new_from_accessor(
-            &gl,
+            gl,
             WebGlRenderingContext::ARRAY_BUFFER,
-            &position_accessor,
-            &gltf,
-            &glb_buffer,
+            position_accessor,
+            gltf,
+            glb_buffer,
         )?;
         let normal =
-            MeshShaderFrameBuffers::try_new(&gl, primitive.attributes.normal, &gltf, &glb_buffer)?;
-        let texture_coord = MeshShaderFrameBuffers::try_new(
-            &gl,
+            MeshShaderFrameBuffers::try_new(gl, primitive.attributes.normal, gltf, glb_buffer)?;
+        // Both sets are uploaded, materials of different variants may sample either one.
+        let texture_coord_0 = MeshShaderFrameBuffers::try_new(
+            gl,
             primitive.attributes.texture_coord_0,
-            &gltf,
-            &glb_buffer,
+            gltf,
+            glb_buffer,
+        )?;
+        let texture_coord_1 = MeshShaderFrameBuffers::try_new(
+            gl,
+            primitive.attributes.texture_coord_1,
+            gltf,
+            glb_buffer,
         )?;
 
         Ok(MeshShaderFrameBuffers {
             index,
             position,
             normal,
-            texture_coord,
+            texture_coord_0,
+            texture_coord_1,
+            index_data,
+            wireframe: None,
         })
     }
 
+    pub(super) fn delete(&self, gl: &dyn Backend) {
+        let buffers = [
+            Some(&self.index),
+            Some(&self.position),
+            self.normal.as_ref(),
+            self.texture_coord_0.as_ref(),
+            self.texture_coord_1.as_ref(),
+        ];
+        for frame_buffer in buffers.iter().flatten() {
+            gl.delete_buffer(&frame_buffer.buffer);
+        }
+        if let Some(wireframe) = &self.wireframe {
+            gl.delete_buffer(&wireframe.buffer);
+        }
+    }
+
+    /// Builds the wireframe index buffer the first time it is needed.
+    pub(super) fn build_wireframe(&mut self, gl: &dyn Backend) -> Result<(), JsValue> {
+        if self.wireframe.is_none() {
+            self.wireframe = Some(WireframeBuffer::new(
+                gl,
+                &self.index_data,
+                self.index.accessor.component_type,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    /// Local bounds from the `min` and `max` of the position accessor.
+    pub(super) fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let accessor = &self.position.accessor;
+        match (accessor.min.as_deref(), accessor.max.as_deref()) {
+            (Some([min_x, min_y, min_z, ..]), Some([max_x, max_y, max_z, ..])) => {
+                Some(([*min_x, *min_y, *min_z], [*max_x, *max_y, *max_z]))
+            }
+            _ => None,
+        }
+    }
+
+    pub(super) fn texture_coord(&self, set: usize) -> Option<&MeshShaderFrameBuffer> {
+        match set {
+            0 => self.texture_coord_0.as_ref(),
+            1 => self.texture_coord_1.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn index_data(
+        accessor: &GlTfAccessor,
+        gltf: &GlTf,
+        glb_buffer: &GlbBuffer,
+    ) -> Result<Vec<u8>, JsValue> {
+        let buffer_view = accessor
+            .buffer_view
+            .and_then(|index| gltf.buffer_views.get(index))
+            .ok_or_else(|| JsValue::from_str("could not find buffer view"))?;
+        let component_size = match accessor.component_type {
+            WebGlRenderingContext::UNSIGNED_BYTE => 1,
+            WebGlRenderingContext::UNSIGNED_SHORT => 2,
+            _ => 4,
+        };
+        let start = accessor.byte_offset.unwrap_or(0) as usize;
+        let end = start + accessor.count * component_size;
+
+        glb_buffer
+            .view(buffer_view)?
+            .get(start..end)
+            .map(|data| data.to_vec())
+            .ok_or_else(|| JsValue::from_str("index accessor exceeds its buffer view"))
+    }
+
     fn try_new(
-        gl: &WebGlRenderingContext,
+        gl: &dyn Backend,
         value: Option<usize>,
         gltf: &GlTf,
         glb_buffer: &GlbBuffer,
@@ -223,13 +332,13 @@ impl MeshShaderFrameBuffers {
                 let accessor = gltf
                     .accessors
                     .get(index)
-                    .ok_or(JsValue::from_str("could not find accessor"))?;
+                    .ok_or_else(|| JsValue::from_str("could not find accessor"))?;
                 Some(MeshShaderFrameBuffer::new_from_accessor(
-                    &gl,
+                    gl,
                     WebGlRenderingContext::ARRAY_BUFFER,
-                    &accessor,
-                    &gltf,
-                    &glb_buffer,
+                    accessor,
+                    gltf,
+                    glb_buffer,
                 )?)
             }
             None => None,
@@ -0,0 +1,59 @@
+use crate::renderer::backend::{Backend, Program};
+use crate::renderer::light::LightingOptions;
+use crate::renderer::mesh::shader::locations::MeshShaderLocations;
+use crate::renderer::mesh::shader::material::MaterialFeatures;
+use crate::renderer::mesh::shader::{program, F_SHADER, V_SHADER};
+use crate::renderer::state;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+/// One compiled mesh shader permutation.
+#[derive(Debug)]
+pub(super) struct MeshProgram {
+    pub(super) id: u32,
+    pub(super) program: Program,
+    pub(super) locations: MeshShaderLocations,
+}
+
+/// Mesh programs by permutation, materials with the same features and lighting share
+/// one program.
+#[derive(Debug, Default)]
+pub(crate) struct ProgramCache {
+    programs: HashMap<(MaterialFeatures, LightingOptions), Rc<MeshProgram>>,
+}
+
+impl ProgramCache {
+    pub(super) fn get(
+        &mut self,
+        gl: &dyn Backend,
+        features: MaterialFeatures,
+        lighting: LightingOptions,
+    ) -> Result<Rc<MeshProgram>, JsValue> {
+        if let Some(program) = self.programs.get(&(features, lighting)) {
+            return Ok(Rc::clone(program));
+        }
+
+        let program = program::compile_to_program(
+            gl,
+            &features.shader_source(V_SHADER, &lighting),
+            &features.shader_source(F_SHADER, &lighting),
+        )?;
+        let program = Rc::new(MeshProgram {
+            id: state::next_id(),
+            locations: MeshShaderLocations::new(gl, &program),
+            program,
+        });
+        self.programs
+            .insert((features, lighting), Rc::clone(&program));
+
+        Ok(program)
+    }
+
+    /// Deletes every program, meshes must not render with them afterwards.
+    pub(crate) fn delete(&self, gl: &dyn Backend) {
+        for program in self.programs.values() {
+            gl.delete_program(&program.program);
+        }
+    }
+}
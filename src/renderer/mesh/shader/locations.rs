@@ -1,10 +1,30 @@
-use web_sys::{WebGlProgram, WebGlRenderingContext, WebGlUniformLocation};
-
 #[derive(Debug)]
 pub(super) struct MeshUniformLocations {
-    pub(super) model_matrix: Option<WebGlUniformLocation>,
-    pub(super) view_matrix: Option<WebGlUniformLocation>,
-    pub(super) projection_matrix: Option<WebGlUniformLocation>,
+    pub(super) model_matrix: Option<UniformLocation>,
+    pub(super) normal_matrix: Option<UniformLocation>,
+    pub(super) view_matrix: Option<UniformLocation>,
+    pub(super) projection_matrix: Option<UniformLocation>,
+    pub(super) camera_position: Option<UniformLocation>,
+    pub(super) base_color_factor: Option<UniformLocation>,
+    pub(super) metallic_roughness: Option<UniformLocation>,
+    pub(super) emissive: Option<UniformLocation>,
+    pub(super) clearcoat: Option<UniformLocation>,
+    pub(super) transmission: Option<UniformLocation>,
+    pub(super) light_count: Option<UniformLocation>,
+    pub(super) light_positions: Option<UniformLocation>,
+    pub(super) light_directions: Option<UniformLocation>,
+    pub(super) light_colors: Option<UniformLocation>,
+    pub(super) light_cones: Option<UniformLocation>,
+    pub(super) ambient_sky: Option<UniformLocation>,
+    pub(super) ambient_ground: Option<UniformLocation>,
+    pub(super) shadow_map: Option<UniformLocation>,
+    pub(super) light_space_matrix: Option<UniformLocation>,
+    pub(super) shadow_params: Option<UniformLocation>,
+    pub(super) shadow_light: Option<UniformLocation>,
+    pub(super) irradiance_map: Option<UniformLocation>,
+    pub(super) specular_map: Option<UniformLocation>,
+    pub(super) brdf_lut: Option<UniformLocation>,
+    pub(super) environment_params: Option<UniformLocation>,
 }
 
 #[derive(Debug)]
@@ -21,19 +41,42 @@ pub(super) struct MeshShaderLocations {
 }
 
 impl MeshShaderLocations {
-    pub(super) fn new(gl: &WebGlRenderingContext, program: &WebGlProgram) -> MeshShaderLocations {
+    pub(super) fn new(gl: &dyn Backend, program: &Program) -> MeshShaderLocations {
         let uniform = MeshUniformLocations {
-            model_matrix: gl.get_uniform_location(&program, "modelMatrix"),
-            view_matrix: gl.get_uniform_location(&program, "viewMatrix"),
-            projection_matrix: gl.get_uniform_location(&program, "projectionMatrix"),
+            model_matrix: gl.get_uniform_location(program, "modelMatrix"),
+            normal_matrix: gl.get_uniform_location(program, "normalMatrix"),
+            view_matrix: gl.get_uniform_location(program, "viewMatrix"),
+            projection_matrix: gl.get_uniform_location(program, "projectionMatrix"),
+            camera_position: gl.get_uniform_location(program, "cameraPosition"),
+            base_color_factor: gl.get_uniform_location(program, "baseColorFactor"),
+            metallic_roughness: gl.get_uniform_location(program, "metallicRoughness"),
+            emissive: gl.get_uniform_location(program, "emissive"),
+            clearcoat: gl.get_uniform_location(program, "clearcoat"),
+            transmission: gl.get_uniform_location(program, "transmission"),
+            light_count: gl.get_uniform_location(program, "lightCount"),
+            light_positions: gl.get_uniform_location(program, "lightPositions"),
+            light_directions: gl.get_uniform_location(program, "lightDirections"),
+            light_colors: gl.get_uniform_location(program, "lightColors"),
+            light_cones: gl.get_uniform_location(program, "lightCones"),
+            ambient_sky: gl.get_uniform_location(program, "ambientSky"),
+            ambient_ground: gl.get_uniform_location(program, "ambientGround"),
+            shadow_map: gl.get_uniform_location(program, "shadowMap"),
+            light_space_matrix: gl.get_uniform_location(program, "lightSpaceMatrix"),
+            shadow_params: gl.get_uniform_location(program, "shadowParams"),
+            shadow_light: gl.get_uniform_location(program, "shadowLight"),
+            irradiance_map: gl.get_uniform_location(program, "irradianceMap"),
+            specular_map: gl.get_uniform_location(program, "specularMap"),
+            brdf_lut: gl.get_uniform_location(program, "brdfLut"),
+            environment_params: gl.get_uniform_location(program, "environmentParams"),
         };
 
         let attribute = MeshAttributeLocations {
-            position: gl.get_attrib_location(&program, "position"),
-            normal: gl.get_attrib_location(&program, "normal"),
-            texture_coord: gl.get_attrib_location(&program, "textureCoords"),
+            position: gl.get_attrib_location(program, "position"),
+            normal: gl.get_attrib_location(program, "normal"),
+            texture_coord: gl.get_attrib_location(program, "textureCoords"),
         };
 
         MeshShaderLocations { uniform, attribute }
     }
 }
+use crate::renderer::backend::{Backend, Program, UniformLocation};
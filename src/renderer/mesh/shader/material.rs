@@ -0,0 +1,178 @@
+use crate::definitions::gltf::GlTfMaterial;
+use crate::renderer::backend::Backend;
+use crate::renderer::environment::EnvironmentSampling;
+use crate::renderer::light::{LightingModel, LightingOptions};
+use crate::renderer::mesh::shader::locations::MeshUniformLocations;
+use crate::renderer::shadow::ShadowDepthFormat;
+
+/// Material properties that select a shader permutation. Every enabled feature is
+/// compiled in as a `#define`, so materials only pay for what they use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub(super) struct MaterialFeatures {
+    pub(super) base_color_texture: bool,
+    pub(super) normals: bool,
+    pub(super) unlit: bool,
+    pub(super) emissive: bool,
+    pub(super) clearcoat: bool,
+    pub(super) transmission: bool,
+}
+
+impl MaterialFeatures {
+    fn defines(&self, lighting: &LightingOptions) -> String {
+        let defines = [
+            (self.base_color_texture, "HAS_BASE_COLOR_TEXTURE"),
+            (self.normals, "HAS_NORMALS"),
+            (self.unlit, "UNLIT"),
+            (self.emissive, "EMISSIVE"),
+            (self.clearcoat, "CLEARCOAT"),
+            (self.transmission, "TRANSMISSION"),
+            (lighting.model == LightingModel::BlinnPhong, "BLINN_PHONG"),
+            (lighting.shadows.is_some(), "SHADOWS"),
+            (
+                lighting.shadows == Some(ShadowDepthFormat::PackedRgba),
+                "SHADOW_PACKED_DEPTH",
+            ),
+            // The Blinn-Phong model keeps its cheap hemispheric ambient light.
+            (
+                lighting.environment.is_some() && lighting.model == LightingModel::Pbr,
+                "IBL",
+            ),
+            (
+                lighting.environment == Some(EnvironmentSampling::TextureLod),
+                "TEXTURE_LOD",
+            ),
+        ];
+
+        defines
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, name)| format!("#define {}\n", name))
+            .chain(std::iter::once(format!(
+                "#define MAX_LIGHTS {}\n",
+                lighting.max_lights
+            )))
+            .collect()
+    }
+
+    /// Inserts the defines of the permutation right after the `#version` directive.
+    pub(super) fn shader_source(&self, source: &str, lighting: &LightingOptions) -> String {
+        let defines = self.defines(lighting);
+        match source.find('\n') {
+            Some(index) => format!("{}\n{}{}", &source[..index], defines, &source[index + 1..]),
+            None => format!("{}{}", defines, source),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct MeshMaterial {
+    pub(super) features: MaterialFeatures,
+    base_color_factor: [f32; 4],
+    metallic_roughness: [f32; 2],
+    emissive: [f32; 3],
+    clearcoat: [f32; 2],
+    transmission: f32,
+}
+
+impl MeshMaterial {
+    pub(super) fn from_gltf(
+        material: Option<&GlTfMaterial>,
+        has_base_color_texture: bool,
+        has_normals: bool,
+    ) -> MeshMaterial {
+        let material = match material {
+            Some(material) => material,
+            None => {
+                return MeshMaterial {
+                    features: MaterialFeatures {
+                        normals: has_normals,
+                        ..MaterialFeatures::default()
+                    },
+                    base_color_factor: [1., 1., 1., 1.],
+                    metallic_roughness: [1., 1.],
+                    emissive: [0., 0., 0.],
+                    clearcoat: [0., 0.],
+                    transmission: 0.,
+                }
+            }
+        };
+        let extensions = material.extensions.as_ref();
+
+        let emissive_strength = extensions
+            .and_then(|extensions| extensions.emissive_strength.as_ref())
+            .and_then(|emissive_strength| emissive_strength.emissive_strength)
+            .unwrap_or(1.);
+        let emissive_factor = material.emissive_factor.unwrap_or([0., 0., 0.]);
+        let emissive = [
+            emissive_factor[0] * emissive_strength,
+            emissive_factor[1] * emissive_strength,
+            emissive_factor[2] * emissive_strength,
+        ];
+
+        let clearcoat = extensions
+            .and_then(|extensions| extensions.clearcoat.as_ref())
+            .map(|clearcoat| {
+                [
+                    clearcoat.clearcoat_factor.unwrap_or(0.),
+                    clearcoat.clearcoat_roughness_factor.unwrap_or(0.),
+                ]
+            })
+            .unwrap_or([0., 0.]);
+        let transmission = extensions
+            .and_then(|extensions| extensions.transmission.as_ref())
+            .and_then(|transmission| transmission.transmission_factor)
+            .unwrap_or(0.);
+
+        let features = MaterialFeatures {
+            base_color_texture: has_base_color_texture,
+            normals: has_normals,
+            unlit: extensions.is_some_and(|extensions| extensions.unlit.is_some()),
+            emissive: emissive.iter().any(|value| *value > 0.),
+            // The clearcoat layer is view dependent and needs normals to be shaded.
+            clearcoat: clearcoat[0] > 0. && has_normals,
+            transmission: transmission > 0.,
+        };
+
+        MeshMaterial {
+            features,
+            base_color_factor: material
+                .pbr_metallic_roughness
+                .base_color_factor
+                .unwrap_or([1., 1., 1., 1.]),
+            metallic_roughness: [
+                material
+                    .pbr_metallic_roughness
+                    .metallic_factor
+                    .unwrap_or(1.),
+                material
+                    .pbr_metallic_roughness
+                    .roughness_factor
+                    .unwrap_or(1.),
+            ],
+            emissive,
+            clearcoat,
+            transmission,
+        }
+    }
+
+    pub(super) fn bind(&self, gl: &dyn Backend, locations: &MeshUniformLocations) {
+        gl.uniform4fv(
+            locations.base_color_factor.as_ref(),
+            &self.base_color_factor,
+        );
+        gl.uniform2fv(
+            locations.metallic_roughness.as_ref(),
+            &self.metallic_roughness,
+        );
+
+        if self.features.emissive {
+            gl.uniform3fv(locations.emissive.as_ref(), &self.emissive);
+        }
+        if self.features.clearcoat {
+            gl.uniform2fv(locations.clearcoat.as_ref(), &self.clearcoat);
+        }
+        if self.features.transmission {
+            gl.uniform1f(locations.transmission.as_ref(), self.transmission);
+        }
+    }
+}
@@ -1,17 +1,37 @@
+use std::collections::HashMap;
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use web_sys::{WebGlProgram, WebGlRenderingContext};
+use web_sys::WebGlRenderingContext;
 
 use crate::definitions::gltf::{GlTf, GlTfMeshPrimitive};
 use crate::loader::glb::GlbBuffer;
+use crate::renderer::backend::{Backend, VertexArray};
+use crate::renderer::debug::DebugAttributes;
+use crate::renderer::environment::{
+    BRDF_LUT_TEXTURE_UNIT, IRRADIANCE_TEXTURE_UNIT, SPECULAR_TEXTURE_UNIT,
+};
+use crate::renderer::light::LightingOptions;
 use crate::renderer::mesh::shader::buffers::{
     Accessor, MeshShaderFrameBuffer, MeshShaderFrameBuffers,
 };
+use crate::renderer::mesh::shader::cache::MeshProgram;
 use crate::renderer::mesh::shader::locations::MeshShaderLocations;
+use crate::renderer::mesh::shader::material::MeshMaterial;
 use crate::renderer::mesh::shader::textures::MeshShaderTextures;
 use crate::renderer::mesh::MeshRenderDescriptor;
+use crate::renderer::queue::{DrawItem, RenderPass};
+use crate::renderer::shadow::SHADOW_TEXTURE_UNIT;
+use crate::renderer::state::{self, StateTracker};
+use crate::renderer::stats::FrameCounters;
+use crate::renderer::texture_format::CompressedTextureSupport;
+
+pub(crate) use cache::ProgramCache;
+pub(crate) use textures::set_basis_transcoder_url;
 
 mod buffers;
+mod cache;
 mod locations;
+mod material;
 pub mod program;
 mod textures;
 
@@ -22,145 +42,943 @@ attribute vec3 normal;
 attribute vec2 textureCoords;
 
 varying vec3 fNormal;
+varying vec3 fWorldPosition;
 varying vec2 fTextureCoords;
+#ifdef SHADOWS
+varying vec4 fLightSpacePosition;
+
+uniform mat4 lightSpaceMatrix;
+#endif
 
 uniform mat4 modelMatrix;
 uniform mat4 viewMatrix;
 uniform mat4 projectionMatrix;
+// Inverse-transpose of the model matrix, keeps normals perpendicular under non-uniform scale.
+uniform mat3 normalMatrix;
+uniform mat3 textureTransform;
 
 void main() {
     vec4 worldPosition = modelMatrix * vec4(position, 1.0);
     gl_Position = projectionMatrix * viewMatrix * worldPosition;
 
-    fNormal = normal;
-    fTextureCoords = textureCoords;
+    fNormal = normalMatrix * normal;
+    fWorldPosition = worldPosition.xyz;
+    fTextureCoords = (textureTransform * vec3(textureCoords, 1.0)).xy;
+#ifdef SHADOWS
+    fLightSpacePosition = lightSpaceMatrix * worldPosition;
+#endif
 }";
 
 const F_SHADER: &str = "#version 100
+#if defined(IBL) && defined(TEXTURE_LOD)
+#extension GL_EXT_shader_texture_lod : enable
+#endif
 
 precision mediump float;
 
+#define PI 3.14159265
+
+#if !defined(UNLIT) && defined(HAS_NORMALS) && MAX_LIGHTS > 0
+#define LIGHTING
+#endif
+#if defined(SHADOWS) && !defined(LIGHTING)
+#undef SHADOWS
+#endif
+#if defined(IBL) && !defined(LIGHTING)
+#undef IBL
+#endif
+#if defined(CLEARCOAT) && !defined(LIGHTING)
+#undef CLEARCOAT
+#endif
+
+varying vec3 fNormal;
+varying vec3 fWorldPosition;
 varying vec2 fTextureCoords;
 
 uniform sampler2D uTexture;
+uniform vec4 baseColorFactor;
+// x: metallic, y: roughness
+uniform vec2 metallicRoughness;
+uniform vec3 cameraPosition;
+
+#ifdef LIGHTING
+uniform int lightCount;
+// xyz: position, w: type (0 directional, 1 point, 2 spot)
+uniform vec4 lightPositions[MAX_LIGHTS];
+// xyz: direction, w: range (0 for infinite)
+uniform vec4 lightDirections[MAX_LIGHTS];
+uniform vec3 lightColors[MAX_LIGHTS];
+// x: cone scale, y: cone offset
+uniform vec2 lightCones[MAX_LIGHTS];
+#ifndef IBL
+uniform vec3 ambientSky;
+uniform vec3 ambientGround;
+#endif
+#endif
+#ifdef SHADOWS
+varying vec4 fLightSpacePosition;
+
+uniform sampler2D shadowMap;
+// Index of the light casting the shadow, -1 for nodes that do not receive shadows.
+uniform int shadowLight;
+// x: texel size, y: depth bias
+uniform vec2 shadowParams;
+#endif
+#ifdef EMISSIVE
+uniform vec3 emissive;
+#endif
+#ifdef CLEARCOAT
+// x: clearcoat factor, y: clearcoat roughness
+uniform vec2 clearcoat;
+#endif
+#ifdef TRANSMISSION
+uniform float transmission;
+#endif
+
+#ifdef LIGHTING
+#ifdef BLINN_PHONG
+vec3 brdf(vec3 n, vec3 v, vec3 l, vec3 baseColor) {
+    float metallic = metallicRoughness.x;
+    float roughness = max(metallicRoughness.y, 0.05);
+    // Clamped, the exponent of smooth surfaces overflows mediump floats.
+    float shininess = min(2.0 / (roughness * roughness * roughness * roughness) - 2.0, 2048.0);
+
+    vec3 h = normalize(l + v);
+    float nDotL = max(dot(n, l), 0.0);
+    float nDotH = max(dot(n, h), 0.0);
+
+    vec3 diffuse = baseColor * (1.0 - metallic);
+    vec3 specular = mix(vec3(0.04), baseColor, metallic) * pow(nDotH, shininess);
+
+    return (diffuse + specular) * nDotL;
+}
+
+vec3 ambient(vec3 n, vec3 baseColor) {
+    return mix(ambientGround, ambientSky, n.y * 0.5 + 0.5) * baseColor;
+}
+#else
+vec3 brdf(vec3 n, vec3 v, vec3 l, vec3 baseColor) {
+    float metallic = metallicRoughness.x;
+    float alphaRoughness = metallicRoughness.y * metallicRoughness.y;
+    float alphaSquared = alphaRoughness * alphaRoughness;
+
+    vec3 h = normalize(l + v);
+    float nDotL = clamp(dot(n, l), 0.0, 1.0);
+    float nDotV = clamp(dot(n, v), 0.0001, 1.0);
+    float nDotH = clamp(dot(n, h), 0.0, 1.0);
+    float vDotH = clamp(dot(v, h), 0.0, 1.0);
+
+    vec3 f0 = mix(vec3(0.04), baseColor, metallic);
+    vec3 fresnel = f0 + (1.0 - f0) * pow(1.0 - vDotH, 5.0);
+
+    float denominator = nDotH * nDotH * (alphaSquared - 1.0) + 1.0;
+    float distribution = alphaSquared / (PI * denominator * denominator);
+    float visibility = 0.5 / max(
+        nDotL * sqrt(nDotV * nDotV * (1.0 - alphaSquared) + alphaSquared)
+            + nDotV * sqrt(nDotL * nDotL * (1.0 - alphaSquared) + alphaSquared),
+        0.0001
+    );
+
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * baseColor / PI;
+    vec3 specular = fresnel * distribution * visibility;
+
+    return (diffuse + specular) * nDotL;
+}
+
+#ifndef IBL
+// Constant ambient light, without an environment nothing tells where it comes from.
+vec3 ambient(vec3 n, vec3 baseColor) {
+    return (ambientSky + ambientGround) * 0.5 * baseColor;
+}
+#endif
+#endif
+
+#ifdef CLEARCOAT
+// Fresnel of the coat layer, which has an index of refraction of 1.5.
+float clearcoatFresnel(float cosTheta) {
+    return 0.04 + 0.96 * pow(1.0 - cosTheta, 5.0);
+}
+
+// GGX specular lobe of the coat layer. The roughness is floored so the
+// distribution stays in mediump range for the default roughness of 0.
+float clearcoatSpecular(vec3 n, vec3 v, vec3 l) {
+    float roughness = max(clearcoat.y, 0.1);
+    float alphaSquared = roughness * roughness * roughness * roughness;
+
+    vec3 h = normalize(l + v);
+    float nDotL = clamp(dot(n, l), 0.0, 1.0);
+    float nDotV = clamp(dot(n, v), 0.0001, 1.0);
+    float nDotH = clamp(dot(n, h), 0.0, 1.0);
+    float vDotH = clamp(dot(v, h), 0.0, 1.0);
+
+    float denominator = nDotH * nDotH * (alphaSquared - 1.0) + 1.0;
+    float distribution = alphaSquared / (PI * denominator * denominator);
+    float visibility = 0.5 / max(
+        nDotL * sqrt(nDotV * nDotV * (1.0 - alphaSquared) + alphaSquared)
+            + nDotV * sqrt(nDotL * nDotL * (1.0 - alphaSquared) + alphaSquared),
+        0.0001
+    );
+
+    return clearcoatFresnel(vDotH) * distribution * visibility * nDotL;
+}
+#endif
+
+#ifdef SHADOWS
+float shadowDepth(vec2 uv) {
+#ifdef SHADOW_PACKED_DEPTH
+    return dot(texture2D(shadowMap, uv), vec4(1.0, 1.0 / 255.0, 1.0 / 65025.0, 1.0 / 16581375.0));
+#else
+    return texture2D(shadowMap, uv).r;
+#endif
+}
+
+// 3x3 percentage closer filtering.
+float shadow() {
+    vec3 position = fLightSpacePosition.xyz / fLightSpacePosition.w * 0.5 + 0.5;
+    if (position.x < 0.0 || position.x > 1.0 || position.y < 0.0 || position.y > 1.0 || position.z > 1.0) {
+        return 1.0;
+    }
+
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec2 offset = vec2(float(x), float(y)) * shadowParams.x;
+            lit += step(position.z - shadowParams.y, shadowDepth(position.xy + offset));
+        }
+    }
+
+    return lit / 9.0;
+}
+#endif
+
+vec3 punctualLight(vec4 position, vec4 direction, vec3 color, vec2 cone, vec3 n, vec3 v, vec3 baseColor) {
+    vec3 l = -direction.xyz;
+    float attenuation = 1.0;
+
+    if (position.w > 0.5) {
+        vec3 toLight = position.xyz - fWorldPosition;
+        float distanceSquared = max(dot(toLight, toLight), 0.0001);
+        l = toLight * inversesqrt(distanceSquared);
+        attenuation = 1.0 / distanceSquared;
+
+        if (direction.w > 0.0) {
+            float ratio = distanceSquared / (direction.w * direction.w);
+            attenuation *= clamp(1.0 - ratio * ratio, 0.0, 1.0);
+        }
+        if (position.w > 1.5) {
+            float spot = clamp(dot(direction.xyz, -l) * cone.x + cone.y, 0.0, 1.0);
+            attenuation *= spot * spot;
+        }
+    }
+
+    vec3 lit = brdf(n, v, l, baseColor);
+#ifdef CLEARCOAT
+    // The coat reflects part of the light before it reaches the base layer.
+    float coatFresnel = clearcoat.x * clearcoatFresnel(clamp(dot(n, v), 0.0, 1.0));
+    lit = lit * (1.0 - coatFresnel) + clearcoat.x * clearcoatSpecular(n, v, l);
+#endif
+
+    return lit * color * attenuation;
+}
+#endif
+
+#ifdef IBL
+uniform samplerCube irradianceMap;
+uniform samplerCube specularMap;
+uniform sampler2D brdfLut;
+// x: intensity, y: mip level of the roughest prefiltered radiance
+uniform vec2 environmentParams;
+
+vec3 decodeRgbm(vec4 rgbm) {
+    return rgbm.rgb * rgbm.a * 8.0;
+}
+
+// Prefiltered radiance of the roughness the mip level stands for.
+vec3 specularRadiance(vec3 r, float level) {
+    level = clamp(level, 0.0, environmentParams.y);
+#ifdef TEXTURE_LOD
+    return decodeRgbm(textureCubeLodEXT(specularMap, r, level));
+#else
+    // Without EXT_shader_texture_lod the level can only bias the level picked from the
+    // screen space derivatives of r. That is close to the explicit level where the
+    // base level is magnified, as on flat and gently curved surfaces, and blurrier
+    // where the derivatives are large, as on strongly curved ones.
+    return decodeRgbm(textureCube(specularMap, r, level));
+#endif
+}
+
+vec3 imageBasedLight(vec3 n, vec3 v, vec3 baseColor) {
+    float metallic = metallicRoughness.x;
+    float roughness = metallicRoughness.y;
+    float nDotV = clamp(dot(n, v), 0.0001, 1.0);
+    vec3 f0 = mix(vec3(0.04), baseColor, metallic);
+    vec3 r = reflect(-v, n);
+    vec3 radiance = specularRadiance(r, roughness * environmentParams.y);
+    vec2 brdfTerm = texture2D(brdfLut, vec2(nDotV, roughness)).rg;
+    vec3 specular = radiance * (f0 * brdfTerm.x + brdfTerm.y);
+    vec3 diffuse = decodeRgbm(textureCube(irradianceMap, n)) * baseColor * (1.0 - metallic);
+
+    return (diffuse + specular) * environmentParams.x;
+}
+
+#ifdef CLEARCOAT
+// Prefiltered radiance reflected by the coat layer at its own roughness.
+vec3 clearcoatReflection(vec3 n, vec3 v) {
+    vec3 r = reflect(-v, n);
+
+    return specularRadiance(r, clearcoat.y * environmentParams.y) * environmentParams.x;
+}
+#endif
+#endif
 
 void main() {
-    // gl_FragColor = vec4(0.8, 0.0, 0.0, 1.0);
-    gl_FragColor = texture2D(uTexture, fTextureCoords);
+    vec4 baseColor = baseColorFactor;
+#ifdef HAS_BASE_COLOR_TEXTURE
+    baseColor *= texture2D(uTexture, fTextureCoords);
+#endif
+
+#ifdef UNLIT
+    gl_FragColor = baseColor;
+#else
+    vec3 color = baseColor.rgb;
+    float alpha = baseColor.a;
+
+#ifdef LIGHTING
+    vec3 n = normalize(fNormal);
+    vec3 v = normalize(cameraPosition - fWorldPosition);
+#endif
+
+#ifdef LIGHTING
+#ifdef CLEARCOAT
+    float coatFresnel = clearcoat.x * clearcoatFresnel(clamp(dot(n, v), 0.0, 1.0));
+#endif
+#if defined(IBL)
+    color = imageBasedLight(n, v, baseColor.rgb);
+#ifdef CLEARCOAT
+    color = color * (1.0 - coatFresnel) + coatFresnel * clearcoatReflection(n, v);
+#endif
+#else
+    // Scenes without any light keep showing the plain base color.
+    if (lightCount > 0) {
+        color = ambient(n, baseColor.rgb);
+#ifdef CLEARCOAT
+        color *= 1.0 - coatFresnel;
+#endif
+    }
+#endif
+    for (int i = 0; i < MAX_LIGHTS; i++) {
+        if (i >= lightCount) {
+            break;
+        }
+        vec3 contribution = punctualLight(
+            lightPositions[i],
+            lightDirections[i],
+            lightColors[i],
+            lightCones[i],
+            n,
+            v,
+            baseColor.rgb
+        );
+#ifdef SHADOWS
+        if (i == shadowLight) {
+            contribution *= shadow();
+        }
+#endif
+        color += contribution;
+    }
+#endif
+#ifdef TRANSMISSION
+    // No refraction yet, transmissive surfaces are blended with what is behind them.
+    alpha *= 1.0 - transmission;
+#endif
+#ifdef EMISSIVE
+    color += emissive;
+#endif
+
+    gl_FragColor = vec4(color, alpha);
+#endif
 }";
 
+/// Everything a primitive needs to be drawn with one glTF material. A primitive keeps
+/// one of these for its default material and for every `KHR_materials_variants` mapping.
+#[derive(Debug)]
+struct MeshShaderMaterial {
+    id: u32,
+    gltf_material: Option<usize>,
+    textures: MeshShaderTextures,
+    material: MeshMaterial,
+    texture_coord_set: usize,
+    program: Rc<MeshProgram>,
+    /// Attribute arrays and index buffer for `program`, where the backend supports
+    /// vertex array objects.
+    vertex_array: Option<VertexArray>,
+}
+
+impl MeshShaderMaterial {
+    #[allow(clippy::too_many_arguments)]
+    async fn from_gltf(
+        gl: &dyn Backend,
+        gltf_material: Option<usize>,
+        frame_buffers: &MeshShaderFrameBuffers,
+        gltf: &GlTf,
+        glb_buffer: &GlbBuffer,
+        texture_support: &CompressedTextureSupport,
+        lighting: LightingOptions,
+        programs: &mut ProgramCache,
+    ) -> Result<MeshShaderMaterial, JsValue> {
+        let material_definition = match gltf_material {
+            Some(index) => Some(
+                gltf.materials
+                    .get(index)
+                    .ok_or_else(|| JsValue::from_str("could not find material"))?,
+            ),
+            None => None,
+        };
+        if let Some(material) = material_definition {
+            let ignored = MeshShaderTextures::ignored_references(material);
+            if !ignored.is_empty() {
+                textures::warn(&format!(
+                    "material {} ignores {}, the mesh shader only samples baseColorTexture",
+                    material.name.as_deref().unwrap_or("without a name"),
+                    ignored.join(", ")
+                ));
+            }
+        }
+        let texture_coord_set = MeshShaderTextures::texture_coord_set(material_definition);
+        if texture_coord_set > 1 {
+            return Err(JsValue::from_str(&format!(
+                "texture coordinate set {} is not supported",
+                texture_coord_set
+            )));
+        }
+
+        let mut textures =
+            MeshShaderTextures::from_gltf(gl, gltf_material, gltf, glb_buffer, texture_support)
+                .await?;
+        let material = MeshMaterial::from_gltf(
+            material_definition,
+            textures.has_base_color(),
+            frame_buffers.normal.is_some(),
+        );
+
+        let program = programs.get(gl, material.features, lighting)?;
+        textures.locate(gl, &program.program);
+        let vertex_array = MeshShader::build_vertex_array(
+            gl,
+            frame_buffers,
+            &program.locations,
+            texture_coord_set,
+        )?;
+
+        Ok(MeshShaderMaterial {
+            id: state::next_id(),
+            gltf_material,
+            textures,
+            material,
+            texture_coord_set,
+            program,
+            vertex_array,
+        })
+    }
+
+    /// Deletes the textures and the vertex array, the program belongs to the cache.
+    fn delete(&self, gl: &dyn Backend) {
+        self.textures.delete(gl);
+        if let Some(vertex_array) = &self.vertex_array {
+            gl.delete_vertex_array(vertex_array);
+        }
+    }
+
+    /// Switches to the program for other lighting options, textures and buffers are kept.
+    /// The vertex array object is rebuilt as attribute locations may differ.
+    fn set_lighting(
+        &mut self,
+        gl: &dyn Backend,
+        frame_buffers: &MeshShaderFrameBuffers,
+        lighting: LightingOptions,
+        programs: &mut ProgramCache,
+    ) -> Result<(), JsValue> {
+        let program = programs.get(gl, self.material.features, lighting)?;
+        self.textures.locate(gl, &program.program);
+        if let Some(vertex_array) = self.vertex_array.take() {
+            gl.delete_vertex_array(&vertex_array);
+        }
+        self.vertex_array = MeshShader::build_vertex_array(
+            gl,
+            frame_buffers,
+            &program.locations,
+            self.texture_coord_set,
+        )?;
+        self.program = program;
+
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct MeshShader {
-    locations: MeshShaderLocations,
     frame_buffers: MeshShaderFrameBuffers,
-    textures: MeshShaderTextures,
-    program: WebGlProgram,
+    materials: Vec<MeshShaderMaterial>,
+    variant_materials: HashMap<usize, usize>,
+    active_material: usize,
 }
 
 impl MeshShader {
     pub(super) async fn from_gltf(
-        gl: &WebGlRenderingContext,
+        gl: &dyn Backend,
         primitive: &GlTfMeshPrimitive,
         gltf: &GlTf,
         glb_buffer: &GlbBuffer,
+        texture_support: &CompressedTextureSupport,
+        lighting: LightingOptions,
+        programs: &mut ProgramCache,
     ) -> Result<MeshShader, JsValue> {
-        let program = program::compile_to_program(&gl, V_SHADER, F_SHADER)?;
-        let locations = MeshShaderLocations::new(&gl, &program);
-        let frame_buffers = MeshShaderFrameBuffers::from_gltf(&gl, &primitive, &gltf, &glb_buffer)?;
-        let textures =
-            MeshShaderTextures::from_gltf(&gl, &program, &primitive, &gltf, &glb_buffer).await?;
+        let frame_buffers = MeshShaderFrameBuffers::from_gltf(gl, primitive, gltf, glb_buffer)?;
+        let mut materials = vec![
+            MeshShaderMaterial::from_gltf(
+                gl,
+                primitive.material,
+                &frame_buffers,
+                gltf,
+                glb_buffer,
+                texture_support,
+                lighting,
+                programs,
+            )
+            .await?,
+        ];
+
+        let mappings = primitive
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.materials_variants.as_ref())
+            .map(|variants| variants.mappings.as_slice())
+            .unwrap_or(&[]);
+        let mut variant_materials = HashMap::new();
+        for mapping in mappings {
+            let position = materials
+                .iter()
+                .position(|material| material.gltf_material == Some(mapping.material));
+            let index = match position {
+                Some(index) => index,
+                None => {
+                    materials.push(
+                        MeshShaderMaterial::from_gltf(
+                            gl,
+                            Some(mapping.material),
+                            &frame_buffers,
+                            gltf,
+                            glb_buffer,
+                            texture_support,
+                            lighting,
+                            programs,
+                        )
+                        .await?,
+                    );
+                    materials.len() - 1
+                }
+            };
+
+            for variant in &mapping.variants {
+                variant_materials.insert(*variant, index);
+            }
+        }
 
         Ok(MeshShader {
-            locations,
             frame_buffers,
-            textures,
-            program,
+            materials,
+            variant_materials,
+            active_material: 0,
         })
     }
 
+    /// Deletes the buffers and textures of the primitive, it must not render afterwards.
+    pub(super) fn delete(&self, gl: &dyn Backend) {
+        for material in &self.materials {
+            material.delete(gl);
+        }
+        self.frame_buffers.delete(gl);
+    }
+
+    /// Switches to the material mapped to `variant`, primitives without a mapping for it
+    /// (and `None`) fall back to their default material.
+    pub(super) fn activate_variant(&mut self, variant: Option<usize>) {
+        self.active_material = variant
+            .and_then(|variant| self.variant_materials.get(&variant))
+            .copied()
+            .unwrap_or(0);
+    }
+
+    pub(super) fn set_lighting(
+        &mut self,
+        gl: &dyn Backend,
+        lighting: LightingOptions,
+        programs: &mut ProgramCache,
+    ) -> Result<(), JsValue> {
+        for material in &mut self.materials {
+            material.set_lighting(gl, &self.frame_buffers, lighting, programs)?;
+        }
+
+        Ok(())
+    }
+
+    /// The primitive as item of the render queue, sorted by its active material.
+    pub(super) fn draw_item(&self, node: usize, primitive: usize, depth: f32) -> DrawItem {
+        let material = &self.materials[self.active_material];
+
+        DrawItem {
+            pass: if material.material.features.transmission {
+                RenderPass::Transparent
+            } else {
+                RenderPass::Opaque
+            },
+            program: material.program.id,
+            material: material.id,
+            texture: material.textures.base_color_id(),
+            depth,
+            node,
+            primitive,
+        }
+    }
+
+    /// Draws the primitive, state that `state` already holds is not set again. Camera,
+    /// lights and environment are set once per program and frame, material uniforms
+    /// when the program last drew another material.
     pub(super) fn render(
         &self,
-        gl: &WebGlRenderingContext,
-        descriptor: &MeshRenderDescriptor,
+        gl: &dyn Backend,
+        descriptor: &MeshRenderDescriptor<'_>,
+        state: &mut StateTracker,
     ) -> Result<(), JsValue> {
-        gl.use_program(Some(&self.program));
+        let material = &self.materials[self.active_material];
+        let program = &material.program;
+        let locations = &program.locations;
+        let counters = descriptor.counters;
 
-        MeshShader::enable_attribute(
-            &gl,
-            &self.frame_buffers.position,
-            self.locations.attribute.position as u32,
-        )?;
+        state.use_program(gl, program.id, &program.program, counters);
+        if state.needs_frame_uniforms(program.id) {
+            MeshShader::set_frame_uniforms(gl, locations, descriptor);
+        }
 
-        if let Some(buffer) = &self.frame_buffers.normal {
-            MeshShader::enable_attribute(&gl, &buffer, self.locations.attribute.normal as u32)?;
+        match &material.vertex_array {
+            Some(vertex_array) => state.bind_vertex_array(gl, material.id, Some(vertex_array)),
+            None => {
+                state.bind_vertex_array(gl, 0, None);
+                let attributes = MeshShader::attributes(
+                    &self.frame_buffers,
+                    locations,
+                    material.texture_coord_set,
+                );
+                let mut enabled = [0; 3];
+                let mut enabled_count = 0;
+                for (buffer, location) in &attributes {
+                    if buffer.is_some() && *location >= 0 {
+                        enabled[enabled_count] = *location as u32;
+                        enabled_count += 1;
+                    }
+                }
+                state.enable_attributes(gl, &enabled[..enabled_count]);
+                for (buffer, location) in &attributes {
+                    if let Some(buffer) = buffer.filter(|_| *location >= 0) {
+                        MeshShader::point_attribute(gl, buffer, *location as u32)?;
+                    }
+                }
+            }
         }
 
-        if let Some(buffer) = &self.frame_buffers.texture_coord {
-            MeshShader::enable_attribute(
-                &gl,
-                &buffer,
-                self.locations.attribute.texture_coord as u32,
-            )?;
+        if let Some(shadow_map) = descriptor.shadow_map {
+            state.bind_texture(
+                gl,
+                SHADOW_TEXTURE_UNIT,
+                WebGlRenderingContext::TEXTURE_2D,
+                shadow_map.id(),
+                shadow_map.texture(),
+                counters,
+            );
+        }
+        if let Some(environment) = descriptor.environment {
+            environment.bind(gl, state, counters);
+        }
+        material.textures.bind(gl, state, counters);
+        if state.needs_material(program.id, material.id) {
+            material.textures.set_uniforms(gl);
+            material.material.bind(gl, &locations.uniform);
         }
 
-        gl.uniform_matrix4fv_with_f32_array(
-            self.locations.uniform.model_matrix.as_ref(),
-            false,
+        gl.uniform_matrix4fv(
+            locations.uniform.model_matrix.as_ref(),
             &descriptor.model_matrix,
         );
-        gl.uniform_matrix4fv_with_f32_array(
-            self.locations.uniform.view_matrix.as_ref(),
-            false,
+        gl.uniform_matrix3fv(
+            locations.uniform.normal_matrix.as_ref(),
+            &descriptor.normal_matrix,
+        );
+        gl.uniform1i(
+            locations.uniform.shadow_light.as_ref(),
+            descriptor.shadow_light,
+        );
+
+        state.set_blend(gl, material.material.features.transmission);
+        match material.vertex_array {
+            Some(_) => self.draw_indexed(gl, counters),
+            None => self.draw(gl, counters),
+        }
+
+        Ok(())
+    }
+
+    fn set_frame_uniforms(
+        gl: &dyn Backend,
+        locations: &MeshShaderLocations,
+        descriptor: &MeshRenderDescriptor<'_>,
+    ) {
+        gl.uniform_matrix4fv(
+            locations.uniform.view_matrix.as_ref(),
             &descriptor.view_matrix,
         );
-        gl.uniform_matrix4fv_with_f32_array(
-            self.locations.uniform.projection_matrix.as_ref(),
-            false,
+        gl.uniform_matrix4fv(
+            locations.uniform.projection_matrix.as_ref(),
             &descriptor.projection_matrix,
         );
+        gl.uniform3fv(
+            locations.uniform.camera_position.as_ref(),
+            &descriptor.camera_position,
+        );
+
+        let lights = descriptor.lights;
+        gl.uniform1i(locations.uniform.light_count.as_ref(), lights.count);
+        if lights.count > 0 {
+            gl.uniform4fv(
+                locations.uniform.light_positions.as_ref(),
+                &lights.positions,
+            );
+            gl.uniform4fv(
+                locations.uniform.light_directions.as_ref(),
+                &lights.directions,
+            );
+            gl.uniform3fv(locations.uniform.light_colors.as_ref(), &lights.colors);
+            gl.uniform2fv(locations.uniform.light_cones.as_ref(), &lights.cones);
+            gl.uniform3fv(locations.uniform.ambient_sky.as_ref(), &lights.ambient_sky);
+            gl.uniform3fv(
+                locations.uniform.ambient_ground.as_ref(),
+                &lights.ambient_ground,
+            );
+        }
+
+        if let Some(shadow_map) = descriptor.shadow_map {
+            gl.uniform1i(
+                locations.uniform.shadow_map.as_ref(),
+                SHADOW_TEXTURE_UNIT as i32,
+            );
+            gl.uniform_matrix4fv(
+                locations.uniform.light_space_matrix.as_ref(),
+                shadow_map.light_space_matrix(),
+            );
+            gl.uniform2fv(
+                locations.uniform.shadow_params.as_ref(),
+                &shadow_map.params(),
+            );
+        }
+
+        if let Some(environment) = descriptor.environment {
+            gl.uniform1i(
+                locations.uniform.irradiance_map.as_ref(),
+                IRRADIANCE_TEXTURE_UNIT as i32,
+            );
+            gl.uniform1i(
+                locations.uniform.specular_map.as_ref(),
+                SPECULAR_TEXTURE_UNIT as i32,
+            );
+            gl.uniform1i(
+                locations.uniform.brdf_lut.as_ref(),
+                BRDF_LUT_TEXTURE_UNIT as i32,
+            );
+            gl.uniform2fv(
+                locations.uniform.environment_params.as_ref(),
+                &environment.params(),
+            );
+        }
+    }
+}
+
+impl MeshShader {
+    /// Draws the primitive into the shadow map, only positions are needed.
+    pub(super) fn render_depth(
+        &self,
+        gl: &dyn Backend,
+        position_location: u32,
+        counters: &FrameCounters,
+    ) -> Result<(), JsValue> {
+        MeshShader::enable_attribute(gl, &self.frame_buffers.position, position_location)?;
+        self.draw(gl, counters);
+
+        Ok(())
+    }
+
+    /// Draws the primitive with the debug surface program, attributes the primitive
+    /// does not have keep their constant default.
+    pub(super) fn render_debug(
+        &self,
+        gl: &dyn Backend,
+        attributes: &DebugAttributes,
+        counters: &FrameCounters,
+    ) -> Result<(), JsValue> {
+        let texture_coord_set = self.materials[self.active_material].texture_coord_set;
+        for (buffer, location) in &[
+            (Some(&self.frame_buffers.position), attributes.position),
+            (self.frame_buffers.normal.as_ref(), attributes.normal),
+            (
+                self.frame_buffers.texture_coord(texture_coord_set),
+                attributes.texture_coord,
+            ),
+        ] {
+            match (buffer, *location) {
+                (_, location) if location < 0 => {}
+                (Some(buffer), location) => {
+                    MeshShader::enable_attribute(gl, buffer, location as u32)?
+                }
+                (None, location) => gl.disable_vertex_attrib_array(location as u32),
+            }
+        }
+        self.draw(gl, counters);
+
+        Ok(())
+    }
+
+    pub(super) fn build_wireframe(&mut self, gl: &dyn Backend) -> Result<(), JsValue> {
+        self.frame_buffers.build_wireframe(gl)
+    }
+
+    /// Draws the triangle edges as lines, once `build_wireframe` created them.
+    pub(super) fn render_wireframe(
+        &self,
+        gl: &dyn Backend,
+        position_location: u32,
+        counters: &FrameCounters,
+    ) -> Result<(), JsValue> {
+        if let Some(wireframe) = &self.frame_buffers.wireframe {
+            MeshShader::enable_attribute(gl, &self.frame_buffers.position, position_location)?;
+            gl.bind_buffer(
+                WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+                Some(&wireframe.buffer),
+            );
+            gl.draw_elements(
+                WebGlRenderingContext::LINES,
+                wireframe.count,
+                wireframe.component_type,
+                0,
+            );
+            counters.draw(0);
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        self.frame_buffers.bounds()
+    }
+
+    fn draw(&self, gl: &dyn Backend, counters: &FrameCounters) {
+        gl.bind_buffer(
+            WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+            Some(&self.frame_buffers.index.buffer),
+        );
+        self.draw_indexed(gl, counters);
+    }
 
-        let byte_offset = match self.frame_buffers.index.accessor.byte_offset {
-            Some(byte_offset) => byte_offset,
-            None => 0,
-        } as i32;
+    /// Draws with the index buffer that is already bound.
+    fn draw_indexed(&self, gl: &dyn Backend, counters: &FrameCounters) {
+        let index = &self.frame_buffers.index;
+        let byte_offset = index.accessor.byte_offset.unwrap_or(0) as i32;
 
-        gl.draw_elements_with_i32(
+        gl.draw_elements(
             WebGlRenderingContext::TRIANGLES,
-            self.frame_buffers.index.accessor.count as i32,
-            self.frame_buffers.index.accessor.component_type,
+            index.accessor.count as i32,
+            index.accessor.component_type,
             byte_offset,
         );
+        counters.draw(index.accessor.count as u32 / 3);
+    }
 
-        Ok(())
+    /// Buffers of the mesh shader attributes with their locations in the program.
+    fn attributes<'a>(
+        frame_buffers: &'a MeshShaderFrameBuffers,
+        locations: &MeshShaderLocations,
+        texture_coord_set: usize,
+    ) -> [(Option<&'a MeshShaderFrameBuffer>, i32); 3] {
+        [
+            (Some(&frame_buffers.position), locations.attribute.position),
+            (frame_buffers.normal.as_ref(), locations.attribute.normal),
+            (
+                frame_buffers.texture_coord(texture_coord_set),
+                locations.attribute.texture_coord,
+            ),
+        ]
+    }
+
+    /// Records the attribute arrays and the index buffer in a vertex array object, so
+    /// drawing only has to bind it.
+    fn build_vertex_array(
+        gl: &dyn Backend,
+        frame_buffers: &MeshShaderFrameBuffers,
+        locations: &MeshShaderLocations,
+        texture_coord_set: usize,
+    ) -> Result<Option<VertexArray>, JsValue> {
+        let vertex_array = match gl.create_vertex_array() {
+            Some(vertex_array) => vertex_array,
+            None => return Ok(None),
+        };
+
+        gl.bind_vertex_array(Some(&vertex_array));
+        let result = MeshShader::attributes(frame_buffers, locations, texture_coord_set)
+            .iter()
+            .filter(|(_, location)| *location >= 0)
+            .try_for_each(|(buffer, location)| match buffer {
+                Some(buffer) => MeshShader::enable_attribute(gl, buffer, *location as u32),
+                None => Ok(()),
+            });
+        gl.bind_buffer(
+            WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+            Some(&frame_buffers.index.buffer),
+        );
+        gl.bind_vertex_array(None);
+
+        match result {
+            Ok(()) => Ok(Some(vertex_array)),
+            Err(error) => {
+                gl.delete_vertex_array(&vertex_array);
+                Err(error)
+            }
+        }
     }
-}
 
-impl MeshShader {
     fn enable_attribute(
-        gl: &WebGlRenderingContext,
+        gl: &dyn Backend,
         frame_buffer: &MeshShaderFrameBuffer,
         location: u32,
     ) -> Result<(), JsValue> {
-        let byte_stride = match frame_buffer.buffer_view.byte_stride {
-            Some(byte_stride) => byte_stride,
-            None => 0,
-        } as i32;
-        let byte_offset = match frame_buffer.accessor.byte_offset {
-            Some(byte_stride) => byte_stride,
-            None => 0,
-        } as i32;
+        gl.enable_vertex_attrib_array(location);
+        MeshShader::point_attribute(gl, frame_buffer, location)
+    }
+
+    fn point_attribute(
+        gl: &dyn Backend,
+        frame_buffer: &MeshShaderFrameBuffer,
+        location: u32,
+    ) -> Result<(), JsValue> {
+        let byte_stride = frame_buffer.byte_stride as i32;
+        let byte_offset = frame_buffer.accessor.byte_offset.unwrap_or(0) as i32;
         let size = MeshShader::accessor_type_2_size(&frame_buffer.accessor)?;
 
         gl.bind_buffer(
             WebGlRenderingContext::ARRAY_BUFFER,
             Some(&frame_buffer.buffer),
         );
-        gl.enable_vertex_attrib_array(location);
-        gl.vertex_attrib_pointer_with_i32(
+        gl.vertex_attrib_pointer(
             location,
             size,
             frame_buffer.accessor.component_type,
-            false,
+            frame_buffer.accessor.normalized,
             byte_stride,
             byte_offset,
         );
@@ -173,6 +991,7 @@ impl MeshShader {
             "SCALAR" => Ok(1),
             "VEC2" => Ok(2),
             "VEC3" => Ok(3),
+            "VEC4" => Ok(4),
             _ => Err(JsValue::from_str("accessor type not supported")),
         }
     }
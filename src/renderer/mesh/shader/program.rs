@@ -1,36 +1,42 @@
+use crate::renderer::backend::{Backend, Program, Shader};
 use wasm_bindgen::prelude::*;
-use web_sys::{WebGlProgram, WebGlRenderingContext, WebGlShader};
+use web_sys::WebGlRenderingContext;
 
-pub(super) fn compile_to_program(
-    gl: &web_sys::WebGlRenderingContext,
+pub(crate) fn compile_to_program(
+    gl: &dyn Backend,
     vertex_shader: &str,
     fragment_shader: &str,
-) -> Result<WebGlProgram, JsValue> {
-    let v_shader = compile_vertex_shader(&gl, vertex_shader)?;
-    let f_shader = compile_fragment_shader(&gl, fragment_shader)?;
+) -> Result<Program, JsValue> {
+    let v_shader = compile_vertex_shader(gl, vertex_shader)?;
+    let f_shader = match compile_fragment_shader(gl, fragment_shader) {
+        Ok(f_shader) => f_shader,
+        Err(error) => {
+            gl.delete_shader(&v_shader);
+            return Err(error);
+        }
+    };
 
-    link_program(&gl, &v_shader, &f_shader)
+    let program = link_program(gl, &v_shader, &f_shader);
+    // The linked program keeps the compiled code, the shader objects are not needed.
+    gl.delete_shader(&v_shader);
+    gl.delete_shader(&f_shader);
+
+    program
 }
 
-fn compile_vertex_shader(
-    gl: &web_sys::WebGlRenderingContext,
-    source: &str,
-) -> Result<WebGlShader, JsValue> {
-    compile_shader(&gl, WebGlRenderingContext::VERTEX_SHADER, source)
+fn compile_vertex_shader(gl: &dyn Backend, source: &str) -> Result<Shader, JsValue> {
+    compile_shader(gl, WebGlRenderingContext::VERTEX_SHADER, source)
 }
 
-fn compile_fragment_shader(
-    gl: &web_sys::WebGlRenderingContext,
-    source: &str,
-) -> Result<WebGlShader, JsValue> {
-    compile_shader(&gl, WebGlRenderingContext::FRAGMENT_SHADER, source)
+fn compile_fragment_shader(gl: &dyn Backend, source: &str) -> Result<Shader, JsValue> {
+    compile_shader(gl, WebGlRenderingContext::FRAGMENT_SHADER, source)
 }
 
 fn link_program(
-    gl: &WebGlRenderingContext,
-    vert_shader: &WebGlShader,
-    frag_shader: &WebGlShader,
-) -> Result<WebGlProgram, JsValue> {
+    gl: &dyn Backend,
+    vert_shader: &Shader,
+    frag_shader: &Shader,
+) -> Result<Program, JsValue> {
     let program = gl
         .create_program()
         .ok_or_else(|| JsValue::from("Unable to create shader object"))?;
@@ -38,42 +44,38 @@ fn link_program(
     gl.attach_shader(&program, vert_shader);
     gl.attach_shader(&program, frag_shader);
     gl.link_program(&program);
+    gl.detach_shader(&program, vert_shader);
+    gl.detach_shader(&program, frag_shader);
 
-    if gl
-        .get_program_parameter(&program, WebGlRenderingContext::LINK_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
+    if gl.program_linked(&program) {
         Ok(program)
     } else {
-        Err(gl
-            .get_program_info_log(&program)
-            .unwrap_or_else(|| String::from("Unknown error creating program object"))
-            .into())
+        let error = gl
+            .program_info_log(&program)
+            .unwrap_or_else(|| String::from("Unknown error creating program object"));
+        gl.delete_program(&program);
+        Err(error.into())
     }
 }
 
 fn compile_shader(
-    context: &WebGlRenderingContext,
+    context: &dyn Backend,
     shader_type: u32,
     source: &str,
-) -> Result<WebGlShader, JsValue> {
+) -> Result<Shader, JsValue> {
     let shader = context
         .create_shader(shader_type)
         .ok_or_else(|| JsValue::from("Unable to create shader object"))?;
     context.shader_source(&shader, source);
     context.compile_shader(&shader);
 
-    if context
-        .get_shader_parameter(&shader, WebGlRenderingContext::COMPILE_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
+    if context.shader_compiled(&shader) {
         Ok(shader)
     } else {
-        Err(context
-            .get_shader_info_log(&shader)
-            .unwrap_or_else(|| String::from("Unknown error creating shader"))
-            .into())
+        let error = context
+            .shader_info_log(&shader)
+            .unwrap_or_else(|| String::from("Unknown error creating shader"));
+        context.delete_shader(&shader);
+        Err(error.into())
     }
 }
@@ -1,23 +1,54 @@
-use crate::definitions::gltf::{GlTf, GlTfMeshPrimitive};
+use crate::definitions::gltf::{
+    GlTf, GlTfMaterial, GlTfSampler, GlTfTexture, GlTfTextureTransform, TextureReference,
+};
 use crate::loader::glb::GlbBuffer;
+use crate::loader::ktx2;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::loader::png;
+use crate::renderer::backend::{Backend, Program, Texture, UniformLocation};
+use crate::renderer::state::{self, StateTracker};
+use crate::renderer::stats::FrameCounters;
+use crate::renderer::texture_format::CompressedTextureSupport;
 use js_sys::Promise;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{
-    HtmlImageElement, WebGlProgram, WebGlRenderingContext, WebGlTexture, WebGlUniformLocation,
-};
+#[cfg(target_arch = "wasm32")]
+use web_sys::HtmlImageElement;
+use web_sys::WebGlRenderingContext;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(module = "/assets/ffi/utility.js")]
 extern "C" {
     fn load_image(data: js_sys::Uint8Array) -> Promise;
 }
 
+#[wasm_bindgen(module = "/assets/ffi/basis.js")]
+extern "C" {
+    fn set_transcoder_url(url: &str);
+    fn transcode_ktx2(data: js_sys::Uint8Array, format: u32) -> Promise;
+}
+
+/// Sets where `basis_transcoder.js` is loaded from, the transcoder is not bundled.
+pub(crate) fn set_basis_transcoder_url(url: &str) {
+    set_transcoder_url(url);
+}
+
+/// Logs to the browser console, outside of the browser warnings are dropped.
+pub(super) fn warn(message: &str) {
+    #[cfg(target_arch = "wasm32")]
+    web_sys::console::warn_1(&JsValue::from_str(message));
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = message;
+}
+
 #[derive(Debug)]
 struct MeshShaderTexture {
-    image: HtmlImageElement,
-    buffer: WebGlTexture,
-    location: Option<WebGlUniformLocation>,
+    id: u32,
+    buffer: Texture,
+    location: Option<UniformLocation>,
+    transform: [f32; 9],
+    transform_location: Option<UniformLocation>,
 }
 
 #[derive(Debug)]
@@ -27,76 +58,41 @@ pub(super) struct MeshShaderTextures {
 
 impl MeshShaderTextures {
     pub(super) async fn from_gltf(
-        gl: &WebGlRenderingContext,
-        program: &WebGlProgram,
-        primitive: &GlTfMeshPrimitive,
+        gl: &dyn Backend,
+        material: Option<usize>,
         gltf: &GlTf,
         glb_buffer: &GlbBuffer,
+        texture_support: &CompressedTextureSupport,
     ) -> Result<MeshShaderTextures, JsValue> {
-        let base_color = match primitive.material {
+        let base_color = match material {
             Some(index) => {
                 let material = gltf
                     .materials
                     .get(index)
-                    .ok_or(JsValue::from_str("could not find texture"))?;
+                    .ok_or_else(|| JsValue::from_str("could not find texture"))?;
 
                 match &material.pbr_metallic_roughness.base_color_texture {
                     Some(reference) => {
                         let texture = gltf
                             .textures
                             .get(reference.index)
-                            .ok_or(JsValue::from_str("could not find texture"))?;
-                        let image = gltf
-                            .images
-                            .get(texture.source)
-                            .ok_or(JsValue::from_str("could not find texture image"))?;
-
-                        match image.buffer_view {
-                            Some(index) => {
-                                let buffer_view = gltf
-                                    .buffer_views
-                                    .get(index)
-                                    .ok_or("could not find texture image buffer view")?;
-
-                                let byte_offset = glb_buffer.byte_offset + buffer_view.byte_offset;
-                                let length = buffer_view.byte_length;
-
-                                let data_array =
-                                    js_sys::Uint8Array::new_with_byte_offset_and_length(
-                                        &glb_buffer.bin,
-                                        byte_offset,
-                                        length,
-                                    );
-                                let mut options = web_sys::BlobPropertyBag::new();
-                                options.type_("image/png");
-
-                                let texture_image = JsFuture::from(load_image(data_array))
-                                    .await?
-                                    .dyn_into::<HtmlImageElement>()?;
-
-                                let texture_buffer = gl.create_texture().unwrap();
-                                gl.bind_texture(
-                                    WebGlRenderingContext::TEXTURE_2D,
-                                    Some(&texture_buffer),
-                                );
-                                gl.tex_image_2d_with_u32_and_u32_and_image(
-                                    WebGlRenderingContext::TEXTURE_2D,
-                                    0,
-                                    WebGlRenderingContext::RGBA as i32,
-                                    WebGlRenderingContext::RGBA,
-                                    WebGlRenderingContext::UNSIGNED_BYTE,
-                                    &texture_image,
-                                )?;
-                                gl.generate_mipmap(WebGlRenderingContext::TEXTURE_2D);
-
-                                Some(MeshShaderTexture {
-                                    buffer: texture_buffer,
-                                    image: texture_image,
-                                    location: gl.get_uniform_location(&program, "uTexture"),
-                                })
-                            }
-                            None => None,
-                        }
+                            .ok_or_else(|| JsValue::from_str("could not find texture"))?;
+
+                        MeshShaderTextures::load_texture(
+                            gl,
+                            texture,
+                            gltf,
+                            glb_buffer,
+                            texture_support,
+                        )
+                        .await?
+                        .map(|buffer| MeshShaderTexture {
+                            id: state::next_id(),
+                            buffer,
+                            location: None,
+                            transform: MeshShaderTextures::texture_transform(reference),
+                            transform_location: None,
+                        })
                     }
                     None => None,
                 }
@@ -106,4 +102,550 @@ impl MeshShaderTextures {
 
         Ok(MeshShaderTextures { base_color })
     }
+
+    pub(super) fn has_base_color(&self) -> bool {
+        self.base_color.is_some()
+    }
+
+    /// Id of the base color texture, 0 without one.
+    pub(super) fn base_color_id(&self) -> u32 {
+        self.base_color
+            .as_ref()
+            .map(|texture| texture.id)
+            .unwrap_or(0)
+    }
+
+    /// Looks up the sampler and transform uniforms once the permutation matching the
+    /// loaded textures has been compiled.
+    pub(super) fn locate(&mut self, gl: &dyn Backend, program: &Program) {
+        if let Some(texture) = &mut self.base_color {
+            texture.location = gl.get_uniform_location(program, "uTexture");
+            texture.transform_location = gl.get_uniform_location(program, "textureTransform");
+        }
+    }
+
+    pub(super) fn delete(&self, gl: &dyn Backend) {
+        if let Some(texture) = &self.base_color {
+            gl.delete_texture(&texture.buffer);
+        }
+    }
+
+    pub(super) fn bind(
+        &self,
+        gl: &dyn Backend,
+        state: &mut StateTracker,
+        counters: &FrameCounters,
+    ) {
+        if let Some(texture) = &self.base_color {
+            state.bind_texture(
+                gl,
+                0,
+                WebGlRenderingContext::TEXTURE_2D,
+                texture.id,
+                &texture.buffer,
+                counters,
+            );
+        }
+    }
+
+    /// Sets the sampler and transform uniforms, only needed when the program last drew
+    /// another material.
+    pub(super) fn set_uniforms(&self, gl: &dyn Backend) {
+        if let Some(texture) = &self.base_color {
+            gl.uniform1i(texture.location.as_ref(), 0);
+            gl.uniform_matrix3fv(texture.transform_location.as_ref(), &texture.transform);
+        }
+    }
+
+    /// Names of the texture references of `material` the mesh shader does not sample.
+    /// Neither their textures nor their `KHR_texture_transform` affect the rendering.
+    pub(super) fn ignored_references(material: &GlTfMaterial) -> Vec<&'static str> {
+        let pbr = &material.pbr_metallic_roughness;
+        let extensions = material.extensions.as_ref();
+        let clearcoat = extensions.and_then(|extensions| extensions.clearcoat.as_ref());
+        let transmission = extensions.and_then(|extensions| extensions.transmission.as_ref());
+        let references = [
+            ("metallicTexture", pbr.metallic_texture.is_some()),
+            ("roughnessTexture", pbr.roughness_texture.is_some()),
+            (
+                "clearcoatTexture",
+                clearcoat.is_some_and(|clearcoat| clearcoat.clearcoat_texture.is_some()),
+            ),
+            (
+                "clearcoatRoughnessTexture",
+                clearcoat.is_some_and(|clearcoat| clearcoat.clearcoat_roughness_texture.is_some()),
+            ),
+            (
+                "clearcoatNormalTexture",
+                clearcoat.is_some_and(|clearcoat| clearcoat.clearcoat_normal_texture.is_some()),
+            ),
+            (
+                "transmissionTexture",
+                transmission
+                    .is_some_and(|transmission| transmission.transmission_texture.is_some()),
+            ),
+        ];
+
+        references
+            .iter()
+            .filter(|(_, present)| *present)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// Texture coordinate set sampled by the base color texture, `KHR_texture_transform`
+    /// may override the one of the texture reference.
+    pub(super) fn texture_coord_set(material: Option<&GlTfMaterial>) -> usize {
+        material
+            .and_then(|material| material.pbr_metallic_roughness.base_color_texture.as_ref())
+            .map(|reference| {
+                MeshShaderTextures::transform_extension(reference)
+                    .and_then(|transform| transform.tex_coord)
+                    .or(reference.tex_coord)
+                    .unwrap_or(0)
+            })
+            .unwrap_or(0)
+    }
+}
+
+impl MeshShaderTextures {
+    fn transform_extension(reference: &TextureReference) -> Option<&GlTfTextureTransform> {
+        reference
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.texture_transform.as_ref())
+    }
+
+    /// Column major UV matrix of `KHR_texture_transform`, translation * rotation * scale.
+    fn texture_transform(reference: &TextureReference) -> [f32; 9] {
+        let transform = MeshShaderTextures::transform_extension(reference);
+        let [offset_x, offset_y] = transform
+            .and_then(|transform| transform.offset)
+            .unwrap_or([0., 0.]);
+        let rotation = transform
+            .and_then(|transform| transform.rotation)
+            .unwrap_or(0.);
+        let [scale_x, scale_y] = transform
+            .and_then(|transform| transform.scale)
+            .unwrap_or([1., 1.]);
+        let (sin, cos) = rotation.sin_cos();
+
+        [
+            scale_x * cos,
+            -scale_x * sin,
+            0.,
+            scale_y * sin,
+            scale_y * cos,
+            0.,
+            offset_x,
+            offset_y,
+            1.,
+        ]
+    }
+
+    /// Prefers the `KHR_texture_basisu` source of a texture over its PNG fallback.
+    async fn load_texture(
+        gl: &dyn Backend,
+        texture: &GlTfTexture,
+        gltf: &GlTf,
+        glb_buffer: &GlbBuffer,
+        texture_support: &CompressedTextureSupport,
+    ) -> Result<Option<Texture>, JsValue> {
+        let basisu_source = texture
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.texture_basisu.as_ref())
+            .map(|basisu| basisu.source);
+        let source = basisu_source
+            .or(texture.source)
+            .ok_or_else(|| JsValue::from_str("texture has no image source"))?;
+        let image = gltf
+            .images
+            .get(source)
+            .ok_or_else(|| JsValue::from_str("could not find texture image"))?;
+        let sampler = match texture.sampler {
+            Some(index) => Some(
+                gltf.samplers
+                    .get(index)
+                    .ok_or_else(|| JsValue::from_str("could not find texture sampler"))?,
+            ),
+            None => None,
+        };
+
+        let data = match image.buffer_view {
+            Some(index) => {
+                let buffer_view = gltf
+                    .buffer_views
+                    .get(index)
+                    .ok_or("could not find texture image buffer view")?;
+                glb_buffer.view(buffer_view)?
+            }
+            None => return Ok(None),
+        };
+
+        let (texture_buffer, mipmaps) =
+            if basisu_source.is_some() || image.mime_type.as_deref() == Some("image/ktx2") {
+                MeshShaderTextures::load_ktx2(gl, data, texture_support).await?
+            } else {
+                let texture_buffer = MeshShaderTextures::load_image(gl, data).await?;
+                gl.generate_mipmap(WebGlRenderingContext::TEXTURE_2D);
+                (texture_buffer, true)
+            };
+        if let Err(error) = MeshShaderTextures::apply_sampler(gl, sampler, mipmaps) {
+            gl.delete_texture(&texture_buffer);
+            return Err(error);
+        }
+
+        Ok(Some(texture_buffer))
+    }
+
+    /// Sets the wrap modes and filters of `sampler` on the bound texture, the glTF
+    /// defaults without one. Textures without mipmaps can not be sampled with a mipmap
+    /// filter, they fall back to filtering the base level the same way.
+    fn apply_sampler(
+        gl: &dyn Backend,
+        sampler: Option<&GlTfSampler>,
+        mipmaps: bool,
+    ) -> Result<(), JsValue> {
+        let default = GlTfSampler::default();
+        let sampler = sampler.unwrap_or(&default);
+        let invalid = |name: &str, value: u32| {
+            JsValue::from_str(&format!("invalid sampler {} {:#x}", name, value))
+        };
+
+        let wrap = |name, value| match value {
+            Some(
+                value @ (WebGlRenderingContext::REPEAT
+                | WebGlRenderingContext::CLAMP_TO_EDGE
+                | WebGlRenderingContext::MIRRORED_REPEAT),
+            ) => Ok(value),
+            Some(value) => Err(invalid(name, value)),
+            None => Ok(WebGlRenderingContext::REPEAT),
+        };
+        let wrap_s = wrap("wrapS", sampler.wrap_s)?;
+        let wrap_t = wrap("wrapT", sampler.wrap_t)?;
+        let mag_filter = match sampler.mag_filter {
+            Some(value @ (WebGlRenderingContext::NEAREST | WebGlRenderingContext::LINEAR)) => {
+                Some(value)
+            }
+            Some(value) => return Err(invalid("magFilter", value)),
+            None => None,
+        };
+        let min_filter = match sampler.min_filter {
+            Some(value) => {
+                let base_level_filter = match value {
+                    WebGlRenderingContext::NEAREST
+                    | WebGlRenderingContext::NEAREST_MIPMAP_NEAREST
+                    | WebGlRenderingContext::NEAREST_MIPMAP_LINEAR => {
+                        WebGlRenderingContext::NEAREST
+                    }
+                    WebGlRenderingContext::LINEAR
+                    | WebGlRenderingContext::LINEAR_MIPMAP_NEAREST
+                    | WebGlRenderingContext::LINEAR_MIPMAP_LINEAR => WebGlRenderingContext::LINEAR,
+                    value => return Err(invalid("minFilter", value)),
+                };
+                Some(if mipmaps { value } else { base_level_filter })
+            }
+            // GL defaults to a mipmap filter, which a texture without mipmaps can not use.
+            None if mipmaps => None,
+            None => Some(WebGlRenderingContext::LINEAR),
+        };
+
+        for (parameter, value) in &[
+            (WebGlRenderingContext::TEXTURE_WRAP_S, Some(wrap_s)),
+            (WebGlRenderingContext::TEXTURE_WRAP_T, Some(wrap_t)),
+            (WebGlRenderingContext::TEXTURE_MAG_FILTER, mag_filter),
+            (WebGlRenderingContext::TEXTURE_MIN_FILTER, min_filter),
+        ] {
+            if let Some(value) = value {
+                gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, *parameter, *value as i32);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decodes the image with the browser, which handles every format it displays.
+    #[cfg(target_arch = "wasm32")]
+    async fn load_image(gl: &dyn Backend, data: &[u8]) -> Result<Texture, JsValue> {
+        let texture_image = JsFuture::from(load_image(js_sys::Uint8Array::from(data)))
+            .await?
+            .dyn_into::<HtmlImageElement>()?;
+
+        let texture_buffer = gl
+            .create_texture()
+            .ok_or_else(|| JsValue::from_str("could not create texture"))?;
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture_buffer));
+        gl.tex_image_2d_image(
+            WebGlRenderingContext::TEXTURE_2D,
+            0,
+            WebGlRenderingContext::RGBA as i32,
+            WebGlRenderingContext::RGBA,
+            WebGlRenderingContext::UNSIGNED_BYTE,
+            &texture_image,
+        )?;
+
+        Ok(texture_buffer)
+    }
+
+    /// Outside of the browser only PNG images can be decoded.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn load_image(gl: &dyn Backend, data: &[u8]) -> Result<Texture, JsValue> {
+        let image = png::parse(data)?;
+
+        let texture_buffer = gl
+            .create_texture()
+            .ok_or_else(|| JsValue::from_str("could not create texture"))?;
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture_buffer));
+        gl.tex_image_2d(
+            WebGlRenderingContext::TEXTURE_2D,
+            0,
+            WebGlRenderingContext::RGBA as i32,
+            image.width as i32,
+            image.height as i32,
+            WebGlRenderingContext::RGBA,
+            WebGlRenderingContext::UNSIGNED_BYTE,
+            Some(&image.data),
+        )?;
+
+        Ok(texture_buffer)
+    }
+
+    /// Transcodes a KTX2 texture to the format picked for the context and uploads all of
+    /// its mip levels, which tells whether it has mipmaps.
+    async fn load_ktx2(
+        gl: &dyn Backend,
+        data: &[u8],
+        texture_support: &CompressedTextureSupport,
+    ) -> Result<(Texture, bool), JsValue> {
+        let header = ktx2::parse(data)?;
+        let target = texture_support.transcode_target(&header);
+
+        let levels = JsFuture::from(transcode_ktx2(
+            js_sys::Uint8Array::from(data),
+            target.basis_format,
+        ))
+        .await?
+        .dyn_into::<js_sys::Array>()?;
+
+        let texture_buffer = gl
+            .create_texture()
+            .ok_or_else(|| JsValue::from_str("could not create texture"))?;
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture_buffer));
+
+        for (level, level_data) in levels.iter().enumerate() {
+            let level_data = level_data.dyn_into::<js_sys::Uint8Array>()?.to_vec();
+            let (width, height) = header.level_size(level as u32);
+
+            match target.gl_format {
+                Some(format) => gl.compressed_tex_image_2d(
+                    WebGlRenderingContext::TEXTURE_2D,
+                    level as i32,
+                    format,
+                    width as i32,
+                    height as i32,
+                    &level_data,
+                ),
+                None => gl.tex_image_2d(
+                    WebGlRenderingContext::TEXTURE_2D,
+                    level as i32,
+                    WebGlRenderingContext::RGBA as i32,
+                    width as i32,
+                    height as i32,
+                    WebGlRenderingContext::RGBA,
+                    WebGlRenderingContext::UNSIGNED_BYTE,
+                    Some(&level_data),
+                )?,
+            }
+        }
+
+        // Compressed textures can not generate mipmaps, so a single level file has to be
+        // sampled without them.
+        Ok((texture_buffer, header.level_count >= 2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::backend::headless::{Command, HeadlessBackend};
+
+    fn reference(json: &str) -> TextureReference {
+        serde_json::from_str(json).unwrap()
+    }
+
+    /// Column major product, like `mat3.multiply` of gl-matrix.
+    fn multiply(a: &[f32; 9], b: &[f32; 9]) -> [f32; 9] {
+        let mut product = [0.; 9];
+        for column in 0..3 {
+            for row in 0..3 {
+                product[column * 3 + row] =
+                    (0..3).map(|k| a[k * 3 + row] * b[column * 3 + k]).sum();
+            }
+        }
+        product
+    }
+
+    fn transform_point(matrix: &[f32; 9], [u, v]: [f32; 2]) -> [f32; 2] {
+        [
+            matrix[0] * u + matrix[3] * v + matrix[6],
+            matrix[1] * u + matrix[4] * v + matrix[7],
+        ]
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-6,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn texture_transform_matches_the_sample_viewer() {
+        let (offset, rotation, scale) = ([0.25, -0.5], 0.7_f32, [2., 3.]);
+        let reference = reference(
+            r#"{"index": 0, "extensions": {"KHR_texture_transform": {
+                "offset": [0.25, -0.5], "rotation": 0.7, "scale": [2, 3]
+            }}}"#,
+        );
+        // The matrices the glTF sample viewer multiplies, translation * rotation * scale.
+        let (sin, cos) = rotation.sin_cos();
+        let translation = [1., 0., 0., 0., 1., 0., offset[0], offset[1], 1.];
+        let rotation = [cos, -sin, 0., sin, cos, 0., 0., 0., 1.];
+        let scale = [scale[0], 0., 0., 0., scale[1], 0., 0., 0., 1.];
+
+        assert_close(
+            &MeshShaderTextures::texture_transform(&reference),
+            &multiply(&multiply(&translation, &rotation), &scale),
+        );
+    }
+
+    #[test]
+    fn texture_transform_rotates_counter_clockwise_in_uv_space() {
+        let reference = reference(
+            r#"{"index": 0, "extensions": {"KHR_texture_transform": {
+                "rotation": 1.5707964
+            }}}"#,
+        );
+        let matrix = MeshShaderTextures::texture_transform(&reference);
+
+        // V points down in glTF texture space, so counter-clockwise turns +U into -V.
+        assert_close(&transform_point(&matrix, [1., 0.]), &[0., -1.]);
+        assert_close(&transform_point(&matrix, [0., 1.]), &[1., 0.]);
+    }
+
+    #[test]
+    fn texture_transform_defaults_to_identity() {
+        assert_close(
+            &MeshShaderTextures::texture_transform(&reference(r#"{"index": 0}"#)),
+            &[1., 0., 0., 0., 1., 0., 0., 0., 1.],
+        );
+    }
+
+    fn sampler_parameters(sampler: Option<&GlTfSampler>, mipmaps: bool) -> Vec<(u32, u32)> {
+        let gl = HeadlessBackend::new(4, 4);
+        let texture = gl.create_texture().unwrap();
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+        gl.take_commands();
+
+        MeshShaderTextures::apply_sampler(&gl, sampler, mipmaps).unwrap();
+
+        assert_eq!(gl.errors(), Vec::<String>::new());
+        gl.take_commands()
+            .into_iter()
+            .filter_map(|command| match command {
+                Command::TexParameter {
+                    parameter, value, ..
+                } => Some((parameter, value as u32)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn applies_the_sampler_wrap_modes_and_filters() {
+        let sampler: GlTfSampler = serde_json::from_str(
+            r#"{"magFilter": 9728, "minFilter": 9985, "wrapS": 33071, "wrapT": 33648}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            sampler_parameters(Some(&sampler), true),
+            vec![
+                (
+                    WebGlRenderingContext::TEXTURE_WRAP_S,
+                    WebGlRenderingContext::CLAMP_TO_EDGE
+                ),
+                (
+                    WebGlRenderingContext::TEXTURE_WRAP_T,
+                    WebGlRenderingContext::MIRRORED_REPEAT
+                ),
+                (
+                    WebGlRenderingContext::TEXTURE_MAG_FILTER,
+                    WebGlRenderingContext::NEAREST
+                ),
+                (
+                    WebGlRenderingContext::TEXTURE_MIN_FILTER,
+                    WebGlRenderingContext::LINEAR_MIPMAP_NEAREST
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn textures_without_mipmaps_filter_the_base_level() {
+        for (min_filter, base_level_filter) in &[
+            (9728, WebGlRenderingContext::NEAREST),
+            (9984, WebGlRenderingContext::NEAREST),
+            (9986, WebGlRenderingContext::NEAREST),
+            (9729, WebGlRenderingContext::LINEAR),
+            (9985, WebGlRenderingContext::LINEAR),
+            (9987, WebGlRenderingContext::LINEAR),
+        ] {
+            let sampler = GlTfSampler {
+                min_filter: Some(*min_filter),
+                ..GlTfSampler::default()
+            };
+
+            assert_eq!(
+                sampler_parameters(Some(&sampler), false).last(),
+                Some(&(
+                    WebGlRenderingContext::TEXTURE_MIN_FILTER,
+                    *base_level_filter
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn textures_without_a_sampler_repeat() {
+        let repeat = vec![
+            (
+                WebGlRenderingContext::TEXTURE_WRAP_S,
+                WebGlRenderingContext::REPEAT,
+            ),
+            (
+                WebGlRenderingContext::TEXTURE_WRAP_T,
+                WebGlRenderingContext::REPEAT,
+            ),
+        ];
+
+        assert_eq!(sampler_parameters(None, true), repeat);
+        assert_eq!(
+            sampler_parameters(None, false),
+            [
+                repeat,
+                vec![(
+                    WebGlRenderingContext::TEXTURE_MIN_FILTER,
+                    WebGlRenderingContext::LINEAR
+                )]
+            ]
+            .concat()
+        );
+    }
 }
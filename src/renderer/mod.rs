@@ -1,3 +1,17 @@
+pub(crate) mod backend;
 mod camera;
+pub(crate) mod capture;
+pub(crate) mod debug;
+pub(crate) mod environment;
+pub(crate) mod light;
 pub(crate) mod mesh;
-mod scene;
+pub(crate) mod post;
+pub(crate) mod queue;
+pub(crate) mod render_target;
+pub(crate) mod scene;
+pub(crate) mod settings;
+pub(crate) mod shadow;
+pub(crate) mod state;
+pub(crate) mod stats;
+pub(crate) mod surface;
+pub(crate) mod texture_format;
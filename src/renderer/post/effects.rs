@@ -0,0 +1,130 @@
+pub(super) const V_SHADER: &str = "#version 100
+
+attribute vec2 position;
+
+varying vec2 fUv;
+
+void main() {
+    fUv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}";
+
+/// Declarations shared by every pass, prepended to the sources below.
+pub(super) const F_SHADER_HEADER: &str = "#version 100
+
+precision mediump float;
+
+varying vec2 fUv;
+
+uniform sampler2D source;
+uniform vec2 texelSize;
+";
+
+/// ACES filmic curve fitted by Krzysztof Narkowicz.
+pub(super) const TONE_MAPPING: &str = "
+uniform float exposure;
+
+void main() {
+    vec3 color = texture2D(source, fUv).rgb * exposure;
+    color = clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+    gl_FragColor = vec4(color, 1.0);
+}";
+
+pub(super) const GAMMA_CORRECTION: &str = "
+void main() {
+    gl_FragColor = vec4(pow(texture2D(source, fUv).rgb, vec3(1.0 / 2.2)), 1.0);
+}";
+
+/// FXAA in the compact form of the original console implementation, works best on
+/// tone mapped, gamma corrected colors.
+pub(super) const FXAA: &str = "
+#define FXAA_REDUCE_MIN (1.0 / 128.0)
+#define FXAA_REDUCE_MUL (1.0 / 8.0)
+#define FXAA_SPAN_MAX 8.0
+
+vec3 sampleSource(vec2 offset) {
+    return texture2D(source, fUv + offset * texelSize).rgb;
+}
+
+void main() {
+    vec3 luma = vec3(0.299, 0.587, 0.114);
+    vec3 rgbM = sampleSource(vec2(0.0));
+    float lumaNW = dot(sampleSource(vec2(-1.0, -1.0)), luma);
+    float lumaNE = dot(sampleSource(vec2(1.0, -1.0)), luma);
+    float lumaSW = dot(sampleSource(vec2(-1.0, 1.0)), luma);
+    float lumaSE = dot(sampleSource(vec2(1.0, 1.0)), luma);
+    float lumaM = dot(rgbM, luma);
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    vec2 direction = vec2(
+        -((lumaNW + lumaNE) - (lumaSW + lumaSE)),
+        (lumaNW + lumaSW) - (lumaNE + lumaSE)
+    );
+    float directionReduce = max(
+        (lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * FXAA_REDUCE_MUL,
+        FXAA_REDUCE_MIN
+    );
+    float inverseDirectionMin = 1.0 / (min(abs(direction.x), abs(direction.y)) + directionReduce);
+    direction = clamp(direction * inverseDirectionMin, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX));
+
+    vec3 rgbA = 0.5 * (
+        sampleSource(direction * (1.0 / 3.0 - 0.5)) +
+        sampleSource(direction * (2.0 / 3.0 - 0.5))
+    );
+    vec3 rgbB = rgbA * 0.5 + 0.25 * (
+        sampleSource(direction * -0.5) +
+        sampleSource(direction * 0.5)
+    );
+    float lumaB = dot(rgbB, luma);
+
+    gl_FragColor = vec4(lumaB < lumaMin || lumaB > lumaMax ? rgbA : rgbB, 1.0);
+}";
+
+pub(super) const VIGNETTE: &str = "
+// x: strength, y: radius where the darkening is complete
+uniform vec2 vignette;
+
+void main() {
+    vec3 color = texture2D(source, fUv).rgb;
+    float distanceToCenter = distance(fUv, vec2(0.5));
+    float darkening = smoothstep(vignette.y - 0.4, vignette.y, distanceToCenter);
+    gl_FragColor = vec4(color * (1.0 - vignette.x * darkening), 1.0);
+}";
+
+/// Keeps the part of every pixel above the bloom threshold.
+pub(super) const BLOOM_BRIGHT: &str = "
+uniform float threshold;
+
+void main() {
+    vec3 color = texture2D(source, fUv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    gl_FragColor = vec4(color * max(brightness - threshold, 0.0) / max(brightness, 0.0001), 1.0);
+}";
+
+/// Separable 9 tap gaussian, `direction` is (1, 0) or (0, 1).
+pub(super) const BLOOM_BLUR: &str = "
+uniform vec2 direction;
+
+void main() {
+    vec2 offset = direction * texelSize;
+    vec3 color = texture2D(source, fUv).rgb * 0.227027;
+    color += texture2D(source, fUv + offset).rgb * 0.1945946;
+    color += texture2D(source, fUv - offset).rgb * 0.1945946;
+    color += texture2D(source, fUv + offset * 2.0).rgb * 0.1216216;
+    color += texture2D(source, fUv - offset * 2.0).rgb * 0.1216216;
+    color += texture2D(source, fUv + offset * 3.0).rgb * 0.054054;
+    color += texture2D(source, fUv - offset * 3.0).rgb * 0.054054;
+    color += texture2D(source, fUv + offset * 4.0).rgb * 0.016216;
+    color += texture2D(source, fUv - offset * 4.0).rgb * 0.016216;
+    gl_FragColor = vec4(color, 1.0);
+}";
+
+pub(super) const BLOOM_COMPOSITE: &str = "
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main() {
+    vec3 color = texture2D(source, fUv).rgb + texture2D(bloom, fUv).rgb * intensity;
+    gl_FragColor = vec4(color, 1.0);
+}";
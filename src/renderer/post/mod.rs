@@ -0,0 +1,352 @@
+use crate::renderer::backend::{Backend, Buffer, Program, UniformLocation};
+use crate::renderer::mesh::shader::program;
+use crate::renderer::render_target::{self, RenderTarget};
+use crate::renderer::stats::FrameCounters;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+mod effects;
+
+const DEFAULT_EXPOSURE: f32 = 1.;
+const DEFAULT_VIGNETTE: [f32; 2] = [0.5, 0.8];
+const DEFAULT_BLOOM_THRESHOLD: f32 = 0.8;
+const DEFAULT_BLOOM_INTENSITY: f32 = 0.6;
+
+/// Full screen effects applied after the scene, in the order they were added. Tone
+/// mapping, gamma correction and FXAA are meant to run in that order.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostEffect {
+    ToneMapping,
+    GammaCorrection,
+    Fxaa,
+    Vignette,
+    Bloom,
+}
+
+#[derive(Debug)]
+enum PassParameter {
+    None,
+    Float(f32),
+    Vec2([f32; 2]),
+}
+
+#[derive(Debug)]
+struct PostPass {
+    program: Program,
+    position: i32,
+    source: Option<UniformLocation>,
+    texel_size: Option<UniformLocation>,
+    /// The one effect specific uniform, exposure, threshold, blur direction...
+    parameter: Option<UniformLocation>,
+    bloom: Option<UniformLocation>,
+}
+
+impl PostPass {
+    fn new(gl: &dyn Backend, source: &str, parameter: Option<&str>) -> Result<PostPass, JsValue> {
+        let program = program::compile_to_program(
+            gl,
+            effects::V_SHADER,
+            &format!("{}{}", effects::F_SHADER_HEADER, source),
+        )?;
+
+        Ok(PostPass {
+            position: gl.get_attrib_location(&program, "position"),
+            source: gl.get_uniform_location(&program, "source"),
+            texel_size: gl.get_uniform_location(&program, "texelSize"),
+            parameter: parameter.and_then(|name| gl.get_uniform_location(&program, name)),
+            bloom: gl.get_uniform_location(&program, "bloom"),
+            program,
+        })
+    }
+}
+
+/// Renders the scene into an offscreen target and runs it through the chain of post
+/// effects, the last one writes to the canvas.
+#[derive(Debug)]
+pub(crate) struct PostProcessing {
+    effects: Vec<PostEffect>,
+    scene: RenderTarget,
+    ping: RenderTarget,
+    pong: RenderTarget,
+    /// Half resolution targets the bloom is blurred in.
+    bloom: [RenderTarget; 2],
+    quad: Buffer,
+    tone_mapping: PostPass,
+    gamma_correction: PostPass,
+    fxaa: PostPass,
+    vignette: PostPass,
+    bloom_bright: PostPass,
+    bloom_blur: PostPass,
+    bloom_composite: PostPass,
+    pub(crate) exposure: f32,
+    /// x: strength, y: radius
+    pub(crate) vignette_params: [f32; 2],
+    pub(crate) bloom_threshold: f32,
+    pub(crate) bloom_intensity: f32,
+}
+
+impl PostProcessing {
+    pub(crate) fn new(gl: &dyn Backend) -> Result<PostProcessing, JsValue> {
+        let width = gl.drawing_buffer_width();
+        let height = gl.drawing_buffer_height();
+        let color_type = RenderTarget::hdr_color_type(gl)?;
+
+        let quad = gl
+            .create_buffer()
+            .ok_or_else(|| JsValue::from_str("could not create post processing buffer"))?;
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&quad));
+        gl.buffer_data_f32(
+            WebGlRenderingContext::ARRAY_BUFFER,
+            &[-1f32, -1., 3., -1., -1., 3.][..],
+            WebGlRenderingContext::STATIC_DRAW,
+        );
+
+        Ok(PostProcessing {
+            effects: vec![],
+            scene: RenderTarget::new(gl, width, height, true, color_type)?,
+            ping: RenderTarget::new(gl, width, height, false, color_type)?,
+            pong: RenderTarget::new(gl, width, height, false, color_type)?,
+            bloom: [
+                RenderTarget::new(gl, width / 2, height / 2, false, color_type)?,
+                RenderTarget::new(gl, width / 2, height / 2, false, color_type)?,
+            ],
+            quad,
+            tone_mapping: PostPass::new(gl, effects::TONE_MAPPING, Some("exposure"))?,
+            gamma_correction: PostPass::new(gl, effects::GAMMA_CORRECTION, None)?,
+            fxaa: PostPass::new(gl, effects::FXAA, None)?,
+            vignette: PostPass::new(gl, effects::VIGNETTE, Some("vignette"))?,
+            bloom_bright: PostPass::new(gl, effects::BLOOM_BRIGHT, Some("threshold"))?,
+            bloom_blur: PostPass::new(gl, effects::BLOOM_BLUR, Some("direction"))?,
+            bloom_composite: PostPass::new(gl, effects::BLOOM_COMPOSITE, Some("intensity"))?,
+            exposure: DEFAULT_EXPOSURE,
+            vignette_params: DEFAULT_VIGNETTE,
+            bloom_threshold: DEFAULT_BLOOM_THRESHOLD,
+            bloom_intensity: DEFAULT_BLOOM_INTENSITY,
+        })
+    }
+
+    /// The same chain and parameters with new GL objects, for a restored context.
+    pub(crate) fn recreate(&self, gl: &dyn Backend) -> Result<PostProcessing, JsValue> {
+        Ok(PostProcessing {
+            effects: self.effects.clone(),
+            exposure: self.exposure,
+            vignette_params: self.vignette_params,
+            bloom_threshold: self.bloom_threshold,
+            bloom_intensity: self.bloom_intensity,
+            ..PostProcessing::new(gl)?
+        })
+    }
+
+    /// Appends `effect` to the chain, effects already in it are kept where they are.
+    pub(crate) fn add(&mut self, effect: PostEffect) {
+        if !self.effects.contains(&effect) {
+            self.effects.push(effect);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, effect: PostEffect) -> bool {
+        let length = self.effects.len();
+        self.effects.retain(|existing| *existing != effect);
+
+        self.effects.len() != length
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        !self.effects.is_empty()
+    }
+
+    /// Redirects the scene into the offscreen target, resized to `output`. The scene
+    /// clears it.
+    pub(crate) fn begin(
+        &mut self,
+        gl: &dyn Backend,
+        output: Option<&RenderTarget>,
+    ) -> Result<(), JsValue> {
+        let (width, height) = render_target::output_size(gl, output);
+        for target in [&mut self.scene, &mut self.ping, &mut self.pong] {
+            target.resize(gl, width, height)?;
+        }
+        for target in &mut self.bloom {
+            target.resize(gl, width / 2, height / 2)?;
+        }
+
+        self.scene.bind(gl);
+
+        Ok(())
+    }
+
+    /// Runs the effect chain on the captured scene, the last effect writes to `output`.
+    pub(crate) fn end(
+        &self,
+        gl: &dyn Backend,
+        output: Option<&RenderTarget>,
+        counters: &FrameCounters,
+    ) {
+        let depth_test = gl.is_enabled(WebGlRenderingContext::DEPTH_TEST);
+        let blend = gl.is_enabled(WebGlRenderingContext::BLEND);
+        gl.disable(WebGlRenderingContext::DEPTH_TEST);
+        gl.disable(WebGlRenderingContext::BLEND);
+
+        let mut input = &self.scene;
+        for (index, effect) in self.effects.iter().enumerate() {
+            let target = match (index + 1 == self.effects.len(), index % 2) {
+                (true, _) => output,
+                (false, 0) => Some(&self.ping),
+                (false, _) => Some(&self.pong),
+            };
+
+            match effect {
+                PostEffect::ToneMapping => self.draw(
+                    gl,
+                    counters,
+                    &self.tone_mapping,
+                    input,
+                    target,
+                    PassParameter::Float(self.exposure),
+                ),
+                PostEffect::GammaCorrection => self.draw(
+                    gl,
+                    counters,
+                    &self.gamma_correction,
+                    input,
+                    target,
+                    PassParameter::None,
+                ),
+                PostEffect::Fxaa => {
+                    self.draw(gl, counters, &self.fxaa, input, target, PassParameter::None)
+                }
+                PostEffect::Vignette => self.draw(
+                    gl,
+                    counters,
+                    &self.vignette,
+                    input,
+                    target,
+                    PassParameter::Vec2(self.vignette_params),
+                ),
+                PostEffect::Bloom => {
+                    let [first, second] = &self.bloom;
+                    self.draw(
+                        gl,
+                        counters,
+                        &self.bloom_bright,
+                        input,
+                        Some(first),
+                        PassParameter::Float(self.bloom_threshold),
+                    );
+                    self.draw(
+                        gl,
+                        counters,
+                        &self.bloom_blur,
+                        first,
+                        Some(second),
+                        PassParameter::Vec2([1., 0.]),
+                    );
+                    self.draw(
+                        gl,
+                        counters,
+                        &self.bloom_blur,
+                        second,
+                        Some(first),
+                        PassParameter::Vec2([0., 1.]),
+                    );
+
+                    gl.active_texture(WebGlRenderingContext::TEXTURE1);
+                    gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(first.texture()));
+                    counters.textures(1);
+                    gl.use_program(Some(&self.bloom_composite.program));
+                    counters.program();
+                    gl.uniform1i(self.bloom_composite.bloom.as_ref(), 1);
+                    self.draw(
+                        gl,
+                        counters,
+                        &self.bloom_composite,
+                        input,
+                        target,
+                        PassParameter::Float(self.bloom_intensity),
+                    );
+                }
+            }
+
+            if let Some(target) = target {
+                input = target;
+            }
+        }
+
+        if depth_test {
+            gl.enable(WebGlRenderingContext::DEPTH_TEST);
+        }
+        if blend {
+            gl.enable(WebGlRenderingContext::BLEND);
+        }
+    }
+
+    pub(crate) fn delete(&self, gl: &dyn Backend) {
+        for target in &[
+            &self.scene,
+            &self.ping,
+            &self.pong,
+            &self.bloom[0],
+            &self.bloom[1],
+        ] {
+            target.delete(gl);
+        }
+        for pass in &[
+            &self.tone_mapping,
+            &self.gamma_correction,
+            &self.fxaa,
+            &self.vignette,
+            &self.bloom_bright,
+            &self.bloom_blur,
+            &self.bloom_composite,
+        ] {
+            gl.delete_program(&pass.program);
+        }
+        gl.delete_buffer(&self.quad);
+    }
+
+    /// Draws one full screen pass, `None` as output renders to the canvas.
+    fn draw(
+        &self,
+        gl: &dyn Backend,
+        counters: &FrameCounters,
+        pass: &PostPass,
+        input: &RenderTarget,
+        output: Option<&RenderTarget>,
+        parameter: PassParameter,
+    ) {
+        render_target::bind_output(gl, output);
+
+        let (width, height) = input.size();
+        gl.use_program(Some(&pass.program));
+        gl.active_texture(WebGlRenderingContext::TEXTURE0);
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(input.texture()));
+        counters.program();
+        counters.textures(1);
+        gl.uniform1i(pass.source.as_ref(), 0);
+        gl.uniform2f(
+            pass.texel_size.as_ref(),
+            1. / width as f32,
+            1. / height as f32,
+        );
+        match parameter {
+            PassParameter::None => {}
+            PassParameter::Float(value) => gl.uniform1f(pass.parameter.as_ref(), value),
+            PassParameter::Vec2(value) => gl.uniform2fv(pass.parameter.as_ref(), &value),
+        }
+
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.quad));
+        gl.enable_vertex_attrib_array(pass.position as u32);
+        gl.vertex_attrib_pointer(
+            pass.position as u32,
+            2,
+            WebGlRenderingContext::FLOAT,
+            false,
+            0,
+            0,
+        );
+        gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, 3);
+        counters.draw(1);
+        gl.disable_vertex_attrib_array(pass.position as u32);
+    }
+}
@@ -0,0 +1,130 @@
+/// Passes draw items are submitted in, opaque surfaces before blended ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum RenderPass {
+    Opaque,
+    Transparent,
+}
+
+/// One primitive of one node, with the keys it is sorted by.
+#[derive(Debug)]
+pub(crate) struct DrawItem {
+    pub(crate) pass: RenderPass,
+    pub(crate) program: u32,
+    pub(crate) material: u32,
+    pub(crate) texture: u32,
+    /// Distance of the node from the camera.
+    pub(crate) depth: f32,
+    pub(crate) node: usize,
+    pub(crate) primitive: usize,
+}
+
+/// Draw items of one frame. The allocation is kept from frame to frame.
+#[derive(Debug, Default)]
+pub(crate) struct RenderQueue {
+    items: Vec<DrawItem>,
+}
+
+impl RenderQueue {
+    pub(crate) fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub(crate) fn push(&mut self, item: DrawItem) {
+        self.items.push(item);
+    }
+
+    /// Orders opaque items by program, material and texture so state changes are
+    /// shared, front to back within equal state. Transparent items are drawn back to
+    /// front, as blending needs. Depths compare totally, so a NaN depth can not break
+    /// the order of the other items.
+    pub(crate) fn sort(&mut self) {
+        self.items.sort_unstable_by(|a, b| {
+            a.pass.cmp(&b.pass).then_with(|| match a.pass {
+                RenderPass::Opaque => (a.program, a.material, a.texture)
+                    .cmp(&(b.program, b.material, b.texture))
+                    .then_with(|| a.depth.total_cmp(&b.depth)),
+                RenderPass::Transparent => b.depth.total_cmp(&a.depth),
+            })
+        });
+    }
+
+    pub(crate) fn items(&self) -> &[DrawItem] {
+        &self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(pass: RenderPass, state: (u32, u32, u32), depth: f32, node: usize) -> DrawItem {
+        DrawItem {
+            pass,
+            program: state.0,
+            material: state.1,
+            texture: state.2,
+            depth,
+            node,
+            primitive: 0,
+        }
+    }
+
+    fn sorted(items: Vec<DrawItem>) -> Vec<usize> {
+        let mut queue = RenderQueue::default();
+        for item in items {
+            queue.push(item);
+        }
+        queue.sort();
+        queue.items().iter().map(|item| item.node).collect()
+    }
+
+    #[test]
+    fn sorts_opaque_items_by_program_material_and_texture() {
+        let nodes = sorted(vec![
+            item(RenderPass::Opaque, (2, 1, 1), 1., 0),
+            item(RenderPass::Opaque, (1, 2, 1), 1., 1),
+            item(RenderPass::Opaque, (1, 1, 2), 1., 2),
+            item(RenderPass::Opaque, (1, 1, 1), 1., 3),
+        ]);
+
+        assert_eq!(nodes, vec![3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn sorts_opaque_items_with_equal_state_front_to_back() {
+        let nodes = sorted(vec![
+            item(RenderPass::Opaque, (1, 1, 1), 5., 0),
+            item(RenderPass::Opaque, (1, 1, 1), 1., 1),
+            item(RenderPass::Opaque, (2, 1, 1), 0., 2),
+            item(RenderPass::Opaque, (1, 1, 1), 3., 3),
+        ]);
+
+        assert_eq!(nodes, vec![1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn sorts_transparent_items_after_opaque_ones_back_to_front() {
+        let nodes = sorted(vec![
+            item(RenderPass::Transparent, (1, 1, 1), 1., 0),
+            item(RenderPass::Opaque, (3, 1, 1), 9., 1),
+            item(RenderPass::Transparent, (2, 1, 1), 5., 2),
+            item(RenderPass::Transparent, (1, 1, 1), 3., 3),
+        ]);
+
+        assert_eq!(nodes, vec![1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn nan_depths_keep_the_other_items_in_order() {
+        let nodes = sorted(vec![
+            item(RenderPass::Opaque, (1, 1, 1), 2., 0),
+            item(RenderPass::Opaque, (1, 1, 1), f32::NAN, 1),
+            item(RenderPass::Opaque, (1, 1, 1), 1., 2),
+            item(RenderPass::Transparent, (1, 1, 1), f32::NAN, 3),
+            item(RenderPass::Transparent, (1, 1, 1), 1., 4),
+            item(RenderPass::Transparent, (1, 1, 1), 2., 5),
+        ]);
+
+        assert_eq!(nodes, vec![2, 0, 1, 3, 5, 4]);
+    }
+}
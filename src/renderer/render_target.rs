@@ -0,0 +1,190 @@
+use crate::renderer::backend::{Backend, Framebuffer, Renderbuffer, Texture, HALF_FLOAT_OES};
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+/// Offscreen framebuffer with a color texture and an optional depth renderbuffer.
+#[derive(Debug)]
+pub(crate) struct RenderTarget {
+    framebuffer: Framebuffer,
+    color: Texture,
+    depth: Option<Renderbuffer>,
+    color_type: u32,
+    width: i32,
+    height: i32,
+}
+
+impl RenderTarget {
+    pub(crate) fn new(
+        gl: &dyn Backend,
+        width: i32,
+        height: i32,
+        depth: bool,
+        color_type: u32,
+    ) -> Result<RenderTarget, JsValue> {
+        let framebuffer = gl
+            .create_framebuffer()
+            .ok_or_else(|| JsValue::from_str("could not create render target framebuffer"))?;
+        let color = gl
+            .create_texture()
+            .ok_or_else(|| JsValue::from_str("could not create render target texture"))?;
+        let depth = match depth {
+            true => Some(
+                gl.create_renderbuffer()
+                    .ok_or_else(|| JsValue::from_str("could not create render target depth"))?,
+            ),
+            false => None,
+        };
+
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&color));
+        for (parameter, value) in &[
+            (
+                WebGlRenderingContext::TEXTURE_MIN_FILTER,
+                WebGlRenderingContext::LINEAR,
+            ),
+            (
+                WebGlRenderingContext::TEXTURE_MAG_FILTER,
+                WebGlRenderingContext::LINEAR,
+            ),
+            (
+                WebGlRenderingContext::TEXTURE_WRAP_S,
+                WebGlRenderingContext::CLAMP_TO_EDGE,
+            ),
+            (
+                WebGlRenderingContext::TEXTURE_WRAP_T,
+                WebGlRenderingContext::CLAMP_TO_EDGE,
+            ),
+        ] {
+            gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, *parameter, *value as i32);
+        }
+
+        let mut target = RenderTarget {
+            framebuffer,
+            color,
+            depth,
+            color_type,
+            width: 0,
+            height: 0,
+        };
+        target.resize(gl, width, height)?;
+
+        Ok(target)
+    }
+
+    /// Color type of targets that keep values above 1 for tone mapping, half floats
+    /// where they can be rendered to and filtered, bytes otherwise.
+    pub(crate) fn hdr_color_type(gl: &dyn Backend) -> Result<u32, JsValue> {
+        for extension in &[
+            "OES_texture_half_float",
+            "OES_texture_half_float_linear",
+            "EXT_color_buffer_half_float",
+        ] {
+            if !gl.has_extension(extension) {
+                return Ok(WebGlRenderingContext::UNSIGNED_BYTE);
+            }
+        }
+
+        Ok(HALF_FLOAT_OES)
+    }
+
+    /// Reallocates the storage when the size changed.
+    pub(crate) fn resize(
+        &mut self,
+        gl: &dyn Backend,
+        width: i32,
+        height: i32,
+    ) -> Result<(), JsValue> {
+        let (width, height) = (width.max(1), height.max(1));
+        if self.width == width && self.height == height {
+            return Ok(());
+        }
+        self.width = width;
+        self.height = height;
+
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&self.color));
+        gl.tex_image_2d(
+            WebGlRenderingContext::TEXTURE_2D,
+            0,
+            WebGlRenderingContext::RGBA as i32,
+            width,
+            height,
+            WebGlRenderingContext::RGBA,
+            self.color_type,
+            None,
+        )?;
+
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&self.framebuffer));
+        gl.framebuffer_texture_2d(
+            WebGlRenderingContext::FRAMEBUFFER,
+            WebGlRenderingContext::COLOR_ATTACHMENT0,
+            WebGlRenderingContext::TEXTURE_2D,
+            Some(&self.color),
+            0,
+        );
+        if let Some(depth) = &self.depth {
+            gl.bind_renderbuffer(WebGlRenderingContext::RENDERBUFFER, Some(depth));
+            gl.renderbuffer_storage(
+                WebGlRenderingContext::RENDERBUFFER,
+                WebGlRenderingContext::DEPTH_COMPONENT16,
+                width,
+                height,
+            );
+            gl.framebuffer_renderbuffer(
+                WebGlRenderingContext::FRAMEBUFFER,
+                WebGlRenderingContext::DEPTH_ATTACHMENT,
+                WebGlRenderingContext::RENDERBUFFER,
+                Some(depth),
+            );
+        }
+
+        let status = gl.check_framebuffer_status(WebGlRenderingContext::FRAMEBUFFER);
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+        if status != WebGlRenderingContext::FRAMEBUFFER_COMPLETE {
+            return Err(JsValue::from_str(&format!(
+                "render target is incomplete: {:#x}",
+                status
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Renders into the target from now on.
+    pub(crate) fn bind(&self, gl: &dyn Backend) {
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&self.framebuffer));
+        gl.viewport(0, 0, self.width, self.height);
+    }
+
+    pub(crate) fn texture(&self) -> &Texture {
+        &self.color
+    }
+
+    pub(crate) fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    pub(crate) fn delete(&self, gl: &dyn Backend) {
+        gl.delete_framebuffer(&self.framebuffer);
+        gl.delete_texture(&self.color);
+        if let Some(depth) = &self.depth {
+            gl.delete_renderbuffer(depth);
+        }
+    }
+}
+
+/// Renders into `target` from now on, or into the canvas when there is none.
+pub(crate) fn bind_output(gl: &dyn Backend, target: Option<&RenderTarget>) {
+    match target {
+        Some(target) => target.bind(gl),
+        None => {
+            gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+            gl.viewport(0, 0, gl.drawing_buffer_width(), gl.drawing_buffer_height());
+        }
+    }
+}
+
+pub(crate) fn output_size(gl: &dyn Backend, target: Option<&RenderTarget>) -> (i32, i32) {
+    match target {
+        Some(target) => target.size(),
+        None => (gl.drawing_buffer_width(), gl.drawing_buffer_height()),
+    }
+}
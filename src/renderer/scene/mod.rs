@@ -1,110 +1,1227 @@
-use crate::definitions::gltf::{GlTf, GlTfScene};
-use crate::loader::glb::GlbBuffer;
+use crate::definitions::gltf::GlTfNode;
+use crate::loader::error::DecodeError;
+use crate::loader::glb::Glb;
+use crate::renderer::backend::{self, Backend};
 use crate::renderer::camera::simple::SimpleCamera;
+use crate::renderer::capture;
+use crate::renderer::debug::{self, DebugOverlay, DebugRenderer, DebugView, LineBatch};
+use crate::renderer::environment::Environment;
+use crate::renderer::light::{
+    Light, LightKind, LightingModel, LightingOptions, ShaderLights, DEFAULT_AMBIENT_GROUND,
+    DEFAULT_AMBIENT_SKY, DEFAULT_MAX_LIGHTS,
+};
+use crate::renderer::mesh::shader::{self, ProgramCache};
 use crate::renderer::mesh::{Mesh, MeshRenderDescriptor};
-use gl_matrix::mat4;
+use crate::renderer::post::{PostEffect, PostProcessing};
+use crate::renderer::queue::RenderQueue;
+use crate::renderer::render_target::{self, RenderTarget};
+use crate::renderer::settings::RenderSettings;
+use crate::renderer::shadow::{ShadowMap, DEFAULT_SHADOW_DISTANCE, DEFAULT_SHADOW_MAP_SIZE};
+use crate::renderer::state::StateTracker;
+use crate::renderer::stats::{Profiler, RenderPhase};
+use crate::renderer::surface::{self, Surface};
+use crate::renderer::texture_format::CompressedTextureSupport;
+use gl_matrix::{mat3, mat4, vec3};
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use web_sys::{HtmlCanvasElement, WebGlRenderingContext};
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug)]
 enum NodeType {
     Mesh(Mesh),
+    Light(Light),
 }
 
 #[derive(Debug)]
 struct SceneNode {
-    name: String,
-    rotation: Option<[f32; 4]>,
-    translation: Option<[f32; 3]>,
+    name: Option<String>,
+    model_matrix: [f32; 16],
     node_type: NodeType,
+    cast_shadows: bool,
+    receive_shadows: bool,
 }
 
 #[wasm_bindgen]
 #[derive(Debug)]
-struct Scene {
+pub struct Scene {
+    gl: Rc<dyn Backend>,
+    /// The loaded file, GPU resources are built from it again after a context loss.
+    glb: Rc<Glb>,
+    surface: Surface,
+    settings: RenderSettings,
     nodes: Vec<SceneNode>,
     camera: SimpleCamera,
+    variants: Vec<String>,
+    /// Index of the active material variant.
+    variant: Option<usize>,
+    lighting: LightingOptions,
+    programs: ProgramCache,
+    ambient_sky: [f32; 3],
+    ambient_ground: [f32; 3],
+    next_light_id: u32,
+    shadow_map: Option<ShadowMap>,
+    shadow_distance: f32,
+    environment: Option<Environment>,
+    post_processing: Option<PostProcessing>,
+    debug: Option<DebugRenderer>,
+    profiler: Profiler,
+    queue: RenderQueue,
+    /// Set from a context loss until the scene is restored, frames are skipped.
+    context_lost: bool,
 }
 
 #[wasm_bindgen]
 impl Scene {
-    pub async fn from_url(
-        gl: WebGlRenderingContext,
+    /// Renders with WebGL 2 where the browser supports it and WebGL 1 otherwise.
+    pub async fn from_url(canvas: HtmlCanvasElement, url: String) -> Result<Scene, JsValue> {
+        Scene::from_url_with_max_lights(canvas, url, DEFAULT_MAX_LIGHTS).await
+    }
+
+    /// Like `from_url`, but passes at most `max_lights` lights to the mesh shaders.
+    pub async fn from_url_with_max_lights(
         canvas: HtmlCanvasElement,
         url: String,
+        max_lights: usize,
     ) -> Result<Scene, JsValue> {
-        let glb = crate::loader::glb::Glb::from_url(&url).await?;
-        let scene_id = glb.json.gltf.scene;
-        let scene = glb
-            .json
-            .gltf
-            .scenes
-            .get(scene_id)
-            .ok_or(JsValue::from_str(&format!(
-                "Scene with id {} not found",
-                scene_id
-            )))?;
-        let aspect = (canvas.width() / canvas.height()) as f32;
+        Scene::load_url(canvas, url, RenderSettings::default(), max_lights).await
+    }
+
+    /// Like `from_url`, but creates the context with the attributes of `settings` and
+    /// renders with its state.
+    pub async fn from_url_with_settings(
+        canvas: HtmlCanvasElement,
+        url: String,
+        settings: RenderSettings,
+    ) -> Result<Scene, JsValue> {
+        Scene::load_url(canvas, url, settings, DEFAULT_MAX_LIGHTS).await
+    }
+
+    /// Sets the URL of `basis_transcoder.js` from the Basis Universal project, its
+    /// `basis_transcoder.wasm` has to be served next to it. Scenes with
+    /// `KHR_texture_basisu` or KTX2 textures fail to load until this is set.
+    pub fn set_basis_transcoder_url(url: &str) {
+        shader::set_basis_transcoder_url(url);
+    }
+
+    pub fn render_settings(&self) -> RenderSettings {
+        self.settings.clone()
+    }
+
+    /// Renders the next frames with `settings`, its context attributes are ignored.
+    pub fn set_render_settings(&mut self, settings: RenderSettings) {
+        self.settings = settings;
+    }
+
+    /// Caps the device pixel ratio the canvas is rendered at, lower ratios render fewer
+    /// pixels on dense screens.
+    pub fn set_max_pixel_ratio(&mut self, max_pixel_ratio: f64) -> Result<(), JsValue> {
+        self.surface.set_max_pixel_ratio(max_pixel_ratio)
+    }
+
+    /// Names of the `KHR_materials_variants` variants of the loaded file.
+    pub fn variants(&self) -> js_sys::Array {
+        self.variants
+            .iter()
+            .map(|variant| JsValue::from_str(variant))
+            .collect()
+    }
+
+    pub fn activate_variant(&mut self, name: &str) -> Result<(), JsValue> {
+        let variant = self
+            .variants
+            .iter()
+            .position(|variant| variant == name)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown material variant {}", name)))?;
+        self.apply_variant(Some(variant));
+
+        Ok(())
+    }
+
+    /// Restores the default material of every primitive.
+    pub fn reset_variant(&mut self) {
+        self.apply_variant(None);
+    }
+
+    /// Adds a light shining along `direction`, returns the id for `remove_light`.
+    pub fn add_directional_light(
+        &mut self,
+        direction: &[f32],
+        color: &[f32],
+        intensity: f32,
+    ) -> Result<u32, JsValue> {
+        let model_matrix = Light::model_matrix(&[0., 0., 0.], &vec3_arg(direction)?);
+        self.add_light(LightKind::Directional, color, intensity, None, model_matrix)
+    }
+
+    pub fn add_point_light(
+        &mut self,
+        position: &[f32],
+        color: &[f32],
+        intensity: f32,
+        range: Option<f32>,
+    ) -> Result<u32, JsValue> {
+        let model_matrix = Light::model_matrix(&vec3_arg(position)?, &[0., 0., -1.]);
+        self.add_light(LightKind::Point, color, intensity, range, model_matrix)
+    }
+
+    /// `cone_angles` holds the inner and outer cone angle in radians.
+    pub fn add_spot_light(
+        &mut self,
+        position: &[f32],
+        direction: &[f32],
+        color: &[f32],
+        intensity: f32,
+        range: Option<f32>,
+        cone_angles: &[f32],
+    ) -> Result<u32, JsValue> {
+        if cone_angles.len() != 2 {
+            return Err(JsValue::from_str(
+                "expected inner and outer cone angle for spot light",
+            ));
+        }
+        let kind = LightKind::Spot {
+            inner_cone_angle: cone_angles[0],
+            outer_cone_angle: cone_angles[1],
+        };
+        let model_matrix = Light::model_matrix(&vec3_arg(position)?, &vec3_arg(direction)?);
+        self.add_light(kind, color, intensity, range, model_matrix)
+    }
+
+    /// Removes a light added at runtime or loaded from the file. Returns false for
+    /// unknown ids.
+    pub fn remove_light(&mut self, id: u32) -> bool {
+        let index = self.nodes.iter().position(|node| match &node.node_type {
+            NodeType::Light(light) => light.id == id,
+            _ => false,
+        });
+
+        match index {
+            Some(index) => {
+                self.nodes.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn lighting_model(&self) -> LightingModel {
+        self.lighting.model
+    }
+
+    /// Switches the lighting model of every mesh, which recompiles their shaders.
+    pub fn set_lighting_model(&mut self, model: LightingModel) -> Result<(), JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        if self.lighting.model == model {
+            return Ok(());
+        }
+        self.lighting.model = model;
+
+        self.apply_lighting(gl)
+    }
+
+    /// Renders shadows of the first directional light into a `map_size` square shadow
+    /// map, 2048 when omitted.
+    pub fn enable_shadows(&mut self, map_size: Option<i32>) -> Result<(), JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        let shadow_map = ShadowMap::new(gl, map_size.unwrap_or(DEFAULT_SHADOW_MAP_SIZE))?;
+        self.lighting.shadows = Some(shadow_map.format);
+        if let Some(previous) = self.shadow_map.replace(shadow_map) {
+            previous.delete(gl);
+        }
+
+        self.apply_lighting(gl)
+    }
+
+    pub fn disable_shadows(&mut self) -> Result<(), JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        if let Some(shadow_map) = self.shadow_map.take() {
+            shadow_map.delete(gl);
+        }
+        self.lighting.shadows = None;
+
+        self.apply_lighting(gl)
+    }
+
+    /// How far from the camera shadows are rendered. Shorter distances give sharper
+    /// shadows.
+    pub fn set_shadow_distance(&mut self, distance: f32) {
+        self.shadow_distance = distance;
+    }
+
+    /// Draws `environment` as skybox and uses it for image based lighting of PBR
+    /// materials.
+    pub fn set_environment(&mut self, environment: Environment) -> Result<(), JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        self.lighting.environment = Some(environment.sampling);
+        if let Some(previous) = self.environment.replace(environment) {
+            previous.delete(gl);
+        }
+
+        self.apply_lighting(gl)
+    }
+
+    pub fn clear_environment(&mut self) -> Result<(), JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        if let Some(environment) = self.environment.take() {
+            environment.delete(gl);
+        }
+        self.lighting.environment = None;
+
+        self.apply_lighting(gl)
+    }
+
+    /// Scales the skybox and the image based lighting.
+    pub fn set_environment_intensity(&mut self, intensity: f32) {
+        if let Some(environment) = &mut self.environment {
+            environment.intensity = intensity;
+        }
+    }
+
+    /// Hides the skybox while keeping the image based lighting.
+    pub fn set_skybox_visible(&mut self, visible: bool) {
+        if let Some(environment) = &mut self.environment {
+            environment.skybox_visible = visible;
+        }
+    }
+
+    /// Appends `effect` to the post processing chain, the scene is rendered offscreen
+    /// as long as the chain is not empty.
+    pub fn add_post_effect(&mut self, effect: PostEffect) -> Result<(), JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        if self.post_processing.is_none() {
+            self.post_processing = Some(PostProcessing::new(gl)?);
+        }
+        if let Some(post_processing) = &mut self.post_processing {
+            post_processing.add(effect);
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_post_effect(&mut self, effect: PostEffect) -> bool {
+        match &mut self.post_processing {
+            Some(post_processing) => post_processing.remove(effect),
+            None => false,
+        }
+    }
+
+    /// Removes every post effect and frees the offscreen targets.
+    pub fn clear_post_effects(&mut self) {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        if let Some(post_processing) = self.post_processing.take() {
+            post_processing.delete(gl);
+        }
+    }
+
+    /// Exposure the scene is scaled with before `PostEffect::ToneMapping`.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        if let Some(post_processing) = &mut self.post_processing {
+            post_processing.exposure = exposure;
+        }
+    }
+
+    /// Darkening at the corners of `PostEffect::Vignette`, from 0 to 1, and the distance
+    /// from the center where it is complete.
+    pub fn set_vignette(&mut self, strength: f32, radius: f32) {
+        if let Some(post_processing) = &mut self.post_processing {
+            post_processing.vignette_params = [strength, radius];
+        }
+    }
+
+    /// Brightness above which `PostEffect::Bloom` makes colors glow and how strongly.
+    pub fn set_bloom(&mut self, threshold: f32, intensity: f32) {
+        if let Some(post_processing) = &mut self.post_processing {
+            post_processing.bloom_threshold = threshold;
+            post_processing.bloom_intensity = intensity;
+        }
+    }
+
+    /// Shows normals or texture coordinates instead of the materials,
+    /// `DebugView::Shaded` switches back.
+    pub fn set_debug_view(&mut self, view: DebugView) -> Result<(), JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        self.update_debug(gl, |debug| debug.view = view)
+    }
+
+    pub fn set_debug_overlay(
+        &mut self,
+        overlay: DebugOverlay,
+        enabled: bool,
+    ) -> Result<(), JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        if overlay == DebugOverlay::Wireframe && enabled {
+            for node in &mut self.nodes {
+                if let NodeType::Mesh(mesh) = &mut node.node_type {
+                    mesh.build_wireframe(gl)?;
+                }
+            }
+        }
+
+        self.update_debug(gl, |debug| debug.set_overlay(overlay, enabled))
+    }
+
+    /// Counters and timings of the last frame: `drawCalls`, `triangles`,
+    /// `programSwitches`, `textureBinds`, `bufferBytesUploaded` and `textureBytesUploaded`
+    /// since the previous frame, `totalBufferBytesUploaded` and `totalTextureBytesUploaded`
+    /// since the scene was created, `cpu` with milliseconds per render phase and `total`,
+    /// and `gpu` in the same shape when `EXT_disjoint_timer_query` is available, `null`
+    /// otherwise. GPU timings lag a few frames behind.
+    pub fn stats(&self) -> Result<JsValue, JsValue> {
+        self.profiler.to_js()
+    }
+
+    /// Shows the stats in a text box in the top left corner of the page.
+    pub fn set_stats_overlay(&mut self, visible: bool) -> Result<(), JsValue> {
+        self.profiler.set_overlay_visible(visible)
+    }
+
+    /// Sets whether the nodes named `name` cast shadows and whether shadows fall on them.
+    pub fn set_node_shadows(
+        &mut self,
+        name: &str,
+        cast: bool,
+        receive: bool,
+    ) -> Result<(), JsValue> {
+        let mut found = false;
+        for node in &mut self.nodes {
+            if node.name.as_deref() == Some(name) {
+                node.cast_shadows = cast;
+                node.receive_shadows = receive;
+                found = true;
+            }
+        }
+
+        if found {
+            Ok(())
+        } else {
+            Err(JsValue::from_str(&format!("unknown node {}", name)))
+        }
+    }
+
+    /// Sky and ground colors of the hemispheric ambient light used by
+    /// `LightingModel::BlinnPhong`. `LightingModel::Pbr` uses their mean while no
+    /// environment is set.
+    pub fn set_ambient_light(&mut self, sky: &[f32], ground: &[f32]) -> Result<(), JsValue> {
+        self.ambient_sky = vec3_arg(sky)?;
+        self.ambient_ground = vec3_arg(ground)?;
+
+        Ok(())
+    }
+
+    /// Renders to the canvas, which is resized to its displayed size first. Nothing is
+    /// rendered while the context is lost.
+    pub fn render(&mut self) -> Result<(), JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        if self.check_context_lost(gl) {
+            return Ok(());
+        }
+        self.fit_surface(gl);
+        self.render_to(gl, None)
+    }
 
+    /// Whether the WebGL context is lost, or was restored and the scene is not yet. A
+    /// `Game` restores its scene by itself, other scenes are restored with `restore`.
+    pub fn is_context_lost(&self) -> bool {
+        self.context_lost || self.gl.is_context_lost()
+    }
+
+    /// Rebuilds the GPU resources once the canvas fires `webglcontextrestored` and
+    /// resolves to the scene again, this handle can not be used any more. Failures are
+    /// logged, a scene whose context was lost again stays lost until the next restore.
+    pub async fn restore(mut self) -> Scene {
+        if let Err(error) = self.restore_context().await {
+            web_sys::console::error_1(&error);
+        }
+
+        self
+    }
+
+    /// Deletes the meshes and every GPU resource of the scene, lights and settings are
+    /// kept. Dropping the scene, like `free` from JS, disposes it as well.
+    pub fn dispose(&mut self) {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        // Objects of a lost context went with it and must not be deleted in a new one.
+        let delete = !self.is_context_lost();
+
+        self.nodes.retain(|node| match &node.node_type {
+            NodeType::Mesh(mesh) => {
+                if delete {
+                    mesh.delete(gl);
+                }
+                false
+            }
+            NodeType::Light(_) => true,
+        });
+        let programs = std::mem::take(&mut self.programs);
+        let shadow_map = self.shadow_map.take();
+        let environment = self.environment.take();
+        let post_processing = self.post_processing.take();
+        let debug = self.debug.take();
+        self.lighting.shadows = None;
+        self.lighting.environment = None;
+        if delete {
+            programs.delete(gl);
+            if let Some(shadow_map) = shadow_map {
+                shadow_map.delete(gl);
+            }
+            if let Some(environment) = environment {
+                environment.delete(gl);
+            }
+            if let Some(post_processing) = post_processing {
+                post_processing.delete(gl);
+            }
+            if let Some(debug) = debug {
+                debug.delete(gl);
+            }
+            self.profiler.delete(gl);
+        } else {
+            self.profiler.discard();
+        }
+    }
+
+    /// Renders a frame and returns it as RGBA bytes, top row first. Without a size the
+    /// canvas is captured, otherwise the scene is rendered offscreen at that size.
+    pub fn capture_pixels(
+        &mut self,
+        width: Option<i32>,
+        height: Option<i32>,
+    ) -> Result<Vec<u8>, JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        let (_, _, pixels) = self.capture(gl, width, height)?;
+
+        Ok(pixels)
+    }
+
+    /// Like `capture_pixels`, but resolves to a PNG `Blob`.
+    pub fn capture_png(
+        &mut self,
+        width: Option<i32>,
+        height: Option<i32>,
+    ) -> Result<js_sys::Promise, JsValue> {
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        let (width, height, pixels) = self.capture(gl, width, height)?;
+
+        Ok(capture::png(width, height, &pixels))
+    }
+}
+
+impl Scene {
+    async fn load_url(
+        canvas: HtmlCanvasElement,
+        url: String,
+        settings: RenderSettings,
+        max_lights: usize,
+    ) -> Result<Scene, JsValue> {
+        let glb = Glb::from_url(&url).await?;
+        let gl = backend::for_canvas_with_attributes(&canvas, &settings.context_attributes())?;
+        let mut scene = Scene::from_glb(gl, Surface::new(Some(canvas)), glb, max_lights).await?;
+        scene.settings = settings;
+
+        Ok(scene)
+    }
+
+    pub(crate) fn canvas(&self) -> Option<&HtmlCanvasElement> {
+        self.surface.canvas()
+    }
+
+    /// Builds every GPU resource again once the context is restored, from the loaded
+    /// file and the sources of the environment. Objects of the lost context are dropped
+    /// without deleting them, they went with it.
+    pub(crate) async fn restore_context(&mut self) -> Result<(), JsValue> {
+        if let Some(canvas) = self.surface.canvas() {
+            // Extension objects of the lost context are invalid as well.
+            self.gl = backend::for_canvas(canvas)?;
+        }
+        let gl = Rc::clone(&self.gl);
+        let gl = &*gl;
+        if gl.is_context_lost() {
+            return Err(JsValue::from_str("the WebGL context is still lost"));
+        }
+
+        self.profiler.restore(gl);
+        if let Some(shadow_map) = &self.shadow_map {
+            let shadow_map = ShadowMap::new(gl, shadow_map.size())?;
+            self.lighting.shadows = Some(shadow_map.format);
+            self.shadow_map = Some(shadow_map);
+        }
+        if let Some(environment) = &self.environment {
+            let environment = environment.reload(gl).await?;
+            self.lighting.environment = Some(environment.sampling);
+            self.environment = Some(environment);
+        }
+        if let Some(post_processing) = &self.post_processing {
+            self.post_processing = Some(post_processing.recreate(gl)?);
+        }
+        if let Some(debug) = &self.debug {
+            self.debug = Some(debug.recreate(gl)?);
+        }
+
+        let texture_support = CompressedTextureSupport::detect(gl);
+        let wireframes = self
+            .debug
+            .as_ref()
+            .is_some_and(|debug| debug.has_overlay(DebugOverlay::Wireframe));
+        let glb = Rc::clone(&self.glb);
+        self.programs = ProgramCache::default();
+        for node in &mut self.nodes {
+            if let NodeType::Mesh(mesh) = &mut node.node_type {
+                let mut restored = Mesh::from_gltf(
+                    gl,
+                    mesh.id(),
+                    &glb.json.gltf,
+                    &glb.buffer,
+                    &texture_support,
+                    self.lighting,
+                    &mut self.programs,
+                )
+                .await?;
+                restored.activate_variant(self.variant);
+                if wireframes {
+                    restored.build_wireframe(gl)?;
+                }
+                *mesh = restored;
+            }
+        }
+        self.context_lost = false;
+
+        Ok(())
+    }
+
+    /// Builds the default scene of `glb` for any backend.
+    pub(crate) async fn from_glb(
+        gl: Rc<dyn Backend>,
+        surface: Surface,
+        glb: Glb,
+        max_lights: usize,
+    ) -> Result<Scene, JsValue> {
+        surface.resize();
+        let aspect = surface::aspect(&*gl).unwrap_or(1.);
         // Todo: Fix hardcoded camera
         let camera = SimpleCamera::new([-2., 5., 10.], [0., 0., 0.], [0., 1., 0.], aspect);
 
-        Ok(Scene::from_gltf(&glb.json.gltf, &scene, &gl, &glb.buffer, Some(camera)).await?)
+        let texture_support = CompressedTextureSupport::detect(&*gl);
+
+        Scene::from_gltf(
+            Rc::new(glb),
+            gl,
+            surface,
+            &texture_support,
+            Some(camera),
+            LightingOptions {
+                max_lights,
+                ..LightingOptions::default()
+            },
+        )
+        .await
+    }
+
+    fn render_to(
+        &mut self,
+        gl: &dyn Backend,
+        output: Option<&RenderTarget>,
+    ) -> Result<(), JsValue> {
+        self.profiler.begin_frame(gl);
+        self.profiler.phase(gl, RenderPhase::Lights);
+        let mut lights = ShaderLights {
+            ambient_sky: self.ambient_sky,
+            ambient_ground: self.ambient_ground,
+            ..ShaderLights::default()
+        };
+        let mut shadow_light = None;
+        for node in &self.nodes {
+            if let NodeType::Light(light) = &node.node_type {
+                if lights.count as usize == self.lighting.max_lights {
+                    break;
+                }
+                if shadow_light.is_none() && light.kind == LightKind::Directional {
+                    shadow_light = Some(lights.count as usize);
+                }
+                lights.push(light, &node.model_matrix);
+            }
+        }
+
+        self.profiler.phase(gl, RenderPhase::Shadows);
+        let counters = &self.profiler.counters;
+        let shadow_rendered = match (&mut self.shadow_map, shadow_light) {
+            (Some(shadow_map), Some(index)) => {
+                shadow_map.fit(
+                    &lights.direction(index),
+                    &self.camera.frustum_corners(self.shadow_distance),
+                );
+                shadow_map.begin(gl, counters);
+                for node in &self.nodes {
+                    if let (NodeType::Mesh(mesh), true) = (&node.node_type, node.cast_shadows) {
+                        shadow_map.set_model_matrix(gl, &node.model_matrix);
+                        mesh.render_depth(gl, shadow_map, counters)?;
+                    }
+                }
+                shadow_map.end(gl);
+
+                true
+            }
+            _ => false,
+        };
+        let shadow_map = self.shadow_map.as_ref().filter(|_| shadow_rendered);
+
+        self.settings.apply(gl);
+        let post_processing_active = match &mut self.post_processing {
+            Some(post_processing) if post_processing.is_active() => {
+                post_processing.begin(gl, output)?;
+                true
+            }
+            _ => {
+                render_target::bind_output(gl, output);
+                false
+            }
+        };
+        self.settings.clear(gl);
+
+        self.profiler.phase(gl, RenderPhase::Skybox);
+        let counters = &self.profiler.counters;
+        if let Some(environment) = &self.environment {
+            environment.render_skybox(gl, &self.camera.view(), &self.camera.projection(), counters);
+        }
+
+        self.profiler.phase(gl, RenderPhase::Meshes);
+        let counters = &self.profiler.counters;
+        let mut view_projection = mat4::create();
+        mat4::multiply(
+            &mut view_projection,
+            &self.camera.projection(),
+            &self.camera.view(),
+        );
+        let debug_view = self
+            .debug
+            .as_ref()
+            .filter(|debug| debug.view != DebugView::Shaded);
+        if let Some(debug) = debug_view {
+            debug.begin_surfaces(gl, &view_projection, counters);
+        }
+
+        match debug_view {
+            Some(debug) => {
+                for node in &self.nodes {
+                    if let NodeType::Mesh(mesh) = &node.node_type {
+                        debug.set_surface_matrices(
+                            gl,
+                            &node.model_matrix,
+                            &Scene::normal_matrix(&node.model_matrix),
+                        );
+                        mesh.render_debug(gl, debug.surface_attributes(), counters)?;
+                    }
+                }
+                debug.end_surfaces(gl);
+            }
+            None => {
+                let view_matrix = self.camera.view();
+                let projection_matrix = self.camera.projection();
+                let camera_position = self.camera.position();
+
+                self.queue.clear();
+                for (index, node) in self.nodes.iter().enumerate() {
+                    if let NodeType::Mesh(mesh) = &node.node_type {
+                        let depth =
+                            Scene::camera_distance(mesh, &node.model_matrix, &camera_position);
+                        mesh.queue(index, depth, &mut self.queue);
+                    }
+                }
+                self.queue.sort();
+
+                let mut state = StateTracker::new(gl, self.settings.blend_func());
+                for item in self.queue.items() {
+                    let node = &self.nodes[item.node];
+                    if let NodeType::Mesh(mesh) = &node.node_type {
+                        let descriptor = MeshRenderDescriptor {
+                            model_matrix: node.model_matrix,
+                            normal_matrix: Scene::normal_matrix(&node.model_matrix),
+                            view_matrix,
+                            projection_matrix,
+                            camera_position,
+                            lights: &lights,
+                            shadow_map,
+                            shadow_light: match (shadow_map, shadow_light) {
+                                (Some(_), Some(index)) if node.receive_shadows => index as i32,
+                                _ => -1,
+                            },
+                            environment: self.environment.as_ref(),
+                            counters,
+                        };
+                        mesh.render_primitive(gl, item.primitive, &descriptor, &mut state)?;
+                    }
+                }
+                state.finish(gl);
+            }
+        }
+
+        if let Some(debug) = &self.debug {
+            self.profiler.phase(gl, RenderPhase::Debug);
+            self.render_debug_overlays(gl, debug, shadow_map, &view_projection)?;
+        }
+
+        if let (Some(post_processing), true) = (&self.post_processing, post_processing_active) {
+            self.profiler.phase(gl, RenderPhase::PostProcessing);
+            post_processing.end(gl, output, &self.profiler.counters);
+        }
+        self.profiler.end_frame(gl);
+
+        Ok(())
     }
 
-    pub fn render(&self, gl: &WebGlRenderingContext) -> Result<(), JsValue> {
+    fn render_debug_overlays(
+        &self,
+        gl: &dyn Backend,
+        debug: &DebugRenderer,
+        shadow_map: Option<&ShadowMap>,
+        view_projection: &[f32; 16],
+    ) -> Result<(), JsValue> {
+        let counters = &self.profiler.counters;
+        if debug.has_overlay(DebugOverlay::Wireframe) {
+            debug.begin_wireframes(gl, view_projection, counters);
+            for node in &self.nodes {
+                if let NodeType::Mesh(mesh) = &node.node_type {
+                    debug.set_wireframe_model_matrix(gl, &node.model_matrix);
+                    mesh.render_wireframe(gl, debug.wireframe_position_location(), counters)?;
+                }
+            }
+            debug.end_wireframes(gl);
+        }
+
+        let mut lines = LineBatch::default();
+        if debug.has_overlay(DebugOverlay::Grid) {
+            lines.grid(debug::GRID_EXTENT);
+        }
         for node in &self.nodes {
+            if debug.has_overlay(DebugOverlay::Axes) {
+                lines.axes(&node.model_matrix, debug::AXIS_LENGTH);
+            }
             match &node.node_type {
-                NodeType::Mesh(mesh) => {
-                    let descriptor = MeshRenderDescriptor {
-                        model_matrix: mat4::create(),
-                        view_matrix: self.camera.view(),
-                        projection_matrix: self.camera.projection(),
-                    };
-                    mesh.render(&gl, &descriptor)?;
+                NodeType::Mesh(mesh) if debug.has_overlay(DebugOverlay::BoundingBoxes) => {
+                    if let Some((min, max)) = mesh.bounds() {
+                        lines.bounding_box(
+                            &min,
+                            &max,
+                            &node.model_matrix,
+                            &debug::BOUNDING_BOX_COLOR,
+                        );
+                    }
                 }
+                NodeType::Light(light) if debug.has_overlay(DebugOverlay::Frustums) => {
+                    let matrix = &node.model_matrix;
+                    match light.kind {
+                        LightKind::Directional => {
+                            let position = [matrix[12], matrix[13], matrix[14]];
+                            let length = debug::LIGHT_OUTLINE_LENGTH;
+                            lines.line(
+                                &position,
+                                &[
+                                    position[0] - matrix[8] * length,
+                                    position[1] - matrix[9] * length,
+                                    position[2] - matrix[10] * length,
+                                ],
+                                &debug::LIGHT_COLOR,
+                            );
+                        }
+                        LightKind::Point => lines.sphere(
+                            matrix,
+                            light.range.unwrap_or(debug::LIGHT_OUTLINE_LENGTH),
+                            &debug::LIGHT_COLOR,
+                        ),
+                        LightKind::Spot {
+                            outer_cone_angle, ..
+                        } => lines.cone(
+                            matrix,
+                            outer_cone_angle,
+                            light.range.unwrap_or(debug::LIGHT_OUTLINE_LENGTH),
+                            &debug::LIGHT_COLOR,
+                        ),
+                    }
+                }
+                _ => {}
+            }
+        }
+        if debug.has_overlay(DebugOverlay::Frustums) {
+            if let Some(shadow_map) = shadow_map {
+                lines.frustum(shadow_map.light_space_matrix(), &debug::LIGHT_COLOR);
             }
+            lines.corners(
+                &self.camera.frustum_corners(self.shadow_distance),
+                &debug::CAMERA_COLOR,
+            );
         }
 
+        debug.draw_lines(gl, &lines, view_projection, counters);
+
         Ok(())
     }
-}
 
-impl Scene {
+    /// Applies `update` to the debug renderer, creating it on first use and freeing it
+    /// once nothing is left to show.
+    fn update_debug(
+        &mut self,
+        gl: &dyn Backend,
+        update: impl FnOnce(&mut DebugRenderer),
+    ) -> Result<(), JsValue> {
+        let mut debug = match self.debug.take() {
+            Some(debug) => debug,
+            None => DebugRenderer::new(gl)?,
+        };
+        update(&mut debug);
+
+        if debug.is_idle() {
+            debug.delete(gl);
+        } else {
+            self.debug = Some(debug);
+        }
+
+        Ok(())
+    }
+
+    /// Notes a loss of the context, returns whether the scene can not render.
+    fn check_context_lost(&mut self, gl: &dyn Backend) -> bool {
+        if gl.is_context_lost() {
+            self.context_lost = true;
+        }
+
+        self.context_lost
+    }
+
+    /// Resizes the canvas to its displayed size and matches the camera to it.
+    fn fit_surface(&mut self, gl: &dyn Backend) {
+        self.surface.resize();
+        if let Some(aspect) = surface::aspect(gl) {
+            if aspect != self.camera.aspect() {
+                self.camera.set_aspect(aspect);
+            }
+        }
+    }
+
+    fn capture(
+        &mut self,
+        gl: &dyn Backend,
+        width: Option<i32>,
+        height: Option<i32>,
+    ) -> Result<(i32, i32, Vec<u8>), JsValue> {
+        if self.check_context_lost(gl) {
+            return Err(JsValue::from_str("the WebGL context is lost"));
+        }
+        let (width, height) = match (width, height) {
+            (None, None) => {
+                self.fit_surface(gl);
+                self.render_to(gl, None)?;
+                let (width, height) = render_target::output_size(gl, None);
+
+                return Ok((width, height, capture::read_pixels(gl, width, height)?));
+            }
+            (Some(width), Some(height)) if width > 0 && height > 0 => (width, height),
+            _ => {
+                return Err(JsValue::from_str(
+                    "capture needs either no size or a positive width and height",
+                ))
+            }
+        };
+        capture::check_size(gl, width, height)?;
+
+        let target = RenderTarget::new(
+            gl,
+            width,
+            height,
+            true,
+            WebGlRenderingContext::UNSIGNED_BYTE,
+        )?;
+        let aspect = self.camera.aspect();
+        self.camera.set_aspect(width as f32 / height as f32);
+        let pixels = self.render_to(gl, Some(&target)).and_then(|_| {
+            target.bind(gl);
+            capture::read_pixels(gl, width, height)
+        });
+        self.camera.set_aspect(aspect);
+
+        render_target::bind_output(gl, None);
+        target.delete(gl);
+
+        Ok((width, height, pixels?))
+    }
     async fn from_gltf(
-        gltf: &GlTf,
-        scene: &GlTfScene,
-        gl: &WebGlRenderingContext,
-        glb_buffer: &GlbBuffer,
+        glb: Rc<Glb>,
+        gl: Rc<dyn Backend>,
+        surface: Surface,
+        texture_support: &CompressedTextureSupport,
         camera: Option<SimpleCamera>,
+        lighting: LightingOptions,
     ) -> Result<Scene, JsValue> {
+        let gltf = &glb.json.gltf;
+        let scene = gltf
+            .scenes
+            .get(gltf.scene)
+            .ok_or_else(|| JsValue::from_str(&format!("Scene with id {} not found", gltf.scene)))?;
         let mut nodes = vec![];
-        for node_id in &scene.nodes {
-            let node = gltf.nodes.get(*node_id).ok_or(JsValue::from_str(&format!(
-                "Node with id {} not found",
-                node_id
-            )))?;
+        let mut programs = ProgramCache::default();
+        let mut next_light_id = 0;
+        for (node_id, model_matrix) in Scene::flatten_nodes(&gltf.nodes, &scene.nodes)? {
+            let node = &gltf.nodes[node_id];
 
             if let Some(mesh_id) = node.mesh {
-                let mesh = gltf.meshes.get(mesh_id).ok_or(JsValue::from_str(&format!(
-                    "could not find mesh with id {}",
-                    mesh_id
-                )))?;
-                let node_type =
-                    NodeType::Mesh(Mesh::from_gltf(&gl, &mesh, &gltf, &glb_buffer).await?);
+                let node_type = NodeType::Mesh(
+                    Mesh::from_gltf(
+                        &*gl,
+                        mesh_id,
+                        gltf,
+                        &glb.buffer,
+                        texture_support,
+                        lighting,
+                        &mut programs,
+                    )
+                    .await?,
+                );
 
                 nodes.push(SceneNode {
                     name: node.name.clone(),
-                    rotation: node.rotation,
-                    translation: node.translation,
+                    model_matrix,
                     node_type,
+                    cast_shadows: true,
+                    receive_shadows: true,
                 })
             }
+
+            if let Some(node_light) = node
+                .extensions
+                .as_ref()
+                .and_then(|extensions| extensions.lights_punctual.as_ref())
+            {
+                let light = gltf
+                    .extensions
+                    .as_ref()
+                    .and_then(|extensions| extensions.lights_punctual.as_ref())
+                    .and_then(|lights_punctual| lights_punctual.lights.get(node_light.light))
+                    .ok_or_else(|| {
+                        JsValue::from_str(&format!(
+                            "could not find light with id {}",
+                            node_light.light
+                        ))
+                    })?;
+
+                nodes.push(SceneNode {
+                    name: node.name.clone(),
+                    model_matrix,
+                    node_type: NodeType::Light(Light::from_gltf(next_light_id, light)?),
+                    cast_shadows: false,
+                    receive_shadows: false,
+                });
+                next_light_id += 1;
+            }
         }
-        let camera = camera.ok_or(JsValue::from_str(
-            "camera from gltf is currently not supported",
-        ))?;
+        let camera = camera
+            .ok_or_else(|| JsValue::from_str("camera from gltf is currently not supported"))?;
+
+        let variants = gltf
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.materials_variants.as_ref())
+            .map(|materials_variants| {
+                materials_variants
+                    .variants
+                    .iter()
+                    .map(|variant| variant.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Scene {
+            profiler: Profiler::new(&*gl),
+            gl,
+            glb,
+            surface,
+            settings: RenderSettings::default(),
+            nodes,
+            camera,
+            variants,
+            variant: None,
+            lighting,
+            programs,
+            ambient_sky: DEFAULT_AMBIENT_SKY,
+            ambient_ground: DEFAULT_AMBIENT_GROUND,
+            next_light_id,
+            shadow_map: None,
+            shadow_distance: DEFAULT_SHADOW_DISTANCE,
+            environment: None,
+            post_processing: None,
+            debug: None,
+            queue: RenderQueue::default(),
+            context_lost: false,
+        })
+    }
+
+    fn add_light(
+        &mut self,
+        kind: LightKind,
+        color: &[f32],
+        intensity: f32,
+        range: Option<f32>,
+        model_matrix: [f32; 16],
+    ) -> Result<u32, JsValue> {
+        let color = vec3_arg(color)?;
+        let id = self.next_light_id;
+        self.next_light_id += 1;
+
+        self.nodes.push(SceneNode {
+            name: None,
+            model_matrix,
+            node_type: NodeType::Light(Light {
+                id,
+                kind,
+                color,
+                intensity,
+                range,
+            }),
+            cast_shadows: false,
+            receive_shadows: false,
+        });
+
+        Ok(id)
+    }
+
+    /// Recompiles the mesh shaders after the lighting options changed, the old programs
+    /// are deleted once no mesh uses them anymore.
+    fn apply_lighting(&mut self, gl: &dyn Backend) -> Result<(), JsValue> {
+        let previous = std::mem::take(&mut self.programs);
+        for node in &mut self.nodes {
+            if let NodeType::Mesh(mesh) = &mut node.node_type {
+                mesh.set_lighting(gl, self.lighting, &mut self.programs)?;
+            }
+        }
+        previous.delete(gl);
+
+        Ok(())
+    }
+
+    fn apply_variant(&mut self, variant: Option<usize>) {
+        self.variant = variant;
+        for node in &mut self.nodes {
+            match &mut node.node_type {
+                NodeType::Mesh(mesh) => mesh.activate_variant(variant),
+                NodeType::Light(_) => {}
+            }
+        }
+    }
+
+    /// Distance from `eye` to the center of the mesh bounds, or to the node origin for
+    /// meshes without bounds.
+    fn camera_distance(mesh: &Mesh, model_matrix: &[f32; 16], eye: &[f32; 3]) -> f32 {
+        let local_center = mesh
+            .bounds()
+            .map(|(min, max)| {
+                [
+                    (min[0] + max[0]) / 2.,
+                    (min[1] + max[1]) / 2.,
+                    (min[2] + max[2]) / 2.,
+                ]
+            })
+            .unwrap_or([0.; 3]);
+        let mut center = vec3::create();
+        vec3::transform_mat4(&mut center, &local_center, model_matrix);
+
+        vec3::distance(&center, eye)
+    }
+
+    fn normal_matrix(model_matrix: &[f32; 16]) -> [f32; 9] {
+        let mut normal_matrix = mat3::create();
+        if mat3::normal_from_mat4(&mut normal_matrix, model_matrix).is_none() {
+            // Degenerate transforms (a zero scale) have no inverse, the mesh is flat anyway.
+            mat3::from_mat4(&mut normal_matrix, model_matrix);
+        }
+
+        normal_matrix
+    }
+
+    /// Node ids under `roots` in depth first order, with their model matrices. glTF nodes
+    /// form strict trees, a node reached twice through a cycle or a second parent is an
+    /// error rather than loaded again.
+    fn flatten_nodes(
+        nodes: &[GlTfNode],
+        roots: &[usize],
+    ) -> Result<Vec<(usize, [f32; 16])>, DecodeError> {
+        let mut visited = vec![false; nodes.len()];
+        let mut flattened = vec![];
+        let mut pending: Vec<(usize, [f32; 16])> = roots
+            .iter()
+            .rev()
+            .map(|node_id| (*node_id, mat4::create()))
+            .collect();
+
+        while let Some((node_id, parent_matrix)) = pending.pop() {
+            let node = nodes
+                .get(node_id)
+                .ok_or_else(|| DecodeError::new(format!("Node with id {} not found", node_id)))?;
+            if std::mem::replace(&mut visited[node_id], true) {
+                return Err(DecodeError::new(format!(
+                    "Node with id {} is reached more than once, nodes must form trees",
+                    node_id
+                )));
+            }
+
+            let mut model_matrix = mat4::create();
+            mat4::multiply(
+                &mut model_matrix,
+                &parent_matrix,
+                &Scene::local_matrix(node),
+            );
+            if let Some(children) = &node.children {
+                for child_id in children.iter().rev() {
+                    pending.push((*child_id, model_matrix));
+                }
+            }
+            flattened.push((node_id, model_matrix));
+        }
+
+        Ok(flattened)
+    }
+
+    // Quantized meshes (KHR_mesh_quantization) rely on the node transform to map the
+    // integer attribute range back into model space, so the full TRS has to be applied.
+    fn local_matrix(node: &GlTfNode) -> [f32; 16] {
+        if let Some(matrix) = node.matrix {
+            return matrix;
+        }
+
+        let rotation = node.rotation.unwrap_or([0., 0., 0., 1.]);
+        let translation = node.translation.unwrap_or([0., 0., 0.]);
+        let scale = node.scale.unwrap_or([1., 1., 1.]);
+
+        let mut matrix = mat4::create();
+        mat4::from_rotation_translation_scale(&mut matrix, &rotation, &translation, &scale);
+
+        matrix
+    }
+}
+
+impl Drop for Scene {
+    fn drop(&mut self) {
+        self.dispose();
+    }
+}
 
-        Ok(Scene { nodes, camera })
+fn vec3_arg(values: &[f32]) -> Result<[f32; 3], JsValue> {
+    match values {
+        [x, y, z] => Ok([*x, *y, *z]),
+        _ => Err(JsValue::from_str(&format!(
+            "expected 3 components, got {}",
+            values.len()
+        ))),
     }
 }
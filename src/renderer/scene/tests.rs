@@ -0,0 +1,435 @@
+use super::Scene;
+use crate::definitions::gltf::GlTfNode;
+use crate::loader::glb::Glb;
+use crate::loader::png;
+use crate::renderer::backend::headless::{Command, HeadlessBackend};
+use crate::renderer::debug::{DebugOverlay, DebugView};
+use crate::renderer::light::{LightingModel, DEFAULT_MAX_LIGHTS};
+use crate::renderer::post::PostEffect;
+use crate::renderer::settings::{CullMode, DepthFunction, RenderSettings};
+use crate::renderer::surface::Surface;
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use web_sys::WebGlRenderingContext;
+
+const GOLDEN_WIDTH: i32 = 256;
+const GOLDEN_HEIGHT: i32 = 192;
+/// Largest difference of a channel before a pixel counts as changed.
+const CHANNEL_TOLERANCE: u8 = 8;
+/// Share of the pixels that may change before a frame no longer matches.
+const CHANGED_PIXELS_TOLERANCE: f32 = 0.005;
+
+/// Runs a future that never waits, which holds for loading without a browser.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let mut context = Context::from_waker(Waker::noop());
+    match future.as_mut().poll(&mut context) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("future is waiting on the browser"),
+    }
+}
+
+fn load(gl: &Rc<HeadlessBackend>, data: &[u8]) -> Scene {
+    let glb = Glb::from_bytes(data).unwrap();
+    let scene = block_on(Scene::from_glb(
+        gl.clone(),
+        Surface::new(None),
+        glb,
+        DEFAULT_MAX_LIGHTS,
+    ))
+    .unwrap();
+    assert_eq!(gl.errors(), Vec::<String>::new());
+
+    scene
+}
+
+fn draws(commands: &[Command]) -> Vec<&Command> {
+    commands
+        .iter()
+        .filter(|command| matches!(command, Command::DrawElements { .. }))
+        .collect()
+}
+
+fn assert_renders_mesh(data: &[u8], index_count: i32) {
+    let gl = Rc::new(HeadlessBackend::new(640, 480));
+    let mut scene = load(&gl, data);
+    let commands = gl.take_commands();
+    assert!(commands.contains(&Command::TexImage {
+        target: WebGlRenderingContext::TEXTURE_2D,
+        level: 0,
+        width: 1024,
+        height: 1024,
+    }));
+
+    scene.render().unwrap();
+
+    assert_eq!(gl.errors(), Vec::<String>::new());
+    assert_eq!(
+        draws(&gl.take_commands()),
+        vec![&Command::DrawElements {
+            mode: WebGlRenderingContext::TRIANGLES,
+            count: index_count,
+            data_type: WebGlRenderingContext::UNSIGNED_SHORT,
+            offset: 0,
+        }]
+    );
+}
+
+#[test]
+fn renders_suzanne() {
+    assert_renders_mesh(include_bytes!("../../../models/test/suzanne.glb"), 2904);
+}
+
+#[test]
+fn renders_suzanne1() {
+    assert_renders_mesh(include_bytes!("../../../models/test/suzanne1.glb"), 2904);
+}
+
+#[test]
+fn renders_suzanne2() {
+    assert_renders_mesh(include_bytes!("../../../models/test/suzanne2.glb"), 11808);
+}
+
+#[test]
+fn renders_shadows_before_the_mesh() {
+    let gl = Rc::new(HeadlessBackend::new(640, 480).with_extensions(&["WEBGL_depth_texture"]));
+    let mut scene = load(&gl, include_bytes!("../../../models/test/suzanne.glb"));
+    scene
+        .add_directional_light(&[0., -1., -1.], &[1., 1., 1.], 1.)
+        .unwrap();
+    scene.enable_shadows(None).unwrap();
+    gl.take_commands();
+
+    scene.render().unwrap();
+
+    assert_eq!(gl.errors(), Vec::<String>::new());
+    let commands = gl.take_commands();
+    let draws = draws(&commands);
+    assert_eq!(draws.len(), 2);
+    assert_eq!(draws[0], draws[1]);
+}
+
+#[test]
+fn renders_every_frame_the_same() {
+    let gl = Rc::new(HeadlessBackend::new(640, 480));
+    let mut scene = load(&gl, include_bytes!("../../../models/test/suzanne.glb"));
+    scene.render().unwrap();
+    gl.take_commands();
+
+    scene.render().unwrap();
+    let first = gl.take_commands();
+    scene.render().unwrap();
+
+    assert_eq!(gl.errors(), Vec::<String>::new());
+    assert_eq!(gl.take_commands(), first);
+}
+
+#[test]
+fn follows_the_drawing_buffer_size() {
+    let gl = Rc::new(HeadlessBackend::new(640, 480));
+    let mut scene = load(&gl, include_bytes!("../../../models/test/suzanne.glb"));
+    assert_eq!(scene.camera.aspect(), 640. / 480.);
+
+    gl.resize(300, 100);
+    gl.take_commands();
+    scene.render().unwrap();
+
+    assert_eq!(scene.camera.aspect(), 3.);
+    assert!(gl
+        .take_commands()
+        .contains(&Command::Viewport(0, 0, 300, 100)));
+}
+
+#[test]
+fn applies_the_render_settings_before_drawing() {
+    let gl = Rc::new(HeadlessBackend::new(640, 480));
+    let mut scene = load(&gl, include_bytes!("../../../models/test/suzanne.glb"));
+    let mut settings = RenderSettings::new();
+    settings.set_clear_color(0.5, 0.25, 0., 1.);
+    settings.set_depth_function(DepthFunction::LessOrEqual);
+    settings.set_cull_mode(CullMode::None);
+    scene.set_render_settings(settings);
+    gl.take_commands();
+
+    scene.render().unwrap();
+
+    let commands = gl.take_commands();
+    let first_draw = commands
+        .iter()
+        .position(|command| matches!(command, Command::DrawElements { .. }))
+        .unwrap();
+    for expected in [
+        Command::Enable(WebGlRenderingContext::DEPTH_TEST),
+        Command::DepthFunc(WebGlRenderingContext::LEQUAL),
+        Command::Disable(WebGlRenderingContext::CULL_FACE),
+        Command::ClearColor([0.5, 0.25, 0., 1.]),
+        Command::Clear(
+            WebGlRenderingContext::COLOR_BUFFER_BIT | WebGlRenderingContext::DEPTH_BUFFER_BIT,
+        ),
+    ] {
+        assert!(commands[..first_draw].contains(&expected), "{:?}", expected);
+    }
+}
+
+#[test]
+fn restores_the_scene_after_the_context_is_lost() {
+    let gl = Rc::new(HeadlessBackend::new(640, 480));
+    let mut scene = load(&gl, include_bytes!("../../../models/test/suzanne.glb"));
+    scene.render().unwrap();
+    let frame = gl.take_commands();
+
+    gl.lose_context();
+    scene.render().unwrap();
+    assert!(scene.is_context_lost());
+    assert_eq!(gl.take_commands(), vec![]);
+
+    gl.restore_context();
+    block_on(scene.restore_context()).unwrap();
+    gl.take_commands();
+    scene.render().unwrap();
+
+    assert!(!scene.is_context_lost());
+    assert_eq!(gl.errors(), Vec::<String>::new());
+    assert_eq!(draws(&gl.take_commands()), draws(&frame));
+}
+
+#[test]
+fn restore_resolves_to_the_restored_scene() {
+    let gl = Rc::new(HeadlessBackend::new(640, 480));
+    let mut scene = load(&gl, include_bytes!("../../../models/test/suzanne.glb"));
+
+    gl.lose_context();
+    scene.render().unwrap();
+    gl.restore_context();
+    let mut scene = block_on(scene.restore());
+    gl.take_commands();
+    scene.render().unwrap();
+
+    assert!(!scene.is_context_lost());
+    assert_eq!(gl.errors(), Vec::<String>::new());
+    assert!(!draws(&gl.take_commands()).is_empty());
+}
+
+#[test]
+fn disposing_deletes_every_gpu_resource() {
+    let gl = Rc::new(HeadlessBackend::new(640, 480).with_extensions(&["WEBGL_depth_texture"]));
+    let mut scene = load(&gl, include_bytes!("../../../models/test/suzanne.glb"));
+    scene
+        .add_directional_light(&[0., -1., -1.], &[1., 1., 1.], 1.)
+        .unwrap();
+    scene.enable_shadows(None).unwrap();
+    scene.add_post_effect(PostEffect::Fxaa).unwrap();
+    scene
+        .set_debug_overlay(DebugOverlay::Wireframe, true)
+        .unwrap();
+    scene.render().unwrap();
+    assert!(gl.live_objects() > 0);
+
+    scene.dispose();
+
+    assert_eq!(gl.errors(), Vec::<String>::new());
+    assert_eq!(gl.live_objects(), 0);
+    gl.take_commands();
+    scene.render().unwrap();
+    assert_eq!(gl.errors(), Vec::<String>::new());
+    assert_eq!(draws(&gl.take_commands()), Vec::<&Command>::new());
+}
+
+#[test]
+fn passes_disable_the_attribute_arrays_they_enable() {
+    let gl = Rc::new(HeadlessBackend::new(640, 480).with_extensions(&["WEBGL_depth_texture"]));
+    let mut scene = load(&gl, include_bytes!("../../../models/test/suzanne.glb"));
+    scene
+        .add_directional_light(&[0., -1., -1.], &[1., 1., 1.], 1.)
+        .unwrap();
+    scene.enable_shadows(None).unwrap();
+    scene.add_post_effect(PostEffect::Fxaa).unwrap();
+    for overlay in &[DebugOverlay::Wireframe, DebugOverlay::Grid] {
+        scene.set_debug_overlay(*overlay, true).unwrap();
+    }
+
+    scene.render().unwrap();
+
+    for view in &[DebugView::Shaded, DebugView::Normals] {
+        scene.set_debug_view(*view).unwrap();
+        gl.take_commands();
+        scene.render().unwrap();
+
+        assert_eq!(gl.errors(), Vec::<String>::new());
+        assert_eq!(gl.enabled_attributes(), Vec::<u32>::new());
+        // Suzanne has a single primitive, so every program is bound at the start of a
+        // pass, when the previous pass must have disabled its arrays.
+        let mut enabled = HashSet::new();
+        for command in gl.take_commands() {
+            match command {
+                Command::EnableAttribute(location) => {
+                    enabled.insert(location);
+                }
+                Command::DisableAttribute(location) => {
+                    enabled.remove(&location);
+                }
+                Command::UseProgram(_) => assert_eq!(enabled, HashSet::new()),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[test]
+fn disposing_a_lost_scene_deletes_nothing() {
+    let gl = Rc::new(HeadlessBackend::new(640, 480));
+    let mut scene = load(&gl, include_bytes!("../../../models/test/suzanne.glb"));
+    scene.render().unwrap();
+    gl.lose_context();
+    gl.take_commands();
+
+    scene.dispose();
+
+    assert_eq!(gl.errors(), Vec::<String>::new());
+    assert_eq!(gl.take_commands(), vec![]);
+}
+
+fn nodes(json: &str) -> Vec<GlTfNode> {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn flattens_nodes_depth_first_with_their_parent_transforms() {
+    let nodes = nodes(
+        r#"[
+            {"children": [1, 2], "translation": [1, 0, 0]},
+            {"translation": [0, 2, 0]},
+            {"children": [3]},
+            {}
+        ]"#,
+    );
+    let flattened = Scene::flatten_nodes(&nodes, &[0]).unwrap();
+
+    let order: Vec<usize> = flattened.iter().map(|(node_id, _)| *node_id).collect();
+    assert_eq!(order, vec![0, 1, 2, 3]);
+    assert_eq!(flattened[1].1[12..15], [1., 2., 0.]);
+    assert_eq!(flattened[3].1[12..15], [1., 0., 0.]);
+}
+
+#[test]
+fn invalid_node_trees_are_an_error() {
+    // A cycle.
+    let cyclic = nodes(r#"[{"children": [1]}, {"children": [0]}]"#);
+    assert!(Scene::flatten_nodes(&cyclic, &[0]).is_err());
+    let self_parent = nodes(r#"[{"children": [0]}]"#);
+    assert!(Scene::flatten_nodes(&self_parent, &[0]).is_err());
+
+    // A node with two parents, or listed as root and child.
+    let shared = nodes(r#"[{"children": [2]}, {"children": [2]}, {}]"#);
+    assert!(Scene::flatten_nodes(&shared, &[0, 1]).is_err());
+    let tree = nodes(r#"[{"children": [1]}, {}]"#);
+    assert!(Scene::flatten_nodes(&tree, &[0, 1]).is_err());
+
+    // A node that does not exist.
+    assert!(Scene::flatten_nodes(&tree, &[2]).is_err());
+}
+
+/// Renders a frame the way the sample page does, with the default settings on a dark
+/// background, and returns it top row first.
+fn render_frame(gl: &HeadlessBackend, scene: &mut Scene) -> Vec<u8> {
+    let mut settings = RenderSettings::new();
+    settings.set_clear_color(0.2, 0.2, 0.2, 1.);
+    scene.set_render_settings(settings);
+
+    let pixels = scene.capture_pixels(None, None).unwrap();
+    assert_eq!(gl.errors(), Vec::<String>::new());
+
+    pixels
+}
+
+/// Compares a frame to `models/test/golden/<name>.png`. Running the tests with
+/// `UPDATE_GOLDEN=1` writes the frames as the new golden images instead.
+fn assert_matches_golden(name: &str, pixels: &[u8]) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("models/test/golden")
+        .join(format!("{}.png", name));
+    let encoded = png::encode(GOLDEN_WIDTH as u32, GOLDEN_HEIGHT as u32, pixels);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, encoded).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read(&path)
+        .unwrap_or_else(|_| panic!("{} is missing, run with UPDATE_GOLDEN=1", path.display()));
+    let golden = png::parse(&golden).unwrap();
+    assert_eq!(
+        (golden.width, golden.height),
+        (GOLDEN_WIDTH as u32, GOLDEN_HEIGHT as u32)
+    );
+    let changed = pixels
+        .chunks_exact(4)
+        .zip(golden.data.chunks_exact(4))
+        .filter(|(pixel, golden)| {
+            pixel
+                .iter()
+                .zip(golden.iter())
+                .any(|(channel, golden)| channel.abs_diff(*golden) > CHANNEL_TOLERANCE)
+        })
+        .count();
+
+    let allowed = (pixels.len() / 4) as f32 * CHANGED_PIXELS_TOLERANCE;
+    if changed as f32 > allowed {
+        let actual = std::env::temp_dir().join(format!("{}.actual.png", name));
+        std::fs::write(&actual, encoded).unwrap();
+        panic!(
+            "{} pixels differ from {}, the frame was written to {}",
+            changed,
+            path.display(),
+            actual.display()
+        );
+    }
+}
+
+fn golden_scene(data: &[u8]) -> (Rc<HeadlessBackend>, Scene) {
+    let gl = Rc::new(HeadlessBackend::new(GOLDEN_WIDTH, GOLDEN_HEIGHT).with_rasterizer());
+    let scene = load(&gl, data);
+
+    (gl, scene)
+}
+
+#[test]
+fn suzanne_matches_golden() {
+    let (gl, mut scene) = golden_scene(include_bytes!("../../../models/test/suzanne.glb"));
+
+    assert_matches_golden("suzanne", &render_frame(&gl, &mut scene));
+}
+
+#[test]
+fn suzanne2_matches_golden() {
+    let (gl, mut scene) = golden_scene(include_bytes!("../../../models/test/suzanne2.glb"));
+
+    assert_matches_golden("suzanne2", &render_frame(&gl, &mut scene));
+}
+
+#[test]
+fn lit_suzanne_matches_golden() {
+    let (gl, mut scene) = golden_scene(include_bytes!("../../../models/test/suzanne.glb"));
+    scene
+        .add_directional_light(&[-1., -1., -1.], &[1., 1., 1.], 3.)
+        .unwrap();
+    scene
+        .add_point_light(&[2., 3., 2.], &[1., 0.5, 0.2], 10., None)
+        .unwrap();
+
+    assert_matches_golden("suzanne_lit", &render_frame(&gl, &mut scene));
+}
+
+#[test]
+fn blinn_phong_suzanne_matches_golden() {
+    let (gl, mut scene) = golden_scene(include_bytes!("../../../models/test/suzanne.glb"));
+    scene.set_lighting_model(LightingModel::BlinnPhong).unwrap();
+    scene
+        .add_directional_light(&[-1., -1., -1.], &[1., 1., 1.], 3.)
+        .unwrap();
+
+    assert_matches_golden("suzanne_blinn_phong", &render_frame(&gl, &mut scene));
+}
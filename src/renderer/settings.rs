@@ -0,0 +1,215 @@
+use crate::renderer::backend::Backend;
+use wasm_bindgen::prelude::*;
+use web_sys::{WebGlContextAttributes, WebGlPowerPreference, WebGlRenderingContext};
+
+/// Comparison a fragment's depth has to pass against the depth buffer.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthFunction {
+    Never,
+    Less,
+    Equal,
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
+impl DepthFunction {
+    fn gl(self) -> u32 {
+        match self {
+            DepthFunction::Never => WebGlRenderingContext::NEVER,
+            DepthFunction::Less => WebGlRenderingContext::LESS,
+            DepthFunction::Equal => WebGlRenderingContext::EQUAL,
+            DepthFunction::LessOrEqual => WebGlRenderingContext::LEQUAL,
+            DepthFunction::Greater => WebGlRenderingContext::GREATER,
+            DepthFunction::NotEqual => WebGlRenderingContext::NOTEQUAL,
+            DepthFunction::GreaterOrEqual => WebGlRenderingContext::GEQUAL,
+            DepthFunction::Always => WebGlRenderingContext::ALWAYS,
+        }
+    }
+}
+
+/// Faces that are not drawn. Front faces wind counter-clockwise, as in glTF.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+/// How blended surfaces, like transmissive materials, combine with what is behind them.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Mixes by the alpha of the surface.
+    Alpha,
+    /// Adds the color weighted by its alpha, for glows and particles.
+    Additive,
+}
+
+/// GPU the browser should pick on devices with more than one.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerPreference {
+    Default,
+    LowPower,
+    HighPerformance,
+}
+
+/// GL state the renderer sets before the main pass of every frame, and the attributes
+/// the WebGL context is created with. The attributes have no effect on a canvas that
+/// already has a context.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct RenderSettings {
+    clear_color: [f32; 4],
+    clear_depth: f32,
+    depth_test: bool,
+    depth_function: DepthFunction,
+    cull_mode: CullMode,
+    blend_mode: BlendMode,
+    antialias: bool,
+    alpha: bool,
+    premultiplied_alpha: bool,
+    preserve_drawing_buffer: bool,
+    power_preference: PowerPreference,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            clear_color: [0., 0., 0., 1.],
+            clear_depth: 1.,
+            depth_test: true,
+            depth_function: DepthFunction::Less,
+            cull_mode: CullMode::Back,
+            blend_mode: BlendMode::Alpha,
+            antialias: true,
+            alpha: true,
+            premultiplied_alpha: true,
+            preserve_drawing_buffer: false,
+            power_preference: PowerPreference::Default,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RenderSettings {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> RenderSettings {
+        RenderSettings::default()
+    }
+
+    pub fn set_clear_color(&mut self, red: f32, green: f32, blue: f32, alpha: f32) {
+        self.clear_color = [red, green, blue, alpha];
+    }
+
+    /// Depth the depth buffer is cleared to, from 0 to 1.
+    pub fn set_clear_depth(&mut self, depth: f32) {
+        self.clear_depth = depth;
+    }
+
+    pub fn set_depth_test(&mut self, enabled: bool) {
+        self.depth_test = enabled;
+    }
+
+    pub fn set_depth_function(&mut self, function: DepthFunction) {
+        self.depth_function = function;
+    }
+
+    pub fn set_cull_mode(&mut self, mode: CullMode) {
+        self.cull_mode = mode;
+    }
+
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    pub fn set_antialias(&mut self, antialias: bool) {
+        self.antialias = antialias;
+    }
+
+    /// Whether the canvas is transparent where the frame is.
+    pub fn set_alpha(&mut self, alpha: bool) {
+        self.alpha = alpha;
+    }
+
+    pub fn set_premultiplied_alpha(&mut self, premultiplied_alpha: bool) {
+        self.premultiplied_alpha = premultiplied_alpha;
+    }
+
+    /// Keeps the frame after it is shown, needed to read the canvas from outside of a
+    /// frame at some cost on tiled GPUs.
+    pub fn set_preserve_drawing_buffer(&mut self, preserve_drawing_buffer: bool) {
+        self.preserve_drawing_buffer = preserve_drawing_buffer;
+    }
+
+    pub fn set_power_preference(&mut self, power_preference: PowerPreference) {
+        self.power_preference = power_preference;
+    }
+}
+
+impl RenderSettings {
+    pub(crate) fn context_attributes(&self) -> WebGlContextAttributes {
+        let attributes = WebGlContextAttributes::new();
+        attributes.set_antialias(self.antialias);
+        attributes.set_alpha(self.alpha);
+        attributes.set_premultiplied_alpha(self.premultiplied_alpha);
+        attributes.set_preserve_drawing_buffer(self.preserve_drawing_buffer);
+        attributes.set_power_preference(match self.power_preference {
+            PowerPreference::Default => WebGlPowerPreference::Default,
+            PowerPreference::LowPower => WebGlPowerPreference::LowPower,
+            PowerPreference::HighPerformance => WebGlPowerPreference::HighPerformance,
+        });
+
+        attributes
+    }
+
+    /// Blend function of blended surfaces as source and destination factor.
+    pub(crate) fn blend_func(&self) -> (u32, u32) {
+        match self.blend_mode {
+            BlendMode::Alpha => (
+                WebGlRenderingContext::SRC_ALPHA,
+                WebGlRenderingContext::ONE_MINUS_SRC_ALPHA,
+            ),
+            BlendMode::Additive => (WebGlRenderingContext::SRC_ALPHA, WebGlRenderingContext::ONE),
+        }
+    }
+
+    /// Sets depth testing, culling and blending, passes change them only temporarily.
+    pub(crate) fn apply(&self, gl: &dyn Backend) {
+        match self.depth_test {
+            true => gl.enable(WebGlRenderingContext::DEPTH_TEST),
+            false => gl.disable(WebGlRenderingContext::DEPTH_TEST),
+        }
+        gl.depth_func(self.depth_function.gl());
+        gl.depth_mask(true);
+
+        gl.front_face(WebGlRenderingContext::CCW);
+        let cull_face = match self.cull_mode {
+            CullMode::None => None,
+            CullMode::Back => Some(WebGlRenderingContext::BACK),
+            CullMode::Front => Some(WebGlRenderingContext::FRONT),
+        };
+        match cull_face {
+            Some(mode) => {
+                gl.enable(WebGlRenderingContext::CULL_FACE);
+                gl.cull_face(mode);
+            }
+            None => gl.disable(WebGlRenderingContext::CULL_FACE),
+        }
+
+        gl.disable(WebGlRenderingContext::BLEND);
+    }
+
+    /// Clears color and depth of the bound framebuffer.
+    pub(crate) fn clear(&self, gl: &dyn Backend) {
+        let [red, green, blue, alpha] = self.clear_color;
+        gl.clear_color(red, green, blue, alpha);
+        gl.clear_depth(self.clear_depth);
+        gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT | WebGlRenderingContext::DEPTH_BUFFER_BIT);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::renderer::backend::{
+    Backend, Framebuffer, Program, Renderbuffer, Texture, UniformLocation,
+};
+use crate::renderer::mesh::shader::program;
+use crate::renderer::state;
+use crate::renderer::stats::FrameCounters;
+use gl_matrix::{mat4, vec3};
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+pub(crate) const DEFAULT_SHADOW_MAP_SIZE: i32 = 2048;
+pub(crate) const DEFAULT_SHADOW_DISTANCE: f32 = 30.;
+/// Texture unit the mesh shaders sample the shadow map from, unit 0 holds the base color.
+pub(crate) const SHADOW_TEXTURE_UNIT: u32 = 1;
+/// Extra distance behind the fitted volume, so casters between the light and the view
+/// frustum still end up in the shadow map.
+const SHADOW_CASTER_MARGIN: f32 = 20.;
+const SHADOW_DEPTH_BIAS: f32 = 0.002;
+
+const V_SHADER: &str = "#version 100
+
+attribute vec3 position;
+
+uniform mat4 lightSpaceMatrix;
+uniform mat4 modelMatrix;
+
+void main() {
+    gl_Position = lightSpaceMatrix * modelMatrix * vec4(position, 1.0);
+}";
+
+const F_SHADER: &str = "#version 100
+
+// The packed depth wants every bit it can get, highp is optional in fragment shaders.
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+void main() {
+#ifdef SHADOW_PACKED_DEPTH
+    vec4 packed = fract(gl_FragCoord.z * vec4(1.0, 255.0, 65025.0, 16581375.0));
+    gl_FragColor = packed - packed.yzww * vec4(1.0 / 255.0, 1.0 / 255.0, 1.0 / 255.0, 0.0);
+#else
+    gl_FragColor = vec4(1.0);
+#endif
+}";
+
+/// How the depth of the shadow pass is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ShadowDepthFormat {
+    /// A `WEBGL_depth_texture` depth attachment.
+    DepthTexture,
+    /// Depth packed into the RGBA channels of a color attachment, for devices without
+    /// depth textures.
+    PackedRgba,
+}
+
+/// Depth map of one directional light, rendered from an orthographic camera fitted
+/// around the view frustum.
+#[derive(Debug)]
+pub(crate) struct ShadowMap {
+    pub(crate) format: ShadowDepthFormat,
+    id: u32,
+    size: i32,
+    framebuffer: Framebuffer,
+    texture: Texture,
+    depth_buffer: Option<Renderbuffer>,
+    program: Program,
+    position_location: i32,
+    light_space_matrix_location: Option<UniformLocation>,
+    model_matrix_location: Option<UniformLocation>,
+    light_space_matrix: [f32; 16],
+}
+
+impl ShadowMap {
+    pub(crate) fn new(gl: &dyn Backend, size: i32) -> Result<ShadowMap, JsValue> {
+        let format = if gl.has_extension("WEBGL_depth_texture") {
+            ShadowDepthFormat::DepthTexture
+        } else {
+            ShadowDepthFormat::PackedRgba
+        };
+
+        let framebuffer = gl
+            .create_framebuffer()
+            .ok_or_else(|| JsValue::from_str("could not create shadow framebuffer"))?;
+        let texture = gl
+            .create_texture()
+            .ok_or_else(|| JsValue::from_str("could not create shadow texture"))?;
+
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&framebuffer));
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+        for (parameter, value) in &[
+            (
+                WebGlRenderingContext::TEXTURE_MIN_FILTER,
+                WebGlRenderingContext::NEAREST,
+            ),
+            (
+                WebGlRenderingContext::TEXTURE_MAG_FILTER,
+                WebGlRenderingContext::NEAREST,
+            ),
+            (
+                WebGlRenderingContext::TEXTURE_WRAP_S,
+                WebGlRenderingContext::CLAMP_TO_EDGE,
+            ),
+            (
+                WebGlRenderingContext::TEXTURE_WRAP_T,
+                WebGlRenderingContext::CLAMP_TO_EDGE,
+            ),
+        ] {
+            gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, *parameter, *value as i32);
+        }
+
+        let depth_buffer = match format {
+            ShadowDepthFormat::DepthTexture => {
+                gl.tex_image_2d(
+                    WebGlRenderingContext::TEXTURE_2D,
+                    0,
+                    WebGlRenderingContext::DEPTH_COMPONENT as i32,
+                    size,
+                    size,
+                    WebGlRenderingContext::DEPTH_COMPONENT,
+                    WebGlRenderingContext::UNSIGNED_INT,
+                    None,
+                )?;
+                gl.framebuffer_texture_2d(
+                    WebGlRenderingContext::FRAMEBUFFER,
+                    WebGlRenderingContext::DEPTH_ATTACHMENT,
+                    WebGlRenderingContext::TEXTURE_2D,
+                    Some(&texture),
+                    0,
+                );
+
+                None
+            }
+            ShadowDepthFormat::PackedRgba => {
+                gl.tex_image_2d(
+                    WebGlRenderingContext::TEXTURE_2D,
+                    0,
+                    WebGlRenderingContext::RGBA as i32,
+                    size,
+                    size,
+                    WebGlRenderingContext::RGBA,
+                    WebGlRenderingContext::UNSIGNED_BYTE,
+                    None,
+                )?;
+                gl.framebuffer_texture_2d(
+                    WebGlRenderingContext::FRAMEBUFFER,
+                    WebGlRenderingContext::COLOR_ATTACHMENT0,
+                    WebGlRenderingContext::TEXTURE_2D,
+                    Some(&texture),
+                    0,
+                );
+
+                let depth_buffer = gl
+                    .create_renderbuffer()
+                    .ok_or_else(|| JsValue::from_str("could not create shadow depth buffer"))?;
+                gl.bind_renderbuffer(WebGlRenderingContext::RENDERBUFFER, Some(&depth_buffer));
+                gl.renderbuffer_storage(
+                    WebGlRenderingContext::RENDERBUFFER,
+                    WebGlRenderingContext::DEPTH_COMPONENT16,
+                    size,
+                    size,
+                );
+                gl.framebuffer_renderbuffer(
+                    WebGlRenderingContext::FRAMEBUFFER,
+                    WebGlRenderingContext::DEPTH_ATTACHMENT,
+                    WebGlRenderingContext::RENDERBUFFER,
+                    Some(&depth_buffer),
+                );
+
+                Some(depth_buffer)
+            }
+        };
+
+        let status = gl.check_framebuffer_status(WebGlRenderingContext::FRAMEBUFFER);
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+        if status != WebGlRenderingContext::FRAMEBUFFER_COMPLETE {
+            return Err(JsValue::from_str(&format!(
+                "shadow framebuffer is incomplete: {:#x}",
+                status
+            )));
+        }
+
+        let fragment_shader = match format {
+            ShadowDepthFormat::DepthTexture => F_SHADER.to_string(),
+            ShadowDepthFormat::PackedRgba => {
+                F_SHADER.replacen("\n", "\n#define SHADOW_PACKED_DEPTH\n", 1)
+            }
+        };
+        let program = program::compile_to_program(gl, V_SHADER, &fragment_shader)?;
+
+        Ok(ShadowMap {
+            format,
+            id: state::next_id(),
+            size,
+            framebuffer,
+            texture,
+            depth_buffer,
+            position_location: gl.get_attrib_location(&program, "position"),
+            light_space_matrix_location: gl.get_uniform_location(&program, "lightSpaceMatrix"),
+            model_matrix_location: gl.get_uniform_location(&program, "modelMatrix"),
+            program,
+            light_space_matrix: mat4::create(),
+        })
+    }
+
+    /// Fits the orthographic light camera around the bounding sphere of `corners`. The
+    /// sphere keeps the projection size stable while the camera rotates, and snapping it
+    /// to whole texels stops shadow edges from shimmering while it moves.
+    pub(crate) fn fit(&mut self, direction: &[f32; 3], corners: &[[f32; 3]; 8]) {
+        let mut center = [0.; 3];
+        for corner in corners {
+            center[0] += corner[0] / 8.;
+            center[1] += corner[1] / 8.;
+            center[2] += corner[2] / 8.;
+        }
+        let radius = corners
+            .iter()
+            .map(|corner| vec3::distance(corner, &center))
+            .fold(0., f32::max)
+            .max(0.001);
+
+        let up = if direction[1].abs() > 0.999 {
+            [0., 0., 1.]
+        } else {
+            [0., 1., 0.]
+        };
+        let distance = radius + SHADOW_CASTER_MARGIN;
+        let eye = [
+            center[0] - direction[0] * distance,
+            center[1] - direction[1] * distance,
+            center[2] - direction[2] * distance,
+        ];
+        let mut view = mat4::create();
+        mat4::look_at(&mut view, &eye, &center, &up);
+
+        let texel = 2. * radius / self.size as f32;
+        view[12] = (view[12] / texel).floor() * texel;
+        view[13] = (view[13] / texel).floor() * texel;
+
+        let mut projection = mat4::create();
+        mat4::ortho(
+            &mut projection,
+            -radius,
+            radius,
+            -radius,
+            radius,
+            0.,
+            distance + radius,
+        );
+        mat4::multiply(&mut self.light_space_matrix, &projection, &view);
+    }
+
+    /// Binds the shadow framebuffer and the depth program for the casters. The depth
+    /// state is set here, the render settings only apply to the main pass.
+    pub(crate) fn begin(&self, gl: &dyn Backend, counters: &FrameCounters) {
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&self.framebuffer));
+        gl.viewport(0, 0, self.size, self.size);
+        gl.enable(WebGlRenderingContext::DEPTH_TEST);
+        gl.depth_func(WebGlRenderingContext::LESS);
+        gl.depth_mask(true);
+        gl.disable(WebGlRenderingContext::CULL_FACE);
+        gl.clear_depth(1.);
+        match self.format {
+            ShadowDepthFormat::DepthTexture => gl.clear(WebGlRenderingContext::DEPTH_BUFFER_BIT),
+            ShadowDepthFormat::PackedRgba => {
+                // Packed white decodes to the far plane.
+                gl.clear_color(1., 1., 1., 1.);
+                gl.clear(
+                    WebGlRenderingContext::COLOR_BUFFER_BIT
+                        | WebGlRenderingContext::DEPTH_BUFFER_BIT,
+                );
+                gl.clear_color(0., 0., 0., 0.);
+            }
+        }
+
+        gl.use_program(Some(&self.program));
+        counters.program();
+        gl.uniform_matrix4fv(
+            self.light_space_matrix_location.as_ref(),
+            &self.light_space_matrix,
+        );
+    }
+
+    pub(crate) fn set_model_matrix(&self, gl: &dyn Backend, model_matrix: &[f32; 16]) {
+        gl.uniform_matrix4fv(self.model_matrix_location.as_ref(), model_matrix);
+    }
+
+    pub(crate) fn position_location(&self) -> u32 {
+        self.position_location as u32
+    }
+
+    /// Switches back to the default framebuffer and disables the position array
+    /// `render_depth` enabled.
+    pub(crate) fn end(&self, gl: &dyn Backend) {
+        gl.disable_vertex_attrib_array(self.position_location as u32);
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+        gl.viewport(0, 0, gl.drawing_buffer_width(), gl.drawing_buffer_height());
+    }
+
+    pub(crate) fn size(&self) -> i32 {
+        self.size
+    }
+
+    pub(crate) fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub(crate) fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub(crate) fn light_space_matrix(&self) -> &[f32; 16] {
+        &self.light_space_matrix
+    }
+
+    /// x: size of one texel in texture coordinates, y: depth bias
+    pub(crate) fn params(&self) -> [f32; 2] {
+        [1. / self.size as f32, SHADOW_DEPTH_BIAS]
+    }
+
+    pub(crate) fn delete(&self, gl: &dyn Backend) {
+        gl.delete_framebuffer(&self.framebuffer);
+        gl.delete_texture(&self.texture);
+        if let Some(depth_buffer) = &self.depth_buffer {
+            gl.delete_renderbuffer(depth_buffer);
+        }
+        gl.delete_program(&self.program);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::renderer::backend::{Backend, Program, Texture, VertexArray};
+use crate::renderer::stats::FrameCounters;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, Ordering};
+use web_sys::WebGlRenderingContext;
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+/// Attribute locations WebGL guarantees, used when the context can not tell.
+const MIN_VERTEX_ATTRIBS: u32 = 8;
+
+/// Unique id of a GL object, cheaper to compare than the JS handles. 0 stands for none.
+pub(crate) fn next_id() -> u32 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// GL state set while submitting the render queue, so state changes that would not
+/// change anything are skipped. Only valid as long as nothing else touches GL, it is
+/// created fresh for every queue submission. Passes outside of the queue disable the
+/// attribute arrays they enable, so none are enabled when it is created.
+#[derive(Debug, Default)]
+pub(crate) struct StateTracker {
+    program: Option<u32>,
+    /// Programs whose per-frame uniforms are already set.
+    frame_programs: HashSet<u32>,
+    /// Material whose uniforms each program currently holds.
+    materials: HashMap<u32, u32>,
+    active_unit: Option<u32>,
+    /// Texture bound to each unit.
+    textures: HashMap<u32, u32>,
+    blend: bool,
+    /// Source and destination factor of blended surfaces.
+    blend_func: (u32, u32),
+    /// Vertex array object bound, 0 for the default one.
+    vertex_array: u32,
+    /// Bit mask of the attribute locations enabled as arrays of the default vertex array
+    /// object.
+    attributes: u32,
+    /// `MAX_VERTEX_ATTRIBS`, at most the 32 locations the mask can hold.
+    max_attributes: u32,
+}
+
+impl StateTracker {
+    pub(crate) fn new(gl: &dyn Backend, blend_func: (u32, u32)) -> StateTracker {
+        let max_attributes = gl
+            .get_parameter_i32(WebGlRenderingContext::MAX_VERTEX_ATTRIBS)
+            .map_or(MIN_VERTEX_ATTRIBS, |max| max.clamp(0, 32) as u32);
+
+        StateTracker {
+            blend_func,
+            max_attributes,
+            ..StateTracker::default()
+        }
+    }
+
+    pub(crate) fn use_program(
+        &mut self,
+        gl: &dyn Backend,
+        id: u32,
+        program: &Program,
+        counters: &FrameCounters,
+    ) {
+        if self.program != Some(id) {
+            gl.use_program(Some(program));
+            counters.program();
+            self.program = Some(id);
+        }
+    }
+
+    /// Whether the camera, light and environment uniforms of `program` still have to be
+    /// set this frame, only the first call per program returns `true`.
+    pub(crate) fn needs_frame_uniforms(&mut self, program: u32) -> bool {
+        self.frame_programs.insert(program)
+    }
+
+    /// Whether `program` holds the uniforms of another material than `material`.
+    pub(crate) fn needs_material(&mut self, program: u32, material: u32) -> bool {
+        self.materials.insert(program, material) != Some(material)
+    }
+
+    pub(crate) fn bind_texture(
+        &mut self,
+        gl: &dyn Backend,
+        unit: u32,
+        target: u32,
+        id: u32,
+        texture: &Texture,
+        counters: &FrameCounters,
+    ) {
+        if self.textures.get(&unit) == Some(&id) {
+            return;
+        }
+
+        if self.active_unit != Some(unit) {
+            gl.active_texture(WebGlRenderingContext::TEXTURE0 + unit);
+            self.active_unit = Some(unit);
+        }
+        gl.bind_texture(target, Some(texture));
+        counters.textures(1);
+        self.textures.insert(unit, id);
+    }
+
+    /// Blending for transmissive surfaces.
+    pub(crate) fn set_blend(&mut self, gl: &dyn Backend, enabled: bool) {
+        if self.blend == enabled {
+            return;
+        }
+
+        if enabled {
+            gl.enable(WebGlRenderingContext::BLEND);
+            gl.blend_func(self.blend_func.0, self.blend_func.1);
+        } else {
+            gl.disable(WebGlRenderingContext::BLEND);
+        }
+        self.blend = enabled;
+    }
+
+    /// Binds `vertex_array` with the id `id`, `None` and 0 for the default one.
+    pub(crate) fn bind_vertex_array(
+        &mut self,
+        gl: &dyn Backend,
+        id: u32,
+        vertex_array: Option<&VertexArray>,
+    ) {
+        if self.vertex_array != id {
+            gl.bind_vertex_array(vertex_array);
+            self.vertex_array = id;
+        }
+    }
+
+    /// Enables exactly the attribute arrays at `locations`, arrays left enabled by the
+    /// previous draw are disabled. Locations of `MAX_VERTEX_ATTRIBS` and above do not
+    /// exist and are skipped, WebGL would reject them.
+    pub(crate) fn enable_attributes(&mut self, gl: &dyn Backend, locations: &[u32]) {
+        let mask = locations
+            .iter()
+            .filter(|location| **location < self.max_attributes)
+            .fold(0, |mask, location| mask | 1 << location);
+
+        for location in 0..self.max_attributes {
+            let bit = 1 << location;
+            match (self.attributes & bit != 0, mask & bit != 0) {
+                (false, true) => gl.enable_vertex_attrib_array(location),
+                (true, false) => gl.disable_vertex_attrib_array(location),
+                _ => {}
+            }
+        }
+        self.attributes = mask;
+    }
+
+    /// Leaves GL with blending off, the default vertex array object bound and no
+    /// attribute arrays enabled.
+    pub(crate) fn finish(&mut self, gl: &dyn Backend) {
+        self.bind_vertex_array(gl, 0, None);
+        self.set_blend(gl, false);
+        self.enable_attributes(gl, &[]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::backend::headless::{Command, HeadlessBackend};
+
+    #[test]
+    fn enables_only_the_attributes_of_each_draw() {
+        let gl = HeadlessBackend::new(4, 4);
+        let mut state = StateTracker::new(&gl, (0, 0));
+
+        state.enable_attributes(&gl, &[0, 2]);
+        state.enable_attributes(&gl, &[2, 1]);
+        state.finish(&gl);
+
+        assert_eq!(
+            gl.take_commands(),
+            vec![
+                Command::EnableAttribute(0),
+                Command::EnableAttribute(2),
+                Command::DisableAttribute(0),
+                Command::EnableAttribute(1),
+                Command::DisableAttribute(1),
+                Command::DisableAttribute(2),
+            ]
+        );
+        assert_eq!(gl.enabled_attributes(), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn skips_locations_beyond_max_vertex_attribs() {
+        let gl = HeadlessBackend::new(4, 4);
+        let mut state = StateTracker::new(&gl, (0, 0));
+
+        state.enable_attributes(&gl, &[1, 16, 31, 32, u32::MAX]);
+
+        assert_eq!(gl.errors(), Vec::<String>::new());
+        assert_eq!(gl.enabled_attributes(), vec![1]);
+    }
+}
@@ -0,0 +1,570 @@
+use crate::renderer::backend::{Backend, Query, UploadTotals};
+use js_sys::{Object, Reflect};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::*;
+use web_sys::Element;
+
+/// How often the overlay text is refreshed, in milliseconds.
+const OVERLAY_INTERVAL: f64 = 250.;
+const OVERLAY_STYLE: &str = "position: fixed; top: 0; left: 0; margin: 0; padding: 4px 8px; \
+    background: rgba(0, 0, 0, 0.6); color: #fff; font: 12px monospace; pointer-events: none; \
+    z-index: 1000;";
+/// Frames of GPU queries waiting for their results, older ones are dropped.
+const MAX_PENDING_GPU_FRAMES: usize = 4;
+
+/// Parts of `Scene::render` that are timed separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RenderPhase {
+    Lights,
+    Shadows,
+    Skybox,
+    Meshes,
+    Debug,
+    PostProcessing,
+}
+
+impl RenderPhase {
+    fn name(&self) -> &'static str {
+        match self {
+            RenderPhase::Lights => "lights",
+            RenderPhase::Shadows => "shadows",
+            RenderPhase::Skybox => "skybox",
+            RenderPhase::Meshes => "meshes",
+            RenderPhase::Debug => "debug",
+            RenderPhase::PostProcessing => "postProcessing",
+        }
+    }
+}
+
+/// Work done by the GPU within one frame. Cells let every draw path record through the
+/// shared reference it is handed. Uploads are counted by the backend instead, they also
+/// happen outside of frames.
+#[derive(Debug, Default)]
+pub(crate) struct FrameCounters {
+    draw_calls: Cell<u32>,
+    triangles: Cell<u32>,
+    program_switches: Cell<u32>,
+    texture_binds: Cell<u32>,
+}
+
+impl FrameCounters {
+    pub(crate) fn draw(&self, triangles: u32) {
+        increase(&self.draw_calls, 1);
+        increase(&self.triangles, triangles);
+    }
+
+    pub(crate) fn program(&self) {
+        increase(&self.program_switches, 1);
+    }
+
+    pub(crate) fn textures(&self, count: u32) {
+        increase(&self.texture_binds, count);
+    }
+
+    fn reset(&mut self) {
+        *self = FrameCounters::default();
+    }
+}
+
+fn increase(counter: &Cell<u32>, amount: u32) {
+    counter.set(counter.get().saturating_add(amount));
+}
+
+/// `EXT_disjoint_timer_query` timings, results arrive a few frames after the queries.
+#[derive(Debug)]
+struct GpuTimer {
+    current: Vec<(RenderPhase, Query)>,
+    pending: VecDeque<Vec<(RenderPhase, Query)>>,
+    times: Vec<(RenderPhase, f64)>,
+}
+
+impl GpuTimer {
+    fn new(gl: &dyn Backend) -> Option<GpuTimer> {
+        if !gl.has_timer_queries() {
+            return None;
+        }
+
+        Some(GpuTimer {
+            current: vec![],
+            pending: VecDeque::new(),
+            times: vec![],
+        })
+    }
+
+    fn begin(&mut self, gl: &dyn Backend, phase: RenderPhase) {
+        if let Some(query) = gl.begin_timer_query() {
+            self.current.push((phase, query));
+        }
+    }
+
+    fn end(&mut self, gl: &dyn Backend) {
+        gl.end_timer_query();
+    }
+
+    fn end_frame(&mut self, gl: &dyn Backend) {
+        let frame = std::mem::take(&mut self.current);
+        if !frame.is_empty() {
+            self.pending.push_back(frame);
+        }
+        while self.pending.len() > MAX_PENDING_GPU_FRAMES {
+            if let Some(frame) = self.pending.pop_front() {
+                GpuTimer::delete(gl, &frame);
+            }
+        }
+    }
+
+    /// Collects the timings of the oldest frame whose queries are done.
+    fn poll(&mut self, gl: &dyn Backend) {
+        let disjoint = gl.timer_disjoint();
+
+        while let Some(frame) = self.pending.front() {
+            let available = frame
+                .last()
+                .is_some_and(|(_, query)| gl.timer_query_result(query).is_some());
+            if !available {
+                break;
+            }
+
+            if !disjoint {
+                self.times = frame
+                    .iter()
+                    .map(|(phase, query)| {
+                        let nanoseconds = gl.timer_query_result(query).unwrap_or(0.);
+                        (*phase, nanoseconds / 1_000_000.)
+                    })
+                    .collect();
+            }
+            if let Some(frame) = self.pending.pop_front() {
+                GpuTimer::delete(gl, &frame);
+            }
+        }
+    }
+
+    fn delete(gl: &dyn Backend, frame: &[(RenderPhase, Query)]) {
+        for (_, query) in frame {
+            gl.delete_query(query);
+        }
+    }
+
+    fn delete_all(&mut self, gl: &dyn Backend) {
+        GpuTimer::delete(gl, &std::mem::take(&mut self.current));
+        for frame in std::mem::take(&mut self.pending) {
+            GpuTimer::delete(gl, &frame);
+        }
+    }
+}
+
+/// Counters and phase timings of the last rendered frame.
+#[derive(Debug)]
+pub(crate) struct Profiler {
+    pub(crate) counters: FrameCounters,
+    /// Uploaded since the end of the previous frame, so uploads between frames count
+    /// towards the next one.
+    uploads: UploadTotals,
+    /// Uploaded since the profiler was created, across context restores.
+    total_uploads: UploadTotals,
+    /// Totals of the backend at the end of the previous frame.
+    backend_uploads: UploadTotals,
+    phase: Option<(RenderPhase, f64)>,
+    frame_start: f64,
+    cpu_times: Vec<(RenderPhase, f64)>,
+    cpu_total: f64,
+    gpu: Option<GpuTimer>,
+    overlay: Option<Element>,
+    overlay_updated: f64,
+}
+
+impl Profiler {
+    pub(crate) fn new(gl: &dyn Backend) -> Profiler {
+        Profiler {
+            counters: FrameCounters::default(),
+            uploads: UploadTotals::default(),
+            total_uploads: UploadTotals::default(),
+            backend_uploads: gl.upload_totals(),
+            phase: None,
+            frame_start: 0.,
+            cpu_times: vec![],
+            cpu_total: 0.,
+            gpu: GpuTimer::new(gl),
+            overlay: None,
+            overlay_updated: 0.,
+        }
+    }
+
+    /// Drops the timer queries of a lost context, they never deliver a result. The
+    /// backend may be a new one whose upload totals start over.
+    pub(crate) fn restore(&mut self, gl: &dyn Backend) {
+        self.phase = None;
+        self.gpu = GpuTimer::new(gl);
+        self.backend_uploads = gl.upload_totals();
+    }
+
+    /// Deletes the timer queries and removes the overlay, no timings are collected
+    /// afterwards.
+    pub(crate) fn delete(&mut self, gl: &dyn Backend) {
+        if let Some(mut gpu) = self.gpu.take() {
+            if self.phase.take().is_some() {
+                gpu.end(gl);
+            }
+            gpu.delete_all(gl);
+        }
+        self.discard();
+    }
+
+    /// Like `delete` for a lost context, whose timer queries went with it. The overlay is
+    /// part of the page and removed all the same.
+    pub(crate) fn discard(&mut self) {
+        self.phase = None;
+        self.gpu = None;
+        if let Some(overlay) = self.overlay.take() {
+            overlay.remove();
+        }
+    }
+
+    pub(crate) fn begin_frame(&mut self, gl: &dyn Backend) {
+        // A frame that failed halfway may have left a phase and its query running.
+        self.end_phase(gl);
+        if let Some(gpu) = &mut self.gpu {
+            gpu.end_frame(gl);
+        }
+
+        self.counters.reset();
+        self.cpu_times.clear();
+        self.frame_start = now();
+        if let Some(gpu) = &mut self.gpu {
+            gpu.poll(gl);
+        }
+    }
+
+    /// Ends the running phase and starts timing `phase`.
+    pub(crate) fn phase(&mut self, gl: &dyn Backend, phase: RenderPhase) {
+        self.end_phase(gl);
+
+        self.phase = Some((phase, now()));
+        if let Some(gpu) = &mut self.gpu {
+            gpu.begin(gl, phase);
+        }
+    }
+
+    pub(crate) fn end_frame(&mut self, gl: &dyn Backend) {
+        self.end_phase(gl);
+        let end = now();
+        self.cpu_total = end - self.frame_start;
+        if let Some(gpu) = &mut self.gpu {
+            gpu.end_frame(gl);
+        }
+
+        let backend_uploads = gl.upload_totals();
+        self.uploads = backend_uploads.since(&self.backend_uploads);
+        self.total_uploads.add(&self.uploads);
+        self.backend_uploads = backend_uploads;
+
+        match &self.overlay {
+            Some(overlay) if end - self.overlay_updated >= OVERLAY_INTERVAL => {
+                overlay.set_text_content(Some(&self.summary()));
+                self.overlay_updated = end;
+            }
+            _ => {}
+        }
+    }
+
+    /// Shows the stats in a text box on top of the page.
+    pub(crate) fn set_overlay_visible(&mut self, visible: bool) -> Result<(), JsValue> {
+        match (visible, &self.overlay) {
+            (true, None) => {
+                let document = web_sys::window()
+                    .and_then(|window| window.document())
+                    .ok_or_else(|| JsValue::from_str("could not get document"))?;
+                let overlay = document.create_element("pre")?;
+                overlay.set_attribute("style", OVERLAY_STYLE)?;
+                document
+                    .body()
+                    .ok_or_else(|| JsValue::from_str("document has no body"))?
+                    .append_child(&overlay)?;
+                self.overlay = Some(overlay);
+                self.overlay_updated = 0.;
+            }
+            (false, Some(overlay)) => {
+                overlay.remove();
+                self.overlay = None;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// The stats as plain JS object, times are in milliseconds.
+    pub(crate) fn to_js(&self) -> Result<JsValue, JsValue> {
+        let stats = Object::new();
+        let counters = &self.counters;
+        for (key, value) in &[
+            ("drawCalls", f64::from(counters.draw_calls.get())),
+            ("triangles", f64::from(counters.triangles.get())),
+            (
+                "programSwitches",
+                f64::from(counters.program_switches.get()),
+            ),
+            ("textureBinds", f64::from(counters.texture_binds.get())),
+            ("bufferBytesUploaded", self.uploads.buffer_bytes as f64),
+            ("textureBytesUploaded", self.uploads.texture_bytes as f64),
+            (
+                "totalBufferBytesUploaded",
+                self.total_uploads.buffer_bytes as f64,
+            ),
+            (
+                "totalTextureBytesUploaded",
+                self.total_uploads.texture_bytes as f64,
+            ),
+        ] {
+            Reflect::set(&stats, &JsValue::from_str(key), &JsValue::from(*value))?;
+        }
+
+        let cpu = timings(&self.cpu_times)?;
+        Reflect::set(
+            &cpu,
+            &JsValue::from_str("total"),
+            &JsValue::from(self.cpu_total),
+        )?;
+        Reflect::set(&stats, &JsValue::from_str("cpu"), &cpu)?;
+        let gpu = match &self.gpu {
+            Some(gpu) if !gpu.times.is_empty() => {
+                let gpu_times = timings(&gpu.times)?;
+                let total: f64 = gpu.times.iter().map(|(_, time)| time).sum();
+                Reflect::set(
+                    &gpu_times,
+                    &JsValue::from_str("total"),
+                    &JsValue::from(total),
+                )?;
+                JsValue::from(gpu_times)
+            }
+            _ => JsValue::NULL,
+        };
+        Reflect::set(&stats, &JsValue::from_str("gpu"), &gpu)?;
+
+        Ok(stats.into())
+    }
+
+    fn end_phase(&mut self, gl: &dyn Backend) {
+        if let Some((phase, start)) = self.phase.take() {
+            self.cpu_times.push((phase, now() - start));
+            if let Some(gpu) = &mut self.gpu {
+                gpu.end(gl);
+            }
+        }
+    }
+
+    fn summary(&self) -> String {
+        let counters = &self.counters;
+        let mut summary = format!(
+            "draw calls {}\ntriangles {}\nprograms {}\ntextures {}\nuploads {} B / {} B\n\
+             total uploads {} B / {} B\ncpu {:.2} ms",
+            counters.draw_calls.get(),
+            counters.triangles.get(),
+            counters.program_switches.get(),
+            counters.texture_binds.get(),
+            self.uploads.buffer_bytes,
+            self.uploads.texture_bytes,
+            self.total_uploads.buffer_bytes,
+            self.total_uploads.texture_bytes,
+            self.cpu_total,
+        );
+        for (phase, time) in &self.cpu_times {
+            summary.push_str(&format!("\n  {} {:.2} ms", phase.name(), time));
+        }
+        if let Some(gpu) = self.gpu.as_ref().filter(|gpu| !gpu.times.is_empty()) {
+            let total: f64 = gpu.times.iter().map(|(_, time)| time).sum();
+            summary.push_str(&format!("\ngpu {:.2} ms", total));
+            for (phase, time) in &gpu.times {
+                summary.push_str(&format!("\n  {} {:.2} ms", phase.name(), time));
+            }
+        }
+
+        summary
+    }
+}
+
+fn timings(times: &[(RenderPhase, f64)]) -> Result<Object, JsValue> {
+    let object = Object::new();
+    for (phase, time) in times {
+        Reflect::set(
+            &object,
+            &JsValue::from_str(phase.name()),
+            &JsValue::from(*time),
+        )?;
+    }
+
+    Ok(object)
+}
+
+/// Milliseconds from `performance.now()`, 0 outside of a browser window.
+#[cfg(target_arch = "wasm32")]
+fn now() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map(|performance| performance.now())
+        .unwrap_or(0.)
+}
+
+/// Milliseconds since the first call, `std::time::Instant` is not available on wasm32.
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> f64 {
+    thread_local! {
+        static START: std::time::Instant = std::time::Instant::now();
+    }
+
+    START.with(|start| start.elapsed().as_secs_f64() * 1000.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameCounters, Profiler, RenderPhase};
+    use crate::renderer::backend::headless::HeadlessBackend;
+    use crate::renderer::backend::{Backend, UploadTotals};
+    use web_sys::WebGlRenderingContext;
+
+    fn upload_buffer(gl: &dyn Backend, bytes: usize) {
+        let buffer = gl.create_buffer().unwrap();
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&buffer));
+        gl.buffer_data_u8(
+            WebGlRenderingContext::ARRAY_BUFFER,
+            &vec![0; bytes],
+            WebGlRenderingContext::STATIC_DRAW,
+        );
+    }
+
+    fn allocate_texture(gl: &dyn Backend, width: i32, height: i32, data_type: u32) {
+        let texture = gl.create_texture().unwrap();
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+        gl.tex_image_2d(
+            WebGlRenderingContext::TEXTURE_2D,
+            0,
+            WebGlRenderingContext::RGBA as i32,
+            width,
+            height,
+            WebGlRenderingContext::RGBA,
+            data_type,
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn backends_count_every_upload() {
+        let gl = HeadlessBackend::new(4, 4);
+        upload_buffer(&gl, 10);
+        gl.buffer_data_f32(
+            WebGlRenderingContext::ARRAY_BUFFER,
+            &[0.; 3],
+            WebGlRenderingContext::STATIC_DRAW,
+        );
+        allocate_texture(&gl, 4, 2, WebGlRenderingContext::UNSIGNED_BYTE);
+        allocate_texture(&gl, 4, 2, WebGlRenderingContext::FLOAT);
+        gl.renderbuffer_storage(
+            WebGlRenderingContext::RENDERBUFFER,
+            WebGlRenderingContext::DEPTH_COMPONENT16,
+            4,
+            2,
+        );
+
+        assert_eq!(
+            gl.upload_totals(),
+            UploadTotals {
+                buffer_bytes: 22,
+                texture_bytes: 32 + 128 + 16,
+            }
+        );
+    }
+
+    #[test]
+    fn uploads_between_frames_count_towards_the_next_frame() {
+        let gl = HeadlessBackend::new(4, 4);
+        upload_buffer(&gl, 100);
+        let mut profiler = Profiler::new(&gl);
+
+        upload_buffer(&gl, 16);
+        profiler.begin_frame(&gl);
+        upload_buffer(&gl, 8);
+        profiler.end_frame(&gl);
+        assert_eq!(profiler.uploads.buffer_bytes, 24);
+
+        allocate_texture(&gl, 2, 2, WebGlRenderingContext::UNSIGNED_BYTE);
+        profiler.begin_frame(&gl);
+        profiler.end_frame(&gl);
+        assert_eq!(
+            profiler.uploads,
+            UploadTotals {
+                buffer_bytes: 0,
+                texture_bytes: 16,
+            }
+        );
+
+        profiler.begin_frame(&gl);
+        profiler.end_frame(&gl);
+        assert_eq!(profiler.uploads, UploadTotals::default());
+        assert_eq!(
+            profiler.total_uploads,
+            UploadTotals {
+                buffer_bytes: 24,
+                texture_bytes: 16,
+            }
+        );
+    }
+
+    #[test]
+    fn counters_are_reset_every_frame() {
+        let gl = HeadlessBackend::new(4, 4);
+        let mut profiler = Profiler::new(&gl);
+
+        profiler.begin_frame(&gl);
+        profiler.counters.draw(2);
+        profiler.counters.draw(1);
+        profiler.counters.program();
+        profiler.counters.textures(3);
+        profiler.end_frame(&gl);
+        let counters = &profiler.counters;
+        assert_eq!(
+            (
+                counters.draw_calls.get(),
+                counters.triangles.get(),
+                counters.program_switches.get(),
+                counters.texture_binds.get()
+            ),
+            (2, 3, 1, 3)
+        );
+
+        profiler.begin_frame(&gl);
+        assert_eq!(profiler.counters.draw_calls.get(), 0);
+        assert_eq!(profiler.counters.triangles.get(), 0);
+    }
+
+    #[test]
+    fn counters_saturate_instead_of_overflowing() {
+        let counters = FrameCounters::default();
+        counters.draw(u32::MAX);
+        counters.draw(u32::MAX);
+        counters.textures(u32::MAX);
+        counters.textures(1);
+
+        assert_eq!(counters.triangles.get(), u32::MAX);
+        assert_eq!(counters.draw_calls.get(), 2);
+        assert_eq!(counters.texture_binds.get(), u32::MAX);
+    }
+
+    #[test]
+    fn times_phases_natively() {
+        let gl = HeadlessBackend::new(4, 4);
+        let mut profiler = Profiler::new(&gl);
+
+        profiler.begin_frame(&gl);
+        profiler.phase(&gl, RenderPhase::Meshes);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        profiler.end_frame(&gl);
+
+        assert_eq!(profiler.cpu_times.len(), 1);
+        assert_eq!(profiler.cpu_times[0].0, RenderPhase::Meshes);
+        assert!(profiler.cpu_times[0].1 >= 2.);
+        assert!(profiler.cpu_total >= profiler.cpu_times[0].1);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::renderer::backend::Backend;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlCanvasElement};
+
+/// Highest device pixel ratio rendered at unless the page asks for another one. Denser
+/// screens cost fill rate for little visible gain.
+pub(crate) const DEFAULT_MAX_PIXEL_RATIO: f64 = 2.;
+
+/// Keeps the drawing buffer of a canvas at the size the canvas is displayed at.
+#[derive(Debug)]
+pub(crate) struct Surface {
+    /// None when rendering without a browser, the drawing buffer then keeps its size.
+    canvas: Option<HtmlCanvasElement>,
+    max_pixel_ratio: f64,
+    /// Prevents the default of `webglcontextlost`, without that the browser never
+    /// restores the context. Removed again when the surface is dropped.
+    context_lost_listener: Option<Closure<dyn FnMut(Event)>>,
+}
+
+impl Surface {
+    pub(crate) fn new(canvas: Option<HtmlCanvasElement>) -> Surface {
+        let context_lost_listener = canvas.as_ref().and_then(|canvas| {
+            let listener = Closure::<dyn FnMut(Event)>::new(|event: Event| {
+                event.prevent_default();
+            });
+            canvas
+                .add_event_listener_with_callback(
+                    "webglcontextlost",
+                    listener.as_ref().unchecked_ref(),
+                )
+                .ok()
+                .map(|_| listener)
+        });
+
+        Surface {
+            canvas,
+            max_pixel_ratio: DEFAULT_MAX_PIXEL_RATIO,
+            context_lost_listener,
+        }
+    }
+
+    pub(crate) fn canvas(&self) -> Option<&HtmlCanvasElement> {
+        self.canvas.as_ref()
+    }
+
+    pub(crate) fn set_max_pixel_ratio(&mut self, max_pixel_ratio: f64) -> Result<(), JsValue> {
+        if !(max_pixel_ratio.is_finite() && max_pixel_ratio > 0.) {
+            return Err(JsValue::from_str("max pixel ratio must be positive"));
+        }
+        self.max_pixel_ratio = max_pixel_ratio;
+
+        Ok(())
+    }
+
+    /// Resizes the drawing buffer to the CSS size of the canvas times the device pixel
+    /// ratio. A canvas that is not displayed keeps its size.
+    pub(crate) fn resize(&self) {
+        let canvas = match &self.canvas {
+            Some(canvas) => canvas,
+            None => return,
+        };
+        let device_pixel_ratio = web_sys::window()
+            .map(|window| window.device_pixel_ratio())
+            .unwrap_or(1.);
+
+        if let Some((width, height)) = drawing_buffer_size(
+            canvas.client_width(),
+            canvas.client_height(),
+            device_pixel_ratio.min(self.max_pixel_ratio),
+        ) {
+            if canvas.width() != width || canvas.height() != height {
+                canvas.set_width(width);
+                canvas.set_height(height);
+            }
+        }
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        if let (Some(canvas), Some(listener)) = (&self.canvas, &self.context_lost_listener) {
+            let _ = canvas.remove_event_listener_with_callback(
+                "webglcontextlost",
+                listener.as_ref().unchecked_ref(),
+            );
+        }
+    }
+}
+
+/// Aspect ratio of the drawing buffer, which may be smaller than the canvas when the
+/// browser limits its size.
+pub(crate) fn aspect(gl: &dyn Backend) -> Option<f32> {
+    let width = gl.drawing_buffer_width();
+    let height = gl.drawing_buffer_height();
+    match width > 0 && height > 0 {
+        true => Some(width as f32 / height as f32),
+        false => None,
+    }
+}
+
+fn drawing_buffer_size(css_width: i32, css_height: i32, pixel_ratio: f64) -> Option<(u32, u32)> {
+    if css_width <= 0 || css_height <= 0 {
+        return None;
+    }
+    let scale = |size: i32| ((size as f64 * pixel_ratio).round() as u32).max(1);
+
+    Some((scale(css_width), scale(css_height)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales_the_css_size_by_the_pixel_ratio() {
+        assert_eq!(drawing_buffer_size(640, 360, 1.5), Some((960, 540)));
+        assert_eq!(drawing_buffer_size(3, 1, 0.1), Some((1, 1)));
+        assert_eq!(drawing_buffer_size(0, 360, 2.), None);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::loader::ktx2::{Ktx2Encoding, Ktx2Header};
+use crate::renderer::backend::Backend;
+
+// Target formats of the Basis Universal transcoder (`basist::transcoder_texture_format`).
+const BASIS_ETC1_RGB: u32 = 0;
+const BASIS_BC1_RGB: u32 = 2;
+const BASIS_BC3_RGBA: u32 = 3;
+const BASIS_PVRTC1_4_RGB: u32 = 8;
+const BASIS_PVRTC1_4_RGBA: u32 = 9;
+const BASIS_ASTC_4X4_RGBA: u32 = 10;
+const BASIS_RGBA32: u32 = 13;
+
+const COMPRESSED_RGB_S3TC_DXT1_EXT: u32 = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: u32 = 0x83F3;
+const COMPRESSED_RGB_ETC1_WEBGL: u32 = 0x8D64;
+const COMPRESSED_RGB_PVRTC_4BPPV1_IMG: u32 = 0x8C00;
+const COMPRESSED_RGBA_PVRTC_4BPPV1_IMG: u32 = 0x8C02;
+const COMPRESSED_RGBA_ASTC_4X4_KHR: u32 = 0x93B0;
+
+/// Format a Basis texture is transcoded to, `gl_format` is `None` for uncompressed RGBA.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TranscodeTarget {
+    pub(crate) basis_format: u32,
+    pub(crate) gl_format: Option<u32>,
+}
+
+/// Compressed texture extensions offered by a context. Querying an extension enables it,
+/// so this is detected once per context and handed to everything uploading textures.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct CompressedTextureSupport {
+    astc: bool,
+    s3tc: bool,
+    etc1: bool,
+    pvrtc: bool,
+}
+
+impl CompressedTextureSupport {
+    pub(crate) fn detect(gl: &dyn Backend) -> CompressedTextureSupport {
+        let has_extension = |names: &[&str]| names.iter().any(|name| gl.has_extension(name));
+
+        CompressedTextureSupport {
+            astc: has_extension(&["WEBGL_compressed_texture_astc"]),
+            s3tc: has_extension(&[
+                "WEBGL_compressed_texture_s3tc",
+                "WEBKIT_WEBGL_compressed_texture_s3tc",
+            ]),
+            etc1: has_extension(&["WEBGL_compressed_texture_etc1"]),
+            pvrtc: has_extension(&[
+                "WEBGL_compressed_texture_pvrtc",
+                "WEBKIT_WEBGL_compressed_texture_pvrtc",
+            ]),
+        }
+    }
+
+    pub(crate) fn transcode_target(&self, header: &Ktx2Header) -> TranscodeTarget {
+        let target = |basis_format, gl_format| TranscodeTarget {
+            basis_format,
+            gl_format: Some(gl_format),
+        };
+        // PVRTC1 is limited to square power of two textures.
+        let pvrtc = self.pvrtc && header.width == header.height && header.width.is_power_of_two();
+        // The other formats store 4x4 blocks, WebGL rejects base levels of other sizes.
+        let blocks = header.width.is_multiple_of(4) && header.height.is_multiple_of(4);
+        // ETC1 has no alpha channel and only pays off for ETC1S, which it is a subset of.
+        let etc1 = self.etc1 && blocks && !header.has_alpha;
+        let astc = self.astc && blocks;
+        let s3tc = self.s3tc && blocks;
+
+        match header.encoding {
+            Ktx2Encoding::Etc1s if etc1 => target(BASIS_ETC1_RGB, COMPRESSED_RGB_ETC1_WEBGL),
+            _ if astc => target(BASIS_ASTC_4X4_RGBA, COMPRESSED_RGBA_ASTC_4X4_KHR),
+            _ if s3tc && header.has_alpha => target(BASIS_BC3_RGBA, COMPRESSED_RGBA_S3TC_DXT5_EXT),
+            _ if s3tc => target(BASIS_BC1_RGB, COMPRESSED_RGB_S3TC_DXT1_EXT),
+            _ if etc1 => target(BASIS_ETC1_RGB, COMPRESSED_RGB_ETC1_WEBGL),
+            _ if pvrtc && header.has_alpha => {
+                target(BASIS_PVRTC1_4_RGBA, COMPRESSED_RGBA_PVRTC_4BPPV1_IMG)
+            }
+            _ if pvrtc => target(BASIS_PVRTC1_4_RGB, COMPRESSED_RGB_PVRTC_4BPPV1_IMG),
+            _ => TranscodeTarget {
+                basis_format: BASIS_RGBA32,
+                gl_format: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: CompressedTextureSupport = CompressedTextureSupport {
+        astc: true,
+        s3tc: true,
+        etc1: true,
+        pvrtc: true,
+    };
+
+    fn header(width: u32, height: u32, encoding: Ktx2Encoding, has_alpha: bool) -> Ktx2Header {
+        Ktx2Header {
+            width,
+            height,
+            level_count: 1,
+            encoding,
+            has_alpha,
+        }
+    }
+
+    fn gl_format(support: CompressedTextureSupport, header: &Ktx2Header) -> Option<u32> {
+        support.transcode_target(header).gl_format
+    }
+
+    #[test]
+    fn prefers_etc1_for_opaque_etc1s_and_astc_otherwise() {
+        assert_eq!(
+            gl_format(ALL, &header(64, 32, Ktx2Encoding::Etc1s, false)),
+            Some(COMPRESSED_RGB_ETC1_WEBGL)
+        );
+        assert_eq!(
+            gl_format(ALL, &header(64, 32, Ktx2Encoding::Etc1s, true)),
+            Some(COMPRESSED_RGBA_ASTC_4X4_KHR)
+        );
+        assert_eq!(
+            gl_format(ALL, &header(64, 32, Ktx2Encoding::Uastc, false)),
+            Some(COMPRESSED_RGBA_ASTC_4X4_KHR)
+        );
+    }
+
+    #[test]
+    fn picks_s3tc_by_alpha() {
+        let s3tc = CompressedTextureSupport {
+            s3tc: true,
+            ..CompressedTextureSupport::default()
+        };
+
+        assert_eq!(
+            gl_format(s3tc, &header(64, 32, Ktx2Encoding::Uastc, true)),
+            Some(COMPRESSED_RGBA_S3TC_DXT5_EXT)
+        );
+        assert_eq!(
+            gl_format(s3tc, &header(64, 32, Ktx2Encoding::Uastc, false)),
+            Some(COMPRESSED_RGB_S3TC_DXT1_EXT)
+        );
+    }
+
+    #[test]
+    fn sizes_that_are_not_multiples_of_4_fall_back_to_rgba() {
+        for (width, height) in &[(30, 32), (32, 30), (2, 6), (6, 6)] {
+            for encoding in &[Ktx2Encoding::Etc1s, Ktx2Encoding::Uastc] {
+                let target = ALL.transcode_target(&header(*width, *height, *encoding, false));
+
+                assert_eq!(
+                    target,
+                    TranscodeTarget {
+                        basis_format: BASIS_RGBA32,
+                        gl_format: None,
+                    },
+                    "{}x{}",
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pvrtc_needs_square_powers_of_two() {
+        let pvrtc = CompressedTextureSupport {
+            pvrtc: true,
+            ..CompressedTextureSupport::default()
+        };
+
+        assert_eq!(
+            gl_format(pvrtc, &header(64, 64, Ktx2Encoding::Uastc, true)),
+            Some(COMPRESSED_RGBA_PVRTC_4BPPV1_IMG)
+        );
+        assert_eq!(
+            gl_format(pvrtc, &header(64, 32, Ktx2Encoding::Uastc, true)),
+            None
+        );
+    }
+}
@@ -0,0 +1,507 @@
+use crate::renderer::scene::Scene;
+use crate::runtime::timestep::{FixedTimestep, DEFAULT_TICK_RATE};
+use js_sys::Function;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use wasm_bindgen::convert::TryFromJsValue;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, EventTarget};
+
+pub(crate) mod timestep;
+
+/// Game logic driven by the loop of a `Game`.
+pub(crate) trait System {
+    /// Advances the game by one tick of `step` seconds.
+    fn update(&mut self, scene: &mut GameScene, step: f64) -> Result<(), JsValue>;
+
+    /// Runs before every rendered frame, `alpha` is how far the frame lies between the
+    /// last tick and the next one.
+    fn interpolate(&mut self, _scene: &mut GameScene, _alpha: f64) -> Result<(), JsValue> {
+        Ok(())
+    }
+}
+
+/// Scene of a game. It is handed to JS for the length of a call and taken back after it,
+/// so JS never holds on to it while the loop runs.
+pub(crate) struct GameScene(Option<Scene>);
+
+impl GameScene {
+    pub(crate) fn get(&mut self) -> Result<&mut Scene, JsValue> {
+        self.0
+            .as_mut()
+            .ok_or_else(|| JsValue::from_str("the scene is in use"))
+    }
+
+    fn into_inner(self) -> Result<Scene, JsValue> {
+        self.0
+            .ok_or_else(|| JsValue::from_str("the scene is in use"))
+    }
+
+    /// Calls `call` with the scene as a JS object and returns what it returned. Fails
+    /// when JS freed the scene.
+    fn lend(
+        &mut self,
+        call: impl FnOnce(&JsValue) -> Result<JsValue, JsValue>,
+    ) -> Result<JsValue, JsValue> {
+        let scene = self
+            .0
+            .take()
+            .ok_or_else(|| JsValue::from_str("the scene is in use"))?;
+        let value = JsValue::from(scene);
+        let result = call(&value);
+        let scene = Scene::try_from_js_value(value)
+            .map_err(|_| JsValue::from_str("the scene was freed while it was lent to JS"))?;
+        self.0 = Some(scene);
+
+        result
+    }
+}
+
+/// System whose update and interpolation are JS functions.
+struct ScriptSystem {
+    update: Function,
+    interpolate: Option<Function>,
+}
+
+impl System for ScriptSystem {
+    fn update(&mut self, scene: &mut GameScene, step: f64) -> Result<(), JsValue> {
+        scene.lend(|scene| {
+            self.update
+                .call2(&JsValue::NULL, &JsValue::from_f64(step), scene)
+        })?;
+
+        Ok(())
+    }
+
+    fn interpolate(&mut self, scene: &mut GameScene, alpha: f64) -> Result<(), JsValue> {
+        if let Some(interpolate) = &self.interpolate {
+            scene.lend(|scene| {
+                interpolate.call2(&JsValue::NULL, &JsValue::from_f64(alpha), scene)
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Event listener of a running loop, removed again when it is dropped.
+struct Listener {
+    target: EventTarget,
+    event: &'static str,
+    callback: Closure<dyn FnMut(Event)>,
+}
+
+impl Listener {
+    fn add(
+        target: EventTarget,
+        event: &'static str,
+        callback: impl FnMut(Event) + 'static,
+    ) -> Result<Listener, JsValue> {
+        let callback = Closure::<dyn FnMut(Event)>::new(callback);
+        target.add_event_listener_with_callback(event, callback.as_ref().unchecked_ref())?;
+
+        Ok(Listener {
+            target,
+            event,
+            callback,
+        })
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = self.target.remove_event_listener_with_callback(
+            self.event,
+            self.callback.as_ref().unchecked_ref(),
+        );
+    }
+}
+
+/// State of the loop itself, systems may change it while they run.
+struct Control {
+    timestep: FixedTimestep,
+    running: bool,
+    /// Set from the loss of the WebGL context until the scene is restored.
+    context_lost: bool,
+    frame_request: Option<i32>,
+    frame_callback: Option<Closure<dyn FnMut(f64)>>,
+    listeners: Vec<Listener>,
+}
+
+impl Control {
+    fn cancel_frame(&mut self) {
+        if let Some(request) = self.frame_request.take() {
+            if let Some(window) = web_sys::window() {
+                let _ = window.cancel_animation_frame(request);
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        self.running = false;
+        self.cancel_frame();
+        self.listeners.clear();
+        self.frame_callback = None;
+    }
+}
+
+impl Drop for Control {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct World {
+    scene: GameScene,
+    systems: Vec<Box<dyn System>>,
+}
+
+struct Shared {
+    control: RefCell<Control>,
+    world: RefCell<World>,
+}
+
+impl Shared {
+    fn request_frame(&self) -> Result<(), JsValue> {
+        let mut control = self.control.borrow_mut();
+        if control.frame_request.is_some() || control.context_lost || page_hidden() {
+            return Ok(());
+        }
+
+        let callback = control
+            .frame_callback
+            .as_ref()
+            .ok_or_else(|| JsValue::from_str("game loop is not running"))?;
+        let request = web_sys::window()
+            .ok_or_else(|| JsValue::from_str("no window"))?
+            .request_animation_frame(callback.as_ref().unchecked_ref())?;
+        control.frame_request = Some(request);
+
+        Ok(())
+    }
+
+    fn on_animation_frame(&self, time: f64) {
+        {
+            let mut control = self.control.borrow_mut();
+            control.frame_request = None;
+            if !control.running {
+                return;
+            }
+        }
+
+        let result = self.run_frame(time).and_then(|_| {
+            let running = self.control.borrow().running;
+            match running {
+                true => self.request_frame(),
+                false => Ok(()),
+            }
+        });
+        if let Err(error) = result {
+            web_sys::console::error_1(&error);
+            self.control.borrow_mut().stop();
+        }
+    }
+
+    /// Runs the ticks that are due and renders once. The control is not borrowed while
+    /// systems run, so they can pause or stop the game.
+    fn run_frame(&self, time: f64) -> Result<(), JsValue> {
+        let (frame, step) = {
+            let mut control = self.control.borrow_mut();
+            let frame = control.timestep.advance(time);
+            (frame, control.timestep.step_seconds())
+        };
+
+        let mut world = self.world.borrow_mut();
+        let World { scene, systems } = &mut *world;
+        for _ in 0..frame.ticks {
+            for system in systems.iter_mut() {
+                system.update(scene, step)?;
+            }
+            if !self.control.borrow().running {
+                return Ok(());
+            }
+        }
+        for system in systems.iter_mut() {
+            system.interpolate(scene, frame.alpha)?;
+        }
+
+        scene.get()?.render()
+    }
+
+    fn on_visibility_change(&self) {
+        if page_hidden() {
+            self.control.borrow_mut().cancel_frame();
+            return;
+        }
+
+        let resume = {
+            let mut control = self.control.borrow_mut();
+            if control.running {
+                control.timestep.reset();
+            }
+            control.running
+        };
+        if resume {
+            if let Err(error) = self.request_frame() {
+                web_sys::console::error_1(&error);
+            }
+        }
+    }
+}
+
+impl Shared {
+    /// Pauses the loop. Preventing the default lets the browser restore the context.
+    fn on_context_lost(&self, event: Event) {
+        event.prevent_default();
+        let mut control = self.control.borrow_mut();
+        control.context_lost = true;
+        control.cancel_frame();
+    }
+
+    /// Rebuilds the GPU resources of the scene, then continues the loop without catching
+    /// up on the time in between. The scene is taken out of the world while it is
+    /// restored, so JS running in between finds it in use instead of a borrowed world.
+    async fn restore_context(&self) {
+        let scene = self.world.borrow_mut().scene.0.take();
+        let restored = match scene {
+            Some(mut scene) => {
+                let restored = scene.restore_context().await;
+                self.world.borrow_mut().scene.0 = Some(scene);
+                restored
+            }
+            None => Err(JsValue::from_str("the scene is in use")),
+        };
+
+        let result = restored.and_then(|_| {
+            let running = {
+                let mut control = self.control.borrow_mut();
+                control.context_lost = false;
+                control.timestep.reset();
+                control.running
+            };
+            match running {
+                true => self.request_frame(),
+                false => Ok(()),
+            }
+        });
+        if let Err(error) = result {
+            web_sys::console::error_1(&error);
+            // A context lost again while restoring is restored with the next event.
+            let lost_again = self
+                .world
+                .borrow_mut()
+                .scene
+                .get()
+                .is_ok_and(|scene| scene.is_context_lost());
+            if !lost_again {
+                self.control.borrow_mut().stop();
+            }
+        }
+    }
+}
+
+fn page_hidden() -> bool {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .map(|document| document.hidden())
+        .unwrap_or(false)
+}
+
+/// Owns a scene and the `requestAnimationFrame` loop that updates and renders it. Updates
+/// run in fixed ticks, rendering runs once per animation frame. The loop stops while the
+/// page is hidden and continues without catching up on the time in between.
+#[wasm_bindgen]
+pub struct Game {
+    shared: Rc<Shared>,
+}
+
+#[wasm_bindgen]
+impl Game {
+    /// Takes over `scene`, `tick_rate` is the number of updates per second.
+    #[wasm_bindgen(constructor)]
+    pub fn new(scene: Scene, tick_rate: Option<f64>) -> Result<Game, JsValue> {
+        let tick_rate = tick_rate.unwrap_or(DEFAULT_TICK_RATE);
+        if !(tick_rate.is_finite() && tick_rate > 0.) {
+            return Err(JsValue::from_str("tick rate must be positive"));
+        }
+
+        Ok(Game {
+            shared: Rc::new(Shared {
+                control: RefCell::new(Control {
+                    timestep: FixedTimestep::new(tick_rate),
+                    running: false,
+                    context_lost: false,
+                    frame_request: None,
+                    frame_callback: None,
+                    listeners: vec![],
+                }),
+                world: RefCell::new(World {
+                    scene: GameScene(Some(scene)),
+                    systems: vec![],
+                }),
+            }),
+        })
+    }
+
+    /// Adds a system written in JS. `update` gets the tick length in seconds, the
+    /// optional `interpolate` the alpha of every rendered frame, both get the scene as
+    /// their second argument. The scene is only valid during the call and must not be
+    /// kept or freed. Systems run in the order they were added.
+    pub fn add_system(
+        &mut self,
+        update: Function,
+        interpolate: Option<Function>,
+    ) -> Result<(), JsValue> {
+        self.push_system(Box::new(ScriptSystem {
+            update,
+            interpolate,
+        }))
+    }
+
+    /// Calls `callback` with the scene, for changes outside of the systems such as
+    /// input handlers. Like in systems the scene is only valid during the call.
+    pub fn with_scene(&mut self, callback: Function) -> Result<JsValue, JsValue> {
+        self.shared
+            .world
+            .try_borrow_mut()
+            .map_err(|_| JsValue::from_str("the scene can not be used while systems run"))?
+            .scene
+            .lend(|scene| callback.call1(&JsValue::NULL, scene))
+    }
+
+    pub fn start(&mut self) -> Result<(), JsValue> {
+        let weak = Rc::downgrade(&self.shared);
+        {
+            let mut control = self.shared.control.borrow_mut();
+            if control.running {
+                return Ok(());
+            }
+
+            let frame_weak: Weak<Shared> = weak.clone();
+            control.frame_callback = Some(Closure::new(move |time: f64| {
+                if let Some(shared) = frame_weak.upgrade() {
+                    shared.on_animation_frame(time);
+                }
+            }));
+
+            let document = web_sys::window()
+                .and_then(|window| window.document())
+                .ok_or_else(|| JsValue::from_str("no document"))?;
+            let visibility_weak = weak.clone();
+            control.listeners.push(Listener::add(
+                document.into(),
+                "visibilitychange",
+                move |_| {
+                    if let Some(shared) = visibility_weak.upgrade() {
+                        shared.on_visibility_change();
+                    }
+                },
+            )?);
+
+            let canvas = self
+                .shared
+                .world
+                .borrow_mut()
+                .scene
+                .get()?
+                .canvas()
+                .cloned();
+            if let Some(canvas) = canvas {
+                let lost_weak = weak.clone();
+                control.listeners.push(Listener::add(
+                    canvas.clone().into(),
+                    "webglcontextlost",
+                    move |event| {
+                        if let Some(shared) = lost_weak.upgrade() {
+                            shared.on_context_lost(event);
+                        }
+                    },
+                )?);
+                let restored_weak = weak.clone();
+                control.listeners.push(Listener::add(
+                    canvas.clone().into(),
+                    "webglcontextrestored",
+                    move |_| {
+                        if let Some(shared) = restored_weak.upgrade() {
+                            wasm_bindgen_futures::spawn_local(async move {
+                                shared.restore_context().await;
+                            });
+                        }
+                    },
+                )?);
+            }
+            control.running = true;
+            control.timestep.reset();
+        }
+
+        self.shared.request_frame()
+    }
+
+    /// Stops the loop, `start` continues it later.
+    pub fn stop(&mut self) {
+        self.shared.control.borrow_mut().stop();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.shared.control.borrow().running
+    }
+
+    /// Stops updates, frames keep being rendered.
+    pub fn pause(&mut self) {
+        self.shared.control.borrow_mut().timestep.set_paused(true);
+    }
+
+    pub fn resume(&mut self) {
+        self.shared.control.borrow_mut().timestep.set_paused(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.shared.control.borrow().timestep.is_paused()
+    }
+
+    /// Runs game time faster (above 1) or slower (below 1) than real time.
+    pub fn set_time_scale(&mut self, time_scale: f64) -> Result<(), JsValue> {
+        if !(time_scale.is_finite() && time_scale >= 0.) {
+            return Err(JsValue::from_str("time scale must not be negative"));
+        }
+        self.shared
+            .control
+            .borrow_mut()
+            .timestep
+            .set_time_scale(time_scale);
+
+        Ok(())
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.shared.control.borrow().timestep.time_scale()
+    }
+
+    /// Update ticks run since the game was created.
+    pub fn tick(&self) -> f64 {
+        self.shared.control.borrow().timestep.tick() as f64
+    }
+
+    /// Stops the loop and hands the scene back.
+    pub fn into_scene(self) -> Result<Scene, JsValue> {
+        let shared = Rc::try_unwrap(self.shared)
+            .map_err(|_| JsValue::from_str("the game is still running a frame"))?;
+
+        shared.world.into_inner().scene.into_inner()
+    }
+}
+
+impl Game {
+    /// Adds a system, fails when called from within a running system.
+    pub(crate) fn push_system(&mut self, system: Box<dyn System>) -> Result<(), JsValue> {
+        self.shared
+            .world
+            .try_borrow_mut()
+            .map_err(|_| JsValue::from_str("systems can not be added while they run"))?
+            .systems
+            .push(system);
+
+        Ok(())
+    }
+}
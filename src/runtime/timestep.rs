@@ -0,0 +1,151 @@
+/// Update ticks per second unless the game asks for another rate.
+pub(crate) const DEFAULT_TICK_RATE: f64 = 60.;
+/// Longest gap between two frames that is simulated, in milliseconds. Longer gaps, like
+/// a breakpoint or a stalled tab, are cut so the loop does not fall ever further behind.
+const MAX_FRAME_TIME: f64 = 250.;
+
+/// Work for one animation frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FrameTicks {
+    /// Fixed updates to run before rendering.
+    pub(crate) ticks: u32,
+    /// How far the rendered frame lies between the last tick and the next, 0 to 1.
+    pub(crate) alpha: f64,
+}
+
+/// Turns frame timestamps into a fixed number of update ticks. Game time only depends on
+/// the timestamps it is fed, so the same timestamps always give the same ticks.
+#[derive(Debug)]
+pub(crate) struct FixedTimestep {
+    /// Length of a tick in milliseconds.
+    step: f64,
+    accumulator: f64,
+    last_time: Option<f64>,
+    time_scale: f64,
+    paused: bool,
+    tick: u64,
+}
+
+impl FixedTimestep {
+    pub(crate) fn new(tick_rate: f64) -> FixedTimestep {
+        FixedTimestep {
+            step: 1000. / tick_rate,
+            accumulator: 0.,
+            last_time: None,
+            time_scale: 1.,
+            paused: false,
+            tick: 0,
+        }
+    }
+
+    /// Length of a tick in seconds.
+    pub(crate) fn step_seconds(&self) -> f64 {
+        self.step / 1000.
+    }
+
+    /// Ticks run so far.
+    pub(crate) fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub(crate) fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Speeds game time up or slows it down, ticks keep their length and only run more
+    /// or less often.
+    pub(crate) fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale;
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Paused frames run no ticks and keep the interpolation where it was.
+    pub(crate) fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Forgets the last frame, so the next one runs no ticks. Used when the loop starts
+    /// again after being stopped or hidden, the time in between is not caught up.
+    pub(crate) fn reset(&mut self) {
+        self.last_time = None;
+    }
+
+    /// Advances game time to the frame at `time`, in milliseconds.
+    pub(crate) fn advance(&mut self, time: f64) -> FrameTicks {
+        let elapsed = match self.last_time {
+            Some(last_time) => (time - last_time).clamp(0., MAX_FRAME_TIME),
+            None => 0.,
+        };
+        self.last_time = Some(time);
+
+        if !self.paused {
+            self.accumulator += elapsed * self.time_scale;
+        }
+        let ticks = (self.accumulator / self.step).floor();
+        self.accumulator -= ticks * self.step;
+        self.tick += ticks as u64;
+
+        FrameTicks {
+            ticks: ticks as u32,
+            alpha: self.accumulator / self.step,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticks(timestep: &mut FixedTimestep, times: &[f64]) -> Vec<u32> {
+        times
+            .iter()
+            .map(|time| timestep.advance(*time).ticks)
+            .collect()
+    }
+
+    #[test]
+    fn runs_ticks_at_a_fixed_rate_whatever_the_frame_rate() {
+        let mut slow = FixedTimestep::new(50.);
+        let mut fast = FixedTimestep::new(50.);
+
+        assert_eq!(ticks(&mut slow, &[0., 50., 100., 150.]), vec![0, 2, 3, 2]);
+        let fast_times: Vec<f64> = (0..=15).map(|frame| frame as f64 * 10.).collect();
+        assert_eq!(ticks(&mut fast, &fast_times).iter().sum::<u32>(), 7);
+        assert_eq!(slow.tick(), fast.tick());
+    }
+
+    #[test]
+    fn interpolates_between_ticks() {
+        let mut timestep = FixedTimestep::new(100.);
+        timestep.advance(0.);
+
+        let frame = timestep.advance(25.);
+        assert_eq!(frame.ticks, 2);
+        assert!((frame.alpha - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pausing_and_scaling_change_game_time_only() {
+        let mut timestep = FixedTimestep::new(100.);
+        timestep.advance(0.);
+        timestep.set_paused(true);
+        assert_eq!(timestep.advance(100.).ticks, 0);
+
+        timestep.set_paused(false);
+        timestep.set_time_scale(0.5);
+        assert_eq!(timestep.advance(200.).ticks, 5);
+    }
+
+    #[test]
+    fn long_gaps_are_not_caught_up() {
+        let mut timestep = FixedTimestep::new(100.);
+        timestep.advance(0.);
+        assert_eq!(timestep.advance(10_000.).ticks, 25);
+
+        timestep.reset();
+        assert_eq!(timestep.advance(20_000.).ticks, 0);
+    }
+}